@@ -0,0 +1,2 @@
+-- Experiments created before salting keep a NULL salt and are bucketed by the raw device id.
+ALTER TABLE experiments ADD COLUMN salt TEXT;
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS assignments (
+    device_id TEXT NOT NULL,
+    experiment_id TEXT NOT NULL,
+    data TEXT NOT NULL,
+    assigned_at TEXT NOT NULL,
+    PRIMARY KEY (device_id, experiment_id),
+    FOREIGN KEY (device_id) REFERENCES devices(id) ON DELETE CASCADE,
+    FOREIGN KEY (experiment_id) REFERENCES experiments(id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+CREATE TABLE IF NOT EXISTS assignment_backfills (
+    completed_at TEXT NOT NULL
+);
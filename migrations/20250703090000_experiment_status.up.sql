@@ -0,0 +1,4 @@
+ALTER TABLE experiments ADD COLUMN status TEXT NOT NULL DEFAULT 'running'
+    CHECK (status IN ('draft', 'running', 'paused', 'finished', 'archived'));
+
+UPDATE experiments SET status = 'finished' WHERE finished_at IS NOT NULL;
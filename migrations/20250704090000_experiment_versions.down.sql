@@ -0,0 +1,6 @@
+DELETE FROM experiment_variants
+WHERE version <> (SELECT version FROM experiments WHERE experiments.id = experiment_variants.experiment_id);
+
+ALTER TABLE assignments DROP COLUMN version;
+ALTER TABLE experiment_variants DROP COLUMN version;
+ALTER TABLE experiments DROP COLUMN version;
@@ -0,0 +1,6 @@
+ALTER TABLE experiments ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+
+-- Every version of an experiment keeps its own copy of the variants.
+ALTER TABLE experiment_variants ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+
+ALTER TABLE assignments ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
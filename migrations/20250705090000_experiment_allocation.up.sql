@@ -0,0 +1 @@
+ALTER TABLE experiments ADD COLUMN allocation REAL NOT NULL DEFAULT 100;
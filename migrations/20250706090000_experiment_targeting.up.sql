@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS experiment_targeting_rules (
+    id TEXT PRIMARY KEY NOT NULL,
+    experiment_id TEXT NOT NULL,
+    attribute TEXT NOT NULL,
+    operator TEXT NOT NULL CHECK (operator IN ('eq', 'in', 'semver', 'lt', 'lte', 'gt', 'gte')),
+    value TEXT NOT NULL,
+    FOREIGN KEY (experiment_id) REFERENCES experiments(id) ON DELETE CASCADE
+);
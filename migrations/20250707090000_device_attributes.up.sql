@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS device_attributes (
+    device_id TEXT PRIMARY KEY NOT NULL,
+    platform TEXT CHECK (platform IN ('ios', 'android')),
+    os_version TEXT,
+    app_version TEXT,
+    country TEXT,
+    locale TEXT,
+    properties TEXT NOT NULL DEFAULT '{}',
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (device_id) REFERENCES devices(id) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+ALTER TABLE devices ADD COLUMN kind TEXT NOT NULL DEFAULT 'idfa'
+    CHECK (kind IN ('idfa', 'aaid', 'idfv', 'install_id'));
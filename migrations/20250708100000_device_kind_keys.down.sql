@@ -0,0 +1,48 @@
+CREATE TABLE devices_old (
+    id TEXT PRIMARY KEY NOT NULL,
+    created_at TEXT NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'idfa'
+        CHECK (kind IN ('idfa', 'aaid', 'idfv', 'install_id'))
+);
+
+INSERT INTO devices_old (id, created_at, kind)
+SELECT id, created_at, kind FROM devices;
+
+CREATE TABLE assignments_old (
+    device_id TEXT NOT NULL,
+    experiment_id TEXT NOT NULL,
+    data TEXT NOT NULL,
+    assigned_at TEXT NOT NULL,
+    version INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (device_id, experiment_id),
+    FOREIGN KEY (device_id) REFERENCES devices_old(id) ON DELETE CASCADE,
+    FOREIGN KEY (experiment_id) REFERENCES experiments(id) ON DELETE CASCADE
+);
+
+INSERT INTO assignments_old (device_id, experiment_id, data, assigned_at, version)
+SELECT device_id, experiment_id, data, assigned_at, version FROM assignments;
+
+CREATE TABLE device_attributes_old (
+    device_id TEXT PRIMARY KEY NOT NULL,
+    platform TEXT CHECK (platform IN ('ios', 'android')),
+    os_version TEXT,
+    app_version TEXT,
+    country TEXT,
+    locale TEXT,
+    properties TEXT NOT NULL DEFAULT '{}',
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (device_id) REFERENCES devices_old(id) ON DELETE CASCADE
+);
+
+INSERT INTO device_attributes_old (device_id, platform, os_version, app_version, country, locale,
+    properties, updated_at)
+SELECT device_id, platform, os_version, app_version, country, locale, properties, updated_at
+FROM device_attributes;
+
+DROP TABLE device_attributes;
+DROP TABLE assignments;
+DROP TABLE devices;
+
+ALTER TABLE devices_old RENAME TO devices;
+ALTER TABLE assignments_old RENAME TO assignments;
+ALTER TABLE device_attributes_old RENAME TO device_attributes;
@@ -0,0 +1,55 @@
+-- Devices are keyed by kind and identifier. Referencing tables are rebuilt next to the new
+-- devices table and renamed together with it, as dropping a table still referenced would cascade.
+CREATE TABLE devices_new (
+    kind TEXT NOT NULL CHECK (kind IN ('idfa', 'aaid', 'idfv', 'install_id')),
+    id TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (kind, id)
+);
+
+INSERT INTO devices_new (kind, id, created_at)
+SELECT kind, id, created_at FROM devices;
+
+CREATE TABLE assignments_new (
+    device_kind TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    experiment_id TEXT NOT NULL,
+    data TEXT NOT NULL,
+    assigned_at TEXT NOT NULL,
+    version INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (device_kind, device_id, experiment_id),
+    FOREIGN KEY (device_kind, device_id) REFERENCES devices_new(kind, id) ON DELETE CASCADE,
+    FOREIGN KEY (experiment_id) REFERENCES experiments(id) ON DELETE CASCADE
+);
+
+INSERT INTO assignments_new (device_kind, device_id, experiment_id, data, assigned_at, version)
+SELECT d.kind, a.device_id, a.experiment_id, a.data, a.assigned_at, a.version
+FROM assignments a JOIN devices d ON d.id = a.device_id;
+
+CREATE TABLE device_attributes_new (
+    device_kind TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    platform TEXT CHECK (platform IN ('ios', 'android')),
+    os_version TEXT,
+    app_version TEXT,
+    country TEXT,
+    locale TEXT,
+    properties TEXT NOT NULL DEFAULT '{}',
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (device_kind, device_id),
+    FOREIGN KEY (device_kind, device_id) REFERENCES devices_new(kind, id) ON DELETE CASCADE
+);
+
+INSERT INTO device_attributes_new (device_kind, device_id, platform, os_version, app_version,
+    country, locale, properties, updated_at)
+SELECT d.kind, da.device_id, da.platform, da.os_version, da.app_version, da.country, da.locale,
+    da.properties, da.updated_at
+FROM device_attributes da JOIN devices d ON d.id = da.device_id;
+
+DROP TABLE device_attributes;
+DROP TABLE assignments;
+DROP TABLE devices;
+
+ALTER TABLE devices_new RENAME TO devices;
+ALTER TABLE assignments_new RENAME TO assignments;
+ALTER TABLE device_attributes_new RENAME TO device_attributes;
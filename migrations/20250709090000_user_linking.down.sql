@@ -0,0 +1,5 @@
+ALTER TABLE experiments DROP COLUMN bucketing;
+
+DROP INDEX IF EXISTS devices_user_id_idx;
+
+ALTER TABLE devices DROP COLUMN user_id;
@@ -0,0 +1,6 @@
+ALTER TABLE devices ADD COLUMN user_id TEXT;
+
+CREATE INDEX IF NOT EXISTS devices_user_id_idx ON devices (user_id);
+
+ALTER TABLE experiments ADD COLUMN bucketing TEXT NOT NULL DEFAULT 'device'
+    CHECK (bucketing IN ('device', 'user'));
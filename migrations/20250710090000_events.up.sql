@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS events (
+    id TEXT PRIMARY KEY NOT NULL,
+    device_kind TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value REAL,
+    occurred_at TEXT NOT NULL,
+    received_at TEXT NOT NULL,
+    FOREIGN KEY (device_kind, device_id) REFERENCES devices(kind, id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS events_name_idx ON events (name);
+CREATE INDEX IF NOT EXISTS events_device_idx ON events (device_kind, device_id);
+
+CREATE TABLE IF NOT EXISTS event_attributions (
+    event_id TEXT NOT NULL,
+    experiment_id TEXT NOT NULL,
+    data TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    PRIMARY KEY (event_id, experiment_id),
+    FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE CASCADE,
+    FOREIGN KEY (experiment_id) REFERENCES experiments(id) ON DELETE CASCADE
+);
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS experiment_metrics (
+    id TEXT PRIMARY KEY NOT NULL,
+    experiment_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('conversion', 'sum', 'mean', 'count', 'ratio')),
+    event TEXT NOT NULL,
+    denominator_event TEXT,
+    role TEXT NOT NULL DEFAULT 'secondary' CHECK (role IN ('primary', 'secondary')),
+    UNIQUE (experiment_id, name),
+    FOREIGN KEY (experiment_id) REFERENCES experiments(id) ON DELETE CASCADE
+);
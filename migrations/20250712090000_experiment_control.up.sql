@@ -0,0 +1 @@
+ALTER TABLE experiments ADD COLUMN control TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE experiments ADD COLUMN analysis_plan TEXT NOT NULL DEFAULT 'fixed'
+    CHECK (analysis_plan IN ('fixed', 'sequential'));
@@ -0,0 +1 @@
+ALTER TABLE experiment_metrics DROP COLUMN mixing_deviation;
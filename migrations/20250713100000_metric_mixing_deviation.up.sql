@@ -0,0 +1,2 @@
+ALTER TABLE experiment_metrics ADD COLUMN mixing_deviation REAL
+    CHECK (mixing_deviation IS NULL OR mixing_deviation > 0);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS event_attributions_experiment_id_idx;
+DROP INDEX IF EXISTS assignments_experiment_id_idx;
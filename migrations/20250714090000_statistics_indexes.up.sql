@@ -0,0 +1,2 @@
+CREATE INDEX IF NOT EXISTS assignments_experiment_id_idx ON assignments (experiment_id);
+CREATE INDEX IF NOT EXISTS event_attributions_experiment_id_idx ON event_attributions (experiment_id);
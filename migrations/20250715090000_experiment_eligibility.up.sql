@@ -0,0 +1,2 @@
+ALTER TABLE experiments ADD COLUMN eligibility TEXT NOT NULL DEFAULT 'existing'
+    CHECK (eligibility IN ('existing', 'new', 'all'));
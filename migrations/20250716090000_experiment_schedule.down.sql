@@ -0,0 +1,2 @@
+ALTER TABLE experiments DROP COLUMN ends_at;
+ALTER TABLE experiments DROP COLUMN starts_at;
@@ -0,0 +1,2 @@
+ALTER TABLE experiments ADD COLUMN starts_at TEXT;
+ALTER TABLE experiments ADD COLUMN ends_at TEXT;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS experiment_audit_log;
+DROP TABLE IF EXISTS experiment_guardrails;
@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS experiment_guardrails (
+    id TEXT PRIMARY KEY NOT NULL,
+    experiment_id TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    max_degradation REAL NOT NULL CHECK (max_degradation > 0),
+    direction TEXT NOT NULL DEFAULT 'increase' CHECK (direction IN ('increase', 'decrease')),
+    action TEXT NOT NULL DEFAULT 'pause' CHECK (action IN ('pause', 'finish')),
+    UNIQUE (experiment_id, metric),
+    FOREIGN KEY (experiment_id) REFERENCES experiments(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS experiment_audit_log (
+    id TEXT PRIMARY KEY NOT NULL,
+    experiment_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (experiment_id) REFERENCES experiments(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS experiment_audit_log_experiment_id_idx ON experiment_audit_log (experiment_id);
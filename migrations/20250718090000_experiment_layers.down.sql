@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS experiments_layer_idx;
+
+ALTER TABLE experiments DROP COLUMN layer_end;
+ALTER TABLE experiments DROP COLUMN layer_start;
+ALTER TABLE experiments DROP COLUMN layer;
@@ -0,0 +1,5 @@
+ALTER TABLE experiments ADD COLUMN layer TEXT;
+ALTER TABLE experiments ADD COLUMN layer_start INTEGER;
+ALTER TABLE experiments ADD COLUMN layer_end INTEGER;
+
+CREATE INDEX IF NOT EXISTS experiments_layer_idx ON experiments (layer);
@@ -1,8 +1,19 @@
+use std::time::Duration;
+
 use abexp::config::Config;
-use abexp::domain::experiment::service::Service;
+use abexp::domain::experiment::ports::ExperimentService;
+use abexp::domain::{device, event, experiment};
+use abexp::inbound::guardrail_evaluator::GuardrailEvaluator;
 use abexp::inbound::http::{HttpServer, HttpServerConfig};
+use abexp::inbound::scheduler::Scheduler;
 use abexp::outbound::sqlite::Sqlite;
 
+/// How often scheduled starts and ends of experiments are applied.
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often experiments are checked against their guardrails.
+const GUARDRAIL_INTERVAL: Duration = Duration::from_secs(300);
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let config = Config::from_env()?;
@@ -10,14 +21,27 @@ async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
 
     let sqlite = Sqlite::new(&config.database_url).await?;
-    let experiment_service = Service::new(sqlite);
+    let experiment_service = experiment::service::Service::new(sqlite.clone());
+    let device_service = device::service::Service::new(sqlite.clone());
+    let event_service = event::service::Service::new(sqlite);
+
+    experiment_service.backfill_assignments().await?;
+
+    tokio::spawn(Scheduler::new(experiment_service.clone(), SCHEDULER_INTERVAL).run());
+    tokio::spawn(GuardrailEvaluator::new(experiment_service.clone(), GUARDRAIL_INTERVAL).run());
 
     let server_config = HttpServerConfig {
         port: &config.server_port,
         auth_token: &config.auth_token,
     };
 
-    let http_server = HttpServer::new(experiment_service, server_config).await?;
+    let http_server = HttpServer::new(
+        experiment_service,
+        device_service,
+        event_service,
+        server_config,
+    )
+    .await?;
 
     http_server.run().await
 }
@@ -1,2 +1,4 @@
 pub mod device;
+pub mod event;
 pub mod experiment;
+pub mod statistics;
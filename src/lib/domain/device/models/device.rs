@@ -1,44 +1,290 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use derive_more::{Display, From};
 use thiserror::Error;
 
+/// Represents kind of a device identifier.
+#[derive(Display, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceIdKind {
+    /// Apple identifier for advertisers.
+    #[display("idfa")]
+    Idfa,
+    /// Google advertising identifier.
+    #[display("aaid")]
+    Aaid,
+    /// Apple identifier for vendors.
+    #[display("idfv")]
+    Idfv,
+    /// Opaque identifier generated by the application on install.
+    #[display("install_id")]
+    InstallId,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid device identifier kind")]
+pub struct DeviceIdKindInvalidError(String);
+impl DeviceIdKind {
+    pub fn new(raw_kind: &str) -> Result<Self, DeviceIdKindInvalidError> {
+        match raw_kind.trim().to_lowercase().as_str() {
+            "idfa" => Ok(Self::Idfa),
+            "aaid" => Ok(Self::Aaid),
+            "idfv" => Ok(Self::Idfv),
+            "install_id" => Ok(Self::InstallId),
+            _ => Err(DeviceIdKindInvalidError(raw_kind.to_string())),
+        }
+    }
+}
+
 /// Represents always valid device identifier.
 #[derive(Display, Debug, Clone, PartialEq, Eq, Hash)]
-pub struct DeviceId(Uuid);
+pub enum DeviceId {
+    Idfa(Uuid),
+    Aaid(Uuid),
+    Idfv(Uuid),
+    InstallId(String),
+    /// Advertising identifier zeroed out by limited ad tracking, it cannot tell devices apart.
+    #[display("anonymous")]
+    Anonymous(DeviceIdKind),
+}
 
 #[derive(Error, Debug, Clone, PartialEq)]
-#[error("{0} is not a valid IDFA")]
-pub struct DeviceIdError(String);
+#[error("{raw} is not a valid {kind}")]
+pub struct DeviceIdError {
+    kind: DeviceIdKind,
+    raw: String,
+}
+
 impl DeviceId {
-    pub fn new(raw_idfa: &str) -> Result<Self, DeviceIdError> {
-        match Uuid::try_parse(raw_idfa) {
-            Ok(uuid) => {
+    /// Maximum length of an install identifier.
+    const INSTALL_ID_MAX_LEN: usize = 128;
+    /// Minimum length of an install identifier.
+    const INSTALL_ID_MIN_LEN: usize = 8;
+
+    /// Parses an identifier of the given kind.
+    ///
+    /// Advertising identifiers (IDFA, AAID) equal to the nil UUID are sent by devices with limited
+    /// ad tracking and are parsed as [DeviceId::Anonymous]. IDFV has to be a non-nil UUID, and an
+    /// install identifier has to be 8 to 128 ASCII letters, digits, `-`, `_` or `.`.
+    pub fn new(kind: DeviceIdKind, raw_id: &str) -> Result<Self, DeviceIdError> {
+        let error = || DeviceIdError {
+            kind,
+            raw: raw_id.to_string(),
+        };
+
+        match kind {
+            DeviceIdKind::Idfa | DeviceIdKind::Aaid => {
+                let uuid = Uuid::try_parse(raw_id).map_err(|_| error())?;
+
                 if uuid.is_nil() {
-                    Err(DeviceIdError(raw_idfa.to_string()))
+                    Ok(Self::Anonymous(kind))
+                } else if kind == DeviceIdKind::Idfa {
+                    Ok(Self::Idfa(uuid))
+                } else {
+                    Ok(Self::Aaid(uuid))
+                }
+            }
+            DeviceIdKind::Idfv => match Uuid::try_parse(raw_id) {
+                Ok(uuid) if !uuid.is_nil() => Ok(Self::Idfv(uuid)),
+                _ => Err(error()),
+            },
+            DeviceIdKind::InstallId => {
+                let valid_len =
+                    (Self::INSTALL_ID_MIN_LEN..=Self::INSTALL_ID_MAX_LEN).contains(&raw_id.len());
+                let valid_chars = raw_id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+                if valid_len && valid_chars {
+                    Ok(Self::InstallId(raw_id.to_string()))
                 } else {
-                    Ok(DeviceId(uuid))
+                    Err(error())
                 }
             }
-            Err(_) => Err(DeviceIdError(raw_idfa.to_string())),
         }
     }
 
-    pub fn into_inner(self) -> Uuid {
-        self.0
+    pub fn kind(&self) -> DeviceIdKind {
+        match self {
+            Self::Idfa(_) => DeviceIdKind::Idfa,
+            Self::Aaid(_) => DeviceIdKind::Aaid,
+            Self::Idfv(_) => DeviceIdKind::Idfv,
+            Self::InstallId(_) => DeviceIdKind::InstallId,
+            Self::Anonymous(kind) => *kind,
+        }
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        matches!(self, Self::Anonymous(_))
+    }
+
+    /// Input for hashing the device into experiment buckets, `None` for anonymous devices.
+    ///
+    /// IDFA hashes the bare UUID as it always did, so that existing devices keep their
+    /// variants. Other kinds are prefixed with the kind, so that equal values of different kinds
+    /// land in different buckets.
+    pub fn hash_key(&self) -> Option<String> {
+        match self {
+            Self::Idfa(uuid) => Some(uuid.to_string()),
+            Self::Anonymous(_) => None,
+            _ => Some(format!("{}:{}", self.kind(), self)),
+        }
+    }
+}
+
+/// Represents always valid identifier of a user in the application.
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserId(String);
+
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("user id must be from 1 to {max} characters long", max = UserId::MAX_LEN)]
+pub struct UserIdInvalidError;
+impl UserId {
+    const MAX_LEN: usize = 256;
+
+    pub fn new(raw_user_id: &str) -> Result<Self, UserIdInvalidError> {
+        let trimmed = raw_user_id.trim();
+        if trimmed.is_empty() || trimmed.len() > Self::MAX_LEN {
+            Err(UserIdInvalidError)
+        } else {
+            Ok(Self(trimmed.to_string()))
+        }
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// Represents platform of a device. iOS devices are identified by IDFA and Android devices by
+/// Google AAID.
+#[derive(Display, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DevicePlatform {
+    #[display("ios")]
+    Ios,
+    #[display("android")]
+    Android,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid device platform")]
+pub struct DevicePlatformInvalidError(String);
+impl DevicePlatform {
+    pub fn new(raw_platform: &str) -> Result<Self, DevicePlatformInvalidError> {
+        match raw_platform.trim().to_lowercase().as_str() {
+            "ios" => Ok(Self::Ios),
+            "android" => Ok(Self::Android),
+            _ => Err(DevicePlatformInvalidError(raw_platform.to_string())),
+        }
+    }
+}
+
+/// Attributes reported by a device. Blank values are treated as not reported.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceAttributes {
+    platform: Option<DevicePlatform>,
+    os_version: Option<String>,
+    app_version: Option<String>,
+    country: Option<String>,
+    locale: Option<String>,
+    properties: HashMap<String, String>,
+}
+
+impl DeviceAttributes {
+    pub fn new(
+        platform: Option<DevicePlatform>,
+        os_version: Option<String>,
+        app_version: Option<String>,
+        country: Option<String>,
+        locale: Option<String>,
+        properties: HashMap<String, String>,
+    ) -> Self {
+        let non_blank = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        Self {
+            platform,
+            os_version: non_blank(os_version),
+            app_version: non_blank(app_version),
+            country: non_blank(country),
+            locale: non_blank(locale),
+            properties: properties
+                .into_iter()
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .filter(|(k, v)| !k.is_empty() && !v.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn platform(&self) -> Option<DevicePlatform> {
+        self.platform
+    }
+
+    pub fn os_version(&self) -> &Option<String> {
+        &self.os_version
+    }
+
+    pub fn app_version(&self) -> &Option<String> {
+        &self.app_version
+    }
+
+    pub fn country(&self) -> &Option<String> {
+        &self.country
+    }
+
+    pub fn locale(&self) -> &Option<String> {
+        &self.locale
+    }
+
+    /// Free-form attributes reported by the device.
+    pub fn properties(&self) -> &HashMap<String, String> {
+        &self.properties
+    }
+
+    /// Overrides the attributes with the ones reported later, keeping the attributes that were
+    /// not reported again.
+    pub fn merge(&self, newer: &DeviceAttributes) -> DeviceAttributes {
+        let mut properties = self.properties.clone();
+        properties.extend(newer.properties.clone());
+
+        Self {
+            platform: newer.platform.or(self.platform),
+            os_version: newer.os_version.clone().or(self.os_version.clone()),
+            app_version: newer.app_version.clone().or(self.app_version.clone()),
+            country: newer.country.clone().or(self.country.clone()),
+            locale: newer.locale.clone().or(self.locale.clone()),
+            properties,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Device {
     id: DeviceId,
     created_at: DateTime<Utc>,
+    attributes: DeviceAttributes,
+    user_id: Option<UserId>,
 }
 
 impl Device {
     pub fn new(id: DeviceId, created_at: DateTime<Utc>) -> Self {
-        Self { id, created_at }
+        Self {
+            id,
+            created_at,
+            attributes: DeviceAttributes::default(),
+            user_id: None,
+        }
+    }
+
+    pub fn with_attributes(mut self, attributes: DeviceAttributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    pub fn with_user_id(mut self, user_id: Option<UserId>) -> Self {
+        self.user_id = user_id;
+        self
     }
 
     pub fn id(&self) -> &DeviceId {
@@ -48,6 +294,16 @@ impl Device {
     pub fn created_at(&self) -> &DateTime<Utc> {
         &self.created_at
     }
+
+    /// Attributes reported by the device on its visits.
+    pub fn attributes(&self) -> &DeviceAttributes {
+        &self.attributes
+    }
+
+    /// User the device is linked to, `None` until the user logs in on the device.
+    pub fn user_id(&self) -> &Option<UserId> {
+        &self.user_id
+    }
 }
 
 /// Data required by the domain to create a [Device].
@@ -66,6 +322,92 @@ impl CreateDeviceRequest {
     }
 }
 
+/// Data required by the domain to update attributes of a [Device].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpdateDeviceAttributesRequest {
+    id: DeviceId,
+    attributes: DeviceAttributes,
+}
+
+impl UpdateDeviceAttributesRequest {
+    pub fn new(id: DeviceId, attributes: DeviceAttributes) -> Self {
+        Self { id, attributes }
+    }
+
+    pub fn id(&self) -> &DeviceId {
+        &self.id
+    }
+
+    pub fn attributes(&self) -> &DeviceAttributes {
+        &self.attributes
+    }
+}
+
+/// Data required by the domain to register a visit of a [Device], creating the device on its
+/// first visit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterDeviceRequest {
+    id: DeviceId,
+    attributes: DeviceAttributes,
+}
+
+impl RegisterDeviceRequest {
+    pub fn new(id: DeviceId, attributes: DeviceAttributes) -> Self {
+        Self { id, attributes }
+    }
+
+    pub fn id(&self) -> &DeviceId {
+        &self.id
+    }
+
+    pub fn attributes(&self) -> &DeviceAttributes {
+        &self.attributes
+    }
+}
+
+/// A [Device] along with whether it was created by the visit or had visited before.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisteredDevice {
+    device: Device,
+    created: bool,
+}
+
+impl RegisteredDevice {
+    pub fn new(device: Device, created: bool) -> Self {
+        Self { device, created }
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// Whether the device visited for the first time.
+    pub fn created(&self) -> bool {
+        self.created
+    }
+}
+
+/// Data required by the domain to link a [Device] to a user.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkDeviceRequest {
+    id: DeviceId,
+    user_id: UserId,
+}
+
+impl LinkDeviceRequest {
+    pub fn new(id: DeviceId, user_id: UserId) -> Self {
+        Self { id, user_id }
+    }
+
+    pub fn id(&self) -> &DeviceId {
+        &self.id
+    }
+
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum CreateDeviceError {
     #[error("device with id {id} already exists")]
@@ -74,6 +416,14 @@ pub enum CreateDeviceError {
     Unknown(#[from] anyhow::Error),
 }
 
+#[derive(Debug, Error)]
+pub enum RegisterDeviceError {
+    #[error("anonymous device cannot be registered")]
+    Anonymous,
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
 #[derive(Debug, Error)]
 pub enum GetDeviceByIdError {
     #[error("device with id {id} not found")]
@@ -82,6 +432,24 @@ pub enum GetDeviceByIdError {
     Unknown(#[from] anyhow::Error),
 }
 
+#[derive(Debug, Error)]
+pub enum UpdateDeviceAttributesError {
+    #[error("device with id {id} not found")]
+    NotFound { id: DeviceId },
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum LinkDeviceError {
+    #[error("device with id {id} not found")]
+    NotFound { id: DeviceId },
+    #[error("anonymous device cannot be linked to a user")]
+    Anonymous,
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
 #[derive(Debug, Error)]
 pub enum GetAllDevicesError {
     #[error(transparent)]
@@ -95,17 +463,29 @@ mod device_id_tests {
     #[test]
     fn test_new_success() {
         let raw_idfa = "550e8400-e29b-41d4-a716-446655440000";
-        let result = DeviceId::new(raw_idfa);
-        let expected = Ok(DeviceId(Uuid::try_parse(raw_idfa).unwrap()));
+        let result = DeviceId::new(DeviceIdKind::Idfa, raw_idfa);
+        let expected = Ok(DeviceId::Idfa(Uuid::try_parse(raw_idfa).unwrap()));
 
         assert_eq!(result, expected);
     }
 
     #[test]
-    fn test_idfa_is_restricted() {
+    fn test_limited_ad_tracking_is_anonymous() {
         let raw_idfa = "00000000-0000-0000-0000-000000000000";
-        let result = DeviceId::new(raw_idfa);
-        let expected = Err(DeviceIdError(raw_idfa.to_string()));
+        let result = DeviceId::new(DeviceIdKind::Aaid, raw_idfa);
+        let expected = Ok(DeviceId::Anonymous(DeviceIdKind::Aaid));
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_nil_idfv_is_restricted() {
+        let raw_idfv = "00000000-0000-0000-0000-000000000000";
+        let result = DeviceId::new(DeviceIdKind::Idfv, raw_idfv);
+        let expected = Err(DeviceIdError {
+            kind: DeviceIdKind::Idfv,
+            raw: raw_idfv.to_string(),
+        });
 
         assert_eq!(result, expected);
     }
@@ -113,8 +493,93 @@ mod device_id_tests {
     #[test]
     fn test_new_invalid_idfa() {
         let raw_idfa = "abracadabra";
-        let result = DeviceId::new(raw_idfa);
-        let expected = Err(DeviceIdError(raw_idfa.to_string()));
+        let result = DeviceId::new(DeviceIdKind::Idfa, raw_idfa);
+        let expected = Err(DeviceIdError {
+            kind: DeviceIdKind::Idfa,
+            raw: raw_idfa.to_string(),
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_new_install_id() {
+        let valid = DeviceId::new(DeviceIdKind::InstallId, "install-42.abc_DEF");
+        let too_short = DeviceId::new(DeviceIdKind::InstallId, "abc");
+        let invalid_chars = DeviceId::new(DeviceIdKind::InstallId, "install id 42");
+
+        assert_eq!(
+            valid,
+            Ok(DeviceId::InstallId("install-42.abc_DEF".to_string()))
+        );
+        assert!(too_short.is_err());
+        assert!(invalid_chars.is_err());
+    }
+
+    #[test]
+    fn test_hash_key() {
+        let raw_id = "550e8400-e29b-41d4-a716-446655440000";
+        let idfa = DeviceId::new(DeviceIdKind::Idfa, raw_id).unwrap();
+        let aaid = DeviceId::new(DeviceIdKind::Aaid, &raw_id.to_uppercase()).unwrap();
+
+        assert_eq!(idfa.hash_key(), Some(raw_id.to_string()));
+        assert_eq!(aaid.hash_key(), Some(format!("aaid:{}", raw_id)));
+        assert_eq!(DeviceId::Anonymous(DeviceIdKind::Idfa).hash_key(), None);
+    }
+}
+
+#[cfg(test)]
+mod device_attributes_tests {
+    use super::*;
+
+    #[test]
+    fn test_new_platform_success() {
+        let result = DevicePlatform::new("Android");
+        let expected = Ok(DevicePlatform::Android);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_new_platform_invalid() {
+        let raw_platform = "symbian";
+        let result = DevicePlatform::new(raw_platform);
+        let expected = Err(DevicePlatformInvalidError(raw_platform.to_string()));
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_merge_keeps_unreported_attributes() {
+        let stored = DeviceAttributes::new(
+            Some(DevicePlatform::Ios),
+            Some("17.4".to_string()),
+            Some("2.0.0".to_string()),
+            None,
+            Some("en-US".to_string()),
+            HashMap::from([("tier".to_string(), "gold".to_string())]),
+        );
+        let reported = DeviceAttributes::new(
+            None,
+            None,
+            Some("2.1.0".to_string()),
+            Some(" ".to_string()),
+            None,
+            HashMap::from([("cohort".to_string(), "b".to_string())]),
+        );
+
+        let result = stored.merge(&reported);
+        let expected = DeviceAttributes::new(
+            Some(DevicePlatform::Ios),
+            Some("17.4".to_string()),
+            Some("2.1.0".to_string()),
+            None,
+            Some("en-US".to_string()),
+            HashMap::from([
+                ("tier".to_string(), "gold".to_string()),
+                ("cohort".to_string(), "b".to_string()),
+            ]),
+        );
 
         assert_eq!(result, expected);
     }
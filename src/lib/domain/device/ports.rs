@@ -3,7 +3,9 @@ use std::future::Future;
 #[allow(unused_imports)]
 use crate::domain::device::models::device::DeviceId;
 use crate::domain::device::models::device::{
-    CreateDeviceError, CreateDeviceRequest, Device, GetDeviceByIdError,
+    CreateDeviceError, CreateDeviceRequest, Device, GetDeviceByIdError, LinkDeviceError,
+    LinkDeviceRequest, RegisterDeviceError, RegisterDeviceRequest, RegisteredDevice,
+    UpdateDeviceAttributesError, UpdateDeviceAttributesRequest,
 };
 
 /// `DeviceService` is the public API for the device domain.
@@ -12,6 +14,26 @@ pub trait DeviceService: Clone + Send + Sync + 'static {
         &self,
         req: &CreateDeviceRequest,
     ) -> impl Future<Output = Result<Device, CreateDeviceError>> + Send;
+
+    /// Records a visit of a device: creates the device on its first visit and merges the
+    /// reported attributes into the stored ones on every visit. Anonymous devices are rejected.
+    fn register_device(
+        &self,
+        req: &RegisterDeviceRequest,
+    ) -> impl Future<Output = Result<RegisteredDevice, RegisterDeviceError>> + Send;
+
+    /// Merges attributes reported by a device into the stored ones.
+    fn update_device_attributes(
+        &self,
+        req: &UpdateDeviceAttributesRequest,
+    ) -> impl Future<Output = Result<Device, UpdateDeviceAttributesError>> + Send;
+
+    /// Links a device to a user, so that user-bucketed experiments follow the user across
+    /// devices.
+    fn link_device_to_user(
+        &self,
+        req: &LinkDeviceRequest,
+    ) -> impl Future<Output = Result<Device, LinkDeviceError>> + Send;
 }
 
 /// `DeviceRepository` represents a store of device data.
@@ -21,8 +43,29 @@ pub trait DeviceRepository: Send + Sync + Clone + 'static {
         req: &CreateDeviceRequest,
     ) -> impl Future<Output = Result<Device, CreateDeviceError>> + Send;
 
+    /// Creates the device unless it already exists. Repeated calls are harmless.
+    ///
+    /// # Returns
+    /// * `RegisteredDevice` - stored device and whether it was created by the call.
+    fn upsert_device(
+        &self,
+        id: &DeviceId,
+    ) -> impl Future<Output = Result<RegisteredDevice, RegisterDeviceError>> + Send;
+
     fn get_device_by_id(
         &self,
         id: &DeviceId,
     ) -> impl Future<Output = Result<Device, GetDeviceByIdError>> + Send;
+
+    /// Merges attributes reported by a device into the stored ones.
+    fn update_device_attributes(
+        &self,
+        req: &UpdateDeviceAttributesRequest,
+    ) -> impl Future<Output = Result<Device, UpdateDeviceAttributesError>> + Send;
+
+    /// Links a device to a user. Assignments the device already has are kept as they are.
+    fn link_device_to_user(
+        &self,
+        req: &LinkDeviceRequest,
+    ) -> impl Future<Output = Result<Device, LinkDeviceError>> + Send;
 }
@@ -1,5 +1,12 @@
-use crate::domain::device::models::device::CreateDeviceError;
-use crate::domain::device::models::device::{CreateDeviceRequest, Device};
+use anyhow::anyhow;
+
+use crate::domain::device::models::device::{
+    CreateDeviceError, LinkDeviceError, RegisterDeviceError, UpdateDeviceAttributesError,
+};
+use crate::domain::device::models::device::{
+    CreateDeviceRequest, Device, LinkDeviceRequest, RegisterDeviceRequest, RegisteredDevice,
+    UpdateDeviceAttributesRequest,
+};
 use crate::domain::device::ports::{DeviceRepository, DeviceService};
 
 /// Canonical implementation of the [DeviceService] port, through which the device domain API is
@@ -19,4 +26,49 @@ impl<R: DeviceRepository> DeviceService for Service<R> {
     async fn create_device(&self, req: &CreateDeviceRequest) -> Result<Device, CreateDeviceError> {
         self.repo.create_device(req).await
     }
+
+    async fn register_device(
+        &self,
+        req: &RegisterDeviceRequest,
+    ) -> Result<RegisteredDevice, RegisterDeviceError> {
+        // Anonymous devices cannot be told apart, so they are never stored.
+        if req.id().is_anonymous() {
+            return Err(RegisterDeviceError::Anonymous);
+        }
+
+        let registered = self.repo.upsert_device(req.id()).await?;
+
+        // Attributes are updated on every visit, including the first one.
+        let update_attributes_req =
+            UpdateDeviceAttributesRequest::new(req.id().to_owned(), req.attributes().to_owned());
+        let device = self
+            .repo
+            .update_device_attributes(&update_attributes_req)
+            .await
+            .map_err(|e| {
+                RegisterDeviceError::Unknown(
+                    anyhow!(e).context("failed to update device attributes"),
+                )
+            })?;
+
+        Ok(RegisteredDevice::new(device, registered.created()))
+    }
+
+    async fn update_device_attributes(
+        &self,
+        req: &UpdateDeviceAttributesRequest,
+    ) -> Result<Device, UpdateDeviceAttributesError> {
+        self.repo.update_device_attributes(req).await
+    }
+
+    async fn link_device_to_user(
+        &self,
+        req: &LinkDeviceRequest,
+    ) -> Result<Device, LinkDeviceError> {
+        if req.id().is_anonymous() {
+            return Err(LinkDeviceError::Anonymous);
+        }
+
+        self.repo.link_device_to_user(req).await
+    }
 }
@@ -0,0 +1,400 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use derive_more::Display;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::domain::device::models::device::DeviceId;
+use crate::domain::experiment::models::assignment::Assignment;
+use crate::domain::experiment::models::experiment::VariantData;
+
+/// Represents always valid name of an event, e.g. `purchase` or `signup`.
+#[derive(Display, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EventName(String);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("event name must be from 1 to {max} characters long", max = EventName::MAX_LEN)]
+pub struct EventNameInvalidError;
+impl EventName {
+    const MAX_LEN: usize = 128;
+
+    pub fn new(raw_name: &str) -> Result<Self, EventNameInvalidError> {
+        let trimmed = raw_name.trim();
+        if trimmed.is_empty() || trimmed.len() > Self::MAX_LEN {
+            Err(EventNameInvalidError)
+        } else {
+            Ok(Self(trimmed.to_string()))
+        }
+    }
+}
+
+/// Represents always valid numeric value of an event, e.g. revenue of a purchase.
+#[derive(Display, Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct EventValue(f64);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("event value must be a finite number")]
+pub struct EventValueInvalidError;
+impl EventValue {
+    pub fn new(value: f64) -> Result<Self, EventValueInvalidError> {
+        if value.is_finite() {
+            Ok(Self(value))
+        } else {
+            Err(EventValueInvalidError)
+        }
+    }
+
+    pub fn into_inner(self) -> f64 {
+        self.0
+    }
+}
+
+/// Represents the variant of an experiment a device was exposed to when an event happened.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventAttribution {
+    experiment_id: Uuid,
+    data: VariantData,
+    version: u32,
+}
+
+impl EventAttribution {
+    pub fn new(experiment_id: Uuid, data: VariantData, version: u32) -> Self {
+        Self {
+            experiment_id,
+            data,
+            version,
+        }
+    }
+
+    pub fn experiment_id(&self) -> &Uuid {
+        &self.experiment_id
+    }
+
+    pub fn data(&self) -> &VariantData {
+        &self.data
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+/// Attributes an event to the variants a device had been assigned by the time the event
+/// occurred. Assignments made after the event do not take credit for it.
+pub fn attribute_event(
+    occurred_at: &DateTime<Utc>,
+    assignments: &[Assignment],
+) -> Vec<EventAttribution> {
+    assignments
+        .iter()
+        .filter(|a| a.assigned_at() <= occurred_at)
+        .map(|a| EventAttribution::new(*a.experiment_id(), a.data().to_owned(), a.version()))
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    id: Uuid,
+    device_id: DeviceId,
+    name: EventName,
+    value: Option<EventValue>,
+    occurred_at: DateTime<Utc>,
+    attributions: Vec<EventAttribution>,
+}
+
+impl Event {
+    pub fn new(
+        id: Uuid,
+        device_id: DeviceId,
+        name: EventName,
+        value: Option<EventValue>,
+        occurred_at: DateTime<Utc>,
+        attributions: Vec<EventAttribution>,
+    ) -> Self {
+        Self {
+            id,
+            device_id,
+            name,
+            value,
+            occurred_at,
+            attributions,
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    pub fn name(&self) -> &EventName {
+        &self.name
+    }
+
+    pub fn value(&self) -> Option<EventValue> {
+        self.value
+    }
+
+    pub fn occurred_at(&self) -> &DateTime<Utc> {
+        &self.occurred_at
+    }
+
+    /// Variants the device had been assigned when the event occurred.
+    pub fn attributions(&self) -> &Vec<EventAttribution> {
+        &self.attributions
+    }
+}
+
+/// Data required by the domain to record a single [Event].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateEventRequest {
+    name: EventName,
+    value: Option<EventValue>,
+    occurred_at: Option<DateTime<Utc>>,
+}
+
+impl CreateEventRequest {
+    /// How long before its ingestion an event may have occurred, e.g. while the device was
+    /// offline.
+    const MAX_DELAY: TimeDelta = TimeDelta::hours(24);
+    /// How far ahead of the server the clock of a device may run.
+    const MAX_CLOCK_SKEW: TimeDelta = TimeDelta::minutes(5);
+
+    pub fn new(
+        name: EventName,
+        value: Option<EventValue>,
+        occurred_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            name,
+            value,
+            occurred_at,
+        }
+    }
+
+    pub fn name(&self) -> &EventName {
+        &self.name
+    }
+
+    pub fn value(&self) -> Option<EventValue> {
+        self.value
+    }
+
+    /// Time the event occurred on the device, the time of ingestion is used when `None`.
+    pub fn occurred_at(&self) -> &Option<DateTime<Utc>> {
+        &self.occurred_at
+    }
+
+    /// Resolves the time the event occurred given the time of its ingestion. Device clocks are
+    /// not trusted beyond a small window around the time of ingestion, so that events cannot be
+    /// backdated into earlier periods of experiments or dated into the future.
+    pub fn occurred_at_or(
+        &self,
+        received_at: DateTime<Utc>,
+    ) -> Result<DateTime<Utc>, EventTimestampInvalidError> {
+        let Some(occurred_at) = self.occurred_at else {
+            return Ok(received_at);
+        };
+
+        if occurred_at < received_at - Self::MAX_DELAY
+            || occurred_at > received_at + Self::MAX_CLOCK_SKEW
+        {
+            Err(EventTimestampInvalidError {
+                occurred_at,
+                received_at,
+            })
+        } else {
+            Ok(occurred_at)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error(
+    "event timestamp {occurred_at} must be at most {max_delay} hours before and {max_skew} minutes after {received_at}",
+    max_delay = CreateEventRequest::MAX_DELAY.num_hours(),
+    max_skew = CreateEventRequest::MAX_CLOCK_SKEW.num_minutes()
+)]
+pub struct EventTimestampInvalidError {
+    pub occurred_at: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Data required by the domain to record a batch of [Event]s of a single device.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateEventsRequest {
+    device_id: DeviceId,
+    events: Vec<CreateEventRequest>,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("events batch must contain from 1 to {max} events", max = CreateEventsRequest::MAX_BATCH_SIZE)]
+pub struct EventsBatchInvalidError;
+impl CreateEventsRequest {
+    const MAX_BATCH_SIZE: usize = 1000;
+
+    pub fn new(
+        device_id: DeviceId,
+        events: Vec<CreateEventRequest>,
+    ) -> Result<Self, EventsBatchInvalidError> {
+        if events.is_empty() || events.len() > Self::MAX_BATCH_SIZE {
+            Err(EventsBatchInvalidError)
+        } else {
+            Ok(Self { device_id, events })
+        }
+    }
+
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    pub fn events(&self) -> &Vec<CreateEventRequest> {
+        &self.events
+    }
+}
+
+/// Data required by the repository to persist an attributed [Event].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveEventRequest {
+    device_id: DeviceId,
+    name: EventName,
+    value: Option<EventValue>,
+    occurred_at: DateTime<Utc>,
+    attributions: Vec<EventAttribution>,
+}
+
+impl SaveEventRequest {
+    pub fn new(
+        device_id: DeviceId,
+        name: EventName,
+        value: Option<EventValue>,
+        occurred_at: DateTime<Utc>,
+        attributions: Vec<EventAttribution>,
+    ) -> Self {
+        Self {
+            device_id,
+            name,
+            value,
+            occurred_at,
+            attributions,
+        }
+    }
+
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    pub fn name(&self) -> &EventName {
+        &self.name
+    }
+
+    pub fn value(&self) -> Option<EventValue> {
+        self.value
+    }
+
+    pub fn occurred_at(&self) -> &DateTime<Utc> {
+        &self.occurred_at
+    }
+
+    pub fn attributions(&self) -> &Vec<EventAttribution> {
+        &self.attributions
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CreateEventsError {
+    #[error("device with id {id} not found")]
+    DeviceNotFound { id: DeviceId },
+    #[error("events of an anonymous device cannot be recorded")]
+    Anonymous,
+    #[error(transparent)]
+    Timestamp(#[from] EventTimestampInvalidError),
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[cfg(test)]
+mod event_tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::domain::device::models::device::DeviceIdKind;
+
+    #[test]
+    fn test_new_value_is_invalid() {
+        let result = EventValue::new(f64::NAN);
+        let expected = Err(EventValueInvalidError);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_new_batch_is_empty() {
+        let device_id =
+            DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let result = CreateEventsRequest::new(device_id, vec![]);
+        let expected = Err(EventsBatchInvalidError);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_occurred_at_or() {
+        let received_at = Utc::now();
+        let event = |occurred_at: Option<DateTime<Utc>>| {
+            CreateEventRequest::new(EventName::new("purchase").unwrap(), None, occurred_at)
+        };
+
+        assert_eq!(event(None).occurred_at_or(received_at), Ok(received_at));
+
+        let offline = received_at - TimeDelta::hours(3);
+        assert_eq!(
+            event(Some(offline)).occurred_at_or(received_at),
+            Ok(offline)
+        );
+
+        for occurred_at in [
+            received_at - TimeDelta::days(30),
+            received_at + TimeDelta::hours(1),
+        ] {
+            assert_eq!(
+                event(Some(occurred_at)).occurred_at_or(received_at),
+                Err(EventTimestampInvalidError {
+                    occurred_at,
+                    received_at
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_attribute_event_ignores_later_assignments() {
+        let device_id =
+            DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let occurred_at = Utc::now();
+        let earlier = Assignment::new(
+            device_id.clone(),
+            Uuid::new_v4(),
+            VariantData::new("blue").unwrap(),
+            1,
+            occurred_at - TimeDelta::minutes(5),
+        );
+        let later = Assignment::new(
+            device_id,
+            Uuid::new_v4(),
+            VariantData::new("red").unwrap(),
+            2,
+            occurred_at + TimeDelta::minutes(5),
+        );
+
+        let result = attribute_event(&occurred_at, &[earlier.clone(), later]);
+        let expected = vec![EventAttribution::new(
+            *earlier.experiment_id(),
+            earlier.data().to_owned(),
+            1,
+        )];
+
+        assert_eq!(result, expected);
+    }
+}
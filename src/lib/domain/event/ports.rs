@@ -0,0 +1,32 @@
+use std::future::Future;
+
+use crate::domain::device::models::device::DeviceId;
+use crate::domain::event::models::event::{
+    CreateEventsError, CreateEventsRequest, Event, SaveEventRequest,
+};
+use crate::domain::experiment::models::assignment::{Assignment, GetAllAssignmentsError};
+
+/// `EventService` is the public API for the event domain.
+pub trait EventService: Clone + Send + Sync + 'static {
+    /// Records a batch of events of a device, attributing each of them to the variants the
+    /// device had been assigned when the event occurred.
+    fn create_events(
+        &self,
+        req: &CreateEventsRequest,
+    ) -> impl Future<Output = Result<Vec<Event>, CreateEventsError>> + Send;
+}
+
+/// `EventRepository` represents a store of event data.
+pub trait EventRepository: Send + Sync + Clone + 'static {
+    fn get_device_assignments(
+        &self,
+        id: &DeviceId,
+    ) -> impl Future<Output = Result<Vec<Assignment>, GetAllAssignmentsError>> + Send;
+
+    /// Saves a batch of events of a single device atomically.
+    fn save_events(
+        &self,
+        id: &DeviceId,
+        reqs: &[SaveEventRequest],
+    ) -> impl Future<Output = Result<Vec<Event>, CreateEventsError>> + Send;
+}
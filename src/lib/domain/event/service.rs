@@ -0,0 +1,58 @@
+use anyhow::anyhow;
+use chrono::Utc;
+
+use crate::domain::event::models::event::{
+    CreateEventsError, CreateEventsRequest, Event, SaveEventRequest, attribute_event,
+};
+use crate::domain::event::ports::{EventRepository, EventService};
+
+/// Canonical implementation of the [EventService] port, through which the event domain API is
+/// consumed.
+#[derive(Debug, Clone)]
+pub struct Service<R: EventRepository> {
+    repo: R,
+}
+
+impl<R: EventRepository> Service<R> {
+    pub fn new(repo: R) -> Self {
+        Self { repo }
+    }
+}
+
+impl<R: EventRepository> EventService for Service<R> {
+    async fn create_events(
+        &self,
+        req: &CreateEventsRequest,
+    ) -> Result<Vec<Event>, CreateEventsError> {
+        if req.device_id().is_anonymous() {
+            return Err(CreateEventsError::Anonymous);
+        }
+
+        let assignments = self
+            .repo
+            .get_device_assignments(req.device_id())
+            .await
+            .map_err(|e| {
+                CreateEventsError::Unknown(anyhow!(e).context("failed to get device assignments"))
+            })?;
+
+        let received_at = Utc::now();
+        let reqs: Vec<SaveEventRequest> = req
+            .events()
+            .iter()
+            .map(|event| {
+                let occurred_at = event.occurred_at_or(received_at)?;
+
+                Ok(SaveEventRequest::new(
+                    req.device_id().to_owned(),
+                    event.name().to_owned(),
+                    event.value(),
+                    occurred_at,
+                    attribute_event(&occurred_at, &assignments),
+                ))
+            })
+            .collect::<Result<_, CreateEventsError>>()?;
+
+        self.repo.save_events(req.device_id(), &reqs).await
+    }
+}
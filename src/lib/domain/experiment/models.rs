@@ -1 +1,9 @@
+pub mod assignment;
 pub mod experiment;
+pub mod guardrail;
+pub mod layer;
+pub mod metric;
+pub mod sample_size;
+pub mod segment;
+pub mod targeting;
+pub mod timeseries;
@@ -0,0 +1,185 @@
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::domain::device::models::device::{DeviceId, DeviceIdError};
+use crate::domain::experiment::models::experiment::{VariantData, VariantDataEmptyError};
+
+/// Represents the first exposure of a device to a variant of an experiment.
+///
+/// Assignments are persisted once and never recomputed, so that changes in the distribution or
+/// the hashing of an experiment cannot rewrite the history of already exposed devices.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assignment {
+    device_id: DeviceId,
+    experiment_id: Uuid,
+    data: VariantData,
+    version: u32,
+    assigned_at: DateTime<Utc>,
+}
+
+impl Assignment {
+    pub fn new(
+        device_id: DeviceId,
+        experiment_id: Uuid,
+        data: VariantData,
+        version: u32,
+        assigned_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            device_id,
+            experiment_id,
+            data,
+            version,
+            assigned_at,
+        }
+    }
+
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    pub fn experiment_id(&self) -> &Uuid {
+        &self.experiment_id
+    }
+
+    pub fn data(&self) -> &VariantData {
+        &self.data
+    }
+
+    /// Version of the experiment the variant was assigned under.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn assigned_at(&self) -> &DateTime<Utc> {
+        &self.assigned_at
+    }
+}
+
+/// Picks the assignment that wins when devices of one user hold conflicting assignments to a
+/// user-bucketed experiment: the earliest exposure wins, so that the user keeps the variant seen
+/// first. Ties are broken by device identifier to keep the choice deterministic.
+pub fn winning_assignment<'a>(
+    assignments: impl IntoIterator<Item = &'a Assignment>,
+) -> Option<&'a Assignment> {
+    assignments.into_iter().min_by(|a, b| {
+        a.assigned_at()
+            .cmp(b.assigned_at())
+            .then_with(|| a.device_id().to_string().cmp(&b.device_id().to_string()))
+    })
+}
+
+/// Data required by the domain to record an [Assignment].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateAssignmentRequest {
+    device_id: DeviceId,
+    experiment_id: Uuid,
+    data: VariantData,
+    version: u32,
+    assigned_at: Option<DateTime<Utc>>,
+}
+
+impl CreateAssignmentRequest {
+    pub fn new(device_id: DeviceId, experiment_id: Uuid, data: VariantData, version: u32) -> Self {
+        Self {
+            device_id,
+            experiment_id,
+            data,
+            version,
+            assigned_at: None,
+        }
+    }
+
+    /// Records an exposure that happened in the past rather than now.
+    pub fn with_assigned_at(self, assigned_at: DateTime<Utc>) -> Self {
+        Self {
+            assigned_at: Some(assigned_at),
+            ..self
+        }
+    }
+
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    pub fn experiment_id(&self) -> &Uuid {
+        &self.experiment_id
+    }
+
+    pub fn data(&self) -> &VariantData {
+        &self.data
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn assigned_at(&self) -> Option<&DateTime<Utc>> {
+        self.assigned_at.as_ref()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CreateAssignmentsError {
+    #[error(transparent)]
+    VariantData(#[from] VariantDataEmptyError),
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum BackfillAssignmentsError {
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum GetAllAssignmentsError {
+    #[error(transparent)]
+    DeviceId(#[from] DeviceIdError),
+    #[error(transparent)]
+    VariantData(#[from] VariantDataEmptyError),
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[cfg(test)]
+mod winning_assignment_tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::domain::device::models::device::DeviceIdKind;
+
+    fn assignment(raw_device_id: &str, data: &str, assigned_at: DateTime<Utc>) -> Assignment {
+        Assignment::new(
+            DeviceId::new(DeviceIdKind::Idfa, raw_device_id).unwrap(),
+            Uuid::nil(),
+            VariantData::new(data).unwrap(),
+            1,
+            assigned_at,
+        )
+    }
+
+    #[test]
+    fn test_earliest_assignment_wins() {
+        let now = Utc::now();
+        let phone = assignment("550e8400-e29b-41d4-a716-446655440000", "red", now);
+        let tablet = assignment(
+            "6ba7b810-9dad-11d1-80b4-00c04fd430c8",
+            "blue",
+            now - TimeDelta::hours(1),
+        );
+
+        let result = winning_assignment([&phone, &tablet]);
+
+        assert_eq!(result, Some(&tablet));
+    }
+
+    #[test]
+    fn test_no_assignments() {
+        let result = winning_assignment([]);
+
+        assert_eq!(result, None);
+    }
+}
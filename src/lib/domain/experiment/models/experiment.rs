@@ -4,6 +4,28 @@ use sha2::{Digest, Sha256};
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::domain::device::models::device::Device;
+use crate::domain::event::models::event::EventNameInvalidError;
+use crate::domain::experiment::models::assignment::Assignment;
+use crate::domain::experiment::models::guardrail::{
+    DegradationDirectionInvalidError, ExperimentGuardrails, ExperimentGuardrailsInvalidError,
+    GuardrailActionInvalidError, MaxDegradationInvalidError,
+};
+use crate::domain::experiment::models::layer::{
+    ExperimentLayer, LAYER_BUCKETS, LayerBucketsInvalidError, LayerName, LayerNameEmptyError,
+};
+use crate::domain::experiment::models::metric::{
+    ExperimentMetrics, ExperimentMetricsInvalidError, MetricName, MetricNameEmptyError,
+    MetricRoleInvalidError, StatisticsMetric,
+};
+use crate::domain::experiment::models::segment::StatisticsSegment;
+use crate::domain::experiment::models::targeting::{
+    SemverRangeInvalidError, TargetingAttributeEmptyError, TargetingAttributes,
+    TargetingInListEmptyError, TargetingRules,
+};
+use crate::domain::statistics::frequentist::{GoodnessOfFitTest, SignificanceLevel};
+use crate::domain::statistics::sequential::MixingDeviationInvalidError;
+
 /// Represents always valid experiment name.
 #[derive(Display, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ExperimentName(String);
@@ -22,6 +44,26 @@ impl ExperimentName {
     }
 }
 
+/// Represents always valid experiment salt.
+///
+/// The salt is hashed together with the device identifier, so that experiments with the same split
+/// do not put the same devices into the same buckets.
+#[derive(Display, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ExperimentSalt(String);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("experiment salt cannot be empty")]
+pub struct ExperimentSaltEmptyError;
+impl ExperimentSalt {
+    pub fn new(raw_salt: &str) -> Result<Self, ExperimentSaltEmptyError> {
+        if raw_salt.is_empty() {
+            Err(ExperimentSaltEmptyError)
+        } else {
+            Ok(Self(raw_salt.to_string()))
+        }
+    }
+}
+
 /// Represents always valid variant distribution.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct VariantDistribution(f64);
@@ -86,6 +128,7 @@ pub struct StatisticsVariant {
     data: VariantData,
     total_devices: usize,
     percentage_devices: f64,
+    metrics: Vec<StatisticsMetric>,
 }
 
 impl StatisticsVariant {
@@ -94,9 +137,15 @@ impl StatisticsVariant {
             data,
             total_devices,
             percentage_devices,
+            metrics: Vec::new(),
         }
     }
 
+    pub fn with_metrics(mut self, metrics: Vec<StatisticsMetric>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
     pub fn data(&self) -> &VariantData {
         &self.data
     }
@@ -108,6 +157,11 @@ impl StatisticsVariant {
     pub fn percentage_devices(&self) -> f64 {
         self.percentage_devices
     }
+
+    /// Values of the metrics of the experiment for the variant.
+    pub fn metrics(&self) -> &Vec<StatisticsMetric> {
+        &self.metrics
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -127,6 +181,10 @@ impl StatisticsVariants {
 #[derive(Clone, Debug, PartialEq)]
 pub struct ExperimentVariants(Vec<Variant>);
 
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("control {0} is not a variant of the experiment")]
+pub struct ExperimentControlInvalidError(VariantData);
+
 #[derive(Clone, Debug, Error, PartialEq)]
 #[error("sum of distributions is not equal to 100")]
 pub struct DistributionSumError;
@@ -145,6 +203,10 @@ impl ExperimentVariants {
         &self.0
     }
 
+    pub fn contains(&self, data: &VariantData) -> bool {
+        self.0.iter().any(|v| v.data() == data)
+    }
+
     /// Validates that the sum of distribution array elements equals 100% within a specified tolerance.
     ///
     /// # Arguments
@@ -180,15 +242,7 @@ impl ExperimentVariants {
     /// # Returns
     /// * `&VariantData` - reference to the assigned variant.
     pub fn assign_variant(&self, hash_input: &str) -> &VariantData {
-        let mut hasher = Sha256::new();
-        hasher.update(hash_input.as_bytes());
-        let hash_result = hasher.finalize();
-
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&hash_result[0..8]);
-        let hash_value = u64::from_be_bytes(bytes);
-
-        let normalized = (hash_value as f64 / u64::MAX as f64) * 100.0;
+        let normalized = hash_percentage(hash_input);
 
         let mut cumulative = 0.0;
         for variant in &self.0 {
@@ -202,42 +256,230 @@ impl ExperimentVariants {
     }
 }
 
+/// Maps hash input onto the `[0, 100)` range.
+///
+/// # Arguments
+/// * `hash_input` - input string used to generate a hash.
+///
+/// # Returns
+/// * `f64` - position of the input in percentages.
+fn hash_percentage(hash_input: &str) -> f64 {
+    let mut hasher = Sha256::new();
+    hasher.update(hash_input.as_bytes());
+    let hash_result = hasher.finalize();
+
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash_result[0..8]);
+    let hash_value = u64::from_be_bytes(bytes);
+
+    (hash_value as f64 / u64::MAX as f64) * 100.0
+}
+
+/// Represents always valid share of devices exposed to an experiment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExperimentAllocation(f64);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("experiment allocation should be more than zero and less than or equal to 100")]
+pub struct ExperimentAllocationInvalidError;
+impl ExperimentAllocation {
+    /// Allocation exposing an experiment to every eligible device.
+    pub const FULL: Self = Self(100.0);
+
+    pub fn new(value: f64) -> Result<Self, ExperimentAllocationInvalidError> {
+        if value <= 0.0 || value > 100.0 {
+            Err(ExperimentAllocationInvalidError)
+        } else {
+            Ok(Self(value))
+        }
+    }
+
+    pub fn into_inner(self) -> f64 {
+        self.0
+    }
+}
+
+/// Represents lifecycle status of an experiment.
+///
+/// Allowed transitions are draft → running, running ↔ paused, running/paused → finished and
+/// finished → archived.
+#[derive(Display, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExperimentStatus {
+    #[display("draft")]
+    Draft,
+    #[display("running")]
+    Running,
+    #[display("paused")]
+    Paused,
+    #[display("finished")]
+    Finished,
+    #[display("archived")]
+    Archived,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid experiment status")]
+pub struct ExperimentStatusInvalidError(String);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("experiment can only be created as draft or running")]
+pub struct ExperimentInitialStatusError;
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("experiment status cannot change from {from} to {to}")]
+pub struct ExperimentStatusTransitionError {
+    pub from: ExperimentStatus,
+    pub to: ExperimentStatus,
+}
+
+impl ExperimentStatus {
+    pub fn new(raw_status: &str) -> Result<Self, ExperimentStatusInvalidError> {
+        match raw_status {
+            "draft" => Ok(Self::Draft),
+            "running" => Ok(Self::Running),
+            "paused" => Ok(Self::Paused),
+            "finished" => Ok(Self::Finished),
+            "archived" => Ok(Self::Archived),
+            _ => Err(ExperimentStatusInvalidError(raw_status.to_string())),
+        }
+    }
+
+    /// Validates that an experiment can be created with the status.
+    pub fn initial(self) -> Result<Self, ExperimentInitialStatusError> {
+        match self {
+            Self::Draft | Self::Running => Ok(self),
+            _ => Err(ExperimentInitialStatusError),
+        }
+    }
+
+    /// Whether name and variants of an experiment can be edited in the status.
+    pub fn is_editable(self) -> bool {
+        matches!(self, Self::Draft | Self::Running)
+    }
+
+    /// Moves the status to the next one if the transition is allowed.
+    ///
+    /// # Returns
+    /// * `Ok(ExperimentStatus)` with the next status if the transition is allowed.
+    /// * `Err(ExperimentStatusTransitionError)` otherwise.
+    pub fn transition_to(self, next: Self) -> Result<Self, ExperimentStatusTransitionError> {
+        use ExperimentStatus::*;
+
+        match (self, next) {
+            (Draft, Running)
+            | (Running, Paused)
+            | (Paused, Running)
+            | (Running, Finished)
+            | (Paused, Finished)
+            | (Finished, Archived) => Ok(next),
+            _ => Err(ExperimentStatusTransitionError {
+                from: self,
+                to: next,
+            }),
+        }
+    }
+}
+
+/// Represents always valid schedule of an experiment: the moments it is planned to start and
+/// end at.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExperimentSchedule {
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("experiment should be scheduled to start before it ends")]
+pub struct ExperimentScheduleInvalidError;
+impl ExperimentSchedule {
+    pub fn new(
+        starts_at: Option<DateTime<Utc>>,
+        ends_at: Option<DateTime<Utc>>,
+    ) -> Result<Self, ExperimentScheduleInvalidError> {
+        match (starts_at, ends_at) {
+            (Some(starts_at), Some(ends_at)) if starts_at >= ends_at => {
+                Err(ExperimentScheduleInvalidError)
+            }
+            _ => Ok(Self { starts_at, ends_at }),
+        }
+    }
+
+    /// Planned start, `None` when the experiment starts once it is running.
+    pub fn starts_at(&self) -> Option<&DateTime<Utc>> {
+        self.starts_at.as_ref()
+    }
+
+    /// Planned end, `None` when the experiment runs until it is finished.
+    pub fn ends_at(&self) -> Option<&DateTime<Utc>> {
+        self.ends_at.as_ref()
+    }
+
+    pub fn has_started(&self, now: &DateTime<Utc>) -> bool {
+        self.starts_at.is_none_or(|starts_at| starts_at <= *now)
+    }
+
+    pub fn has_ended(&self, now: &DateTime<Utc>) -> bool {
+        self.ends_at.is_some_and(|ends_at| ends_at <= *now)
+    }
+}
+
+/// Represents lifecycle of an experiment: its status and the moments it went through.
 #[derive(Clone, Debug, PartialEq)]
-pub struct Experiment {
-    id: Uuid,
-    name: ExperimentName,
-    variants: ExperimentVariants,
+pub struct ExperimentLifecycle {
+    status: ExperimentStatus,
     created_at: DateTime<Utc>,
     finished_at: Option<DateTime<Utc>>,
+    schedule: ExperimentSchedule,
 }
 
-impl Experiment {
+impl ExperimentLifecycle {
     pub fn new(
-        id: Uuid,
-        name: ExperimentName,
-        variants: ExperimentVariants,
+        status: ExperimentStatus,
         created_at: DateTime<Utc>,
         finished_at: Option<DateTime<Utc>>,
     ) -> Self {
         Self {
-            id,
-            name,
-            variants,
+            status,
             created_at,
             finished_at,
+            schedule: ExperimentSchedule::default(),
         }
     }
 
-    pub fn id(&self) -> &Uuid {
-        &self.id
+    pub fn with_schedule(mut self, schedule: ExperimentSchedule) -> Self {
+        self.schedule = schedule;
+        self
     }
 
-    pub fn name(&self) -> &ExperimentName {
-        &self.name
+    pub fn schedule(&self) -> &ExperimentSchedule {
+        &self.schedule
     }
 
-    pub fn variants(&self) -> &ExperimentVariants {
-        &self.variants
+    /// Status the experiment should be in at the given moment according to its schedule: a
+    /// draft scheduled to start is running once the start has passed, and a running or paused
+    /// experiment is finished once the end has passed.
+    pub fn scheduled_status(&self, now: &DateTime<Utc>) -> ExperimentStatus {
+        let started = match self.status {
+            ExperimentStatus::Draft
+                if self.schedule.starts_at.is_some() && self.schedule.has_started(now) =>
+            {
+                ExperimentStatus::Running
+            }
+            status => status,
+        };
+
+        match started {
+            ExperimentStatus::Running | ExperimentStatus::Paused
+                if self.schedule.has_ended(now) =>
+            {
+                ExperimentStatus::Finished
+            }
+            status => status,
+        }
+    }
+
+    pub fn status(&self) -> ExperimentStatus {
+        self.status
     }
 
     pub fn created_at(&self) -> &DateTime<Utc> {
@@ -249,81 +491,194 @@ impl Experiment {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct DeviceExperiment {
-    id: Uuid,
-    name: ExperimentName,
-    data: VariantData,
+/// Represents what an experiment buckets into variants.
+#[derive(Display, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ExperimentBucketing {
+    /// Every device is bucketed on its own.
+    #[default]
+    #[display("device")]
+    Device,
+    /// Devices linked to a user are bucketed by the user, so that the user sees the same variant
+    /// on all of them. Devices without a user are bucketed on their own.
+    #[display("user")]
+    User,
 }
 
-impl DeviceExperiment {
-    pub fn new(id: Uuid, name: ExperimentName, data: VariantData) -> Self {
-        Self { id, name, data }
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid experiment bucketing")]
+pub struct ExperimentBucketingInvalidError(String);
+impl ExperimentBucketing {
+    pub fn new(raw_bucketing: &str) -> Result<Self, ExperimentBucketingInvalidError> {
+        match raw_bucketing {
+            "device" => Ok(Self::Device),
+            "user" => Ok(Self::User),
+            _ => Err(ExperimentBucketingInvalidError(raw_bucketing.to_string())),
+        }
     }
+}
 
-    pub fn id(&self) -> &Uuid {
-        &self.id
+/// Represents how the results of an experiment are going to be read, chosen before it starts.
+#[derive(Display, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AnalysisPlan {
+    /// Results are read once, after the planned sample size is reached.
+    #[default]
+    #[display("fixed")]
+    Fixed,
+    /// Results are monitored continuously and the experiment may be stopped as soon as they are
+    /// significant, which requires always valid p-values and confidence intervals.
+    #[display("sequential")]
+    Sequential,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid analysis plan")]
+pub struct AnalysisPlanInvalidError(String);
+impl AnalysisPlan {
+    pub fn new(raw_plan: &str) -> Result<Self, AnalysisPlanInvalidError> {
+        match raw_plan {
+            "fixed" => Ok(Self::Fixed),
+            "sequential" => Ok(Self::Sequential),
+            _ => Err(AnalysisPlanInvalidError(raw_plan.to_string())),
+        }
     }
+}
 
-    pub fn name(&self) -> &ExperimentName {
-        &self.name
+/// Represents which devices may be enrolled into an experiment, by when they were first seen
+/// relative to the creation of the experiment.
+#[derive(Display, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ExperimentEligibility {
+    /// Only devices seen before the experiment was created, so that devices are not moved into
+    /// experiments mid-session.
+    #[default]
+    #[display("existing")]
+    Existing,
+    /// Only devices first seen after the experiment was created, e.g. to test onboarding.
+    #[display("new")]
+    New,
+    #[display("all")]
+    All,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid experiment eligibility")]
+pub struct ExperimentEligibilityInvalidError(String);
+impl ExperimentEligibility {
+    pub fn new(raw_eligibility: &str) -> Result<Self, ExperimentEligibilityInvalidError> {
+        match raw_eligibility {
+            "existing" => Ok(Self::Existing),
+            "new" => Ok(Self::New),
+            "all" => Ok(Self::All),
+            _ => Err(ExperimentEligibilityInvalidError(
+                raw_eligibility.to_string(),
+            )),
+        }
     }
 
-    pub fn data(&self) -> &VariantData {
-        &self.data
+    /// Whether a device created at the given time may be enrolled into an experiment started at
+    /// the given time.
+    pub fn admits(
+        &self,
+        experiment_started_at: &DateTime<Utc>,
+        device_created_at: &DateTime<Utc>,
+    ) -> bool {
+        match self {
+            Self::Existing => device_created_at <= experiment_started_at,
+            Self::New => device_created_at > experiment_started_at,
+            Self::All => true,
+        }
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct StaticticsExperiment {
+pub struct Experiment {
     id: Uuid,
     name: ExperimentName,
-    total_devices: usize,
-    variants: StatisticsVariants,
+    variants: ExperimentVariants,
+    version: u32,
+    salt: Option<ExperimentSalt>,
+    allocation: ExperimentAllocation,
+    lifecycle: ExperimentLifecycle,
+    targeting: TargetingRules,
+    bucketing: ExperimentBucketing,
+    metrics: ExperimentMetrics,
+    control: Option<VariantData>,
+    analysis_plan: AnalysisPlan,
+    eligibility: ExperimentEligibility,
+    guardrails: ExperimentGuardrails,
+    layer: Option<ExperimentLayer>,
 }
 
-impl StaticticsExperiment {
+impl Experiment {
     pub fn new(
         id: Uuid,
         name: ExperimentName,
-        total_devices: usize,
-        variants: StatisticsVariants,
+        variants: ExperimentVariants,
+        version: u32,
+        salt: Option<ExperimentSalt>,
+        allocation: ExperimentAllocation,
+        lifecycle: ExperimentLifecycle,
     ) -> Self {
         Self {
             id,
             name,
-            total_devices,
             variants,
+            version,
+            salt,
+            allocation,
+            lifecycle,
+            targeting: TargetingRules::default(),
+            bucketing: ExperimentBucketing::default(),
+            metrics: ExperimentMetrics::default(),
+            control: None,
+            analysis_plan: AnalysisPlan::default(),
+            eligibility: ExperimentEligibility::default(),
+            guardrails: ExperimentGuardrails::default(),
+            layer: None,
         }
     }
 
-    pub fn id(&self) -> &Uuid {
-        &self.id
+    pub fn with_targeting(mut self, targeting: TargetingRules) -> Self {
+        self.targeting = targeting;
+        self
     }
 
-    pub fn name(&self) -> &ExperimentName {
-        &self.name
+    pub fn with_bucketing(mut self, bucketing: ExperimentBucketing) -> Self {
+        self.bucketing = bucketing;
+        self
     }
 
-    pub fn total_devices(&self) -> usize {
-        self.total_devices
+    pub fn with_metrics(mut self, metrics: ExperimentMetrics) -> Self {
+        self.metrics = metrics;
+        self
     }
 
-    pub fn variants(&self) -> &StatisticsVariants {
-        &self.variants
+    pub fn with_control(mut self, control: VariantData) -> Self {
+        self.control = Some(control);
+        self
     }
-}
 
-/// Data required by the domain to create an [Experiment].
-#[derive(Clone, Debug, From)]
-pub struct CreateExperimentRequest {
-    name: ExperimentName,
-    variants: ExperimentVariants,
-}
+    pub fn with_analysis_plan(mut self, analysis_plan: AnalysisPlan) -> Self {
+        self.analysis_plan = analysis_plan;
+        self
+    }
 
-impl CreateExperimentRequest {
-    pub fn new(name: ExperimentName, variants: ExperimentVariants) -> Self {
-        Self { name, variants }
+    pub fn with_eligibility(mut self, eligibility: ExperimentEligibility) -> Self {
+        self.eligibility = eligibility;
+        self
+    }
+
+    pub fn with_guardrails(mut self, guardrails: ExperimentGuardrails) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: ExperimentLayer) -> Self {
+        self.layer = Some(layer);
+        self
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
     }
 
     pub fn name(&self) -> &ExperimentName {
@@ -333,106 +688,1012 @@ impl CreateExperimentRequest {
     pub fn variants(&self) -> &ExperimentVariants {
         &self.variants
     }
-}
 
-#[derive(Debug, Error)]
-pub enum CreateExperimentError {
-    #[error("experiment with name {name} already exists")]
-    Duplicate { name: ExperimentName },
-    #[error(transparent)]
-    Unknown(#[from] anyhow::Error),
-}
+    /// Version of the experiment, incremented on every edit starting from 1.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
 
-#[derive(Debug, Error)]
-pub enum FinishExperimentError {
-    #[error("experiment with id {id} does not exist")]
-    NotFound { id: Uuid },
-    #[error("experiment with id {id} is already finished")]
-    Finished { id: Uuid },
-    #[error(transparent)]
-    Unknown(#[from] anyhow::Error),
-}
+    /// Salt of the experiment, `None` for legacy experiments created before salting was
+    /// introduced.
+    pub fn salt(&self) -> &Option<ExperimentSalt> {
+        &self.salt
+    }
 
-#[derive(Debug, Error)]
-pub enum GetAllExperimentsError {
-    #[error(transparent)]
-    Name(#[from] ExperimentNameEmptyError),
-    #[error(transparent)]
-    VariantData(#[from] VariantDataEmptyError),
-    #[error(transparent)]
-    VariantDistribution(#[from] VariantDistributionInvalidError),
-    #[error(transparent)]
-    DistributionSum(#[from] DistributionSumError),
-    #[error(transparent)]
-    Unknown(#[from] anyhow::Error),
-}
+    /// Share of eligible devices exposed to the experiment.
+    pub fn allocation(&self) -> ExperimentAllocation {
+        self.allocation
+    }
 
-#[derive(Debug, Error)]
-pub enum GetAllDeviceExperimentsError {
-    #[error(transparent)]
-    Unknown(#[from] anyhow::Error),
-}
+    pub fn lifecycle(&self) -> &ExperimentLifecycle {
+        &self.lifecycle
+    }
 
-#[cfg(test)]
-mod variant_distribution_tests {
-    use super::*;
+    pub fn status(&self) -> ExperimentStatus {
+        self.lifecycle.status()
+    }
 
-    #[test]
-    fn test_new_success() {
-        let value = 75.0;
-        let result = VariantDistribution::new(value);
-        let expected = Ok(VariantDistribution(75.0));
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        self.lifecycle.created_at()
+    }
 
-        assert_eq!(result, expected);
+    pub fn finished_at(&self) -> &Option<DateTime<Utc>> {
+        self.lifecycle.finished_at()
     }
 
-    #[test]
-    fn test_distribution_is_invalid() {
-        let value = 0.0;
-        let result = VariantDistribution::new(value);
-        let expected = Err(VariantDistributionInvalidError);
+    pub fn schedule(&self) -> &ExperimentSchedule {
+        self.lifecycle.schedule()
+    }
 
-        assert_eq!(result, expected);
+    /// Audience of the experiment, empty when every device is targeted.
+    pub fn targeting(&self) -> &TargetingRules {
+        &self.targeting
     }
-}
 
-#[cfg(test)]
-mod experiment_tests {
-    use super::*;
+    pub fn bucketing(&self) -> ExperimentBucketing {
+        self.bucketing
+    }
 
-    #[test]
-    fn test_new_success() {
-        let variant_1_data = VariantData::new("10").unwrap();
-        let variant_1_distribution = VariantDistribution::new(75.0).unwrap();
-        let variant_1 = Variant::new(variant_1_distribution, variant_1_data);
+    /// Goals the experiment is measured by.
+    pub fn metrics(&self) -> &ExperimentMetrics {
+        &self.metrics
+    }
 
-        let variant_2_data = VariantData::new("20").unwrap();
-        let variant_2_distribution = VariantDistribution::new(10.0).unwrap();
-        let variant_2 = Variant::new(variant_2_distribution, variant_2_data);
+    /// Variant the others are compared against, the first variant unless another one is
+    /// designated.
+    pub fn control(&self) -> &VariantData {
+        self.control
+            .as_ref()
+            .filter(|control| self.variants.contains(control))
+            .unwrap_or_else(|| self.variants.variants()[0].data())
+    }
 
-        let variant_3_data = VariantData::new("50").unwrap();
-        let variant_3_distribution = VariantDistribution::new(5.0).unwrap();
-        let variant_3 = Variant::new(variant_3_distribution, variant_3_data);
+    pub fn analysis_plan(&self) -> AnalysisPlan {
+        self.analysis_plan
+    }
 
-        let variant_4_data = VariantData::new("5").unwrap();
-        let variant_4_distribution = VariantDistribution::new(10.0).unwrap();
-        let variant_4 = Variant::new(variant_4_distribution, variant_4_data);
+    pub fn eligibility(&self) -> ExperimentEligibility {
+        self.eligibility
+    }
 
-        let experiment_variants_result = ExperimentVariants::new(vec![
-            variant_1.clone(),
-            variant_2.clone(),
-            variant_3.clone(),
-            variant_4.clone(),
-        ]);
+    /// Limits on degradation of the metrics the experiment is stopped at.
+    pub fn guardrails(&self) -> &ExperimentGuardrails {
+        &self.guardrails
+    }
 
-        let experiment_variants_expected = Ok(ExperimentVariants(vec![
-            variant_1.clone(),
-            variant_2.clone(),
-            variant_3.clone(),
-            variant_4.clone(),
-        ]));
+    /// Buckets of the layer the experiment claims, `None` when it shares devices freely.
+    pub fn layer(&self) -> Option<&ExperimentLayer> {
+        self.layer.as_ref()
+    }
 
-        assert_eq!(experiment_variants_result, experiment_variants_expected);
+    /// Key a device is hashed by, `None` for anonymous devices.
+    fn hash_key(&self, device: &Device) -> Option<String> {
+        match (self.bucketing, device.user_id()) {
+            (ExperimentBucketing::User, Some(user_id)) => Some(format!("user:{}", user_id)),
+            _ => device.id().hash_key(),
+        }
+    }
+
+    /// Whether a device with the given attributes belongs to the audience of the experiment.
+    pub fn is_targeted(&self, attributes: &TargetingAttributes) -> bool {
+        self.targeting.matches(attributes)
+    }
+
+    /// Whether a device created at the given time may be enrolled under the eligibility policy,
+    /// relative to the scheduled start of the experiment, or its creation if it is not scheduled.
+    pub fn is_eligible(&self, device_created_at: &DateTime<Utc>) -> bool {
+        let started_at = self.schedule().starts_at().unwrap_or(self.created_at());
+
+        self.eligibility.admits(started_at, device_created_at)
+    }
+
+    /// Whether the experiment is shown to the device at the given moment: it is running within
+    /// its schedule, targets the device and the device is eligible for it.
+    ///
+    /// The schedule is checked on its own, so that experiments are neither shown before their
+    /// start nor after their end while their status has not caught up yet.
+    pub fn is_available_to(&self, device: &Device, now: &DateTime<Utc>) -> bool {
+        self.lifecycle.scheduled_status(now) == ExperimentStatus::Running
+            && self.schedule().has_started(now)
+            && self.is_eligible(device.created_at())
+            && self.is_targeted(&TargetingAttributes::from(device.attributes()))
+    }
+
+    /// Whether a device falls into the buckets of the layer the experiment claims, always `true`
+    /// for experiments outside of layers.
+    ///
+    /// The layer hash depends on the layer name and the hash key only, and active experiments
+    /// of a layer share the bucketing, so that every experiment of the layer sees the device in
+    /// the same bucket.
+    pub fn is_in_layer(&self, device: &Device) -> bool {
+        let Some(layer) = &self.layer else {
+            return true;
+        };
+        let Some(hash_key) = self.hash_key(device) else {
+            return false;
+        };
+
+        let position = hash_percentage(format!("layer:{}:{}", layer.name(), hash_key).as_str());
+        let bucket = (position / 100.0 * LAYER_BUCKETS as f64) as u32;
+
+        layer.buckets().contains(bucket.min(LAYER_BUCKETS - 1))
+    }
+
+    /// Whether a device falls into the allocated share of the experiment.
+    ///
+    /// The allocation hash is independent of the variant hash, and a device stays allocated when
+    /// the allocation is raised, so raising it only enrolls new devices.
+    pub fn is_allocated(&self, device: &Device) -> bool {
+        let Some(hash_key) = self.hash_key(device) else {
+            return false;
+        };
+        let salt = match &self.salt {
+            Some(salt) => salt.to_string(),
+            None => self.id.to_string(),
+        };
+
+        hash_percentage(format!("allocation:{}:{}", salt, hash_key).as_str())
+            < self.allocation.into_inner()
+    }
+
+    /// Assigns a variant of the experiment to a device.
+    ///
+    /// Legacy experiments without a salt hash the bare hash key, so that devices keep the
+    /// variants they were assigned before salting was introduced.
+    ///
+    /// # Returns
+    /// * `Some(&VariantData)` with the assigned variant.
+    /// * `None` if the device is anonymous or outside of the allocation.
+    pub fn assign_variant(&self, device: &Device) -> Option<&VariantData> {
+        if !self.is_allocated(device) {
+            return None;
+        }
+
+        let hash_key = self.hash_key(device)?;
+
+        let data = match &self.salt {
+            Some(salt) => self
+                .variants
+                .assign_variant(format!("{}:{}", salt, hash_key).as_str()),
+            None => self.variants.assign_variant(hash_key.as_str()),
+        };
+
+        Some(data)
+    }
+
+    /// Whether a device has to be assigned again, as its variant was removed by an edit made
+    /// after the device was assigned.
+    pub fn is_outdated(&self, assignment: &Assignment) -> bool {
+        assignment.version() < self.version && !self.variants.contains(assignment.data())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceExperiment {
+    id: Uuid,
+    name: ExperimentName,
+    data: VariantData,
+    version: u32,
+}
+
+impl DeviceExperiment {
+    pub fn new(id: Uuid, name: ExperimentName, data: VariantData, version: u32) -> Self {
+        Self {
+            id,
+            name,
+            data,
+            version,
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn name(&self) -> &ExperimentName {
+        &self.name
+    }
+
+    pub fn data(&self) -> &VariantData {
+        &self.data
+    }
+
+    /// Version of the experiment the variant was assigned under.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+/// Statistics of devices assigned under a single version of an experiment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatisticsVersion {
+    version: u32,
+    total_devices: usize,
+    variants: StatisticsVariants,
+}
+
+impl StatisticsVersion {
+    pub fn new(version: u32, total_devices: usize, variants: StatisticsVariants) -> Self {
+        Self {
+            version,
+            total_devices,
+            variants,
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn total_devices(&self) -> usize {
+        self.total_devices
+    }
+
+    pub fn variants(&self) -> &StatisticsVariants {
+        &self.variants
+    }
+}
+
+/// Outcome of the check that devices of the current version are split between the variants
+/// as configured. A mismatch points to a bug in bucketing or logging rather than to an effect
+/// of the experiment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampleRatioCheck {
+    statistic: f64,
+    p_value: f64,
+}
+
+impl SampleRatioCheck {
+    /// P-value below which the split is considered mismatched.
+    pub const THRESHOLD: f64 = 0.001;
+
+    pub fn new(test: &GoodnessOfFitTest) -> Self {
+        Self {
+            statistic: test.statistic(),
+            p_value: test.p_value(),
+        }
+    }
+
+    /// Chi-square statistic of the observed split against the expected one.
+    pub fn statistic(&self) -> f64 {
+        self.statistic
+    }
+
+    pub fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    pub fn is_mismatch(&self) -> bool {
+        self.p_value < Self::THRESHOLD
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StaticticsExperiment {
+    id: Uuid,
+    name: ExperimentName,
+    version: u32,
+    total_devices: usize,
+    control: VariantData,
+    variants: StatisticsVariants,
+    versions: Vec<StatisticsVersion>,
+    sample_ratio: Option<SampleRatioCheck>,
+    analysis_plan: AnalysisPlan,
+    eligibility: ExperimentEligibility,
+    segments: Vec<StatisticsSegment>,
+}
+
+impl StaticticsExperiment {
+    pub fn new(
+        id: Uuid,
+        name: ExperimentName,
+        version: u32,
+        total_devices: usize,
+        control: VariantData,
+        variants: StatisticsVariants,
+        versions: Vec<StatisticsVersion>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            version,
+            total_devices,
+            control,
+            variants,
+            versions,
+            sample_ratio: None,
+            analysis_plan: AnalysisPlan::default(),
+            eligibility: ExperimentEligibility::default(),
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn with_sample_ratio(mut self, sample_ratio: Option<SampleRatioCheck>) -> Self {
+        self.sample_ratio = sample_ratio;
+        self
+    }
+
+    pub fn with_analysis_plan(mut self, analysis_plan: AnalysisPlan) -> Self {
+        self.analysis_plan = analysis_plan;
+        self
+    }
+
+    pub fn with_eligibility(mut self, eligibility: ExperimentEligibility) -> Self {
+        self.eligibility = eligibility;
+        self
+    }
+
+    pub fn with_segments(mut self, segments: Vec<StatisticsSegment>) -> Self {
+        self.segments = segments;
+        self
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn name(&self) -> &ExperimentName {
+        &self.name
+    }
+
+    /// Current version of the experiment.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn total_devices(&self) -> usize {
+        self.total_devices
+    }
+
+    /// Variant the others are compared against.
+    pub fn control(&self) -> &VariantData {
+        &self.control
+    }
+
+    /// Variants of the current version with devices counted across all versions.
+    pub fn variants(&self) -> &StatisticsVariants {
+        &self.variants
+    }
+
+    /// Breakdown of devices by the version they were assigned under.
+    pub fn versions(&self) -> &Vec<StatisticsVersion> {
+        &self.versions
+    }
+
+    /// Check of the split of the current version, `None` if it has no devices yet.
+    pub fn sample_ratio(&self) -> &Option<SampleRatioCheck> {
+        &self.sample_ratio
+    }
+
+    /// Plan the tests of the variants against the control follow.
+    pub fn analysis_plan(&self) -> AnalysisPlan {
+        self.analysis_plan
+    }
+
+    pub fn eligibility(&self) -> ExperimentEligibility {
+        self.eligibility
+    }
+
+    /// Breakdown of devices by values of the requested attribute, empty when none is requested.
+    pub fn segments(&self) -> &[StatisticsSegment] {
+        &self.segments
+    }
+}
+
+/// Represents the way variants of an experiment are compared.
+#[derive(Display, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AnalysisMethod {
+    /// Significance tests of every variant against the control.
+    #[default]
+    #[display("frequentist")]
+    Frequentist,
+    /// Probability to be best and expected loss of every variant, estimated from posteriors.
+    #[display("bayesian")]
+    Bayesian,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid analysis method")]
+pub struct AnalysisMethodInvalidError(String);
+impl AnalysisMethod {
+    pub fn new(raw_method: &str) -> Result<Self, AnalysisMethodInvalidError> {
+        match raw_method {
+            "frequentist" => Ok(Self::Frequentist),
+            "bayesian" => Ok(Self::Bayesian),
+            _ => Err(AnalysisMethodInvalidError(raw_method.to_string())),
+        }
+    }
+}
+
+/// Represents always valid number of days before the creation of an experiment the values of
+/// the devices that CUPED adjusts by are taken from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CovariateWindow(u32);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("covariate window should be from 1 to {max} days", max = CovariateWindow::MAX_DAYS)]
+pub struct CovariateWindowInvalidError;
+impl CovariateWindow {
+    pub const MAX_DAYS: u32 = 365;
+
+    pub fn new(days: u32) -> Result<Self, CovariateWindowInvalidError> {
+        if days == 0 || days > Self::MAX_DAYS {
+            Err(CovariateWindowInvalidError)
+        } else {
+            Ok(Self(days))
+        }
+    }
+
+    pub fn days(&self) -> u32 {
+        self.0
+    }
+}
+
+/// Data required by the domain to compute [StaticticsExperiment]s.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GetStatisticsRequest {
+    alpha: SignificanceLevel,
+    method: AnalysisMethod,
+    cuped: Option<CovariateWindow>,
+}
+
+impl GetStatisticsRequest {
+    pub fn new(alpha: SignificanceLevel) -> Self {
+        Self {
+            alpha,
+            method: AnalysisMethod::default(),
+            cuped: None,
+        }
+    }
+
+    pub fn with_method(mut self, method: AnalysisMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Requests CUPED-adjusted effects of the metrics, with the values of the devices taken
+    /// from the `window` before the experiment.
+    pub fn with_cuped(mut self, window: CovariateWindow) -> Self {
+        self.cuped = Some(window);
+        self
+    }
+
+    /// Significance level variants are tested against the control at.
+    pub fn alpha(&self) -> SignificanceLevel {
+        self.alpha
+    }
+
+    pub fn method(&self) -> AnalysisMethod {
+        self.method
+    }
+
+    /// Window of the values CUPED adjusts by, `None` when it is not requested.
+    pub fn cuped(&self) -> Option<CovariateWindow> {
+        self.cuped
+    }
+}
+
+/// Data required by the domain to create an [Experiment].
+#[derive(Clone, Debug, From)]
+pub struct CreateExperimentRequest {
+    name: ExperimentName,
+    variants: ExperimentVariants,
+    salt: Option<ExperimentSalt>,
+    allocation: ExperimentAllocation,
+    status: ExperimentStatus,
+    targeting: TargetingRules,
+    bucketing: ExperimentBucketing,
+    metrics: ExperimentMetrics,
+    control: Option<VariantData>,
+    analysis_plan: AnalysisPlan,
+    eligibility: ExperimentEligibility,
+    schedule: ExperimentSchedule,
+    guardrails: ExperimentGuardrails,
+    layer: Option<ExperimentLayer>,
+}
+
+impl CreateExperimentRequest {
+    pub fn new(
+        name: ExperimentName,
+        variants: ExperimentVariants,
+        salt: Option<ExperimentSalt>,
+        allocation: ExperimentAllocation,
+        status: ExperimentStatus,
+    ) -> Self {
+        Self {
+            name,
+            variants,
+            salt,
+            allocation,
+            status,
+            targeting: TargetingRules::default(),
+            bucketing: ExperimentBucketing::default(),
+            metrics: ExperimentMetrics::default(),
+            control: None,
+            analysis_plan: AnalysisPlan::default(),
+            eligibility: ExperimentEligibility::default(),
+            guardrails: ExperimentGuardrails::default(),
+            schedule: ExperimentSchedule::default(),
+            layer: None,
+        }
+    }
+
+    pub fn with_targeting(mut self, targeting: TargetingRules) -> Self {
+        self.targeting = targeting;
+        self
+    }
+
+    pub fn with_bucketing(mut self, bucketing: ExperimentBucketing) -> Self {
+        self.bucketing = bucketing;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: ExperimentMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn with_analysis_plan(mut self, analysis_plan: AnalysisPlan) -> Self {
+        self.analysis_plan = analysis_plan;
+        self
+    }
+
+    pub fn with_eligibility(mut self, eligibility: ExperimentEligibility) -> Self {
+        self.eligibility = eligibility;
+        self
+    }
+
+    pub fn with_guardrails(mut self, guardrails: ExperimentGuardrails) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    pub fn with_schedule(mut self, schedule: ExperimentSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: ExperimentLayer) -> Self {
+        self.layer = Some(layer);
+        self
+    }
+
+    /// Sets the variant the others are compared against.
+    ///
+    /// # Returns
+    /// * `Ok(CreateExperimentRequest)` if the control is one of the variants.
+    /// * `Err(ExperimentControlInvalidError)` otherwise.
+    pub fn with_control(
+        mut self,
+        control: VariantData,
+    ) -> Result<Self, ExperimentControlInvalidError> {
+        if !self.variants.contains(&control) {
+            return Err(ExperimentControlInvalidError(control));
+        }
+
+        self.control = Some(control);
+        Ok(self)
+    }
+
+    pub fn name(&self) -> &ExperimentName {
+        &self.name
+    }
+
+    pub fn variants(&self) -> &ExperimentVariants {
+        &self.variants
+    }
+
+    /// Salt requested for the experiment, the experiment id is used when `None`.
+    pub fn salt(&self) -> &Option<ExperimentSalt> {
+        &self.salt
+    }
+
+    pub fn allocation(&self) -> ExperimentAllocation {
+        self.allocation
+    }
+
+    pub fn status(&self) -> ExperimentStatus {
+        self.status
+    }
+
+    pub fn targeting(&self) -> &TargetingRules {
+        &self.targeting
+    }
+
+    pub fn bucketing(&self) -> ExperimentBucketing {
+        self.bucketing
+    }
+
+    /// Goals the experiment is measured by.
+    pub fn metrics(&self) -> &ExperimentMetrics {
+        &self.metrics
+    }
+
+    /// Designated control variant, the first variant is the control when `None`.
+    pub fn control(&self) -> &Option<VariantData> {
+        &self.control
+    }
+
+    pub fn analysis_plan(&self) -> AnalysisPlan {
+        self.analysis_plan
+    }
+
+    pub fn eligibility(&self) -> ExperimentEligibility {
+        self.eligibility
+    }
+
+    /// Limits on degradation of the metrics the experiment is stopped at.
+    pub fn guardrails(&self) -> &ExperimentGuardrails {
+        &self.guardrails
+    }
+
+    pub fn schedule(&self) -> &ExperimentSchedule {
+        &self.schedule
+    }
+
+    /// Buckets of the layer requested for the experiment, they must not overlap the buckets
+    /// claimed by other active experiments of the layer.
+    pub fn layer(&self) -> Option<&ExperimentLayer> {
+        self.layer.as_ref()
+    }
+}
+
+/// Data required by the domain to edit an [Experiment]. Fields set to `None` are left unchanged.
+#[derive(Clone, Debug)]
+pub struct UpdateExperimentRequest {
+    id: Uuid,
+    name: Option<ExperimentName>,
+    variants: Option<ExperimentVariants>,
+    allocation: Option<ExperimentAllocation>,
+    targeting: Option<TargetingRules>,
+    metrics: Option<ExperimentMetrics>,
+    control: Option<VariantData>,
+    status: Option<ExperimentStatus>,
+}
+
+impl UpdateExperimentRequest {
+    pub fn new(
+        id: Uuid,
+        name: Option<ExperimentName>,
+        variants: Option<ExperimentVariants>,
+        allocation: Option<ExperimentAllocation>,
+        targeting: Option<TargetingRules>,
+        metrics: Option<ExperimentMetrics>,
+        control: Option<VariantData>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            variants,
+            allocation,
+            targeting,
+            metrics,
+            control,
+            status: None,
+        }
+    }
+
+    /// Moves the experiment to the status along with the edits, e.g. to start an edited draft.
+    pub fn with_status(self, status: Option<ExperimentStatus>) -> Self {
+        Self { status, ..self }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn name(&self) -> &Option<ExperimentName> {
+        &self.name
+    }
+
+    pub fn variants(&self) -> &Option<ExperimentVariants> {
+        &self.variants
+    }
+
+    pub fn allocation(&self) -> Option<ExperimentAllocation> {
+        self.allocation
+    }
+
+    /// Rules replacing the audience of the experiment.
+    pub fn targeting(&self) -> &Option<TargetingRules> {
+        &self.targeting
+    }
+
+    /// Metrics replacing the goals of the experiment.
+    pub fn metrics(&self) -> &Option<ExperimentMetrics> {
+        &self.metrics
+    }
+
+    /// Variant designated as the control, it must be one of the variants after the edit.
+    pub fn control(&self) -> &Option<VariantData> {
+        &self.control
+    }
+
+    /// Status the experiment moves to once edited.
+    pub fn status(&self) -> Option<ExperimentStatus> {
+        self.status
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CreateExperimentError {
+    #[error("experiment with name {name} already exists")]
+    Duplicate { name: ExperimentName },
+    #[error("buckets of layer {layer} overlap the buckets of experiment {name}")]
+    LayerOverlap {
+        layer: LayerName,
+        name: ExperimentName,
+    },
+    #[error("experiment {name} of layer {layer} buckets devices by {bucketing}")]
+    LayerBucketing {
+        layer: LayerName,
+        name: ExperimentName,
+        bucketing: ExperimentBucketing,
+    },
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum FinishExperimentError {
+    #[error("experiment with id {id} does not exist")]
+    NotFound { id: Uuid },
+    #[error("experiment with id {id} is already finished")]
+    Finished { id: Uuid },
+    #[error("experiment with id {id} cannot be finished from {from}")]
+    InvalidTransition { id: Uuid, from: ExperimentStatus },
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum UpdateExperimentError {
+    #[error("experiment with id {id} does not exist")]
+    NotFound { id: Uuid },
+    #[error("experiment with id {id} cannot be edited in status {status}")]
+    NotEditable { id: Uuid, status: ExperimentStatus },
+    #[error("experiment with name {name} already exists")]
+    Duplicate { name: ExperimentName },
+    #[error("control {control} is not a variant of experiment with id {id}")]
+    InvalidControl { id: Uuid, control: VariantData },
+    #[error(
+        "metric {metric} of experiment with id {id} is watched by a guardrail and needs a mixing deviation"
+    )]
+    GuardrailMetric { id: Uuid, metric: MetricName },
+    #[error("experiment with id {id} cannot change status from {from} to {to}")]
+    InvalidTransition {
+        id: Uuid,
+        from: ExperimentStatus,
+        to: ExperimentStatus,
+    },
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum ChangeExperimentStatusError {
+    #[error("experiment with id {id} does not exist")]
+    NotFound { id: Uuid },
+    #[error("experiment with id {id} cannot change status from {from} to {to}")]
+    InvalidTransition {
+        id: Uuid,
+        from: ExperimentStatus,
+        to: ExperimentStatus,
+    },
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum ApplySchedulesError {
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum GetAllExperimentsError {
+    #[error(transparent)]
+    Name(#[from] ExperimentNameEmptyError),
+    #[error(transparent)]
+    VariantData(#[from] VariantDataEmptyError),
+    #[error(transparent)]
+    VariantDistribution(#[from] VariantDistributionInvalidError),
+    #[error(transparent)]
+    DistributionSum(#[from] DistributionSumError),
+    #[error(transparent)]
+    Salt(#[from] ExperimentSaltEmptyError),
+    #[error(transparent)]
+    Status(#[from] ExperimentStatusInvalidError),
+    #[error(transparent)]
+    Allocation(#[from] ExperimentAllocationInvalidError),
+    #[error(transparent)]
+    TargetingAttribute(#[from] TargetingAttributeEmptyError),
+    #[error(transparent)]
+    TargetingInList(#[from] TargetingInListEmptyError),
+    #[error(transparent)]
+    SemverRange(#[from] SemverRangeInvalidError),
+    #[error(transparent)]
+    Bucketing(#[from] ExperimentBucketingInvalidError),
+    #[error(transparent)]
+    MetricName(#[from] MetricNameEmptyError),
+    #[error(transparent)]
+    MetricRole(#[from] MetricRoleInvalidError),
+    #[error(transparent)]
+    MetricEvent(#[from] EventNameInvalidError),
+    #[error(transparent)]
+    Metrics(#[from] ExperimentMetricsInvalidError),
+    #[error(transparent)]
+    AnalysisPlan(#[from] AnalysisPlanInvalidError),
+    #[error(transparent)]
+    MixingDeviation(#[from] MixingDeviationInvalidError),
+    #[error(transparent)]
+    Eligibility(#[from] ExperimentEligibilityInvalidError),
+    #[error(transparent)]
+    Schedule(#[from] ExperimentScheduleInvalidError),
+    #[error(transparent)]
+    GuardrailDirection(#[from] DegradationDirectionInvalidError),
+    #[error(transparent)]
+    GuardrailAction(#[from] GuardrailActionInvalidError),
+    #[error(transparent)]
+    MaxDegradation(#[from] MaxDegradationInvalidError),
+    #[error(transparent)]
+    Guardrails(#[from] ExperimentGuardrailsInvalidError),
+    #[error(transparent)]
+    LayerName(#[from] LayerNameEmptyError),
+    #[error(transparent)]
+    LayerBuckets(#[from] LayerBucketsInvalidError),
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum GetExperimentByIdError {
+    #[error("experiment with id {id} not found")]
+    NotFound { id: Uuid },
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum GetAllDeviceExperimentsError {
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[cfg(test)]
+mod variant_distribution_tests {
+    use super::*;
+
+    #[test]
+    fn test_new_success() {
+        let value = 75.0;
+        let result = VariantDistribution::new(value);
+        let expected = Ok(VariantDistribution(75.0));
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_distribution_is_invalid() {
+        let value = 0.0;
+        let result = VariantDistribution::new(value);
+        let expected = Err(VariantDistributionInvalidError);
+
+        assert_eq!(result, expected);
+    }
+}
+
+#[cfg(test)]
+mod experiment_status_tests {
+    use super::*;
+
+    #[test]
+    fn test_transition_success() {
+        use ExperimentStatus::*;
+
+        let transitions = [
+            (Draft, Running),
+            (Running, Paused),
+            (Paused, Running),
+            (Running, Finished),
+            (Paused, Finished),
+            (Finished, Archived),
+        ];
+
+        for (from, to) in transitions {
+            assert_eq!(from.transition_to(to), Ok(to));
+        }
+    }
+
+    #[test]
+    fn test_transition_is_invalid() {
+        use ExperimentStatus::*;
+
+        let transitions = [
+            (Draft, Paused),
+            (Draft, Finished),
+            (Running, Draft),
+            (Running, Running),
+            (Finished, Running),
+            (Archived, Finished),
+        ];
+
+        for (from, to) in transitions {
+            let expected = Err(ExperimentStatusTransitionError { from, to });
+
+            assert_eq!(from.transition_to(to), expected);
+        }
+    }
+
+    #[test]
+    fn test_is_editable() {
+        use ExperimentStatus::*;
+
+        assert!(Draft.is_editable());
+        assert!(Running.is_editable());
+        assert!(!Paused.is_editable());
+        assert!(!Finished.is_editable());
+        assert!(!Archived.is_editable());
+    }
+
+    #[test]
+    fn test_initial_status_is_invalid() {
+        let result = ExperimentStatus::Paused.initial();
+        let expected = Err(ExperimentInitialStatusError);
+
+        assert_eq!(result, expected);
+    }
+}
+
+#[cfg(test)]
+mod experiment_tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    use crate::domain::device::models::device::{DeviceId, DeviceIdKind, UserId};
+    use crate::domain::experiment::models::layer::LayerBuckets;
+    use crate::domain::statistics::frequentist::chi_square_goodness_of_fit;
+
+    fn device(raw_idfa: &str) -> Device {
+        Device::new(
+            DeviceId::new(DeviceIdKind::Idfa, raw_idfa).unwrap(),
+            Utc::now(),
+        )
+    }
+
+    #[test]
+    fn test_new_success() {
+        let variant_1_data = VariantData::new("10").unwrap();
+        let variant_1_distribution = VariantDistribution::new(75.0).unwrap();
+        let variant_1 = Variant::new(variant_1_distribution, variant_1_data);
+
+        let variant_2_data = VariantData::new("20").unwrap();
+        let variant_2_distribution = VariantDistribution::new(10.0).unwrap();
+        let variant_2 = Variant::new(variant_2_distribution, variant_2_data);
+
+        let variant_3_data = VariantData::new("50").unwrap();
+        let variant_3_distribution = VariantDistribution::new(5.0).unwrap();
+        let variant_3 = Variant::new(variant_3_distribution, variant_3_data);
+
+        let variant_4_data = VariantData::new("5").unwrap();
+        let variant_4_distribution = VariantDistribution::new(10.0).unwrap();
+        let variant_4 = Variant::new(variant_4_distribution, variant_4_data);
+
+        let experiment_variants_result = ExperimentVariants::new(vec![
+            variant_1.clone(),
+            variant_2.clone(),
+            variant_3.clone(),
+            variant_4.clone(),
+        ]);
+
+        let experiment_variants_expected = Ok(ExperimentVariants(vec![
+            variant_1.clone(),
+            variant_2.clone(),
+            variant_3.clone(),
+            variant_4.clone(),
+        ]));
+
+        assert_eq!(experiment_variants_result, experiment_variants_expected);
 
         let experiment_name_result = ExperimentName::new("price");
         let experiment_name_expected = Ok(ExperimentName("price".to_string()));
@@ -483,4 +1744,349 @@ mod experiment_tests {
 
         assert_eq!(experiment_variants_result, experiment_variants_expected);
     }
+
+    fn two_variants_experiment(
+        salt: Option<ExperimentSalt>,
+        allocation: ExperimentAllocation,
+    ) -> Experiment {
+        let variant_1 = Variant::new(
+            VariantDistribution::new(50.0).unwrap(),
+            VariantData::new("blue").unwrap(),
+        );
+        let variant_2 = Variant::new(
+            VariantDistribution::new(50.0).unwrap(),
+            VariantData::new("red").unwrap(),
+        );
+        let variants = ExperimentVariants::new(vec![variant_1, variant_2]).unwrap();
+
+        Experiment::new(
+            Uuid::new_v4(),
+            ExperimentName::new("color").unwrap(),
+            variants,
+            1,
+            salt,
+            allocation,
+            ExperimentLifecycle::new(ExperimentStatus::Running, Utc::now(), None),
+        )
+    }
+
+    #[test]
+    fn test_is_available_to() {
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let device = Device::new(id.clone(), Utc::now() - TimeDelta::minutes(1));
+        let experiment = two_variants_experiment(None, ExperimentAllocation::FULL);
+        let now = Utc::now();
+
+        assert!(experiment.is_available_to(&device, &now));
+
+        // Devices created after the experiment only receive it when new devices are eligible.
+        let newer_device = Device::new(id, Utc::now() + TimeDelta::minutes(1));
+        assert!(!experiment.is_available_to(&newer_device, &now));
+        let for_new = experiment
+            .clone()
+            .with_eligibility(ExperimentEligibility::New);
+        assert!(for_new.is_available_to(&newer_device, &now));
+        assert!(!for_new.is_available_to(&device, &now));
+        let for_all = experiment
+            .clone()
+            .with_eligibility(ExperimentEligibility::All);
+        assert!(for_all.is_available_to(&newer_device, &now));
+        assert!(for_all.is_available_to(&device, &now));
+
+        let paused = Experiment::new(
+            *experiment.id(),
+            experiment.name().to_owned(),
+            experiment.variants().to_owned(),
+            1,
+            None,
+            ExperimentAllocation::FULL,
+            ExperimentLifecycle::new(ExperimentStatus::Paused, Utc::now(), None),
+        );
+        assert!(!paused.is_available_to(&device, &now));
+
+        // The schedule is respected even while the status has not caught up with it.
+        let scheduled = |starts_at: Option<DateTime<Utc>>, ends_at: Option<DateTime<Utc>>| {
+            let lifecycle =
+                ExperimentLifecycle::new(ExperimentStatus::Running, *experiment.created_at(), None)
+                    .with_schedule(ExperimentSchedule::new(starts_at, ends_at).unwrap());
+
+            Experiment::new(
+                *experiment.id(),
+                experiment.name().to_owned(),
+                experiment.variants().to_owned(),
+                1,
+                None,
+                ExperimentAllocation::FULL,
+                lifecycle,
+            )
+        };
+        let hour = TimeDelta::hours(1);
+        let device_before_start = Device::new(device.id().to_owned(), now - hour * 2);
+        assert!(!scheduled(Some(now + hour), None).is_available_to(&device, &now));
+        assert!(
+            scheduled(Some(now - hour), Some(now + hour))
+                .is_available_to(&device_before_start, &now)
+        );
+        assert!(!scheduled(None, Some(now - hour)).is_available_to(&device, &now));
+
+        // Eligibility is relative to the scheduled start rather than the creation.
+        let started_later = scheduled(Some(now - hour), None);
+        let device_after_start = Device::new(device.id().to_owned(), now - hour / 2);
+        assert!(started_later.is_available_to(&device_before_start, &now));
+        assert!(!started_later.is_available_to(&device_after_start, &now));
+        let started_later = started_later.with_eligibility(ExperimentEligibility::New);
+        assert!(!started_later.is_available_to(&device_before_start, &now));
+        assert!(started_later.is_available_to(&device_after_start, &now));
+    }
+
+    #[test]
+    fn test_scheduled_status() {
+        let now = Utc::now();
+        let hour = TimeDelta::hours(1);
+        let lifecycle = |status: ExperimentStatus,
+                         starts_at: Option<DateTime<Utc>>,
+                         ends_at: Option<DateTime<Utc>>| {
+            ExperimentLifecycle::new(status, now - hour * 2, None)
+                .with_schedule(ExperimentSchedule::new(starts_at, ends_at).unwrap())
+        };
+
+        assert_eq!(
+            lifecycle(ExperimentStatus::Draft, Some(now + hour), None).scheduled_status(&now),
+            ExperimentStatus::Draft
+        );
+        assert_eq!(
+            lifecycle(ExperimentStatus::Draft, Some(now - hour), None).scheduled_status(&now),
+            ExperimentStatus::Running
+        );
+        assert_eq!(
+            lifecycle(ExperimentStatus::Draft, None, Some(now - hour)).scheduled_status(&now),
+            ExperimentStatus::Draft
+        );
+        assert_eq!(
+            lifecycle(
+                ExperimentStatus::Draft,
+                Some(now - hour * 2),
+                Some(now - hour)
+            )
+            .scheduled_status(&now),
+            ExperimentStatus::Finished
+        );
+        assert_eq!(
+            lifecycle(ExperimentStatus::Paused, None, Some(now - hour)).scheduled_status(&now),
+            ExperimentStatus::Finished
+        );
+        assert_eq!(
+            lifecycle(ExperimentStatus::Running, None, Some(now + hour)).scheduled_status(&now),
+            ExperimentStatus::Running
+        );
+        assert_eq!(
+            ExperimentSchedule::new(Some(now), Some(now)),
+            Err(ExperimentScheduleInvalidError)
+        );
+    }
+
+    #[test]
+    fn test_assign_variant_legacy_unsalted() {
+        let raw_idfa = "550e8400-e29b-41d4-a716-446655440000";
+        let device = device(raw_idfa);
+        let experiment = two_variants_experiment(None, ExperimentAllocation::FULL);
+
+        let result = experiment.assign_variant(&device);
+        let expected = Some(experiment.variants().assign_variant(raw_idfa));
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_assign_variant_anonymous() {
+        let device = Device::new(DeviceId::Anonymous(DeviceIdKind::Idfa), Utc::now());
+        let experiment = two_variants_experiment(None, ExperimentAllocation::FULL);
+
+        let result = experiment.assign_variant(&device);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_assign_variant_salted() {
+        let raw_idfa = "550e8400-e29b-41d4-a716-446655440000";
+        let device = device(raw_idfa);
+        let salt = ExperimentSalt::new("color-2025").unwrap();
+        let experiment = two_variants_experiment(Some(salt), ExperimentAllocation::FULL);
+
+        let result = experiment.assign_variant(&device);
+        let expected = Some(
+            experiment
+                .variants()
+                .assign_variant(format!("color-2025:{}", raw_idfa).as_str()),
+        );
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_assign_variant_by_user() {
+        let salt = ExperimentSalt::new("color-2025").unwrap();
+        let experiment = two_variants_experiment(Some(salt), ExperimentAllocation::FULL)
+            .with_bucketing(ExperimentBucketing::User);
+        let user_id = UserId::new("user-42").unwrap();
+
+        let result = experiment.assign_variant(
+            &device("550e8400-e29b-41d4-a716-446655440000").with_user_id(Some(user_id)),
+        );
+        let expected = Some(
+            experiment
+                .variants()
+                .assign_variant("color-2025:user:user-42"),
+        );
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_control() {
+        let experiment = two_variants_experiment(None, ExperimentAllocation::FULL);
+        let first = experiment.variants().variants()[0].data().to_owned();
+        let second = experiment.variants().variants()[1].data().to_owned();
+
+        assert_eq!(experiment.control(), &first);
+        assert_eq!(
+            experiment.clone().with_control(second.clone()).control(),
+            &second
+        );
+        // A control removed by an edit of the variants falls back to the first variant.
+        assert_eq!(
+            experiment
+                .with_control(VariantData::new("removed").unwrap())
+                .control(),
+            &first
+        );
+    }
+
+    #[test]
+    fn test_sample_ratio_check() {
+        let even = chi_square_goodness_of_fit(&[520, 480], &[50.0, 50.0]).unwrap();
+        let skewed = chi_square_goodness_of_fit(&[600, 400], &[50.0, 50.0]).unwrap();
+
+        assert!(!SampleRatioCheck::new(&even).is_mismatch());
+        assert!(SampleRatioCheck::new(&skewed).is_mismatch());
+    }
+
+    #[test]
+    fn test_new_request_control_is_invalid() {
+        let experiment = two_variants_experiment(None, ExperimentAllocation::FULL);
+        let req = CreateExperimentRequest::new(
+            experiment.name().to_owned(),
+            experiment.variants().to_owned(),
+            None,
+            ExperimentAllocation::FULL,
+            ExperimentStatus::Running,
+        );
+
+        let result = req.with_control(VariantData::new("removed").unwrap());
+
+        assert!(matches!(result, Err(ExperimentControlInvalidError(_))));
+    }
+
+    #[test]
+    fn test_is_outdated() {
+        let edited = two_variants_experiment(None, ExperimentAllocation::FULL);
+        let edited = Experiment::new(
+            *edited.id(),
+            edited.name().to_owned(),
+            edited.variants().to_owned(),
+            2,
+            None,
+            ExperimentAllocation::FULL,
+            edited.lifecycle().to_owned(),
+        );
+        let assignment = |data: &str, version: u32| {
+            Assignment::new(
+                DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap(),
+                *edited.id(),
+                VariantData::new(data).unwrap(),
+                version,
+                Utc::now(),
+            )
+        };
+
+        assert!(edited.is_outdated(&assignment("green", 1)));
+        assert!(!edited.is_outdated(&assignment("blue", 1)));
+        assert!(!edited.is_outdated(&assignment("green", 2)));
+    }
+
+    #[test]
+    fn test_new_salt_is_empty() {
+        let result = ExperimentSalt::new("");
+        let expected = Err(ExperimentSaltEmptyError);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_raising_allocation_only_adds_devices() {
+        let salt = ExperimentSalt::new("color-2025").unwrap();
+        let narrow =
+            two_variants_experiment(Some(salt.clone()), ExperimentAllocation::new(10.0).unwrap());
+        let wide = two_variants_experiment(Some(salt), ExperimentAllocation::new(50.0).unwrap());
+
+        let devices: Vec<Device> = (0..1000)
+            .map(|_| device(&Uuid::new_v4().to_string()))
+            .collect();
+
+        let narrow_devices: Vec<&Device> = devices
+            .iter()
+            .filter(|device| narrow.is_allocated(device))
+            .collect();
+        let wide_devices: Vec<&Device> = devices
+            .iter()
+            .filter(|device| wide.is_allocated(device))
+            .collect();
+
+        assert!(narrow_devices.iter().all(|d| wide_devices.contains(d)));
+        assert!(narrow_devices.len() < wide_devices.len());
+        for device in narrow_devices {
+            assert_eq!(narrow.assign_variant(device), wide.assign_variant(device));
+        }
+    }
+
+    #[test]
+    fn test_layers_partition_devices() {
+        let in_layer = |start: u32, end: u32| {
+            two_variants_experiment(None, ExperimentAllocation::FULL).with_layer(
+                ExperimentLayer::new(
+                    LayerName::new("checkout").unwrap(),
+                    LayerBuckets::new(start, end).unwrap(),
+                ),
+            )
+        };
+        let left = in_layer(0, 300);
+        let right = in_layer(300, LAYER_BUCKETS);
+        let outside = two_variants_experiment(None, ExperimentAllocation::FULL);
+
+        let devices: Vec<Device> = (0..1000)
+            .map(|_| device(&Uuid::new_v4().to_string()))
+            .collect();
+
+        let left_devices = devices.iter().filter(|d| left.is_in_layer(d)).count();
+        assert!(
+            devices
+                .iter()
+                .all(|d| left.is_in_layer(d) != right.is_in_layer(d))
+        );
+        assert!(devices.iter().all(|d| outside.is_in_layer(d)));
+        assert!((200..400).contains(&left_devices));
+
+        let anonymous = Device::new(DeviceId::Anonymous(DeviceIdKind::Idfa), Utc::now());
+        assert!(!left.is_in_layer(&anonymous));
+        assert!(!right.is_in_layer(&anonymous));
+    }
+
+    #[test]
+    fn test_new_allocation_is_invalid() {
+        let result = ExperimentAllocation::new(0.0);
+        let expected = Err(ExperimentAllocationInvalidError);
+
+        assert_eq!(result, expected);
+    }
 }
@@ -0,0 +1,447 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::domain::experiment::models::experiment::{ExperimentStatus, VariantData};
+use crate::domain::experiment::models::metric::{ExperimentMetrics, MetricName, StatisticsMetric};
+
+/// Represents which change of a guardrail metric is a degradation.
+#[derive(Display, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DegradationDirection {
+    /// Growth of the metric is a degradation, e.g. crash rate.
+    #[default]
+    #[display("increase")]
+    Increase,
+    /// Decline of the metric is a degradation, e.g. retention.
+    #[display("decrease")]
+    Decrease,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid degradation direction")]
+pub struct DegradationDirectionInvalidError(String);
+impl DegradationDirection {
+    pub fn new(raw_direction: &str) -> Result<Self, DegradationDirectionInvalidError> {
+        match raw_direction {
+            "increase" => Ok(Self::Increase),
+            "decrease" => Ok(Self::Decrease),
+            _ => Err(DegradationDirectionInvalidError(raw_direction.to_string())),
+        }
+    }
+}
+
+/// Represents what happens to an experiment that breaches a guardrail.
+#[derive(Display, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GuardrailAction {
+    #[default]
+    #[display("pause")]
+    Pause,
+    #[display("finish")]
+    Finish,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid guardrail action")]
+pub struct GuardrailActionInvalidError(String);
+impl GuardrailAction {
+    pub fn new(raw_action: &str) -> Result<Self, GuardrailActionInvalidError> {
+        match raw_action {
+            "pause" => Ok(Self::Pause),
+            "finish" => Ok(Self::Finish),
+            _ => Err(GuardrailActionInvalidError(raw_action.to_string())),
+        }
+    }
+
+    /// Status the experiment is moved to.
+    pub fn status(&self) -> ExperimentStatus {
+        match self {
+            Self::Pause => ExperimentStatus::Paused,
+            Self::Finish => ExperimentStatus::Finished,
+        }
+    }
+}
+
+/// Represents always valid maximum degradation of a guardrail metric relative to the control,
+/// e.g. `0.1` allows a variant to be at most 10% worse than the control.
+#[derive(Display, Clone, Copy, Debug, PartialEq)]
+pub struct MaxDegradation(f64);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("maximum degradation should be a positive number, got {0}")]
+pub struct MaxDegradationInvalidError(f64);
+impl MaxDegradation {
+    pub fn new(raw_degradation: f64) -> Result<Self, MaxDegradationInvalidError> {
+        if raw_degradation.is_finite() && raw_degradation > 0.0 {
+            Ok(Self(raw_degradation))
+        } else {
+            Err(MaxDegradationInvalidError(raw_degradation))
+        }
+    }
+
+    pub fn into_inner(self) -> f64 {
+        self.0
+    }
+}
+
+/// Represents a limit on how much a metric of an experiment may degrade in any of the variants
+/// compared with the control before the experiment is stopped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Guardrail {
+    metric: MetricName,
+    max_degradation: MaxDegradation,
+    direction: DegradationDirection,
+    action: GuardrailAction,
+}
+
+impl Guardrail {
+    pub fn new(metric: MetricName, max_degradation: MaxDegradation) -> Self {
+        Self {
+            metric,
+            max_degradation,
+            direction: DegradationDirection::default(),
+            action: GuardrailAction::default(),
+        }
+    }
+
+    pub fn with_direction(mut self, direction: DegradationDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn with_action(mut self, action: GuardrailAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Name of the experiment metric the guardrail watches.
+    pub fn metric(&self) -> &MetricName {
+        &self.metric
+    }
+
+    pub fn max_degradation(&self) -> MaxDegradation {
+        self.max_degradation
+    }
+
+    pub fn direction(&self) -> DegradationDirection {
+        self.direction
+    }
+
+    pub fn action(&self) -> GuardrailAction {
+        self.action
+    }
+
+    /// Degradation of the metric of a variant relative to the control, if it exceeds the
+    /// maximum even at the most favourable bound of the confidence interval of the difference.
+    ///
+    /// # Returns
+    /// * `Some(f64)` with the least relative degradation if the guardrail is breached.
+    /// * `None` if it is not, or the variant has no test against a non-zero control.
+    pub fn breach(&self, control: &StatisticsMetric, metric: &StatisticsMetric) -> Option<f64> {
+        let control = control.value().filter(|value| *value != 0.0)?;
+        let test = metric.test().as_ref()?;
+        let (lower, upper) = test.confidence_interval();
+        let degradation = match self.direction {
+            DegradationDirection::Increase => lower,
+            DegradationDirection::Decrease => -upper,
+        } / control.abs();
+
+        (degradation > self.max_degradation.0).then_some(degradation)
+    }
+}
+
+/// Represents always valid list of guardrails of an experiment, each watching a distinct
+/// metric of the experiment.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExperimentGuardrails(Vec<Guardrail>);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum ExperimentGuardrailsInvalidError {
+    #[error("guardrail metric {0} is not a metric of the experiment")]
+    UnknownMetric(MetricName),
+    #[error("metric {0} has more than one guardrail")]
+    DuplicateMetric(MetricName),
+    #[error("guardrail metric {0} has no mixing deviation to be tested sequentially")]
+    NoMixingDeviation(MetricName),
+}
+
+impl ExperimentGuardrails {
+    pub fn new(
+        guardrails: Vec<Guardrail>,
+        metrics: &ExperimentMetrics,
+    ) -> Result<Self, ExperimentGuardrailsInvalidError> {
+        let mut names = HashSet::new();
+        for guardrail in &guardrails {
+            let Some(metric) = metrics
+                .metrics()
+                .iter()
+                .find(|m| m.name() == guardrail.metric())
+            else {
+                return Err(ExperimentGuardrailsInvalidError::UnknownMetric(
+                    guardrail.metric().to_owned(),
+                ));
+            };
+            // Guardrails are evaluated continuously, so only with the sequential test.
+            if metric.mixing_deviation().is_none() {
+                return Err(ExperimentGuardrailsInvalidError::NoMixingDeviation(
+                    guardrail.metric().to_owned(),
+                ));
+            }
+            if !names.insert(guardrail.metric()) {
+                return Err(ExperimentGuardrailsInvalidError::DuplicateMetric(
+                    guardrail.metric().to_owned(),
+                ));
+            }
+        }
+
+        Ok(Self(guardrails))
+    }
+
+    pub fn guardrails(&self) -> &Vec<Guardrail> {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Represents a change of the status of an experiment made by the service on its own, along
+/// with the reason for it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditEntry {
+    experiment_id: Uuid,
+    status: ExperimentStatus,
+    reason: String,
+    created_at: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        experiment_id: Uuid,
+        status: ExperimentStatus,
+        reason: String,
+        created_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            experiment_id,
+            status,
+            reason,
+            created_at,
+        }
+    }
+
+    pub fn experiment_id(&self) -> &Uuid {
+        &self.experiment_id
+    }
+
+    /// Status the experiment was moved to.
+    pub fn status(&self) -> ExperimentStatus {
+        self.status
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        &self.created_at
+    }
+}
+
+/// Data required by the domain to record an [AuditEntry].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateAuditEntryRequest {
+    experiment_id: Uuid,
+    status: ExperimentStatus,
+    reason: String,
+}
+
+impl CreateAuditEntryRequest {
+    pub fn new(experiment_id: Uuid, status: ExperimentStatus, reason: String) -> Self {
+        Self {
+            experiment_id,
+            status,
+            reason,
+        }
+    }
+
+    /// Reason of stopping an experiment whose variant breached a guardrail.
+    pub fn guardrail_breach(
+        experiment_id: Uuid,
+        guardrail: &Guardrail,
+        data: &VariantData,
+        degradation: f64,
+    ) -> Self {
+        let reason = format!(
+            "guardrail on metric {} breached: variant {} degraded by at least {:.1}% \
+            against the control, allowed {:.1}%",
+            guardrail.metric(),
+            data,
+            degradation * 100.0,
+            guardrail.max_degradation().into_inner() * 100.0,
+        );
+
+        Self::new(experiment_id, guardrail.action().status(), reason)
+    }
+
+    pub fn experiment_id(&self) -> &Uuid {
+        &self.experiment_id
+    }
+
+    pub fn status(&self) -> ExperimentStatus {
+        self.status
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StopExperimentError {
+    #[error("experiment with id {id} does not exist")]
+    NotFound { id: Uuid },
+    #[error("experiment with id {id} cannot change status from {from} to {to}")]
+    InvalidTransition {
+        id: Uuid,
+        from: ExperimentStatus,
+        to: ExperimentStatus,
+    },
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum GetAuditTrailError {
+    #[error("experiment with id {id} does not exist")]
+    NotFound { id: Uuid },
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum EvaluateGuardrailsError {
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[cfg(test)]
+mod guardrail_tests {
+    use super::*;
+    use crate::domain::event::models::event::EventName;
+    use crate::domain::experiment::models::metric::{Metric, MetricKind, MetricRole};
+    use crate::domain::statistics::frequentist::{
+        ProportionSample, SignificanceLevel, two_proportion_z_test,
+    };
+
+    fn crash_rate() -> Metric {
+        Metric::new(
+            MetricName::new("crash_rate").unwrap(),
+            MetricKind::Conversion(EventName::new("crash").unwrap()),
+            MetricRole::Secondary,
+        )
+    }
+
+    fn tested(control: u64, treatment: u64, devices: u64) -> (StatisticsMetric, StatisticsMetric) {
+        let test = two_proportion_z_test(
+            &ProportionSample::new(control, devices),
+            &ProportionSample::new(treatment, devices),
+            SignificanceLevel::default(),
+        );
+
+        (
+            StatisticsMetric::new(&crash_rate(), Some(control as f64 / devices as f64)),
+            StatisticsMetric::new(&crash_rate(), Some(treatment as f64 / devices as f64))
+                .with_test(test),
+        )
+    }
+
+    #[test]
+    fn test_breach() {
+        let guardrail = Guardrail::new(
+            MetricName::new("crash_rate").unwrap(),
+            MaxDegradation::new(0.1).unwrap(),
+        );
+
+        // Crash rate grows from 5% to 10% on 2000 devices, by at least 0.03375 / 0.05.
+        let (control, treatment) = tested(100, 200, 2000);
+        let degradation = guardrail.breach(&control, &treatment);
+        assert!(degradation.is_some_and(|d| (d - 0.675).abs() < 1e-3));
+
+        // The same growth on 20 devices is not significant.
+        let (control, treatment) = tested(1, 2, 20);
+        assert_eq!(guardrail.breach(&control, &treatment), None);
+
+        // A decline is an improvement unless a decline is the degradation.
+        let (control, treatment) = tested(200, 100, 2000);
+        assert_eq!(guardrail.breach(&control, &treatment), None);
+        let decrease = guardrail
+            .clone()
+            .with_direction(DegradationDirection::Decrease);
+        let degradation = decrease.breach(&control, &treatment);
+        assert!(degradation.is_some_and(|d| (d - 0.3375).abs() < 1e-3));
+
+        // The point estimate of 100% exceeds the maximum, but the bound of the interval does not.
+        let lenient = Guardrail::new(
+            MetricName::new("crash_rate").unwrap(),
+            MaxDegradation::new(0.7).unwrap(),
+        );
+        let (control, treatment) = tested(100, 200, 2000);
+        assert_eq!(lenient.breach(&control, &treatment), None);
+
+        // Nothing to compare against a zero control.
+        let (control, treatment) = tested(0, 200, 2000);
+        assert_eq!(guardrail.breach(&control, &treatment), None);
+    }
+
+    #[test]
+    fn test_experiment_guardrails() {
+        let metrics = ExperimentMetrics::new(vec![crash_rate()]).unwrap();
+        let guardrail = Guardrail::new(
+            MetricName::new("crash_rate").unwrap(),
+            MaxDegradation::new(0.1).unwrap(),
+        );
+
+        assert!(ExperimentGuardrails::new(vec![guardrail.clone()], &metrics).is_ok());
+        assert_eq!(
+            ExperimentGuardrails::new(vec![guardrail.clone(), guardrail], &metrics),
+            Err(ExperimentGuardrailsInvalidError::DuplicateMetric(
+                MetricName::new("crash_rate").unwrap()
+            ))
+        );
+        assert_eq!(
+            ExperimentGuardrails::new(
+                vec![Guardrail::new(
+                    MetricName::new("revenue").unwrap(),
+                    MaxDegradation::new(0.1).unwrap(),
+                )],
+                &metrics
+            ),
+            Err(ExperimentGuardrailsInvalidError::UnknownMetric(
+                MetricName::new("revenue").unwrap()
+            ))
+        );
+        assert_eq!(
+            ExperimentGuardrails::new(
+                vec![Guardrail::new(
+                    MetricName::new("revenue").unwrap(),
+                    MaxDegradation::new(0.1).unwrap(),
+                )],
+                &ExperimentMetrics::new(vec![Metric::new(
+                    MetricName::new("revenue").unwrap(),
+                    MetricKind::Mean(EventName::new("purchase").unwrap()),
+                    MetricRole::Secondary,
+                )])
+                .unwrap()
+            ),
+            Err(ExperimentGuardrailsInvalidError::NoMixingDeviation(
+                MetricName::new("revenue").unwrap()
+            ))
+        );
+        assert!(MaxDegradation::new(0.0).is_err());
+        assert!(MaxDegradation::new(f64::NAN).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use derive_more::Display;
+use thiserror::Error;
+
+/// Number of buckets the hash space of every layer is split into.
+pub const LAYER_BUCKETS: u32 = 1000;
+
+/// Represents always valid name of a layer, also called a universe: a partition of devices
+/// shared by mutually exclusive experiments.
+#[derive(Display, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LayerName(String);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("layer name cannot be empty")]
+pub struct LayerNameEmptyError;
+impl LayerName {
+    pub fn new(raw_name: &str) -> Result<Self, LayerNameEmptyError> {
+        let trimmed = raw_name.trim();
+        if trimmed.is_empty() {
+            Err(LayerNameEmptyError)
+        } else {
+            Ok(Self(trimmed.to_string()))
+        }
+    }
+}
+
+/// Represents always valid range of buckets of a layer, from `start` inclusive to `end`
+/// exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LayerBuckets {
+    start: u32,
+    end: u32,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error(
+    "layer buckets should be a non-empty range within 0 and {LAYER_BUCKETS}, got {start}..{end}"
+)]
+pub struct LayerBucketsInvalidError {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl LayerBuckets {
+    pub fn new(start: u32, end: u32) -> Result<Self, LayerBucketsInvalidError> {
+        if start < end && end <= LAYER_BUCKETS {
+            Ok(Self { start, end })
+        } else {
+            Err(LayerBucketsInvalidError { start, end })
+        }
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    pub fn contains(&self, bucket: u32) -> bool {
+        self.start <= bucket && bucket < self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+/// Represents the range of buckets of a layer an experiment claims. Devices hashed into the
+/// range are the only ones the experiment may enroll.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExperimentLayer {
+    name: LayerName,
+    buckets: LayerBuckets,
+}
+
+impl ExperimentLayer {
+    pub fn new(name: LayerName, buckets: LayerBuckets) -> Self {
+        Self { name, buckets }
+    }
+
+    pub fn name(&self) -> &LayerName {
+        &self.name
+    }
+
+    pub fn buckets(&self) -> &LayerBuckets {
+        &self.buckets
+    }
+
+    /// Whether the experiment shares devices with another one claiming buckets of the layer.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.name == other.name && self.buckets.overlaps(&other.buckets)
+    }
+}
+
+#[cfg(test)]
+mod layer_tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_buckets() {
+        assert!(LayerBuckets::new(0, LAYER_BUCKETS).is_ok());
+        assert_eq!(
+            LayerBuckets::new(500, 500),
+            Err(LayerBucketsInvalidError {
+                start: 500,
+                end: 500
+            })
+        );
+        assert!(LayerBuckets::new(0, LAYER_BUCKETS + 1).is_err());
+
+        let buckets = LayerBuckets::new(0, 500).unwrap();
+        assert!(buckets.contains(0));
+        assert!(buckets.contains(499));
+        assert!(!buckets.contains(500));
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let layer = |name: &str, start: u32, end: u32| {
+            ExperimentLayer::new(
+                LayerName::new(name).unwrap(),
+                LayerBuckets::new(start, end).unwrap(),
+            )
+        };
+
+        assert!(layer("checkout", 0, 500).overlaps(&layer("checkout", 499, 1000)));
+        assert!(layer("checkout", 100, 200).overlaps(&layer("checkout", 0, 1000)));
+        assert!(!layer("checkout", 0, 500).overlaps(&layer("checkout", 500, 1000)));
+        assert!(!layer("checkout", 0, 500).overlaps(&layer("home", 0, 500)));
+    }
+}
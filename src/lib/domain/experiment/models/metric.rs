@@ -0,0 +1,794 @@
+use std::collections::{HashMap, HashSet};
+
+use derive_more::Display;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::domain::device::models::device::{DeviceId, DeviceIdError};
+use crate::domain::event::models::event::{EventName, EventNameInvalidError};
+use crate::domain::experiment::models::experiment::{VariantData, VariantDataEmptyError};
+use crate::domain::statistics::bayesian::{BayesianComparison, Posterior};
+use crate::domain::statistics::cuped::{CovariateSample, CupedEstimate};
+use crate::domain::statistics::frequentist::{
+    MeanSample, ProportionSample, SignificanceLevel, SignificanceTest, two_proportion_z_test,
+    welch_t_test,
+};
+use crate::domain::statistics::sequential::{
+    MixingDeviation, sequential_mean_test, sequential_proportion_test,
+};
+
+/// Represents always valid metric name, unique within an experiment.
+#[derive(Display, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MetricName(String);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("metric name cannot be empty")]
+pub struct MetricNameEmptyError;
+impl MetricName {
+    pub fn new(raw_name: &str) -> Result<Self, MetricNameEmptyError> {
+        let trimmed = raw_name.trim();
+        if trimmed.is_empty() {
+            Err(MetricNameEmptyError)
+        } else {
+            Ok(Self(trimmed.to_string()))
+        }
+    }
+}
+
+/// Represents what a metric measures.
+#[derive(Display, Clone, Debug, PartialEq)]
+pub enum MetricKind {
+    /// Share of devices that sent the event at least once.
+    #[display("conversion")]
+    Conversion(EventName),
+    /// Sum of values of the event.
+    #[display("sum")]
+    Sum(EventName),
+    /// Mean value of the event.
+    #[display("mean")]
+    Mean(EventName),
+    /// Mean number of the events sent by a device.
+    #[display("count")]
+    Count(EventName),
+    /// Number of numerator events per a denominator event.
+    #[display("ratio")]
+    Ratio {
+        numerator: EventName,
+        denominator: EventName,
+    },
+}
+
+/// Represents importance of a metric for the outcome of an experiment.
+#[derive(Display, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MetricRole {
+    /// The metric the experiment is decided by.
+    #[display("primary")]
+    Primary,
+    #[default]
+    #[display("secondary")]
+    Secondary,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid metric role")]
+pub struct MetricRoleInvalidError(String);
+impl MetricRole {
+    pub fn new(raw_role: &str) -> Result<Self, MetricRoleInvalidError> {
+        match raw_role {
+            "primary" => Ok(Self::Primary),
+            "secondary" => Ok(Self::Secondary),
+            _ => Err(MetricRoleInvalidError(raw_role.to_string())),
+        }
+    }
+}
+
+/// Aggregated events of a single name sent by a device while it was assigned to a variant of
+/// an experiment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricObservation {
+    experiment_id: Uuid,
+    device_id: DeviceId,
+    data: VariantData,
+    event: EventName,
+    count: u64,
+    value_count: u64,
+    sum: f64,
+    sum_of_squares: f64,
+    segment: Option<String>,
+}
+
+impl MetricObservation {
+    pub fn new(
+        experiment_id: Uuid,
+        device_id: DeviceId,
+        data: VariantData,
+        event: EventName,
+        count: u64,
+        sum: f64,
+        sum_of_squares: f64,
+    ) -> Self {
+        Self {
+            experiment_id,
+            device_id,
+            data,
+            event,
+            count,
+            value_count: count,
+            sum,
+            sum_of_squares,
+            segment: None,
+        }
+    }
+
+    /// Sets the number of the events carrying a value, all of them by default.
+    pub fn with_value_count(mut self, value_count: u64) -> Self {
+        self.value_count = value_count;
+        self
+    }
+
+    pub fn with_segment(mut self, segment: Option<String>) -> Self {
+        self.segment = segment;
+        self
+    }
+
+    pub fn experiment_id(&self) -> &Uuid {
+        &self.experiment_id
+    }
+
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    pub fn data(&self) -> &VariantData {
+        &self.data
+    }
+
+    pub fn event(&self) -> &EventName {
+        &self.event
+    }
+
+    /// Number of the events.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Number of the events carrying a value, the ones a mean is taken over.
+    pub fn value_count(&self) -> u64 {
+        self.value_count
+    }
+
+    /// Sum of values of the events, events without a value are not summed.
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// Sum of squared values of the events, used to estimate their variance.
+    pub fn sum_of_squares(&self) -> f64 {
+        self.sum_of_squares
+    }
+
+    /// Value of the segment attribute of the device, `None` for devices without it or when no
+    /// segment is requested.
+    pub fn segment(&self) -> Option<&str> {
+        self.segment.as_deref()
+    }
+}
+
+/// Sample a metric is tested on, only conversion and mean metrics can be tested.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetricSample {
+    Proportion(ProportionSample),
+    Mean(MeanSample),
+}
+
+impl MetricSample {
+    /// Tests a treatment sample against the control one, with a two-proportion z-test for
+    /// proportions and Welch's t-test for means.
+    ///
+    /// # Returns
+    /// * `Some(SignificanceTest)` with the outcome of the test.
+    /// * `None` if the samples are of different kinds or too small to be tested.
+    pub fn test(&self, treatment: &Self, alpha: SignificanceLevel) -> Option<SignificanceTest> {
+        match (self, treatment) {
+            (Self::Proportion(control), Self::Proportion(treatment)) => {
+                two_proportion_z_test(control, treatment, alpha)
+            }
+            (Self::Mean(control), Self::Mean(treatment)) => welch_t_test(control, treatment, alpha),
+            _ => None,
+        }
+    }
+
+    /// Tests a treatment sample against the control one with the mixture sequential
+    /// probability ratio test, which stays valid under continuous monitoring.
+    ///
+    /// # Returns
+    /// * `Some(SignificanceTest)` with the outcome of the test.
+    /// * `None` if the samples are of different kinds or too small to be tested.
+    pub fn sequential_test(
+        &self,
+        treatment: &Self,
+        mixing: MixingDeviation,
+        alpha: SignificanceLevel,
+    ) -> Option<SignificanceTest> {
+        match (self, treatment) {
+            (Self::Proportion(control), Self::Proportion(treatment)) => {
+                sequential_proportion_test(control, treatment, mixing, alpha)
+            }
+            (Self::Mean(control), Self::Mean(treatment)) => {
+                sequential_mean_test(control, treatment, mixing, alpha)
+            }
+            _ => None,
+        }
+    }
+
+    /// Posterior of the value of the metric, Beta-Binomial for proportions and a normal
+    /// approximation for means.
+    ///
+    /// # Returns
+    /// * `Some(Posterior)` with the posterior.
+    /// * `None` if the sample is too small to approximate the posterior.
+    pub fn posterior(&self) -> Option<Posterior> {
+        match self {
+            Self::Proportion(sample) => Some(Posterior::from_proportion(sample)),
+            Self::Mean(sample) => Posterior::from_mean(sample),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metric {
+    name: MetricName,
+    kind: MetricKind,
+    role: MetricRole,
+    mixing_deviation: Option<MixingDeviation>,
+}
+
+impl Metric {
+    pub fn new(name: MetricName, kind: MetricKind, role: MetricRole) -> Self {
+        // Proportions share a scale, so conversions are tested sequentially out of the box.
+        let mixing_deviation = match kind {
+            MetricKind::Conversion(_) => Some(MixingDeviation::PROPORTION),
+            _ => None,
+        };
+
+        Self {
+            name,
+            kind,
+            role,
+            mixing_deviation,
+        }
+    }
+
+    pub fn with_mixing_deviation(mut self, mixing_deviation: MixingDeviation) -> Self {
+        self.mixing_deviation = Some(mixing_deviation);
+        self
+    }
+
+    pub fn name(&self) -> &MetricName {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &MetricKind {
+        &self.kind
+    }
+
+    pub fn role(&self) -> MetricRole {
+        self.role
+    }
+
+    /// Deviation of the effect the sequential test mixes over, fixed when the metric is defined.
+    /// `None` for metrics on a scale of their own, e.g. means, until it is set, which are not
+    /// tested sequentially.
+    pub fn mixing_deviation(&self) -> Option<MixingDeviation> {
+        self.mixing_deviation
+    }
+
+    /// Evaluates the metric for a variant.
+    ///
+    /// # Arguments
+    /// * `total_devices` - number of devices assigned to the variant.
+    /// * `observations` - events attributed to the variant.
+    ///
+    /// # Returns
+    /// * `Some(f64)` with the value of the metric.
+    /// * `None` if the metric is undefined, e.g. the variant has no devices yet.
+    pub fn evaluate(
+        &self,
+        total_devices: usize,
+        observations: &[&MetricObservation],
+    ) -> Option<f64> {
+        let per_device = |value: f64| (total_devices > 0).then(|| value / total_devices as f64);
+
+        match &self.kind {
+            MetricKind::Conversion(event) => {
+                let converted: HashSet<&DeviceId> = observations_of(observations, event)
+                    .map(|o| o.device_id())
+                    .collect();
+
+                per_device(converted.len() as f64)
+            }
+            MetricKind::Sum(event) => Some(sum_of(observations, event)),
+            MetricKind::Mean(event) => {
+                let count = value_count_of(observations, event);
+
+                (count > 0.0).then(|| sum_of(observations, event) / count)
+            }
+            MetricKind::Count(event) => per_device(count_of(observations, event)),
+            MetricKind::Ratio {
+                numerator,
+                denominator,
+            } => {
+                let denominator = count_of(observations, denominator);
+
+                (denominator > 0.0).then(|| count_of(observations, numerator) / denominator)
+            }
+        }
+    }
+
+    /// Collects the sample of a variant the metric is tested on.
+    ///
+    /// # Returns
+    /// * `Some(MetricSample)` for conversion and mean metrics.
+    /// * `None` for other metrics.
+    pub fn sample(
+        &self,
+        total_devices: usize,
+        observations: &[&MetricObservation],
+    ) -> Option<MetricSample> {
+        match &self.kind {
+            MetricKind::Conversion(event) => {
+                let converted: HashSet<&DeviceId> = observations_of(observations, event)
+                    .map(|o| o.device_id())
+                    .collect();
+
+                Some(MetricSample::Proportion(ProportionSample::new(
+                    converted.len() as u64,
+                    total_devices as u64,
+                )))
+            }
+            MetricKind::Mean(event) => {
+                // Events of a device are not independent, so the mean is tested over devices.
+                let mut units: HashMap<&DeviceId, (f64, u64)> = HashMap::new();
+                for o in observations_of(observations, event) {
+                    let unit = units.entry(o.device_id()).or_default();
+                    unit.0 += o.sum();
+                    unit.1 += o.value_count();
+                }
+                let units: Vec<(f64, u64)> = units.into_values().collect();
+
+                Some(MetricSample::Mean(MeanSample::from_unit_sums(&units)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Collects the values of the devices of a variant paired with their values before the
+    /// experiment, the sample CUPED adjusts.
+    ///
+    /// # Arguments
+    /// * `total_devices` - number of devices assigned to the variant.
+    /// * `observations` - events attributed to the variant.
+    /// * `pre_observations` - events the devices of the variant sent before the experiment.
+    ///
+    /// # Returns
+    /// * `Some(CovariateSample)` for conversion, sum and count metrics, which have a value per
+    ///   device.
+    /// * `None` for other metrics.
+    pub fn covariate_sample(
+        &self,
+        total_devices: usize,
+        observations: &[&MetricObservation],
+        pre_observations: &[&MetricObservation],
+    ) -> Option<CovariateSample> {
+        let (event, value): (&EventName, fn(&MetricObservation) -> f64) = match &self.kind {
+            MetricKind::Conversion(event) => (event, |_| 1.0),
+            MetricKind::Sum(event) => (event, |o| o.sum()),
+            MetricKind::Count(event) => (event, |o| o.count() as f64),
+            _ => return None,
+        };
+
+        let mut values: HashMap<&DeviceId, (f64, f64)> = HashMap::new();
+        for o in observations_of(observations, event) {
+            values.entry(o.device_id()).or_default().0 += value(o);
+        }
+        for o in observations_of(pre_observations, event) {
+            values.entry(o.device_id()).or_default().1 += value(o);
+        }
+
+        let values: Vec<(f64, f64)> = values
+            .into_values()
+            .map(|(outcome, covariate)| match self.kind {
+                // A device converts once however many events it sends.
+                MetricKind::Conversion(_) => (outcome.min(1.0), covariate.min(1.0)),
+                _ => (outcome, covariate),
+            })
+            .collect();
+
+        Some(CovariateSample::new(total_devices as u64, &values))
+    }
+}
+
+fn observations_of<'a>(
+    observations: &'a [&'a MetricObservation],
+    event: &'a EventName,
+) -> impl Iterator<Item = &'a MetricObservation> {
+    observations
+        .iter()
+        .copied()
+        .filter(move |o| o.event() == event)
+}
+
+fn count_of(observations: &[&MetricObservation], event: &EventName) -> f64 {
+    observations_of(observations, event)
+        .map(|o| o.count())
+        .sum::<u64>() as f64
+}
+
+fn value_count_of(observations: &[&MetricObservation], event: &EventName) -> f64 {
+    observations_of(observations, event)
+        .map(|o| o.value_count())
+        .sum::<u64>() as f64
+}
+
+fn sum_of(observations: &[&MetricObservation], event: &EventName) -> f64 {
+    observations_of(observations, event).fold(0.0, |sum, o| sum + o.sum())
+}
+
+/// Represents always valid list of metrics of an experiment, with unique names and at most one
+/// primary metric.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExperimentMetrics(Vec<Metric>);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum ExperimentMetricsInvalidError {
+    #[error("metric name {0} is used more than once")]
+    DuplicateName(MetricName),
+    #[error("experiment can have only one primary metric")]
+    MultiplePrimary,
+}
+
+impl ExperimentMetrics {
+    pub fn new(metrics: Vec<Metric>) -> Result<Self, ExperimentMetricsInvalidError> {
+        let mut names = HashSet::new();
+        for metric in &metrics {
+            if !names.insert(metric.name()) {
+                return Err(ExperimentMetricsInvalidError::DuplicateName(
+                    metric.name().to_owned(),
+                ));
+            }
+        }
+
+        let primary = metrics
+            .iter()
+            .filter(|m| m.role() == MetricRole::Primary)
+            .count();
+        if primary > 1 {
+            return Err(ExperimentMetricsInvalidError::MultiplePrimary);
+        }
+
+        Ok(Self(metrics))
+    }
+
+    pub fn metrics(&self) -> &Vec<Metric> {
+        &self.0
+    }
+
+    pub fn primary(&self) -> Option<&Metric> {
+        self.0.iter().find(|m| m.role() == MetricRole::Primary)
+    }
+}
+
+/// Value of a metric for a single variant of an experiment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatisticsMetric {
+    name: MetricName,
+    kind: MetricKind,
+    role: MetricRole,
+    value: Option<f64>,
+    test: Option<SignificanceTest>,
+    comparison: Option<BayesianComparison>,
+    cuped: Option<CupedEstimate>,
+}
+
+impl StatisticsMetric {
+    pub fn new(metric: &Metric, value: Option<f64>) -> Self {
+        Self {
+            name: metric.name().to_owned(),
+            kind: metric.kind().to_owned(),
+            role: metric.role(),
+            value,
+            test: None,
+            comparison: None,
+            cuped: None,
+        }
+    }
+
+    pub fn with_test(mut self, test: Option<SignificanceTest>) -> Self {
+        self.test = test;
+        self
+    }
+
+    pub fn with_comparison(mut self, comparison: Option<BayesianComparison>) -> Self {
+        self.comparison = comparison;
+        self
+    }
+
+    pub fn with_cuped(mut self, cuped: Option<CupedEstimate>) -> Self {
+        self.cuped = cuped;
+        self
+    }
+
+    pub fn name(&self) -> &MetricName {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &MetricKind {
+        &self.kind
+    }
+
+    pub fn role(&self) -> MetricRole {
+        self.role
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+
+    /// Comparison of the variant against the control, `None` for the control itself and for
+    /// metrics that cannot be tested.
+    pub fn test(&self) -> &Option<SignificanceTest> {
+        &self.test
+    }
+
+    /// Comparison of the variant against all the others, `None` unless the statistics are
+    /// computed with the bayesian method and the metric can be sampled in every variant.
+    pub fn comparison(&self) -> &Option<BayesianComparison> {
+        &self.comparison
+    }
+
+    /// Effect against the control adjusted by the values of the devices before the experiment,
+    /// `None` unless requested and for the control itself.
+    pub fn cuped(&self) -> &Option<CupedEstimate> {
+        &self.cuped
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GetAllMetricObservationsError {
+    #[error(transparent)]
+    DeviceId(#[from] DeviceIdError),
+    #[error(transparent)]
+    VariantData(#[from] VariantDataEmptyError),
+    #[error(transparent)]
+    EventName(#[from] EventNameInvalidError),
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[cfg(test)]
+mod metric_tests {
+    use super::*;
+    use crate::domain::device::models::device::DeviceIdKind;
+
+    fn observation(raw_idfa: &str, event: &str, count: u64, sum: f64) -> MetricObservation {
+        MetricObservation::new(
+            Uuid::nil(),
+            DeviceId::new(DeviceIdKind::Idfa, raw_idfa).unwrap(),
+            VariantData::new("blue").unwrap(),
+            EventName::new(event).unwrap(),
+            count,
+            sum,
+            sum * sum,
+        )
+    }
+
+    fn metric(kind: MetricKind) -> Metric {
+        Metric::new(MetricName::new("goal").unwrap(), kind, MetricRole::Primary)
+    }
+
+    fn event(name: &str) -> EventName {
+        EventName::new(name).unwrap()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let observations = [
+            observation("550e8400-e29b-41d4-a716-446655440000", "purchase", 2, 30.0),
+            observation("6ba7b810-9dad-11d1-80b4-00c04fd430c8", "purchase", 1, 10.0),
+            observation("6ba7b810-9dad-11d1-80b4-00c04fd430c8", "view", 8, 0.0),
+        ];
+        let observations: Vec<&MetricObservation> = observations.iter().collect();
+
+        let cases = [
+            (MetricKind::Conversion(event("purchase")), Some(0.5)),
+            (MetricKind::Sum(event("purchase")), Some(40.0)),
+            (MetricKind::Mean(event("purchase")), Some(40.0 / 3.0)),
+            (MetricKind::Count(event("purchase")), Some(0.75)),
+            (
+                MetricKind::Ratio {
+                    numerator: event("purchase"),
+                    denominator: event("view"),
+                },
+                Some(0.375),
+            ),
+        ];
+
+        for (kind, expected) in cases {
+            assert_eq!(metric(kind).evaluate(4, &observations), expected);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_mean_skips_events_without_value() {
+        // Three purchases of which only two carry a value.
+        let observations =
+            [
+                observation("550e8400-e29b-41d4-a716-446655440000", "purchase", 3, 30.0)
+                    .with_value_count(2),
+            ];
+        let observations: Vec<&MetricObservation> = observations.iter().collect();
+
+        let mean = metric(MetricKind::Mean(event("purchase")));
+        let count = metric(MetricKind::Count(event("purchase")));
+
+        assert_eq!(mean.evaluate(1, &observations), Some(15.0));
+        assert_eq!(count.evaluate(1, &observations), Some(3.0));
+    }
+
+    #[test]
+    fn test_evaluate_undefined() {
+        let conversion = metric(MetricKind::Conversion(event("purchase")));
+        let mean = metric(MetricKind::Mean(event("purchase")));
+
+        assert_eq!(conversion.evaluate(0, &[]), None);
+        assert_eq!(mean.evaluate(4, &[]), None);
+    }
+
+    #[test]
+    fn test_sample() {
+        let observations = [
+            observation("550e8400-e29b-41d4-a716-446655440000", "purchase", 1, 3.0),
+            observation("6ba7b810-9dad-11d1-80b4-00c04fd430c8", "purchase", 1, 5.0),
+        ];
+        let observations: Vec<&MetricObservation> = observations.iter().collect();
+
+        let conversion = metric(MetricKind::Conversion(event("purchase")));
+        let mean = metric(MetricKind::Mean(event("purchase")));
+        let count = metric(MetricKind::Count(event("purchase")));
+
+        assert_eq!(
+            conversion.sample(4, &observations),
+            Some(MetricSample::Proportion(ProportionSample::new(2, 4)))
+        );
+        assert_eq!(
+            mean.sample(4, &observations),
+            Some(MetricSample::Mean(MeanSample::new(2, 4.0, 2.0)))
+        );
+        assert_eq!(count.sample(4, &observations), None);
+    }
+
+    #[test]
+    fn test_covariate_sample() {
+        let first = "550e8400-e29b-41d4-a716-446655440000";
+        let second = "6ba7b810-9dad-11d1-80b4-00c04fd430c8";
+        let third = "6ba7b811-9dad-11d1-80b4-00c04fd430c8";
+        let observations = [
+            observation(first, "purchase", 1, 3.0),
+            observation(second, "purchase", 1, 5.0),
+        ];
+        let observations: Vec<&MetricObservation> = observations.iter().collect();
+        let pre_observations = [
+            observation(first, "purchase", 2, 4.0),
+            observation(third, "purchase", 1, 1.0),
+        ];
+        let pre_observations: Vec<&MetricObservation> = pre_observations.iter().collect();
+
+        let conversion = metric(MetricKind::Conversion(event("purchase")));
+        let sum = metric(MetricKind::Sum(event("purchase")));
+        let mean = metric(MetricKind::Mean(event("purchase")));
+
+        assert_eq!(
+            conversion.covariate_sample(4, &observations, &pre_observations),
+            Some(CovariateSample::new(
+                4,
+                &[(1.0, 1.0), (1.0, 0.0), (0.0, 1.0)]
+            ))
+        );
+        assert_eq!(
+            sum.covariate_sample(4, &observations, &pre_observations),
+            Some(CovariateSample::new(
+                4,
+                &[(3.0, 4.0), (5.0, 0.0), (0.0, 1.0)]
+            ))
+        );
+        assert_eq!(
+            mean.covariate_sample(4, &observations, &pre_observations),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sample_posterior() {
+        assert_eq!(
+            MetricSample::Proportion(ProportionSample::new(2, 4)).posterior(),
+            Some(Posterior::Beta {
+                alpha: 3.0,
+                beta: 3.0
+            })
+        );
+        assert_eq!(
+            MetricSample::Mean(MeanSample::new(2, 4.0, 2.0)).posterior(),
+            Some(Posterior::Normal {
+                mean: 4.0,
+                standard_deviation: 1.0
+            })
+        );
+        assert_eq!(
+            MetricSample::Mean(MeanSample::new(1, 4.0, 0.0)).posterior(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_mean_posterior_is_over_devices() {
+        let mean = metric(MetricKind::Mean(event("purchase")));
+
+        // Many purchases of a single device tell nothing about the spread between devices.
+        let single = [observation(
+            "550e8400-e29b-41d4-a716-446655440000",
+            "purchase",
+            50,
+            500.0,
+        )];
+        let single: Vec<&MetricObservation> = single.iter().collect();
+
+        assert_eq!(mean.sample(1, &single).unwrap().posterior(), None);
+
+        let observations = [
+            observation("550e8400-e29b-41d4-a716-446655440000", "purchase", 3, 3.0),
+            observation("6ba7b810-9dad-11d1-80b4-00c04fd430c8", "purchase", 1, 4.0),
+        ];
+        let observations: Vec<&MetricObservation> = observations.iter().collect();
+
+        assert_eq!(
+            mean.sample(2, &observations).unwrap().posterior(),
+            Some(Posterior::Normal {
+                mean: 1.75,
+                // Delta method variance of 2.53125 over two devices.
+                standard_deviation: (2.0 * 2.25 * 2.25 / 4.0 / 2.0_f64).sqrt(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_new_metrics_multiple_primary() {
+        let result = ExperimentMetrics::new(vec![
+            metric(MetricKind::Sum(event("purchase"))),
+            Metric::new(
+                MetricName::new("views").unwrap(),
+                MetricKind::Count(event("view")),
+                MetricRole::Primary,
+            ),
+        ]);
+        let expected = Err(ExperimentMetricsInvalidError::MultiplePrimary);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_new_metrics_duplicate_name() {
+        let result = ExperimentMetrics::new(vec![
+            metric(MetricKind::Sum(event("purchase"))),
+            Metric::new(
+                MetricName::new("goal").unwrap(),
+                MetricKind::Count(event("view")),
+                MetricRole::Secondary,
+            ),
+        ]);
+        let expected = Err(ExperimentMetricsInvalidError::DuplicateName(
+            MetricName::new("goal").unwrap(),
+        ));
+
+        assert_eq!(result, expected);
+    }
+}
@@ -0,0 +1,377 @@
+use thiserror::Error;
+
+use crate::domain::experiment::models::experiment::{
+    ExperimentAllocation, ExperimentEligibility, ExperimentVariants, Variant, VariantData,
+};
+use crate::domain::statistics::frequentist::SignificanceLevel;
+use crate::domain::statistics::power::{PlannedGroup, Power, required_sample_size};
+
+/// Represents always valid value of a metric without the experiment, the value effects are
+/// relative to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Baseline {
+    /// Share of devices that convert.
+    Conversion(f64),
+    /// Mean value per device and its variance across devices.
+    Mean { mean: f64, variance: f64 },
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum BaselineInvalidError {
+    #[error("baseline conversion rate should be more than zero and less than 1")]
+    ConversionRate,
+    #[error("baseline mean should be a finite non-zero number")]
+    Mean,
+    #[error("baseline variance should be more than zero")]
+    Variance,
+}
+
+impl Baseline {
+    pub fn conversion(rate: f64) -> Result<Self, BaselineInvalidError> {
+        if rate > 0.0 && rate < 1.0 {
+            Ok(Self::Conversion(rate))
+        } else {
+            Err(BaselineInvalidError::ConversionRate)
+        }
+    }
+
+    pub fn mean(mean: f64, variance: f64) -> Result<Self, BaselineInvalidError> {
+        if !mean.is_finite() || mean == 0.0 {
+            return Err(BaselineInvalidError::Mean);
+        }
+        if !variance.is_finite() || variance <= 0.0 {
+            return Err(BaselineInvalidError::Variance);
+        }
+
+        Ok(Self::Mean { mean, variance })
+    }
+
+    pub fn value(&self) -> f64 {
+        match self {
+            Self::Conversion(rate) => *rate,
+            Self::Mean { mean, .. } => *mean,
+        }
+    }
+
+    /// Variance per device once the metric has the given value. Only the variance of a
+    /// conversion depends on its value.
+    fn variance_at(&self, value: f64) -> f64 {
+        match self {
+            Self::Conversion(_) => value * (1.0 - value),
+            Self::Mean { variance, .. } => *variance,
+        }
+    }
+}
+
+/// Represents always valid smallest effect an experiment should detect, relative to the
+/// baseline, e.g. `0.05` for a lift of 5%.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct MinimumDetectableEffect(f64);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("minimum detectable effect should be more than zero")]
+pub struct MinimumDetectableEffectInvalidError;
+impl MinimumDetectableEffect {
+    pub fn new(value: f64) -> Result<Self, MinimumDetectableEffectInvalidError> {
+        if value.is_finite() && value > 0.0 {
+            Ok(Self(value))
+        } else {
+            Err(MinimumDetectableEffectInvalidError)
+        }
+    }
+
+    pub fn into_inner(self) -> f64 {
+        self.0
+    }
+}
+
+/// Data required by the domain to calculate the [SampleSize] of a planned experiment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleSizeRequest {
+    baseline: Baseline,
+    effect: MinimumDetectableEffect,
+    variants: ExperimentVariants,
+    alpha: SignificanceLevel,
+    power: Power,
+    allocation: ExperimentAllocation,
+    eligibility: ExperimentEligibility,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum SampleSizeRequestInvalidError {
+    #[error("at least two variants are required to compare")]
+    SingleVariant,
+    #[error(
+        "baseline conversion rate increased by the minimum detectable effect should be less than 1"
+    )]
+    ConversionEffect,
+}
+
+impl SampleSizeRequest {
+    /// # Arguments
+    /// * `baseline` - value of the metric in the control variant.
+    /// * `effect` - smallest effect to detect in every other variant.
+    /// * `variants` - planned variants, the first one is the control.
+    pub fn new(
+        baseline: Baseline,
+        effect: MinimumDetectableEffect,
+        variants: ExperimentVariants,
+    ) -> Result<Self, SampleSizeRequestInvalidError> {
+        if variants.variants().len() < 2 {
+            return Err(SampleSizeRequestInvalidError::SingleVariant);
+        }
+        if let Baseline::Conversion(rate) = baseline
+            && rate * (1.0 + effect.into_inner()) >= 1.0
+        {
+            return Err(SampleSizeRequestInvalidError::ConversionEffect);
+        }
+
+        Ok(Self {
+            baseline,
+            effect,
+            variants,
+            alpha: SignificanceLevel::default(),
+            power: Power::default(),
+            allocation: ExperimentAllocation::FULL,
+            eligibility: ExperimentEligibility::default(),
+        })
+    }
+
+    pub fn with_alpha(mut self, alpha: SignificanceLevel) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn with_power(mut self, power: Power) -> Self {
+        self.power = power;
+        self
+    }
+
+    pub fn with_allocation(mut self, allocation: ExperimentAllocation) -> Self {
+        self.allocation = allocation;
+        self
+    }
+
+    pub fn with_eligibility(mut self, eligibility: ExperimentEligibility) -> Self {
+        self.eligibility = eligibility;
+        self
+    }
+
+    pub fn baseline(&self) -> &Baseline {
+        &self.baseline
+    }
+
+    pub fn effect(&self) -> MinimumDetectableEffect {
+        self.effect
+    }
+
+    pub fn variants(&self) -> &ExperimentVariants {
+        &self.variants
+    }
+
+    pub fn alpha(&self) -> SignificanceLevel {
+        self.alpha
+    }
+
+    pub fn power(&self) -> Power {
+        self.power
+    }
+
+    /// Planned share of devices exposed to the experiment.
+    pub fn allocation(&self) -> ExperimentAllocation {
+        self.allocation
+    }
+
+    /// Planned devices that may be enrolled into the experiment.
+    pub fn eligibility(&self) -> ExperimentEligibility {
+        self.eligibility
+    }
+
+    /// Calculates the devices every variant needs for each comparison against the control to
+    /// reach the power. The comparisons are not corrected for multiple testing.
+    pub fn required_devices(&self) -> Vec<VariantSampleSize> {
+        let share = |variant: &Variant| variant.distribution().into_inner() / 100.0;
+
+        let variants = self.variants.variants();
+        let baseline = self.baseline.value();
+        let difference = baseline * self.effect.into_inner();
+        let control = PlannedGroup::new(self.baseline.variance_at(baseline), share(&variants[0]));
+        let treatment_variance = self.baseline.variance_at(baseline + difference);
+
+        let total_devices = variants[1..]
+            .iter()
+            .filter_map(|variant| {
+                required_sample_size(
+                    control,
+                    PlannedGroup::new(treatment_variance, share(variant)),
+                    difference,
+                    self.alpha,
+                    self.power,
+                )
+            })
+            .fold(0.0, f64::max);
+
+        variants
+            .iter()
+            .map(|variant| {
+                VariantSampleSize::new(
+                    variant.data().to_owned(),
+                    (total_devices * share(variant)).ceil() as u64,
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariantSampleSize {
+    data: VariantData,
+    devices: u64,
+}
+
+impl VariantSampleSize {
+    pub fn new(data: VariantData, devices: u64) -> Self {
+        Self { data, devices }
+    }
+
+    pub fn data(&self) -> &VariantData {
+        &self.data
+    }
+
+    pub fn devices(&self) -> u64 {
+        self.devices
+    }
+}
+
+/// Devices a planned experiment needs and the time it takes them to arrive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleSize {
+    variants: Vec<VariantSampleSize>,
+    daily_devices: f64,
+}
+
+impl SampleSize {
+    /// Number of recent days the rate of active eligible devices is estimated over.
+    pub const ACTIVITY_WINDOW_DAYS: i64 = 28;
+
+    /// # Arguments
+    /// * `variants` - devices required by each of the variants.
+    /// * `daily_devices` - devices expected to be exposed to the experiment per day, i.e. active
+    ///   devices the eligibility of the experiment admits, within its allocation.
+    pub fn new(variants: Vec<VariantSampleSize>, daily_devices: f64) -> Self {
+        Self {
+            variants,
+            daily_devices,
+        }
+    }
+
+    pub fn variants(&self) -> &Vec<VariantSampleSize> {
+        &self.variants
+    }
+
+    pub fn total_devices(&self) -> u64 {
+        self.variants.iter().map(|v| v.devices()).sum()
+    }
+
+    pub fn daily_devices(&self) -> f64 {
+        self.daily_devices
+    }
+
+    /// Estimated number of days to collect the devices, `None` if no devices were active recently.
+    pub fn duration_days(&self) -> Option<u64> {
+        (self.daily_devices > 0.0)
+            .then(|| (self.total_devices() as f64 / self.daily_devices).ceil() as u64)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CalculateSampleSizeError {
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[cfg(test)]
+mod sample_size_tests {
+    use super::*;
+    use crate::domain::experiment::models::experiment::VariantDistribution;
+
+    fn variants(distributions: &[f64]) -> ExperimentVariants {
+        ExperimentVariants::new(
+            distributions
+                .iter()
+                .enumerate()
+                .map(|(i, &distribution)| {
+                    Variant::new(
+                        VariantDistribution::new(distribution).unwrap(),
+                        VariantData::new(&format!("v{i}")).unwrap(),
+                    )
+                })
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    fn devices(sample_sizes: &[VariantSampleSize]) -> Vec<u64> {
+        sample_sizes.iter().map(|s| s.devices()).collect()
+    }
+
+    #[test]
+    fn test_required_devices_conversion() {
+        // Conversion of 10% against 11%: 29496.09 devices in total.
+        let req = SampleSizeRequest::new(
+            Baseline::conversion(0.1).unwrap(),
+            MinimumDetectableEffect::new(0.1).unwrap(),
+            variants(&[50.0, 50.0]),
+        )
+        .unwrap();
+
+        assert_eq!(devices(&req.required_devices()), vec![14749, 14749]);
+    }
+
+    #[test]
+    fn test_required_devices_unequal_split() {
+        // Mean of 20 against 21 with variance of 25: 1226.39 devices in total.
+        let req = SampleSizeRequest::new(
+            Baseline::mean(20.0, 25.0).unwrap(),
+            MinimumDetectableEffect::new(0.05).unwrap(),
+            variants(&[80.0, 20.0]),
+        )
+        .unwrap();
+
+        assert_eq!(devices(&req.required_devices()), vec![982, 246]);
+    }
+
+    #[test]
+    fn test_new_request_invalid() {
+        assert_eq!(
+            SampleSizeRequest::new(
+                Baseline::conversion(0.1).unwrap(),
+                MinimumDetectableEffect::new(0.1).unwrap(),
+                variants(&[100.0]),
+            ),
+            Err(SampleSizeRequestInvalidError::SingleVariant)
+        );
+        assert_eq!(
+            SampleSizeRequest::new(
+                Baseline::conversion(0.6).unwrap(),
+                MinimumDetectableEffect::new(1.0).unwrap(),
+                variants(&[50.0, 50.0]),
+            ),
+            Err(SampleSizeRequestInvalidError::ConversionEffect)
+        );
+    }
+
+    #[test]
+    fn test_duration_days() {
+        let variants = vec![
+            VariantSampleSize::new(VariantData::new("a").unwrap(), 150),
+            VariantSampleSize::new(VariantData::new("b").unwrap(), 151),
+        ];
+
+        assert_eq!(
+            SampleSize::new(variants.clone(), 100.0).duration_days(),
+            Some(4)
+        );
+        assert_eq!(SampleSize::new(variants, 0.0).duration_days(), None);
+    }
+}
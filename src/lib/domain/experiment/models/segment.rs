@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::domain::experiment::models::experiment::{
+    GetStatisticsRequest, StatisticsVariants, VariantData,
+};
+use crate::domain::experiment::models::targeting::TargetingAttribute;
+
+/// Represents always valid period statistics of an experiment are limited to. Only devices
+/// assigned within the period and their events that occurred within it are counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StatisticsPeriod {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("start of the period should be before its end")]
+pub struct StatisticsPeriodInvalidError;
+impl StatisticsPeriod {
+    pub fn new(
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Self, StatisticsPeriodInvalidError> {
+        match (from, to) {
+            (Some(from), Some(to)) if from >= to => Err(StatisticsPeriodInvalidError),
+            _ => Ok(Self { from, to }),
+        }
+    }
+
+    /// Inclusive start of the period, `None` when it is open.
+    pub fn from(&self) -> Option<&DateTime<Utc>> {
+        self.from.as_ref()
+    }
+
+    /// Exclusive end of the period, `None` when it is open.
+    pub fn to(&self) -> Option<&DateTime<Utc>> {
+        self.to.as_ref()
+    }
+}
+
+/// Data required by the domain to compute statistics of a single experiment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GetExperimentStatisticsRequest {
+    id: Uuid,
+    statistics: GetStatisticsRequest,
+    period: StatisticsPeriod,
+    segment: Option<TargetingAttribute>,
+}
+
+impl GetExperimentStatisticsRequest {
+    pub fn new(id: Uuid, statistics: GetStatisticsRequest) -> Self {
+        Self {
+            id,
+            statistics,
+            period: StatisticsPeriod::default(),
+            segment: None,
+        }
+    }
+
+    pub fn with_period(mut self, period: StatisticsPeriod) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// Requests a breakdown of the statistics by values of the device attribute. The current
+    /// value of the attribute is used, so a device that changed it since its assignment moves to
+    /// the segment of the new value.
+    pub fn with_segment(mut self, attribute: TargetingAttribute) -> Self {
+        self.segment = Some(attribute);
+        self
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn statistics(&self) -> &GetStatisticsRequest {
+        &self.statistics
+    }
+
+    pub fn period(&self) -> &StatisticsPeriod {
+        &self.period
+    }
+
+    /// Attribute the statistics are broken down by, `None` when it is not requested.
+    pub fn segment(&self) -> Option<&TargetingAttribute> {
+        self.segment.as_ref()
+    }
+}
+
+/// Represents the number of devices assigned to a variant under a version of an experiment
+/// that share a value of the segment attribute.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParticipantCount {
+    data: VariantData,
+    version: u32,
+    segment: Option<String>,
+    devices: usize,
+}
+
+impl ParticipantCount {
+    pub fn new(data: VariantData, version: u32, segment: Option<String>, devices: usize) -> Self {
+        Self {
+            data,
+            version,
+            segment,
+            devices,
+        }
+    }
+
+    pub fn data(&self) -> &VariantData {
+        &self.data
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Value of the segment attribute, `None` for devices without it or when no segment is
+    /// requested.
+    pub fn segment(&self) -> Option<&str> {
+        self.segment.as_deref()
+    }
+
+    pub fn devices(&self) -> usize {
+        self.devices
+    }
+}
+
+/// Statistics of the devices of an experiment that share a value of the segment attribute.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatisticsSegment {
+    value: Option<String>,
+    total_devices: usize,
+    variants: StatisticsVariants,
+}
+
+impl StatisticsSegment {
+    pub fn new(value: Option<String>, total_devices: usize, variants: StatisticsVariants) -> Self {
+        Self {
+            value,
+            total_devices,
+            variants,
+        }
+    }
+
+    /// Value of the segment attribute, `None` for devices without it.
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    pub fn total_devices(&self) -> usize {
+        self.total_devices
+    }
+
+    pub fn variants(&self) -> &StatisticsVariants {
+        &self.variants
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GetExperimentStatisticsError {
+    #[error("experiment with id {id} does not exist")]
+    NotFound { id: Uuid },
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[cfg(test)]
+mod segment_tests {
+    use super::*;
+
+    #[test]
+    fn test_statistics_period() {
+        let from: DateTime<Utc> = "2025-07-01T00:00:00Z".parse().unwrap();
+        let to: DateTime<Utc> = "2025-07-08T00:00:00Z".parse().unwrap();
+
+        let period = StatisticsPeriod::new(Some(from), Some(to)).unwrap();
+        assert_eq!(period.from(), Some(&from));
+        assert_eq!(period.to(), Some(&to));
+        assert!(StatisticsPeriod::new(Some(from), None).is_ok());
+        assert!(StatisticsPeriod::new(None, Some(to)).is_ok());
+        assert_eq!(
+            StatisticsPeriod::new(Some(to), Some(from)),
+            Err(StatisticsPeriodInvalidError)
+        );
+        assert_eq!(
+            StatisticsPeriod::new(Some(from), Some(from)),
+            Err(StatisticsPeriodInvalidError)
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use derive_more::Display;
+use thiserror::Error;
+
+use crate::domain::device::models::device::DeviceAttributes;
+
+/// Attributes of a device that targeting rules are matched against, e.g. `platform`,
+/// `app_version`, `country`, `locale` or any custom key.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TargetingAttributes(HashMap<String, String>);
+
+impl TargetingAttributes {
+    pub fn new(attributes: HashMap<String, String>) -> Self {
+        Self(attributes)
+    }
+
+    pub fn get(&self, attribute: &TargetingAttribute) -> Option<&String> {
+        self.0.get(&attribute.0)
+    }
+
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        &self.0
+    }
+}
+
+/// Exposes reported device attributes to targeting rules as `platform`, `os_version`,
+/// `app_version`, `country`, `locale` and the free-form properties under their own keys.
+impl From<&DeviceAttributes> for TargetingAttributes {
+    fn from(device_attributes: &DeviceAttributes) -> Self {
+        let mut attributes = device_attributes.properties().clone();
+
+        let known = [
+            (
+                "platform",
+                device_attributes.platform().map(|p| p.to_string()),
+            ),
+            ("os_version", device_attributes.os_version().clone()),
+            ("app_version", device_attributes.app_version().clone()),
+            ("country", device_attributes.country().clone()),
+            ("locale", device_attributes.locale().clone()),
+        ];
+        for (attribute, value) in known {
+            if let Some(value) = value {
+                attributes.insert(attribute.to_string(), value);
+            }
+        }
+
+        Self(attributes)
+    }
+}
+
+/// Represents always valid name of a targeted attribute.
+#[derive(Display, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TargetingAttribute(String);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("targeting attribute cannot be empty")]
+pub struct TargetingAttributeEmptyError;
+impl TargetingAttribute {
+    pub fn new(raw_attribute: &str) -> Result<Self, TargetingAttributeEmptyError> {
+        let trimmed = raw_attribute.trim();
+        if trimmed.is_empty() {
+            Err(TargetingAttributeEmptyError)
+        } else {
+            Ok(Self(trimmed.to_string()))
+        }
+    }
+}
+
+/// Represents semantic version of an application.
+///
+/// Missing minor and patch components are treated as zeros. Pre-release and build metadata are
+/// ignored.
+#[derive(Display, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[display("{major}.{minor}.{patch}")]
+pub struct Semver {
+    major: u64,
+    minor: u64,
+    patch: u64,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid semantic version")]
+pub struct SemverInvalidError(String);
+impl Semver {
+    pub fn new(raw_version: &str) -> Result<Self, SemverInvalidError> {
+        let error = || SemverInvalidError(raw_version.to_string());
+
+        let core = raw_version
+            .trim()
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .ok_or_else(error)?;
+
+        let components = core
+            .split('.')
+            .map(|c| c.parse::<u64>().map_err(|_| error()))
+            .collect::<Result<Vec<u64>, SemverInvalidError>>()?;
+
+        match components.as_slice() {
+            [major] => Ok(Self {
+                major: *major,
+                minor: 0,
+                patch: 0,
+            }),
+            [major, minor] => Ok(Self {
+                major: *major,
+                minor: *minor,
+                patch: 0,
+            }),
+            [major, minor, patch] => Ok(Self {
+                major: *major,
+                minor: *minor,
+                patch: *patch,
+            }),
+            _ => Err(error()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SemverOperator {
+    Eq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SemverComparator {
+    operator: SemverOperator,
+    version: Semver,
+}
+
+impl SemverComparator {
+    fn matches(&self, version: &Semver) -> bool {
+        let ordering = version.cmp(&self.version);
+
+        match self.operator {
+            SemverOperator::Eq => ordering == Ordering::Equal,
+            SemverOperator::Lt => ordering == Ordering::Less,
+            SemverOperator::Lte => ordering != Ordering::Greater,
+            SemverOperator::Gt => ordering == Ordering::Greater,
+            SemverOperator::Gte => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// Represents always valid range of semantic versions, e.g. `>=1.2.0 <2.0.0`, `^1.4` or `~1.4.2`.
+///
+/// Comparators are separated by spaces or commas, and a version has to satisfy all of them.
+#[derive(Display, Clone, Debug, PartialEq, Eq)]
+#[display("{raw}")]
+pub struct SemverRange {
+    raw: String,
+    comparators: Vec<SemverComparator>,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid semantic version range")]
+pub struct SemverRangeInvalidError(String);
+impl SemverRange {
+    pub fn new(raw_range: &str) -> Result<Self, SemverRangeInvalidError> {
+        let error = || SemverRangeInvalidError(raw_range.to_string());
+
+        let mut comparators = Vec::new();
+        for token in raw_range
+            .split([' ', ','])
+            .filter(|token| !token.is_empty())
+        {
+            let (operator, raw_version) = if let Some(v) = token.strip_prefix(">=") {
+                (">=", v)
+            } else if let Some(v) = token.strip_prefix("<=") {
+                ("<=", v)
+            } else if let Some(v) = token.strip_prefix('>') {
+                (">", v)
+            } else if let Some(v) = token.strip_prefix('<') {
+                ("<", v)
+            } else if let Some(v) = token.strip_prefix('=') {
+                ("=", v)
+            } else if let Some(v) = token.strip_prefix('^') {
+                ("^", v)
+            } else if let Some(v) = token.strip_prefix('~') {
+                ("~", v)
+            } else {
+                ("=", token)
+            };
+
+            let version = Semver::new(raw_version).map_err(|_| error())?;
+
+            match operator {
+                ">=" => comparators.push(SemverComparator {
+                    operator: SemverOperator::Gte,
+                    version,
+                }),
+                "<=" => comparators.push(SemverComparator {
+                    operator: SemverOperator::Lte,
+                    version,
+                }),
+                ">" => comparators.push(SemverComparator {
+                    operator: SemverOperator::Gt,
+                    version,
+                }),
+                "<" => comparators.push(SemverComparator {
+                    operator: SemverOperator::Lt,
+                    version,
+                }),
+                "=" => comparators.push(SemverComparator {
+                    operator: SemverOperator::Eq,
+                    version,
+                }),
+                // Caret allows changes that do not modify the left-most non-zero component.
+                "^" => {
+                    let upper = if version.major > 0 {
+                        Semver {
+                            major: version.major + 1,
+                            minor: 0,
+                            patch: 0,
+                        }
+                    } else if version.minor > 0 {
+                        Semver {
+                            major: 0,
+                            minor: version.minor + 1,
+                            patch: 0,
+                        }
+                    } else {
+                        Semver {
+                            major: 0,
+                            minor: 0,
+                            patch: version.patch + 1,
+                        }
+                    };
+
+                    comparators.push(SemverComparator {
+                        operator: SemverOperator::Gte,
+                        version,
+                    });
+                    comparators.push(SemverComparator {
+                        operator: SemverOperator::Lt,
+                        version: upper,
+                    });
+                }
+                // Tilde allows patch-level changes when a minor version is given, and
+                // minor-level changes otherwise.
+                _ => {
+                    let upper = if raw_version.contains('.') {
+                        Semver {
+                            major: version.major,
+                            minor: version.minor + 1,
+                            patch: 0,
+                        }
+                    } else {
+                        Semver {
+                            major: version.major + 1,
+                            minor: 0,
+                            patch: 0,
+                        }
+                    };
+
+                    comparators.push(SemverComparator {
+                        operator: SemverOperator::Gte,
+                        version,
+                    });
+                    comparators.push(SemverComparator {
+                        operator: SemverOperator::Lt,
+                        version: upper,
+                    });
+                }
+            }
+        }
+
+        if comparators.is_empty() {
+            return Err(error());
+        }
+
+        Ok(Self {
+            raw: raw_range.trim().to_string(),
+            comparators,
+        })
+    }
+
+    pub fn matches(&self, version: &Semver) -> bool {
+        self.comparators.iter().all(|c| c.matches(version))
+    }
+}
+
+/// Represents comparison of a numeric attribute against a threshold.
+#[derive(Display, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumericComparison {
+    #[display("lt")]
+    Lt,
+    #[display("lte")]
+    Lte,
+    #[display("gt")]
+    Gt,
+    #[display("gte")]
+    Gte,
+}
+
+/// Represents condition a targeted attribute has to satisfy.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TargetingOperator {
+    Equals(String),
+    In(Vec<String>),
+    SemverRange(SemverRange),
+    Numeric(NumericComparison, f64),
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("targeting in-list cannot be empty")]
+pub struct TargetingInListEmptyError;
+
+impl TargetingOperator {
+    /// Creates an in-list operator, the list has to contain at least one value.
+    pub fn new_in(values: Vec<String>) -> Result<Self, TargetingInListEmptyError> {
+        if values.is_empty() {
+            Err(TargetingInListEmptyError)
+        } else {
+            Ok(Self::In(values))
+        }
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Self::Equals(expected) => value == expected,
+            Self::In(expected) => expected.iter().any(|e| e == value),
+            Self::SemverRange(range) => Semver::new(value)
+                .map(|version| range.matches(&version))
+                .unwrap_or(false),
+            Self::Numeric(comparison, threshold) => match value.trim().parse::<f64>() {
+                Ok(number) => match comparison {
+                    NumericComparison::Lt => number < *threshold,
+                    NumericComparison::Lte => number <= *threshold,
+                    NumericComparison::Gt => number > *threshold,
+                    NumericComparison::Gte => number >= *threshold,
+                },
+                Err(_) => false,
+            },
+        }
+    }
+}
+
+/// Represents a condition on a single device attribute.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TargetingRule {
+    attribute: TargetingAttribute,
+    operator: TargetingOperator,
+}
+
+impl TargetingRule {
+    pub fn new(attribute: TargetingAttribute, operator: TargetingOperator) -> Self {
+        Self {
+            attribute,
+            operator,
+        }
+    }
+
+    pub fn attribute(&self) -> &TargetingAttribute {
+        &self.attribute
+    }
+
+    pub fn operator(&self) -> &TargetingOperator {
+        &self.operator
+    }
+
+    /// A device missing the attribute never matches the rule.
+    pub fn matches(&self, attributes: &TargetingAttributes) -> bool {
+        attributes
+            .get(&self.attribute)
+            .is_some_and(|value| self.operator.matches(value))
+    }
+}
+
+/// Represents audience of an experiment. A device belongs to the audience when it matches every
+/// rule, so experiments without rules target every device.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TargetingRules(Vec<TargetingRule>);
+
+impl TargetingRules {
+    pub fn new(rules: Vec<TargetingRule>) -> Self {
+        Self(rules)
+    }
+
+    pub fn rules(&self) -> &Vec<TargetingRule> {
+        &self.0
+    }
+
+    pub fn matches(&self, attributes: &TargetingAttributes) -> bool {
+        self.0.iter().all(|rule| rule.matches(attributes))
+    }
+}
+
+#[cfg(test)]
+mod semver_tests {
+    use super::*;
+
+    #[test]
+    fn test_new_success() {
+        let result = Semver::new("1.2.3-beta.1");
+        let expected = Ok(Semver {
+            major: 1,
+            minor: 2,
+            patch: 3,
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_new_invalid_semver() {
+        let raw_version = "1.x";
+        let result = Semver::new(raw_version);
+        let expected = Err(SemverInvalidError(raw_version.to_string()));
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_range_matches() {
+        let range = SemverRange::new(">=1.2.0, <2.0.0").unwrap();
+
+        assert!(range.matches(&Semver::new("1.2.0").unwrap()));
+        assert!(range.matches(&Semver::new("1.10.4").unwrap()));
+        assert!(!range.matches(&Semver::new("1.1.9").unwrap()));
+        assert!(!range.matches(&Semver::new("2.0.0").unwrap()));
+    }
+
+    #[test]
+    fn test_caret_and_tilde_ranges_match() {
+        let caret = SemverRange::new("^1.4").unwrap();
+        let tilde = SemverRange::new("~1.4.2").unwrap();
+
+        assert!(caret.matches(&Semver::new("1.9.0").unwrap()));
+        assert!(!caret.matches(&Semver::new("2.0.0").unwrap()));
+        assert!(tilde.matches(&Semver::new("1.4.9").unwrap()));
+        assert!(!tilde.matches(&Semver::new("1.5.0").unwrap()));
+    }
+
+    #[test]
+    fn test_tilde_range_with_major_only_matches_minor_changes() {
+        let tilde = SemverRange::new("~1").unwrap();
+
+        assert!(tilde.matches(&Semver::new("1.0.0").unwrap()));
+        assert!(tilde.matches(&Semver::new("1.9.3").unwrap()));
+        assert!(!tilde.matches(&Semver::new("2.0.0").unwrap()));
+        assert!(!tilde.matches(&Semver::new("0.9.0").unwrap()));
+    }
+
+    #[test]
+    fn test_new_invalid_range() {
+        let raw_range = ">=";
+        let result = SemverRange::new(raw_range);
+        let expected = Err(SemverRangeInvalidError(raw_range.to_string()));
+
+        assert_eq!(result, expected);
+    }
+}
+
+#[cfg(test)]
+mod targeting_rules_tests {
+    use super::*;
+
+    fn attributes(pairs: &[(&str, &str)]) -> TargetingAttributes {
+        TargetingAttributes::new(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    fn rule(attribute: &str, operator: TargetingOperator) -> TargetingRule {
+        TargetingRule::new(TargetingAttribute::new(attribute).unwrap(), operator)
+    }
+
+    #[test]
+    fn test_rules_match() {
+        let rules = TargetingRules::new(vec![
+            rule("platform", TargetingOperator::Equals("ios".to_string())),
+            rule(
+                "country",
+                TargetingOperator::new_in(vec!["US".to_string(), "CA".to_string()]).unwrap(),
+            ),
+            rule(
+                "app_version",
+                TargetingOperator::SemverRange(SemverRange::new(">=2.1").unwrap()),
+            ),
+            rule(
+                "sessions",
+                TargetingOperator::Numeric(NumericComparison::Gte, 5.0),
+            ),
+        ]);
+
+        let matching = attributes(&[
+            ("platform", "ios"),
+            ("country", "CA"),
+            ("app_version", "2.3.0"),
+            ("sessions", "12"),
+        ]);
+        let not_matching = attributes(&[
+            ("platform", "ios"),
+            ("country", "DE"),
+            ("app_version", "2.3.0"),
+            ("sessions", "12"),
+        ]);
+
+        assert!(rules.matches(&matching));
+        assert!(!rules.matches(&not_matching));
+    }
+
+    #[test]
+    fn test_missing_attribute_does_not_match() {
+        let rules = TargetingRules::new(vec![rule(
+            "platform",
+            TargetingOperator::Equals("ios".to_string()),
+        )]);
+
+        assert!(!rules.matches(&TargetingAttributes::default()));
+        assert!(TargetingRules::default().matches(&TargetingAttributes::default()));
+    }
+
+    #[test]
+    fn test_new_in_list_is_empty() {
+        let result = TargetingOperator::new_in(vec![]);
+        let expected = Err(TargetingInListEmptyError);
+
+        assert_eq!(result, expected);
+    }
+}
@@ -0,0 +1,393 @@
+use chrono::{DateTime, FixedOffset, Offset, TimeDelta, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use derive_more::Display;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::domain::experiment::models::experiment::VariantData;
+use crate::domain::experiment::models::metric::{MetricObservation, StatisticsMetric};
+
+/// Represents the length of the buckets a timeseries is split into.
+#[derive(Display, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TimeseriesGranularity {
+    #[default]
+    #[display("day")]
+    Day,
+    #[display("hour")]
+    Hour,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{0} is not a valid timeseries granularity")]
+pub struct TimeseriesGranularityInvalidError(String);
+impl TimeseriesGranularity {
+    pub fn new(raw_granularity: &str) -> Result<Self, TimeseriesGranularityInvalidError> {
+        match raw_granularity {
+            "day" => Ok(Self::Day),
+            "hour" => Ok(Self::Hour),
+            _ => Err(TimeseriesGranularityInvalidError(
+                raw_granularity.to_string(),
+            )),
+        }
+    }
+
+    /// Start of the bucket the time falls into, in the given timezone.
+    pub fn bucket_start(
+        &self,
+        at: &DateTime<Utc>,
+        timezone: &TimeseriesTimezone,
+    ) -> DateTime<FixedOffset> {
+        match timezone {
+            TimeseriesTimezone::Named(tz) => self.local_bucket_start(at, tz),
+            TimeseriesTimezone::Fixed(offset) => self.local_bucket_start(at, offset),
+        }
+    }
+
+    /// Start of the bucket following the one that starts at the given time. Days are 23 to 25
+    /// hours long when daylight saving time changes.
+    pub fn next_bucket_start(
+        &self,
+        start: &DateTime<FixedOffset>,
+        timezone: &TimeseriesTimezone,
+    ) -> DateTime<FixedOffset> {
+        let length = match self {
+            Self::Day => TimeDelta::days(1),
+            Self::Hour => TimeDelta::hours(1),
+        };
+
+        // Half a bucket later is within the next bucket however long the current one is.
+        self.bucket_start(&(start.to_utc() + length * 3 / 2), timezone)
+    }
+
+    fn local_bucket_start<T: TimeZone>(&self, at: &DateTime<Utc>, tz: &T) -> DateTime<FixedOffset> {
+        let local = at.with_timezone(tz);
+
+        let start = match self {
+            // Hours are truncated as instants, so that an hour repeated when clocks go back
+            // stays two separate buckets.
+            Self::Hour => {
+                local.clone()
+                    - TimeDelta::minutes(local.minute().into())
+                    - TimeDelta::seconds(local.second().into())
+                    - TimeDelta::nanoseconds(local.nanosecond().into())
+            }
+            // Clocks go forward at midnight in some timezones, then the day starts later.
+            Self::Day => (0..24)
+                .find_map(|hour| {
+                    local
+                        .date_naive()
+                        .and_hms_opt(hour, 0, 0)?
+                        .and_local_timezone(tz.clone())
+                        .earliest()
+                })
+                .unwrap_or(local),
+        };
+
+        start.fixed_offset()
+    }
+}
+
+/// Represents always valid timezone buckets of a timeseries are aligned to, either an IANA
+/// timezone that follows daylight saving time, e.g. `Europe/Berlin`, or a fixed offset from
+/// UTC, e.g. `+03:00`.
+#[derive(Display, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeseriesTimezone {
+    #[display("{_0}")]
+    Named(Tz),
+    #[display("{_0}")]
+    Fixed(FixedOffset),
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error(
+    "{0} is not a valid timezone, expected UTC, an IANA timezone such as Europe/Berlin or an offset such as +03:00"
+)]
+pub struct TimeseriesTimezoneInvalidError(String);
+impl TimeseriesTimezone {
+    pub fn new(raw_timezone: &str) -> Result<Self, TimeseriesTimezoneInvalidError> {
+        match raw_timezone {
+            "UTC" | "Z" => Ok(Self::default()),
+            _ => raw_timezone
+                .parse()
+                .map(Self::Named)
+                .or_else(|_| raw_timezone.parse().map(Self::Fixed))
+                .map_err(|_| TimeseriesTimezoneInvalidError(raw_timezone.to_string())),
+        }
+    }
+}
+
+impl Default for TimeseriesTimezone {
+    fn default() -> Self {
+        Self::Fixed(Utc.fix())
+    }
+}
+
+/// Data required by the domain to build a [Timeseries] of an experiment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GetTimeseriesRequest {
+    experiment_id: Uuid,
+    granularity: TimeseriesGranularity,
+    timezone: TimeseriesTimezone,
+}
+
+impl GetTimeseriesRequest {
+    pub fn new(experiment_id: Uuid) -> Self {
+        Self {
+            experiment_id,
+            granularity: TimeseriesGranularity::default(),
+            timezone: TimeseriesTimezone::default(),
+        }
+    }
+
+    pub fn with_granularity(mut self, granularity: TimeseriesGranularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    pub fn with_timezone(mut self, timezone: TimeseriesTimezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    pub fn experiment_id(&self) -> &Uuid {
+        &self.experiment_id
+    }
+
+    pub fn granularity(&self) -> TimeseriesGranularity {
+        self.granularity
+    }
+
+    pub fn timezone(&self) -> &TimeseriesTimezone {
+        &self.timezone
+    }
+}
+
+/// Represents a single event attributed to a variant of an experiment, along with the time it
+/// occurred at.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimedMetricObservation {
+    observation: MetricObservation,
+    occurred_at: DateTime<Utc>,
+}
+
+impl TimedMetricObservation {
+    pub fn new(observation: MetricObservation, occurred_at: DateTime<Utc>) -> Self {
+        Self {
+            observation,
+            occurred_at,
+        }
+    }
+
+    pub fn observation(&self) -> &MetricObservation {
+        &self.observation
+    }
+
+    pub fn occurred_at(&self) -> &DateTime<Utc> {
+        &self.occurred_at
+    }
+}
+
+/// Enrolment and metrics of a variant by the end of a bucket.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeseriesVariant {
+    data: VariantData,
+    new_devices: usize,
+    total_devices: usize,
+    metrics: Vec<StatisticsMetric>,
+}
+
+impl TimeseriesVariant {
+    pub fn new(
+        data: VariantData,
+        new_devices: usize,
+        total_devices: usize,
+        metrics: Vec<StatisticsMetric>,
+    ) -> Self {
+        Self {
+            data,
+            new_devices,
+            total_devices,
+            metrics,
+        }
+    }
+
+    pub fn data(&self) -> &VariantData {
+        &self.data
+    }
+
+    /// Devices enrolled within the bucket.
+    pub fn new_devices(&self) -> usize {
+        self.new_devices
+    }
+
+    /// Devices enrolled since the start of the experiment.
+    pub fn total_devices(&self) -> usize {
+        self.total_devices
+    }
+
+    /// Values of the metrics over all events up to the end of the bucket.
+    pub fn metrics(&self) -> &[StatisticsMetric] {
+        &self.metrics
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeseriesBucket {
+    start: DateTime<FixedOffset>,
+    variants: Vec<TimeseriesVariant>,
+}
+
+impl TimeseriesBucket {
+    pub fn new(start: DateTime<FixedOffset>, variants: Vec<TimeseriesVariant>) -> Self {
+        Self { start, variants }
+    }
+
+    pub fn start(&self) -> &DateTime<FixedOffset> {
+        &self.start
+    }
+
+    pub fn variants(&self) -> &[TimeseriesVariant] {
+        &self.variants
+    }
+}
+
+/// Represents cumulative results of an experiment, from the bucket of the first enrolment up
+/// to the bucket of the latest enrolment or event, but not past the end of the experiment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timeseries {
+    experiment_id: Uuid,
+    granularity: TimeseriesGranularity,
+    timezone: TimeseriesTimezone,
+    buckets: Vec<TimeseriesBucket>,
+}
+
+impl Timeseries {
+    /// Maximum number of buckets of a timeseries, e.g. a little over a month of hours.
+    pub const MAX_BUCKETS: usize = 1000;
+
+    pub fn new(req: &GetTimeseriesRequest, buckets: Vec<TimeseriesBucket>) -> Self {
+        Self {
+            experiment_id: req.experiment_id,
+            granularity: req.granularity,
+            timezone: req.timezone,
+            buckets,
+        }
+    }
+
+    pub fn experiment_id(&self) -> &Uuid {
+        &self.experiment_id
+    }
+
+    pub fn granularity(&self) -> TimeseriesGranularity {
+        self.granularity
+    }
+
+    pub fn timezone(&self) -> &TimeseriesTimezone {
+        &self.timezone
+    }
+
+    pub fn buckets(&self) -> &[TimeseriesBucket] {
+        &self.buckets
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GetTimeseriesError {
+    #[error("experiment with id {id} does not exist")]
+    NotFound { id: Uuid },
+    #[error("timeseries would have more than {max} buckets, use a coarser granularity")]
+    TooManyBuckets { max: usize },
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+#[cfg(test)]
+mod timeseries_tests {
+    use super::*;
+
+    #[test]
+    fn test_timezone() {
+        assert_eq!(
+            TimeseriesTimezone::new("UTC"),
+            Ok(TimeseriesTimezone::default())
+        );
+        assert_eq!(
+            TimeseriesTimezone::new("+03:00").map(|tz| tz.to_string()),
+            Ok("+03:00".to_string())
+        );
+        assert_eq!(
+            TimeseriesTimezone::new("-05:30").map(|tz| tz.to_string()),
+            Ok("-05:30".to_string())
+        );
+        assert_eq!(
+            TimeseriesTimezone::new("Europe/Berlin").map(|tz| tz.to_string()),
+            Ok("Europe/Berlin".to_string())
+        );
+        assert!(TimeseriesTimezone::new("Europe/Atlantis").is_err());
+        assert!(TimeseriesTimezone::new("+25:00").is_err());
+    }
+
+    #[test]
+    fn test_bucket_start() {
+        let at: DateTime<Utc> = "2025-07-10T22:45:10Z".parse().unwrap();
+        let utc = TimeseriesTimezone::default();
+        let minsk = TimeseriesTimezone::new("+03:00").unwrap();
+        let new_york = TimeseriesTimezone::new("-04:00").unwrap();
+
+        assert_eq!(
+            TimeseriesGranularity::Day
+                .bucket_start(&at, &utc)
+                .to_rfc3339(),
+            "2025-07-10T00:00:00+00:00"
+        );
+        assert_eq!(
+            TimeseriesGranularity::Day
+                .bucket_start(&at, &minsk)
+                .to_rfc3339(),
+            "2025-07-11T00:00:00+03:00"
+        );
+        assert_eq!(
+            TimeseriesGranularity::Day
+                .bucket_start(&at, &new_york)
+                .to_rfc3339(),
+            "2025-07-10T00:00:00-04:00"
+        );
+        assert_eq!(
+            TimeseriesGranularity::Hour
+                .bucket_start(&at, &minsk)
+                .to_rfc3339(),
+            "2025-07-11T01:00:00+03:00"
+        );
+    }
+    #[test]
+    fn test_buckets_across_daylight_saving_time() {
+        let berlin = TimeseriesTimezone::new("Europe/Berlin").unwrap();
+        // Clocks go back from 03:00 to 02:00 on 2025-10-26 in Berlin.
+        let at: DateTime<Utc> = "2025-10-26T12:00:00Z".parse().unwrap();
+        let day = TimeseriesGranularity::Day.bucket_start(&at, &berlin);
+        let next_day = TimeseriesGranularity::Day.next_bucket_start(&day, &berlin);
+
+        assert_eq!(day.to_rfc3339(), "2025-10-26T00:00:00+02:00");
+        assert_eq!(next_day.to_rfc3339(), "2025-10-27T00:00:00+01:00");
+        assert_eq!(next_day - day, TimeDelta::hours(25));
+
+        // The repeated hour from 02:00 to 03:00 is two buckets.
+        let first: DateTime<Utc> = "2025-10-26T00:30:00Z".parse().unwrap();
+        let second: DateTime<Utc> = "2025-10-26T01:30:00Z".parse().unwrap();
+        let first_hour = TimeseriesGranularity::Hour.bucket_start(&first, &berlin);
+
+        assert_eq!(first_hour.to_rfc3339(), "2025-10-26T02:00:00+02:00");
+        assert_eq!(
+            TimeseriesGranularity::Hour
+                .bucket_start(&second, &berlin)
+                .to_rfc3339(),
+            "2025-10-26T02:00:00+01:00"
+        );
+        assert_eq!(
+            TimeseriesGranularity::Hour
+                .next_bucket_start(&first_hour, &berlin)
+                .to_rfc3339(),
+            "2025-10-26T02:00:00+01:00"
+        );
+    }
+}
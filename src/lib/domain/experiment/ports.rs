@@ -1,15 +1,36 @@
 use std::future::Future;
 
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
-use crate::domain::device::models::device::{Device, DeviceId, GetAllDevicesError};
+use crate::domain::device::models::device::{Device, GetAllDevicesError, RegisteredDevice, UserId};
+use crate::domain::experiment::models::assignment::{
+    Assignment, BackfillAssignmentsError, CreateAssignmentRequest, CreateAssignmentsError,
+    GetAllAssignmentsError,
+};
 #[allow(unused_imports)]
 use crate::domain::experiment::models::experiment::ExperimentName;
 use crate::domain::experiment::models::experiment::{
-    CreateExperimentError, DeviceExperiment, FinishExperimentError, GetAllDeviceExperimentsError,
-    GetAllExperimentsError, StaticticsExperiment,
+    ApplySchedulesError, ChangeExperimentStatusError, CovariateWindow, CreateExperimentError,
+    DeviceExperiment, ExperimentEligibility, ExperimentStatus, FinishExperimentError,
+    GetAllDeviceExperimentsError, GetAllExperimentsError, GetExperimentByIdError,
+    GetStatisticsRequest, StaticticsExperiment, UpdateExperimentError, UpdateExperimentRequest,
 };
 use crate::domain::experiment::models::experiment::{CreateExperimentRequest, Experiment};
+use crate::domain::experiment::models::guardrail::{
+    AuditEntry, CreateAuditEntryRequest, EvaluateGuardrailsError, GetAuditTrailError,
+    StopExperimentError,
+};
+use crate::domain::experiment::models::metric::{GetAllMetricObservationsError, MetricObservation};
+use crate::domain::experiment::models::sample_size::{
+    CalculateSampleSizeError, SampleSize, SampleSizeRequest,
+};
+use crate::domain::experiment::models::segment::{
+    GetExperimentStatisticsError, GetExperimentStatisticsRequest, ParticipantCount,
+};
+use crate::domain::experiment::models::timeseries::{
+    GetTimeseriesError, GetTimeseriesRequest, TimedMetricObservation, Timeseries,
+};
 
 /// `ExperimentService` is the public API for the experiment domain.
 pub trait ExperimentService: Clone + Send + Sync + 'static {
@@ -22,24 +43,85 @@ pub trait ExperimentService: Clone + Send + Sync + 'static {
         &self,
     ) -> impl Future<Output = Result<Vec<Experiment>, GetAllExperimentsError>> + Send;
 
+    /// Picks experiments available to a registered device and assigns their variants to it.
     fn get_all_device_participating_experiments(
         &self,
-        id: &DeviceId,
+        device: &RegisteredDevice,
     ) -> impl Future<Output = Result<Vec<DeviceExperiment>, GetAllDeviceExperimentsError>> + Send;
 
+    /// Edits a draft or running experiment, incrementing its version, and moves it to the
+    /// requested status in the same step.
+    fn update_experiment(
+        &self,
+        req: &UpdateExperimentRequest,
+    ) -> impl Future<Output = Result<Uuid, UpdateExperimentError>> + Send;
+
     fn finish_experiment(
         &self,
         id: &Uuid,
     ) -> impl Future<Output = Result<Uuid, FinishExperimentError>> + Send;
 
+    fn change_experiment_status(
+        &self,
+        id: &Uuid,
+        status: ExperimentStatus,
+    ) -> impl Future<Output = Result<Uuid, ChangeExperimentStatusError>> + Send;
+
+    /// Starts and finishes experiments whose scheduled start or end has passed, returning ids of
+    /// the experiments it moved. Experiments that fail to move are logged and left for the next
+    /// call.
+    fn apply_schedules(
+        &self,
+        now: &DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<Uuid>, ApplySchedulesError>> + Send;
+
+    /// Records assignments of devices that were only ever assigned on the fly, before
+    /// assignments were persisted, so that statistics keep counting them. The backfill runs
+    /// once, later calls do nothing.
+    fn backfill_assignments(
+        &self,
+    ) -> impl Future<Output = Result<(), BackfillAssignmentsError>> + Send;
+
     fn get_all_devices(
         &self,
     ) -> impl Future<Output = Result<Vec<Device>, GetAllDevicesError>> + Send;
 
     fn get_statistics(
         &self,
-        devices: Vec<Device>,
+        req: &GetStatisticsRequest,
     ) -> impl Future<Output = Result<Vec<StaticticsExperiment>, GetAllExperimentsError>> + Send;
+
+    /// Computes statistics of a single experiment within a period, optionally broken down by a
+    /// device attribute.
+    fn get_experiment_statistics(
+        &self,
+        req: &GetExperimentStatisticsRequest,
+    ) -> impl Future<Output = Result<StaticticsExperiment, GetExperimentStatisticsError>> + Send;
+
+    /// Calculates the devices a planned experiment needs and how long they take to arrive.
+    fn calculate_sample_size(
+        &self,
+        req: &SampleSizeRequest,
+    ) -> impl Future<Output = Result<SampleSize, CalculateSampleSizeError>> + Send;
+
+    /// Builds cumulative enrolment and metrics of the variants of an experiment for every
+    /// bucket of its runtime.
+    fn get_timeseries(
+        &self,
+        req: &GetTimeseriesRequest,
+    ) -> impl Future<Output = Result<Timeseries, GetTimeseriesError>> + Send;
+
+    /// Stops running experiments that breach any of their guardrails according to the
+    /// sequential test, returning the audit entries recorded for them. Experiments that fail
+    /// to be evaluated or stopped are logged and evaluated again next time.
+    fn evaluate_guardrails(
+        &self,
+    ) -> impl Future<Output = Result<Vec<AuditEntry>, EvaluateGuardrailsError>> + Send;
+
+    fn get_audit_trail(
+        &self,
+        experiment_id: &Uuid,
+    ) -> impl Future<Output = Result<Vec<AuditEntry>, GetAuditTrailError>> + Send;
 }
 
 /// `ExperimentRepository` represents a store of experiment data.
@@ -57,13 +139,141 @@ pub trait ExperimentRepository: Send + Sync + Clone + 'static {
         &self,
     ) -> impl Future<Output = Result<Vec<Experiment>, GetAllExperimentsError>> + Send;
 
-    fn get_all_device_participating_experiments(
+    fn get_experiment_by_id(
         &self,
-        id: &DeviceId,
-    ) -> impl Future<Output = Result<Vec<DeviceExperiment>, GetAllDeviceExperimentsError>> + Send;
+        id: &Uuid,
+    ) -> impl Future<Output = Result<Experiment, GetExperimentByIdError>> + Send;
+
+    /// Edits a draft or running experiment, incrementing its version, and moves it to the
+    /// requested status in the same step.
+    fn update_experiment(
+        &self,
+        req: &UpdateExperimentRequest,
+    ) -> impl Future<Output = Result<Uuid, UpdateExperimentError>> + Send;
 
     fn finish_experiment(
         &self,
         id: &Uuid,
     ) -> impl Future<Output = Result<Uuid, FinishExperimentError>> + Send;
+
+    fn change_experiment_status(
+        &self,
+        id: &Uuid,
+        status: ExperimentStatus,
+    ) -> impl Future<Output = Result<Uuid, ChangeExperimentStatusError>> + Send;
+
+    /// Records the first exposure of devices to experiments. Already recorded assignments are
+    /// left untouched.
+    ///
+    /// # Returns
+    /// * `Vec<Assignment>` - persisted assignments for every request.
+    fn create_assignments(
+        &self,
+        reqs: &[CreateAssignmentRequest],
+    ) -> impl Future<Output = Result<Vec<Assignment>, CreateAssignmentsError>> + Send;
+
+    /// Records assignments of devices made again, replacing the previous ones, e.g. when the
+    /// variant a device was assigned no longer exists.
+    fn replace_assignments(
+        &self,
+        reqs: &[CreateAssignmentRequest],
+    ) -> impl Future<Output = Result<Vec<Assignment>, CreateAssignmentsError>> + Send;
+
+    fn get_all_assignments(
+        &self,
+    ) -> impl Future<Output = Result<Vec<Assignment>, GetAllAssignmentsError>> + Send;
+
+    fn get_experiment_assignments(
+        &self,
+        experiment_id: &Uuid,
+    ) -> impl Future<Output = Result<Vec<Assignment>, GetAllAssignmentsError>> + Send;
+
+    /// Time assignments started to be persisted at. Devices and experiments created before it
+    /// were assigned on the fly only.
+    fn get_assignments_persisted_since(
+        &self,
+    ) -> impl Future<Output = Result<DateTime<Utc>, BackfillAssignmentsError>> + Send;
+
+    /// Whether assignments of devices that were only ever assigned on the fly have already been
+    /// backfilled.
+    fn is_assignments_backfilled(
+        &self,
+    ) -> impl Future<Output = Result<bool, BackfillAssignmentsError>> + Send;
+
+    /// Saves backfilled assignments and records that the backfill has run, both or neither.
+    fn save_backfilled_assignments(
+        &self,
+        reqs: &[CreateAssignmentRequest],
+    ) -> impl Future<Output = Result<(), BackfillAssignmentsError>> + Send;
+
+    /// Fetches assignments of all the devices linked to the user in user-bucketed experiments.
+    fn get_all_user_assignments(
+        &self,
+        user_id: &UserId,
+    ) -> impl Future<Output = Result<Vec<Assignment>, GetAllAssignmentsError>> + Send;
+
+    /// Fetches events attributed to experiments, aggregated per device, variant and event name.
+    /// Only events attributed to the variant a device is assigned to are fetched.
+    fn get_all_metric_observations(
+        &self,
+    ) -> impl Future<Output = Result<Vec<MetricObservation>, GetAllMetricObservationsError>> + Send;
+
+    /// Counts devices assigned to the experiment within the period of the request per variant,
+    /// version and value of the segment attribute. Devices are segmented by their current
+    /// attributes, not by the attributes they had when assigned.
+    fn count_experiment_participants(
+        &self,
+        req: &GetExperimentStatisticsRequest,
+    ) -> impl Future<Output = Result<Vec<ParticipantCount>, GetExperimentStatisticsError>> + Send;
+
+    /// Fetches events attributed to the experiment within the period of the request,
+    /// aggregated per device, variant and event name, along with the segment of the device.
+    fn get_experiment_metric_observations(
+        &self,
+        req: &GetExperimentStatisticsRequest,
+    ) -> impl Future<Output = Result<Vec<MetricObservation>, GetAllMetricObservationsError>> + Send;
+
+    /// Fetches every event attributed to the experiment along with the time it occurred at. Only
+    /// events attributed to the variant a device is assigned to are fetched.
+    fn get_timed_metric_observations(
+        &self,
+        experiment_id: &Uuid,
+    ) -> impl Future<Output = Result<Vec<TimedMetricObservation>, GetAllMetricObservationsError>> + Send;
+
+    /// Fetches events devices sent within the `window` before the creation of the experiments
+    /// they are assigned to, aggregated per device, variant and event name.
+    fn get_all_pre_experiment_observations(
+        &self,
+        window: CovariateWindow,
+    ) -> impl Future<Output = Result<Vec<MetricObservation>, GetAllMetricObservationsError>> + Send;
+
+    /// Fetches events devices sent within the `window` before the creation of the experiment,
+    /// aggregated per device, variant and event name, along with the segment of the device. Only
+    /// devices assigned within the period of the request are fetched.
+    fn get_pre_experiment_observations(
+        &self,
+        req: &GetExperimentStatisticsRequest,
+        window: CovariateWindow,
+    ) -> impl Future<Output = Result<Vec<MetricObservation>, GetAllMetricObservationsError>> + Send;
+
+    /// Counts devices active since the given time, i.e. created, assigned to an experiment or
+    /// sending events, that the eligibility admits into an experiment created at that time.
+    fn count_active_devices_since(
+        &self,
+        since: &DateTime<Utc>,
+        eligibility: ExperimentEligibility,
+    ) -> impl Future<Output = Result<u64, CalculateSampleSizeError>> + Send;
+
+    /// Moves the experiment to the status of the request and records the reason in its audit
+    /// trail, both or neither.
+    fn stop_experiment(
+        &self,
+        req: &CreateAuditEntryRequest,
+    ) -> impl Future<Output = Result<AuditEntry, StopExperimentError>> + Send;
+
+    /// Fetches audit entries of the experiment, oldest first.
+    fn get_audit_trail(
+        &self,
+        experiment_id: &Uuid,
+    ) -> impl Future<Output = Result<Vec<AuditEntry>, GetAuditTrailError>> + Send;
 }
@@ -1,12 +1,45 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, anyhow};
+use chrono::{DateTime, TimeDelta, Utc};
 use uuid::Uuid;
 
-use crate::domain::device::models::device::{Device, DeviceId, GetAllDevicesError};
+use crate::domain::device::models::device::{
+    Device, DeviceId, GetAllDevicesError, RegisteredDevice,
+};
+use crate::domain::event::models::event::EventName;
+use crate::domain::experiment::models::assignment::{
+    Assignment, BackfillAssignmentsError, CreateAssignmentRequest, winning_assignment,
+};
 use crate::domain::experiment::models::experiment::{
-    CreateExperimentError, CreateExperimentRequest, DeviceExperiment, Experiment,
+    AnalysisMethod, AnalysisPlan, ApplySchedulesError, ChangeExperimentStatusError,
+    CreateExperimentError, CreateExperimentRequest, DeviceExperiment, Experiment, ExperimentStatus,
     FinishExperimentError, GetAllDeviceExperimentsError, GetAllExperimentsError,
-    StaticticsExperiment, StatisticsVariant, StatisticsVariants, VariantData,
+    GetExperimentByIdError, GetStatisticsRequest, SampleRatioCheck, StaticticsExperiment,
+    StatisticsVariant, StatisticsVariants, StatisticsVersion, UpdateExperimentError,
+    UpdateExperimentRequest, VariantData,
+};
+use crate::domain::experiment::models::guardrail::{
+    AuditEntry, CreateAuditEntryRequest, EvaluateGuardrailsError, GetAuditTrailError,
+};
+use crate::domain::experiment::models::metric::{MetricObservation, StatisticsMetric};
+use crate::domain::experiment::models::sample_size::{
+    CalculateSampleSizeError, SampleSize, SampleSizeRequest,
+};
+use crate::domain::experiment::models::segment::{
+    GetExperimentStatisticsError, GetExperimentStatisticsRequest, ParticipantCount,
+    StatisticsSegment,
+};
+use crate::domain::experiment::models::timeseries::{
+    GetTimeseriesError, GetTimeseriesRequest, TimedMetricObservation, Timeseries, TimeseriesBucket,
+    TimeseriesVariant,
 };
 use crate::domain::experiment::ports::{ExperimentRepository, ExperimentService};
+use crate::domain::statistics::bayesian::{
+    self, BayesianComparison, DEFAULT_DRAWS, DEFAULT_SEED, Posterior,
+};
+use crate::domain::statistics::cuped;
+use crate::domain::statistics::frequentist::chi_square_goodness_of_fit;
 
 #[derive(Debug, Clone)]
 pub struct Service<R: ExperimentRepository> {
@@ -17,6 +50,190 @@ impl<R: ExperimentRepository> Service<R> {
     pub fn new(repo: R) -> Self {
         Self { repo }
     }
+
+    /// Moves the experiment to the status its schedule requires.
+    async fn apply_schedule(
+        &self,
+        exp: &Experiment,
+        scheduled: ExperimentStatus,
+    ) -> Result<(), anyhow::Error> {
+        // A draft is started before it is finished, as drafts cannot be finished directly.
+        if exp.status() == ExperimentStatus::Draft {
+            self.repo
+                .change_experiment_status(exp.id(), ExperimentStatus::Running)
+                .await
+                .context("failed to start experiment")?;
+        }
+        if scheduled == ExperimentStatus::Finished {
+            self.repo
+                .finish_experiment(exp.id())
+                .await
+                .context("failed to finish experiment")?;
+        }
+
+        Ok(())
+    }
+
+    /// Stops the experiment if any of its variants breaches a guardrail. Guardrails are checked
+    /// continuously, so the variants are compared with the sequential test whatever the
+    /// analysis plan of the experiment is.
+    async fn evaluate_experiment_guardrails(
+        &self,
+        exp: &Experiment,
+    ) -> Result<Option<AuditEntry>, anyhow::Error> {
+        let sequential = exp.clone().with_analysis_plan(AnalysisPlan::Sequential);
+        let req = GetExperimentStatisticsRequest::new(*exp.id(), GetStatisticsRequest::default());
+        let statistics = self
+            .experiment_statistics(&sequential, &req)
+            .await
+            .context("failed to get statistics")?;
+        let Some(req) = guardrail_breach(exp, &statistics) else {
+            return Ok(None);
+        };
+
+        let entry = self
+            .repo
+            .stop_experiment(&req)
+            .await
+            .context("failed to stop experiment")?;
+
+        Ok(Some(entry))
+    }
+
+    /// Calculates statistics of the experiment by aggregating its assignments and events in the
+    /// repository.
+    async fn experiment_statistics(
+        &self,
+        exp: &Experiment,
+        req: &GetExperimentStatisticsRequest,
+    ) -> Result<StaticticsExperiment, GetExperimentStatisticsError> {
+        let counts = self.repo.count_experiment_participants(req).await?;
+        let counts: Vec<&ParticipantCount> = counts.iter().collect();
+        let observations = self
+            .repo
+            .get_experiment_metric_observations(req)
+            .await
+            .map_err(|e| {
+                GetExperimentStatisticsError::Unknown(
+                    anyhow!(e).context("failed to get experiment metric observations"),
+                )
+            })?;
+        let observations: Vec<&MetricObservation> = observations.iter().collect();
+        let pre_observations = match req.statistics().cuped() {
+            Some(window) => self
+                .repo
+                .get_pre_experiment_observations(req, window)
+                .await
+                .map_err(|e| {
+                    GetExperimentStatisticsError::Unknown(
+                        anyhow!(e).context("failed to get pre-experiment observations"),
+                    )
+                })?,
+            None => Vec::new(),
+        };
+        let pre_observations: Vec<&MetricObservation> = pre_observations.iter().collect();
+
+        let variants_data: Vec<&VariantData> =
+            exp.variants().variants().iter().map(|v| v.data()).collect();
+        let variants = statistics_metrics(
+            exp,
+            participant_variants(&variants_data, &counts),
+            &observations,
+            &pre_observations,
+            req.statistics(),
+        );
+
+        let mut version_numbers: Vec<u32> = counts.iter().map(|c| c.version()).collect();
+        version_numbers.sort();
+        version_numbers.dedup();
+
+        let versions: Vec<StatisticsVersion> = version_numbers
+            .into_iter()
+            .map(|version| {
+                let version_counts: Vec<&ParticipantCount> = counts
+                    .iter()
+                    .filter(|c| c.version() == version)
+                    .copied()
+                    .collect();
+
+                let mut version_variants_data: Vec<&VariantData> =
+                    version_counts.iter().map(|c| c.data()).collect();
+                version_variants_data.sort_by_key(|data| data.to_string());
+                version_variants_data.dedup();
+
+                StatisticsVersion::new(
+                    version,
+                    total_participants(&version_counts),
+                    participant_variants(&version_variants_data, &version_counts),
+                )
+            })
+            .collect();
+
+        let sample_ratio = sample_ratio_check(exp, |data| {
+            counts
+                .iter()
+                .filter(|c| c.version() == exp.version() && c.data() == data)
+                .map(|c| c.devices())
+                .sum()
+        });
+
+        let segments = match req.segment() {
+            Some(_) => {
+                // Devices without the attribute are grouped last.
+                let mut values: Vec<Option<&str>> = counts.iter().map(|c| c.segment()).collect();
+                values.sort_by_key(|value| (value.is_none(), *value));
+                values.dedup();
+
+                values
+                    .into_iter()
+                    .map(|value| {
+                        let segment_counts: Vec<&ParticipantCount> = counts
+                            .iter()
+                            .filter(|c| c.segment() == value)
+                            .copied()
+                            .collect();
+                        let segment_observations: Vec<&MetricObservation> = observations
+                            .iter()
+                            .filter(|o| o.segment() == value)
+                            .copied()
+                            .collect();
+                        let segment_pre_observations: Vec<&MetricObservation> = pre_observations
+                            .iter()
+                            .filter(|o| o.segment() == value)
+                            .copied()
+                            .collect();
+
+                        StatisticsSegment::new(
+                            value.map(str::to_string),
+                            total_participants(&segment_counts),
+                            statistics_metrics(
+                                exp,
+                                participant_variants(&variants_data, &segment_counts),
+                                &segment_observations,
+                                &segment_pre_observations,
+                                req.statistics(),
+                            ),
+                        )
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        Ok(StaticticsExperiment::new(
+            exp.id().to_owned(),
+            exp.name().to_owned(),
+            exp.version(),
+            total_participants(&counts),
+            exp.control().to_owned(),
+            variants,
+            versions,
+        )
+        .with_sample_ratio(sample_ratio)
+        .with_analysis_plan(exp.analysis_plan())
+        .with_eligibility(exp.eligibility())
+        .with_segments(segments))
+    }
 }
 
 impl<R: ExperimentRepository> ExperimentService for Service<R> {
@@ -33,9 +250,142 @@ impl<R: ExperimentRepository> ExperimentService for Service<R> {
 
     async fn get_all_device_participating_experiments(
         &self,
-        id: &DeviceId,
+        device: &RegisteredDevice,
     ) -> Result<Vec<DeviceExperiment>, GetAllDeviceExperimentsError> {
-        self.repo.get_all_device_participating_experiments(id).await
+        let device = device.device();
+        let id = device.id();
+        let now = Utc::now();
+
+        let experiments = self.repo.get_all_experiments().await.map_err(|e| {
+            GetAllDeviceExperimentsError::Unknown(
+                anyhow!(e).context("failed to get all experiments"),
+            )
+        })?;
+
+        // Variants already assigned to other devices of the user take precedence over hashing
+        // in user-bucketed experiments.
+        let user_assignments = match device.user_id() {
+            Some(user_id) => self
+                .repo
+                .get_all_user_assignments(user_id)
+                .await
+                .map_err(|e| {
+                    GetAllDeviceExperimentsError::Unknown(
+                        anyhow!(e).context("failed to get user assignments"),
+                    )
+                })?,
+            None => vec![],
+        };
+
+        let available: Vec<&Experiment> = experiments
+            .iter()
+            .filter(|exp| exp.is_available_to(device, &now) && exp.is_in_layer(device))
+            .collect();
+
+        let experiments: Vec<DeviceExperiment> = available
+            .iter()
+            .filter_map(|exp| {
+                // Variants removed since other devices of the user were assigned are not
+                // inherited.
+                let user_assignment = winning_assignment(
+                    user_assignments
+                        .iter()
+                        .filter(|a| a.experiment_id() == exp.id() && !exp.is_outdated(a)),
+                );
+
+                let (data, version) = match user_assignment {
+                    Some(assignment) => (assignment.data(), assignment.version()),
+                    None => (exp.assign_variant(device)?, exp.version()),
+                };
+
+                Some(DeviceExperiment::new(
+                    *exp.id(),
+                    exp.name().to_owned(),
+                    data.to_owned(),
+                    version,
+                ))
+            })
+            .collect();
+
+        let reqs: Vec<CreateAssignmentRequest> = experiments
+            .iter()
+            .map(|exp| {
+                CreateAssignmentRequest::new(
+                    id.to_owned(),
+                    *exp.id(),
+                    exp.data().to_owned(),
+                    exp.version(),
+                )
+            })
+            .collect();
+
+        let assignments = self.repo.create_assignments(&reqs).await.map_err(|e| {
+            GetAllDeviceExperimentsError::Unknown(anyhow!(e).context("failed to save assignments"))
+        })?;
+
+        // Devices keep the variant of their first exposure, unless an edit removed it.
+        let outdated: Vec<CreateAssignmentRequest> = reqs
+            .into_iter()
+            .filter(|req| {
+                assignments.iter().any(|a| {
+                    a.experiment_id() == req.experiment_id()
+                        && available
+                            .iter()
+                            .any(|exp| exp.id() == a.experiment_id() && exp.is_outdated(a))
+                })
+            })
+            .collect();
+        let reassigned = self
+            .repo
+            .replace_assignments(&outdated)
+            .await
+            .map_err(|e| {
+                GetAllDeviceExperimentsError::Unknown(
+                    anyhow!(e).context("failed to replace outdated assignments"),
+                )
+            })?;
+
+        let experiments = experiments
+            .into_iter()
+            .map(|exp| {
+                match reassigned
+                    .iter()
+                    .chain(&assignments)
+                    .find(|a| a.experiment_id() == exp.id())
+                {
+                    Some(assignment) => DeviceExperiment::new(
+                        *exp.id(),
+                        exp.name().to_owned(),
+                        assignment.data().to_owned(),
+                        assignment.version(),
+                    ),
+                    None => exp,
+                }
+            })
+            .collect();
+
+        Ok(experiments)
+    }
+
+    async fn backfill_assignments(&self) -> Result<(), BackfillAssignmentsError> {
+        if self.repo.is_assignments_backfilled().await? {
+            return Ok(());
+        }
+
+        let since = self.repo.get_assignments_persisted_since().await?;
+        let experiments = self
+            .repo
+            .get_all_experiments()
+            .await
+            .map_err(|e| anyhow!(e).context("failed to get all experiments"))?;
+        let devices = self
+            .repo
+            .get_all_devices()
+            .await
+            .map_err(|e| anyhow!(e).context("failed to get all devices"))?;
+
+        let reqs = legacy_assignments(&experiments, &devices, &since);
+        self.repo.save_backfilled_assignments(&reqs).await
     }
 
     async fn get_all_devices(&self) -> Result<Vec<Device>, GetAllDevicesError> {
@@ -44,60 +394,1128 @@ impl<R: ExperimentRepository> ExperimentService for Service<R> {
 
     async fn get_statistics(
         &self,
-        devices: Vec<Device>,
+        req: &GetStatisticsRequest,
     ) -> Result<Vec<StaticticsExperiment>, GetAllExperimentsError> {
         let experiments = self.repo.get_all_experiments().await?;
+        let assignments = self.repo.get_all_assignments().await.map_err(|e| {
+            GetAllExperimentsError::Unknown(anyhow!(e).context("failed to get all assignments"))
+        })?;
+        let observations = self.repo.get_all_metric_observations().await.map_err(|e| {
+            GetAllExperimentsError::Unknown(
+                anyhow!(e).context("failed to get all metric observations"),
+            )
+        })?;
+        let pre_observations = match req.cuped() {
+            Some(window) => self
+                .repo
+                .get_all_pre_experiment_observations(window)
+                .await
+                .map_err(|e| {
+                    GetAllExperimentsError::Unknown(
+                        anyhow!(e).context("failed to get all pre-experiment observations"),
+                    )
+                })?,
+            None => Vec::new(),
+        };
 
         let experiments: Vec<StaticticsExperiment> = experiments
             .iter()
             .map(|exp| {
-                let participants: Vec<&Device> = devices
+                let participants: Vec<&Assignment> = assignments
                     .iter()
-                    .filter(|dev| exp.created_at().cmp(dev.created_at()).is_ge())
+                    .filter(|a| a.experiment_id() == exp.id())
                     .collect();
 
-                let total_devices = participants.len();
+                let variants_data: Vec<&VariantData> =
+                    exp.variants().variants().iter().map(|v| v.data()).collect();
+                let variants = statistics_variants(&variants_data, &participants);
 
-                let variants_data: Vec<&VariantData> = participants
+                let experiment_observations: Vec<&MetricObservation> = observations
                     .iter()
-                    .map(|p| {
-                        exp.variants()
-                            .assign_variant(format!("{}", p.id().to_owned().into_inner()).as_str())
-                    })
+                    .filter(|o| o.experiment_id() == exp.id())
                     .collect();
-
-                let statistics_variants: Vec<StatisticsVariant> = exp
-                    .variants()
-                    .variants()
+                let experiment_pre_observations: Vec<&MetricObservation> = pre_observations
                     .iter()
-                    .map(move |variant| {
-                        let assigned_total_devices = variants_data
+                    .filter(|o| o.experiment_id() == exp.id())
+                    .collect();
+                let variants = statistics_metrics(
+                    exp,
+                    variants,
+                    &experiment_observations,
+                    &experiment_pre_observations,
+                    req,
+                );
+
+                let mut version_numbers: Vec<u32> =
+                    participants.iter().map(|a| a.version()).collect();
+                version_numbers.sort();
+                version_numbers.dedup();
+
+                let versions: Vec<StatisticsVersion> = version_numbers
+                    .into_iter()
+                    .map(|version| {
+                        let version_participants: Vec<&Assignment> = participants
                             .iter()
-                            .filter(|v| v.to_string() == variant.data().to_string())
-                            .count();
-                        let percentage_devices =
-                            (assigned_total_devices as f64 / total_devices as f64) * 100.0;
-
-                        StatisticsVariant::new(
-                            variant.data().to_owned(),
-                            assigned_total_devices,
-                            percentage_devices,
+                            .filter(|a| a.version() == version)
+                            .copied()
+                            .collect();
+
+                        // Variants of previous versions are only known from their assignments.
+                        let mut version_variants_data: Vec<&VariantData> =
+                            version_participants.iter().map(|a| a.data()).collect();
+                        version_variants_data.sort_by_key(|data| data.to_string());
+                        version_variants_data.dedup();
+
+                        StatisticsVersion::new(
+                            version,
+                            version_participants.len(),
+                            statistics_variants(&version_variants_data, &version_participants),
                         )
                     })
                     .collect();
 
-                let id = exp.id().to_owned();
-                let name = exp.name().to_owned();
-                let variants = StatisticsVariants::new(statistics_variants);
+                let sample_ratio = sample_ratio_check(exp, |data| {
+                    participants
+                        .iter()
+                        .filter(|a| a.version() == exp.version() && a.data() == data)
+                        .count()
+                });
 
-                StaticticsExperiment::new(id, name, total_devices, variants)
+                StaticticsExperiment::new(
+                    exp.id().to_owned(),
+                    exp.name().to_owned(),
+                    exp.version(),
+                    participants.len(),
+                    exp.control().to_owned(),
+                    variants,
+                    versions,
+                )
+                .with_sample_ratio(sample_ratio)
+                .with_analysis_plan(exp.analysis_plan())
+                .with_eligibility(exp.eligibility())
             })
             .collect();
 
         Ok(experiments)
     }
 
+    async fn get_experiment_statistics(
+        &self,
+        req: &GetExperimentStatisticsRequest,
+    ) -> Result<StaticticsExperiment, GetExperimentStatisticsError> {
+        let exp = self
+            .repo
+            .get_experiment_by_id(req.id())
+            .await
+            .map_err(|e| match e {
+                GetExperimentByIdError::NotFound { id } => {
+                    GetExperimentStatisticsError::NotFound { id }
+                }
+                e => GetExperimentStatisticsError::Unknown(
+                    anyhow!(e).context("failed to get experiment"),
+                ),
+            })?;
+
+        self.experiment_statistics(&exp, req).await
+    }
+
+    async fn calculate_sample_size(
+        &self,
+        req: &SampleSizeRequest,
+    ) -> Result<SampleSize, CalculateSampleSizeError> {
+        let since = Utc::now() - TimeDelta::days(SampleSize::ACTIVITY_WINDOW_DAYS);
+        let active_devices = self
+            .repo
+            .count_active_devices_since(&since, req.eligibility())
+            .await?;
+
+        let daily_devices = active_devices as f64 / SampleSize::ACTIVITY_WINDOW_DAYS as f64
+            * req.allocation().into_inner()
+            / 100.0;
+
+        Ok(SampleSize::new(req.required_devices(), daily_devices))
+    }
+
+    async fn get_timeseries(
+        &self,
+        req: &GetTimeseriesRequest,
+    ) -> Result<Timeseries, GetTimeseriesError> {
+        let id = req.experiment_id();
+
+        let experiment = self
+            .repo
+            .get_experiment_by_id(id)
+            .await
+            .map_err(|e| match e {
+                GetExperimentByIdError::NotFound { id } => GetTimeseriesError::NotFound { id },
+                e => GetTimeseriesError::Unknown(anyhow!(e).context("failed to get experiment")),
+            })?;
+
+        let participants = self
+            .repo
+            .get_experiment_assignments(id)
+            .await
+            .map_err(|e| {
+                GetTimeseriesError::Unknown(
+                    anyhow!(e).context("failed to get experiment assignments"),
+                )
+            })?;
+
+        let observations = self
+            .repo
+            .get_timed_metric_observations(id)
+            .await
+            .map_err(|e| {
+                GetTimeseriesError::Unknown(
+                    anyhow!(e).context("failed to get timed metric observations"),
+                )
+            })?;
+
+        let buckets =
+            timeseries_buckets(&experiment, participants, observations, req, &Utc::now())?;
+
+        Ok(Timeseries::new(req, buckets))
+    }
+
+    async fn update_experiment(
+        &self,
+        req: &UpdateExperimentRequest,
+    ) -> Result<Uuid, UpdateExperimentError> {
+        self.repo.update_experiment(req).await
+    }
+
     async fn finish_experiment(&self, id: &Uuid) -> Result<Uuid, FinishExperimentError> {
         self.repo.finish_experiment(id).await
     }
+
+    async fn change_experiment_status(
+        &self,
+        id: &Uuid,
+        status: ExperimentStatus,
+    ) -> Result<Uuid, ChangeExperimentStatusError> {
+        self.repo.change_experiment_status(id, status).await
+    }
+
+    async fn apply_schedules(&self, now: &DateTime<Utc>) -> Result<Vec<Uuid>, ApplySchedulesError> {
+        let experiments = self.repo.get_all_experiments().await.map_err(|e| {
+            ApplySchedulesError::Unknown(anyhow!(e).context("failed to get all experiments"))
+        })?;
+
+        let mut moved = Vec::new();
+        for exp in experiments {
+            let scheduled = exp.lifecycle().scheduled_status(now);
+            if scheduled == exp.status() {
+                continue;
+            }
+
+            // A failing experiment does not hold back the others, it is retried next time.
+            match self.apply_schedule(&exp, scheduled).await {
+                Ok(()) => moved.push(*exp.id()),
+                Err(e) => tracing::error!(
+                    "failed to apply the schedule of experiment {}: {:?}",
+                    exp.id(),
+                    e
+                ),
+            }
+        }
+
+        Ok(moved)
+    }
+
+    async fn evaluate_guardrails(&self) -> Result<Vec<AuditEntry>, EvaluateGuardrailsError> {
+        let experiments = self.repo.get_all_experiments().await.map_err(|e| {
+            EvaluateGuardrailsError::Unknown(anyhow!(e).context("failed to get all experiments"))
+        })?;
+
+        let mut entries = Vec::new();
+        for exp in experiments
+            .iter()
+            .filter(|exp| exp.status() == ExperimentStatus::Running && !exp.guardrails().is_empty())
+        {
+            // A failing experiment does not hold back the others, it is evaluated next time.
+            match self.evaluate_experiment_guardrails(exp).await {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) => {}
+                Err(e) => tracing::error!(
+                    "failed to evaluate guardrails of experiment {}: {:?}",
+                    exp.id(),
+                    e
+                ),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    async fn get_audit_trail(
+        &self,
+        experiment_id: &Uuid,
+    ) -> Result<Vec<AuditEntry>, GetAuditTrailError> {
+        self.repo.get_audit_trail(experiment_id).await
+    }
+}
+
+/// Finds the first guardrail of an experiment breached by any of its variants.
+fn guardrail_breach(
+    experiment: &Experiment,
+    statistics: &StaticticsExperiment,
+) -> Option<CreateAuditEntryRequest> {
+    let variants = statistics.variants().variants();
+    let control = variants
+        .iter()
+        .find(|variant| variant.data() == experiment.control())?;
+
+    experiment
+        .guardrails()
+        .guardrails()
+        .iter()
+        .find_map(|guardrail| {
+            let control_metric = control
+                .metrics()
+                .iter()
+                .find(|metric| metric.name() == guardrail.metric())?;
+
+            variants
+                .iter()
+                .filter(|variant| variant.data() != experiment.control())
+                .find_map(|variant| {
+                    let metric = variant
+                        .metrics()
+                        .iter()
+                        .find(|metric| metric.name() == guardrail.metric())?;
+                    let degradation = guardrail.breach(control_metric, metric)?;
+
+                    Some(CreateAuditEntryRequest::guardrail_breach(
+                        *experiment.id(),
+                        guardrail,
+                        variant.data(),
+                        degradation,
+                    ))
+                })
+        })
+}
+
+/// Assignments of devices to experiments that both existed before assignments were persisted,
+/// as they were computed on the fly back then. Only devices created before an experiment were
+/// served it, and they are considered exposed as soon as the experiment existed. Already
+/// persisted assignments are kept as they are.
+fn legacy_assignments(
+    experiments: &[Experiment],
+    devices: &[Device],
+    since: &DateTime<Utc>,
+) -> Vec<CreateAssignmentRequest> {
+    experiments
+        .iter()
+        .filter(|exp| exp.created_at() < since)
+        .flat_map(|exp| {
+            devices
+                .iter()
+                .filter(|device| device.created_at() <= exp.created_at())
+                .filter_map(move |device| {
+                    let data = exp.assign_variant(device)?;
+
+                    Some(
+                        CreateAssignmentRequest::new(
+                            device.id().to_owned(),
+                            *exp.id(),
+                            data.to_owned(),
+                            exp.version(),
+                        )
+                        .with_assigned_at(*exp.created_at()),
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Evaluates metrics of an experiment for each of the variants and compares the variants
+/// with the method of the request.
+fn statistics_metrics(
+    experiment: &Experiment,
+    variants: StatisticsVariants,
+    observations: &[&MetricObservation],
+    pre_observations: &[&MetricObservation],
+    req: &GetStatisticsRequest,
+) -> StatisticsVariants {
+    let observations_of =
+        |variant: &StatisticsVariant| observations_of_variant(observations, variant);
+    let pre_observations_of =
+        |variant: &StatisticsVariant| observations_of_variant(pre_observations, variant);
+
+    let control = variants
+        .variants()
+        .iter()
+        .find(|v| v.data() == experiment.control());
+    let control_observations = control.map(observations_of).unwrap_or_default();
+    let control_pre_observations = control.map(pre_observations_of).unwrap_or_default();
+
+    let variants_observations: Vec<Vec<&MetricObservation>> =
+        variants.variants().iter().map(observations_of).collect();
+
+    // Comparisons of all the variants for each of the metrics, in the order of the variants.
+    let comparisons: Vec<Vec<BayesianComparison>> = experiment
+        .metrics()
+        .metrics()
+        .iter()
+        .map(|metric| match req.method() {
+            AnalysisMethod::Frequentist => Vec::new(),
+            AnalysisMethod::Bayesian => {
+                let posteriors: Option<Vec<Posterior>> = variants
+                    .variants()
+                    .iter()
+                    .zip(&variants_observations)
+                    .map(|(variant, observations)| {
+                        metric
+                            .sample(variant.total_devices(), observations)?
+                            .posterior()
+                    })
+                    .collect();
+
+                posteriors
+                    .filter(|posteriors| posteriors.len() >= 2)
+                    .map(|posteriors| bayesian::compare(&posteriors, DEFAULT_DRAWS, DEFAULT_SEED))
+                    .unwrap_or_default()
+            }
+        })
+        .collect();
+
+    let variants = variants
+        .variants()
+        .iter()
+        .zip(&variants_observations)
+        .enumerate()
+        .map(|(i, (variant, variant_observations))| {
+            let is_control = variant.data() == experiment.control();
+            let variant_pre_observations = pre_observations_of(variant);
+
+            let metrics = experiment
+                .metrics()
+                .metrics()
+                .iter()
+                .zip(&comparisons)
+                .map(|(metric, comparisons)| {
+                    let value = metric.evaluate(variant.total_devices(), variant_observations);
+                    let test = control
+                        .filter(|_| !is_control && req.method() == AnalysisMethod::Frequentist)
+                        .and_then(|control| {
+                            let control_sample =
+                                metric.sample(control.total_devices(), &control_observations)?;
+                            let sample =
+                                metric.sample(variant.total_devices(), variant_observations)?;
+
+                            match experiment.analysis_plan() {
+                                AnalysisPlan::Fixed => control_sample.test(&sample, req.alpha()),
+                                AnalysisPlan::Sequential => control_sample.sequential_test(
+                                    &sample,
+                                    metric.mixing_deviation()?,
+                                    req.alpha(),
+                                ),
+                            }
+                        });
+
+                    let cuped = control
+                        .filter(|_| !is_control && req.cuped().is_some())
+                        .and_then(|control| {
+                            let control_sample = metric.covariate_sample(
+                                control.total_devices(),
+                                &control_observations,
+                                &control_pre_observations,
+                            )?;
+                            let sample = metric.covariate_sample(
+                                variant.total_devices(),
+                                variant_observations,
+                                &variant_pre_observations,
+                            )?;
+
+                            cuped::cuped(&control_sample, &sample, req.alpha())
+                        });
+
+                    StatisticsMetric::new(metric, value)
+                        .with_test(test)
+                        .with_comparison(comparisons.get(i).copied())
+                        .with_cuped(cuped)
+                })
+                .collect();
+
+            variant.to_owned().with_metrics(metrics)
+        })
+        .collect();
+
+    StatisticsVariants::new(variants)
+}
+
+fn observations_of_variant<'a>(
+    observations: &[&'a MetricObservation],
+    variant: &StatisticsVariant,
+) -> Vec<&'a MetricObservation> {
+    observations
+        .iter()
+        .filter(|o| o.data() == variant.data())
+        .copied()
+        .collect()
+}
+
+/// Tests the split of participants of the current version, as counted by `count_of` for each
+/// of the variants, against the configured distribution of the variants. Earlier versions are
+/// left out as their distribution may differ.
+fn sample_ratio_check(
+    experiment: &Experiment,
+    count_of: impl Fn(&VariantData) -> usize,
+) -> Option<SampleRatioCheck> {
+    let variants = experiment.variants().variants();
+
+    let observed: Vec<u64> = variants
+        .iter()
+        .map(|variant| count_of(variant.data()) as u64)
+        .collect();
+    let expected_shares: Vec<f64> = variants
+        .iter()
+        .map(|variant| variant.distribution().into_inner())
+        .collect();
+
+    chi_square_goodness_of_fit(&observed, &expected_shares).map(|test| SampleRatioCheck::new(&test))
+}
+
+/// Counts participants assigned to each of the variants.
+fn statistics_variants(
+    variants_data: &[&VariantData],
+    participants: &[&Assignment],
+) -> StatisticsVariants {
+    variants_of_counts(variants_data, participants.len(), |data| {
+        participants.iter().filter(|a| a.data() == data).count()
+    })
+}
+
+/// Sums counted participants of each of the variants.
+fn participant_variants(
+    variants_data: &[&VariantData],
+    counts: &[&ParticipantCount],
+) -> StatisticsVariants {
+    variants_of_counts(variants_data, total_participants(counts), |data| {
+        counts
+            .iter()
+            .filter(|c| c.data() == data)
+            .map(|c| c.devices())
+            .sum()
+    })
+}
+
+fn total_participants(counts: &[&ParticipantCount]) -> usize {
+    counts.iter().map(|c| c.devices()).sum()
+}
+
+/// Shares the devices between the variants by the number `count_of` gives for each of them.
+fn variants_of_counts(
+    variants_data: &[&VariantData],
+    total_devices: usize,
+    count_of: impl Fn(&VariantData) -> usize,
+) -> StatisticsVariants {
+    let variants = variants_data
+        .iter()
+        .map(|&data| {
+            let assigned_total_devices = count_of(data);
+            let percentage_devices = if total_devices == 0 {
+                0.0
+            } else {
+                (assigned_total_devices as f64 / total_devices as f64) * 100.0
+            };
+
+            StatisticsVariant::new(data.to_owned(), assigned_total_devices, percentage_devices)
+        })
+        .collect();
+
+    StatisticsVariants::new(variants)
+}
+
+/// Builds the buckets of a timeseries, accumulating enrolments and events bucket by bucket.
+/// Buckets end with the one of the latest enrolment or event, but not past the end of the
+/// experiment or the current time.
+fn timeseries_buckets(
+    experiment: &Experiment,
+    mut participants: Vec<Assignment>,
+    mut observations: Vec<TimedMetricObservation>,
+    req: &GetTimeseriesRequest,
+    now: &DateTime<Utc>,
+) -> Result<Vec<TimeseriesBucket>, GetTimeseriesError> {
+    let granularity = req.granularity();
+    let timezone = req.timezone();
+    participants.sort_by_key(|a| *a.assigned_at());
+    observations.sort_by_key(|o| *o.occurred_at());
+
+    let Some(first) = participants.first().map(|a| *a.assigned_at()) else {
+        return Ok(Vec::new());
+    };
+    let latest = participants
+        .last()
+        .map(|a| *a.assigned_at())
+        .into_iter()
+        .chain(observations.last().map(|o| *o.occurred_at()))
+        .max()
+        .unwrap_or(first);
+    let end = [
+        Some(*now),
+        experiment.schedule().ends_at().copied(),
+        *experiment.finished_at(),
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+    .unwrap_or(*now);
+    let last_start = granularity.bucket_start(&latest.min(end).max(first), timezone);
+
+    let mut starts = vec![granularity.bucket_start(&first, timezone)];
+    while let Some(start) = starts.last().copied().filter(|start| *start < last_start) {
+        if starts.len() == Timeseries::MAX_BUCKETS {
+            return Err(GetTimeseriesError::TooManyBuckets {
+                max: Timeseries::MAX_BUCKETS,
+            });
+        }
+        starts.push(granularity.next_bucket_start(&start, timezone));
+    }
+
+    let mut participants = participants.iter().peekable();
+    let mut observations = observations.iter().peekable();
+    let mut total_devices: HashMap<&VariantData, usize> = HashMap::new();
+    // Events are aggregated per device and event name within each variant, the way the
+    // repository aggregates observations of all time.
+    let mut aggregated: HashMap<&VariantData, HashMap<(&DeviceId, &EventName), MetricObservation>> =
+        HashMap::new();
+
+    let mut buckets = Vec::with_capacity(starts.len());
+    for start in starts {
+        let end_utc = granularity.next_bucket_start(&start, timezone).to_utc();
+
+        let mut new_devices: HashMap<&VariantData, usize> = HashMap::new();
+        while let Some(a) = participants.next_if(|a| *a.assigned_at() < end_utc) {
+            *new_devices.entry(a.data()).or_default() += 1;
+        }
+        while let Some(timed) = observations.next_if(|o| *o.occurred_at() < end_utc) {
+            let o = timed.observation();
+
+            aggregated
+                .entry(o.data())
+                .or_default()
+                .entry((o.device_id(), o.event()))
+                .and_modify(|total| {
+                    *total = MetricObservation::new(
+                        *o.experiment_id(),
+                        o.device_id().to_owned(),
+                        o.data().to_owned(),
+                        o.event().to_owned(),
+                        total.count() + o.count(),
+                        total.sum() + o.sum(),
+                        total.sum_of_squares() + o.sum_of_squares(),
+                    )
+                    .with_value_count(total.value_count() + o.value_count())
+                })
+                .or_insert_with(|| o.to_owned());
+        }
+
+        let variants = experiment
+            .variants()
+            .variants()
+            .iter()
+            .map(|variant| {
+                let new_devices = new_devices.get(variant.data()).copied().unwrap_or_default();
+                let total_devices = total_devices.entry(variant.data()).or_default();
+                *total_devices += new_devices;
+
+                let variant_observations: Vec<&MetricObservation> = aggregated
+                    .get(variant.data())
+                    .map(|observations| observations.values().collect())
+                    .unwrap_or_default();
+                let metrics = experiment
+                    .metrics()
+                    .metrics()
+                    .iter()
+                    .map(|metric| {
+                        let value = metric.evaluate(*total_devices, &variant_observations);
+                        StatisticsMetric::new(metric, value)
+                    })
+                    .collect();
+
+                TimeseriesVariant::new(
+                    variant.data().to_owned(),
+                    new_devices,
+                    *total_devices,
+                    metrics,
+                )
+            })
+            .collect();
+
+        buckets.push(TimeseriesBucket::new(start, variants));
+    }
+
+    Ok(buckets)
+}
+
+#[cfg(test)]
+mod service_tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::domain::device::models::device::{CreateDeviceRequest, DeviceIdKind};
+    use crate::domain::device::ports::DeviceRepository;
+    use crate::domain::event::models::event::{EventAttribution, SaveEventRequest};
+    use crate::domain::event::ports::EventRepository;
+    use crate::domain::experiment::models::experiment::{
+        ExperimentAllocation, ExperimentBucketing, ExperimentEligibility, ExperimentLifecycle,
+        ExperimentName, ExperimentSchedule, ExperimentVariants, Variant, VariantData,
+        VariantDistribution,
+    };
+    use crate::domain::experiment::models::guardrail::{
+        ExperimentGuardrails, Guardrail, MaxDegradation,
+    };
+    use crate::domain::experiment::models::layer::{ExperimentLayer, LayerBuckets, LayerName};
+    use crate::domain::experiment::models::metric::{
+        ExperimentMetrics, Metric, MetricKind, MetricName, MetricRole,
+    };
+    use crate::domain::experiment::models::timeseries::TimeseriesGranularity;
+    use crate::domain::statistics::sequential::MixingDeviation;
+    use crate::outbound::sqlite::{Sqlite, in_memory_sqlite};
+
+    fn experiment(name: &str, created_at: DateTime<Utc>) -> Experiment {
+        let variants = ExperimentVariants::new(vec![
+            Variant::new(
+                VariantDistribution::new(50.0).unwrap(),
+                VariantData::new("blue").unwrap(),
+            ),
+            Variant::new(
+                VariantDistribution::new(50.0).unwrap(),
+                VariantData::new("red").unwrap(),
+            ),
+        ])
+        .unwrap();
+
+        Experiment::new(
+            Uuid::new_v4(),
+            ExperimentName::new(name).unwrap(),
+            variants,
+            1,
+            None,
+            ExperimentAllocation::new(100.0).unwrap(),
+            ExperimentLifecycle::new(ExperimentStatus::Running, created_at, None),
+        )
+    }
+
+    fn device(raw_idfa: &str, created_at: DateTime<Utc>) -> Device {
+        Device::new(
+            DeviceId::new(DeviceIdKind::Idfa, raw_idfa).unwrap(),
+            created_at,
+        )
+    }
+
+    #[test]
+    fn test_legacy_assignments() {
+        let now = Utc::now();
+        let since = now - TimeDelta::days(1);
+        let legacy_experiment = experiment("legacy", now - TimeDelta::days(2));
+        let experiments = vec![legacy_experiment.clone(), experiment("recent", now)];
+        let legacy_device = device(
+            "550e8400-e29b-41d4-a716-446655440000",
+            now - TimeDelta::days(3),
+        );
+        // The second device appeared after the legacy experiment, so it was never served it.
+        let devices = vec![
+            legacy_device.clone(),
+            device(
+                "6ba7b811-9dad-11d1-80b4-00c04fd430c8",
+                now - TimeDelta::hours(36),
+            ),
+            device("6ba7b810-9dad-11d1-80b4-00c04fd430c8", now),
+        ];
+
+        let reqs = legacy_assignments(&experiments, &devices, &since);
+
+        assert_eq!(
+            reqs,
+            vec![
+                CreateAssignmentRequest::new(
+                    legacy_device.id().to_owned(),
+                    *legacy_experiment.id(),
+                    legacy_experiment
+                        .assign_variant(&legacy_device)
+                        .unwrap()
+                        .to_owned(),
+                    1,
+                )
+                .with_assigned_at(*legacy_experiment.created_at())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timeseries_buckets() {
+        let created_at: DateTime<Utc> = "2025-07-10T00:00:00Z".parse().unwrap();
+        let metrics = ExperimentMetrics::new(vec![Metric::new(
+            MetricName::new("purchases").unwrap(),
+            MetricKind::Conversion(EventName::new("purchase").unwrap()),
+            MetricRole::Primary,
+        )])
+        .unwrap();
+        let exp = experiment("color", created_at).with_metrics(metrics);
+        let (blue, red) = (
+            VariantData::new("blue").unwrap(),
+            VariantData::new("red").unwrap(),
+        );
+        let phone = device("550e8400-e29b-41d4-a716-446655440000", created_at);
+        let tablet = device("6ba7b810-9dad-11d1-80b4-00c04fd430c8", created_at);
+        let participants = vec![
+            Assignment::new(
+                tablet.id().to_owned(),
+                *exp.id(),
+                red.clone(),
+                1,
+                "2025-07-11T09:00:00Z".parse().unwrap(),
+            ),
+            Assignment::new(
+                phone.id().to_owned(),
+                *exp.id(),
+                blue.clone(),
+                1,
+                "2025-07-10T10:00:00Z".parse().unwrap(),
+            ),
+        ];
+        let purchase = |occurred_at: &str| {
+            TimedMetricObservation::new(
+                MetricObservation::new(
+                    *exp.id(),
+                    phone.id().to_owned(),
+                    blue.clone(),
+                    EventName::new("purchase").unwrap(),
+                    1,
+                    0.0,
+                    0.0,
+                ),
+                occurred_at.parse().unwrap(),
+            )
+        };
+        // The second purchase is yet to come, e.g. sent by a device with a clock ahead.
+        let observations = vec![
+            purchase("2025-07-13T00:00:00Z"),
+            purchase("2025-07-11T12:00:00Z"),
+        ];
+        let now: DateTime<Utc> = "2025-07-12T06:00:00Z".parse().unwrap();
+
+        let buckets = timeseries_buckets(
+            &exp,
+            participants.clone(),
+            observations,
+            &GetTimeseriesRequest::new(*exp.id()),
+            &now,
+        )
+        .unwrap();
+
+        let summary: Vec<_> = buckets
+            .iter()
+            .map(|bucket| {
+                let variants = bucket
+                    .variants()
+                    .iter()
+                    .map(|v| (v.new_devices(), v.total_devices(), v.metrics()[0].value()))
+                    .collect::<Vec<_>>();
+                (bucket.start().to_rfc3339(), variants)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "2025-07-10T00:00:00+00:00".to_string(),
+                    vec![(1, 1, Some(0.0)), (0, 0, None)]
+                ),
+                (
+                    "2025-07-11T00:00:00+00:00".to_string(),
+                    vec![(0, 1, Some(1.0)), (1, 1, Some(0.0))]
+                ),
+                (
+                    "2025-07-12T00:00:00+00:00".to_string(),
+                    vec![(0, 1, Some(1.0)), (0, 1, Some(0.0))]
+                ),
+            ]
+        );
+
+        // Hours from the first enrolment to one two months later are too many.
+        let mut participants = participants;
+        participants.push(Assignment::new(
+            device("6ba7b811-9dad-11d1-80b4-00c04fd430c8", created_at)
+                .id()
+                .to_owned(),
+            *exp.id(),
+            red,
+            1,
+            "2025-09-10T00:00:00Z".parse().unwrap(),
+        ));
+        let too_many = timeseries_buckets(
+            &exp,
+            participants,
+            Vec::new(),
+            &GetTimeseriesRequest::new(*exp.id()).with_granularity(TimeseriesGranularity::Hour),
+            &"2025-09-11T00:00:00Z".parse().unwrap(),
+        );
+
+        assert!(matches!(
+            too_many,
+            Err(GetTimeseriesError::TooManyBuckets { .. })
+        ));
+    }
+
+    async fn create_scheduled_experiment(
+        service: &Service<Sqlite>,
+        name: &str,
+        status: ExperimentStatus,
+        schedule: ExperimentSchedule,
+    ) -> Uuid {
+        let variants = ExperimentVariants::new(vec![Variant::new(
+            VariantDistribution::new(100.0).unwrap(),
+            VariantData::new("blue").unwrap(),
+        )])
+        .unwrap();
+        let req = CreateExperimentRequest::new(
+            ExperimentName::new(name).unwrap(),
+            variants,
+            None,
+            ExperimentAllocation::FULL,
+            status,
+        )
+        .with_schedule(schedule);
+
+        service.create_experiment(&req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_apply_schedules() {
+        let service = Service::new(in_memory_sqlite().await);
+        let now = Utc::now();
+        let hour = TimeDelta::hours(1);
+        let due = create_scheduled_experiment(
+            &service,
+            "due",
+            ExperimentStatus::Draft,
+            ExperimentSchedule::new(Some(now - hour), None).unwrap(),
+        )
+        .await;
+        let ended = create_scheduled_experiment(
+            &service,
+            "ended",
+            ExperimentStatus::Draft,
+            ExperimentSchedule::new(Some(now - hour * 2), Some(now - hour)).unwrap(),
+        )
+        .await;
+        let upcoming = create_scheduled_experiment(
+            &service,
+            "upcoming",
+            ExperimentStatus::Draft,
+            ExperimentSchedule::new(Some(now + hour), None).unwrap(),
+        )
+        .await;
+
+        let mut moved = service.apply_schedules(&now).await.unwrap();
+        let status = async |id: &Uuid| {
+            service
+                .repo
+                .get_experiment_by_id(id)
+                .await
+                .unwrap()
+                .status()
+        };
+
+        moved.sort();
+        let mut expected = vec![due, ended];
+        expected.sort();
+        assert_eq!(moved, expected);
+        assert_eq!(status(&due).await, ExperimentStatus::Running);
+        assert_eq!(status(&ended).await, ExperimentStatus::Finished);
+        assert_eq!(status(&upcoming).await, ExperimentStatus::Draft);
+        // Nothing is left to move on the next call.
+        assert!(service.apply_schedules(&now).await.unwrap().is_empty());
+
+        // Devices that appeared between the creation and the scheduled start are existing ones.
+        let due = service.repo.get_experiment_by_id(&due).await.unwrap();
+        assert!(due.is_eligible(&(now - hour * 2)));
+        assert!(!due.is_eligible(&(now - hour / 2)));
+    }
+
+    async fn create_guarded_experiment(
+        service: &Service<Sqlite>,
+        name: &str,
+        mixing_deviation: MixingDeviation,
+    ) -> Uuid {
+        let variants = ExperimentVariants::new(vec![
+            Variant::new(
+                VariantDistribution::new(50.0).unwrap(),
+                VariantData::new("blue").unwrap(),
+            ),
+            Variant::new(
+                VariantDistribution::new(50.0).unwrap(),
+                VariantData::new("red").unwrap(),
+            ),
+        ])
+        .unwrap();
+        let metrics = ExperimentMetrics::new(vec![
+            Metric::new(
+                MetricName::new("crash_rate").unwrap(),
+                MetricKind::Conversion(EventName::new("crash").unwrap()),
+                MetricRole::Secondary,
+            )
+            .with_mixing_deviation(mixing_deviation),
+        ])
+        .unwrap();
+        let guardrails = ExperimentGuardrails::new(
+            vec![Guardrail::new(
+                MetricName::new("crash_rate").unwrap(),
+                MaxDegradation::new(0.1).unwrap(),
+            )],
+            &metrics,
+        )
+        .unwrap();
+        let req = CreateExperimentRequest::new(
+            ExperimentName::new(name).unwrap(),
+            variants,
+            None,
+            ExperimentAllocation::FULL,
+            ExperimentStatus::Running,
+        )
+        .with_metrics(metrics)
+        .with_guardrails(guardrails);
+
+        service.create_experiment(&req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_guardrails() {
+        let service = Service::new(in_memory_sqlite().await);
+        let breached =
+            create_guarded_experiment(&service, "breached", MixingDeviation::new(0.5).unwrap())
+                .await;
+        let kept = create_guarded_experiment(&service, "kept", MixingDeviation::PROPORTION).await;
+
+        // Both experiments see 40 devices in each variant, crashing in 2 of the control and 24
+        // of the treatment devices.
+        for i in 0..80 {
+            let id = DeviceId::new(DeviceIdKind::Idfa, &Uuid::new_v4().to_string()).unwrap();
+            service
+                .repo
+                .create_device(&CreateDeviceRequest::new(id.clone()))
+                .await
+                .unwrap();
+            let data = VariantData::new(if i < 40 { "blue" } else { "red" }).unwrap();
+            let reqs: Vec<CreateAssignmentRequest> = [breached, kept]
+                .into_iter()
+                .map(|experiment_id| {
+                    CreateAssignmentRequest::new(id.clone(), experiment_id, data.clone(), 1)
+                })
+                .collect();
+            service.repo.create_assignments(&reqs).await.unwrap();
+
+            if i < 2 || (40..64).contains(&i) {
+                let attributions = [breached, kept]
+                    .into_iter()
+                    .map(|experiment_id| EventAttribution::new(experiment_id, data.clone(), 1))
+                    .collect();
+                let crash = SaveEventRequest::new(
+                    id.clone(),
+                    EventName::new("crash").unwrap(),
+                    None,
+                    Utc::now(),
+                    attributions,
+                );
+                service.repo.save_events(&id, &[crash]).await.unwrap();
+            }
+        }
+
+        let entries = service.evaluate_guardrails().await.unwrap();
+        let status = async |id: &Uuid| {
+            service
+                .repo
+                .get_experiment_by_id(id)
+                .await
+                .unwrap()
+                .status()
+        };
+
+        // The fixed test would stop both, but with the narrow mixing deviation the sequential
+        // test is not yet sure the treatment is worse.
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].experiment_id(), &breached);
+        assert_eq!(entries[0].status(), ExperimentStatus::Paused);
+        assert!(entries[0].reason().contains("variant red"));
+        assert_eq!(status(&breached).await, ExperimentStatus::Paused);
+        assert_eq!(status(&kept).await, ExperimentStatus::Running);
+
+        assert_eq!(service.get_audit_trail(&breached).await.unwrap(), entries);
+        assert!(service.get_audit_trail(&kept).await.unwrap().is_empty());
+        // A paused experiment is not evaluated again.
+        assert!(service.evaluate_guardrails().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_layers() {
+        let service = Service::new(in_memory_sqlite().await);
+        let req = |name: &str| {
+            let variants = ExperimentVariants::new(vec![Variant::new(
+                VariantDistribution::new(100.0).unwrap(),
+                VariantData::new("blue").unwrap(),
+            )])
+            .unwrap();
+
+            CreateExperimentRequest::new(
+                ExperimentName::new(name).unwrap(),
+                variants,
+                None,
+                ExperimentAllocation::FULL,
+                ExperimentStatus::Running,
+            )
+            .with_eligibility(ExperimentEligibility::All)
+        };
+        let layer = |start: u32, end: u32| {
+            ExperimentLayer::new(
+                LayerName::new("checkout").unwrap(),
+                LayerBuckets::new(start, end).unwrap(),
+            )
+        };
+        let left = service
+            .create_experiment(&req("button").with_layer(layer(0, 500)))
+            .await
+            .unwrap();
+        let right = service
+            .create_experiment(&req("banner").with_layer(layer(500, 1000)))
+            .await
+            .unwrap();
+        let free = service.create_experiment(&req("color")).await.unwrap();
+
+        // Every device takes part in exactly one experiment of the layer, and in the experiment
+        // outside of it.
+        let mut layered = Vec::new();
+        for _ in 0..50 {
+            let id = DeviceId::new(DeviceIdKind::Idfa, &Uuid::new_v4().to_string()).unwrap();
+            let device = service.repo.upsert_device(&id).await.unwrap();
+            let experiments: Vec<Uuid> = service
+                .get_all_device_participating_experiments(&device)
+                .await
+                .unwrap()
+                .iter()
+                .map(|exp| *exp.id())
+                .collect();
+
+            assert_eq!(experiments.len(), 2);
+            assert!(experiments.contains(&free));
+            layered.extend(experiments.into_iter().filter(|id| *id != free));
+        }
+        assert!(layered.contains(&left));
+        assert!(layered.contains(&right));
+
+        // A user-bucketed experiment would hash devices of a user elsewhere in the layer.
+        let result = service
+            .create_experiment(
+                &req("copy")
+                    .with_bucketing(ExperimentBucketing::User)
+                    .with_layer(layer(0, 500)),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(CreateExperimentError::LayerBucketing { .. })
+        ));
+    }
 }
@@ -0,0 +1,9 @@
+//! Statistical methods the experiment analysis is built on. The module is pure math: it knows
+//! nothing about experiments, storage or transport.
+
+pub mod bayesian;
+pub mod cuped;
+pub mod distribution;
+pub mod frequentist;
+pub mod power;
+pub mod sequential;
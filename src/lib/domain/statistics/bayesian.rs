@@ -0,0 +1,239 @@
+//! Bayesian comparison of variants by Monte Carlo sampling of their posteriors.
+
+use crate::domain::statistics::frequentist::{MeanSample, ProportionSample};
+
+/// Seed the comparisons start from, so that the same data always yields the same result.
+pub const DEFAULT_SEED: u64 = 0x5EED_AB0E_0000_0001;
+/// Number of joint draws from the posteriors.
+pub const DEFAULT_DRAWS: usize = 20_000;
+
+/// Small deterministic pseudo-random generator (SplitMix64). It is not suitable for
+/// cryptography, only for reproducible simulations.
+#[derive(Clone, Debug)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        z ^ (z >> 31)
+    }
+
+    /// Uniform number in the `(0, 1)` range.
+    pub fn next_uniform(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    /// Standard normal number (Box–Muller transform).
+    pub fn next_normal(&mut self) -> f64 {
+        let u1 = self.next_uniform();
+        let u2 = self.next_uniform();
+
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+
+    /// Gamma distributed number with the given shape and unit scale (Marsaglia–Tsang method).
+    pub fn next_gamma(&mut self, shape: f64) -> f64 {
+        if shape < 1.0 {
+            // Boosts the shape above 1, see Marsaglia and Tsang (2000).
+            return self.next_gamma(shape + 1.0) * self.next_uniform().powf(1.0 / shape);
+        }
+
+        let d = shape - 1.0 / 3.0;
+        let c = 1.0 / (9.0 * d).sqrt();
+        loop {
+            let x = self.next_normal();
+            let v = (1.0 + c * x).powi(3);
+            if v <= 0.0 {
+                continue;
+            }
+
+            let u = self.next_uniform();
+            if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+                return d * v;
+            }
+        }
+    }
+
+    /// Beta distributed number.
+    pub fn next_beta(&mut self, alpha: f64, beta: f64) -> f64 {
+        let x = self.next_gamma(alpha);
+        let y = self.next_gamma(beta);
+
+        x / (x + y)
+    }
+}
+
+/// Posterior distribution of the value of a metric in a variant.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Posterior {
+    /// Posterior of a proportion.
+    Beta { alpha: f64, beta: f64 },
+    /// Normal approximation of the posterior of a mean.
+    Normal { mean: f64, standard_deviation: f64 },
+}
+
+impl Posterior {
+    /// Beta-Binomial posterior of a proportion under the uniform `Beta(1, 1)` prior.
+    pub fn from_proportion(sample: &ProportionSample) -> Self {
+        Self::Beta {
+            alpha: 1.0 + sample.successes() as f64,
+            beta: 1.0 + (sample.trials() - sample.successes()) as f64,
+        }
+    }
+
+    /// Normal approximation of the posterior of a mean under a flat prior. The spread is taken
+    /// from the units of the sample, e.g. devices rather than their events.
+    ///
+    /// # Returns
+    /// * `Some(Posterior)` with the posterior.
+    /// * `None` if the sample has less than two units.
+    pub fn from_mean(sample: &MeanSample) -> Option<Self> {
+        (sample.count() >= 2).then(|| Self::Normal {
+            mean: sample.mean(),
+            standard_deviation: (sample.variance() / sample.count() as f64).sqrt(),
+        })
+    }
+
+    pub fn sample(&self, rng: &mut SplitMix64) -> f64 {
+        match *self {
+            Self::Beta { alpha, beta } => rng.next_beta(alpha, beta),
+            Self::Normal {
+                mean,
+                standard_deviation,
+            } => mean + standard_deviation * rng.next_normal(),
+        }
+    }
+}
+
+/// Outcome of comparing a variant against all the others.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BayesianComparison {
+    probability_to_be_best: f64,
+    expected_loss: f64,
+}
+
+impl BayesianComparison {
+    /// Probability that the variant has the highest value of all.
+    pub fn probability_to_be_best(&self) -> f64 {
+        self.probability_to_be_best
+    }
+
+    /// Expected value lost by choosing the variant if it is not the best, in units of the
+    /// metric.
+    pub fn expected_loss(&self) -> f64 {
+        self.expected_loss
+    }
+}
+
+/// Compares variants by jointly sampling their posteriors, higher values being better.
+///
+/// # Arguments
+/// * `posteriors` - posteriors of the variants.
+/// * `draws` - number of joint draws.
+/// * `seed` - seed of the generator, the same seed yields the same comparison.
+///
+/// # Returns
+/// * `Vec<BayesianComparison>` - comparison of every variant in the order of `posteriors`.
+pub fn compare(posteriors: &[Posterior], draws: usize, seed: u64) -> Vec<BayesianComparison> {
+    let mut rng = SplitMix64::new(seed);
+    let mut wins = vec![0usize; posteriors.len()];
+    let mut losses = vec![0.0; posteriors.len()];
+    let mut values = vec![0.0; posteriors.len()];
+
+    for _ in 0..draws {
+        for (value, posterior) in values.iter_mut().zip(posteriors) {
+            *value = posterior.sample(&mut rng);
+        }
+
+        let (best, max) =
+            values
+                .iter()
+                .enumerate()
+                .fold((0, f64::NEG_INFINITY), |(best, max), (i, &value)| {
+                    if value > max { (i, value) } else { (best, max) }
+                });
+
+        wins[best] += 1;
+        for (loss, value) in losses.iter_mut().zip(&values) {
+            *loss += max - value;
+        }
+    }
+
+    let draws = draws.max(1) as f64;
+
+    wins.into_iter()
+        .zip(losses)
+        .map(|(wins, loss)| BayesianComparison {
+            probability_to_be_best: wins as f64 / draws,
+            expected_loss: loss / draws,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod bayesian_tests {
+    use super::*;
+
+    #[test]
+    fn test_split_mix_is_deterministic() {
+        let mut first = SplitMix64::new(42);
+        let mut second = SplitMix64::new(42);
+
+        for _ in 0..100 {
+            assert_eq!(first.next_u64(), second.next_u64());
+        }
+    }
+
+    #[test]
+    fn test_next_beta_mean() {
+        let mut rng = SplitMix64::new(DEFAULT_SEED);
+        let draws = 50_000;
+
+        let mean = (0..draws).map(|_| rng.next_beta(2.0, 6.0)).sum::<f64>() / draws as f64;
+
+        assert!((mean - 0.25).abs() < 0.005, "{mean} is not close to 0.25");
+    }
+
+    #[test]
+    fn test_compare_equal_posteriors() {
+        let posterior = Posterior::from_proportion(&ProportionSample::new(50, 100));
+
+        let result = compare(&[posterior, posterior], DEFAULT_DRAWS, DEFAULT_SEED);
+
+        assert!((result[0].probability_to_be_best() - 0.5).abs() < 0.02);
+        assert!((result[0].expected_loss() - result[1].expected_loss()).abs() < 0.005);
+    }
+
+    #[test]
+    fn test_compare_normal_posteriors() {
+        // The difference of the two is N(1, 1), so the second one is better with probability
+        // Φ(1) and the expected loss of the first one is φ(1) + Φ(1) = 1.0833.
+        let control = Posterior::Normal {
+            mean: 10.0,
+            standard_deviation: 0.5f64.sqrt(),
+        };
+        let treatment = Posterior::Normal {
+            mean: 11.0,
+            standard_deviation: 0.5f64.sqrt(),
+        };
+
+        let result = compare(&[control, treatment], DEFAULT_DRAWS, DEFAULT_SEED);
+
+        assert!((result[1].probability_to_be_best() - 0.8413).abs() < 0.01);
+        assert!((result[0].expected_loss() - 1.0833).abs() < 0.02);
+        assert_eq!(
+            result,
+            compare(&[control, treatment], DEFAULT_DRAWS, DEFAULT_SEED)
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! CUPED (Controlled-experiment Using Pre-Experiment Data) variance reduction. The outcome of
+//! every unit is adjusted by its pre-experiment value of the same metric, which removes the part
+//! of the variance explained by differences between units that existed before the experiment.
+
+use crate::domain::statistics::frequentist::{
+    MeanSample, SignificanceLevel, SignificanceTest, welch_t_test,
+};
+
+/// Sums of the outcomes of units and their pre-experiment covariates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CovariateSample {
+    count: u64,
+    sum_outcome: f64,
+    sum_covariate: f64,
+    sum_outcome_squares: f64,
+    sum_covariate_squares: f64,
+    sum_products: f64,
+}
+
+impl CovariateSample {
+    /// Creates a sample of `count` units, those missing from `values` have zero outcome and
+    /// covariate.
+    ///
+    /// # Arguments
+    /// * `count` - number of units in the sample.
+    /// * `values` - pairs of the outcome and the covariate of units with non-zero values.
+    ///
+    /// # Panics
+    /// If there are more values than units.
+    pub fn new(count: u64, values: &[(f64, f64)]) -> Self {
+        assert!(
+            values.len() as u64 <= count,
+            "values cannot exceed the number of units"
+        );
+
+        values.iter().fold(
+            Self {
+                count,
+                ..Self::default()
+            },
+            |sample, &(outcome, covariate)| Self {
+                sum_outcome: sample.sum_outcome + outcome,
+                sum_covariate: sample.sum_covariate + covariate,
+                sum_outcome_squares: sample.sum_outcome_squares + outcome * outcome,
+                sum_covariate_squares: sample.sum_covariate_squares + covariate * covariate,
+                sum_products: sample.sum_products + outcome * covariate,
+                ..sample
+            },
+        )
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Self {
+            count: self.count + other.count,
+            sum_outcome: self.sum_outcome + other.sum_outcome,
+            sum_covariate: self.sum_covariate + other.sum_covariate,
+            sum_outcome_squares: self.sum_outcome_squares + other.sum_outcome_squares,
+            sum_covariate_squares: self.sum_covariate_squares + other.sum_covariate_squares,
+            sum_products: self.sum_products + other.sum_products,
+        }
+    }
+
+    fn mean_outcome(&self) -> f64 {
+        self.sum_outcome / self.count as f64
+    }
+
+    fn mean_covariate(&self) -> f64 {
+        self.sum_covariate / self.count as f64
+    }
+
+    fn variance_outcome(&self) -> f64 {
+        self.covariance_of(
+            self.sum_outcome_squares,
+            self.mean_outcome(),
+            self.mean_outcome(),
+        )
+    }
+
+    fn variance_covariate(&self) -> f64 {
+        self.covariance_of(
+            self.sum_covariate_squares,
+            self.mean_covariate(),
+            self.mean_covariate(),
+        )
+    }
+
+    fn covariance(&self) -> f64 {
+        self.covariance_of(
+            self.sum_products,
+            self.mean_outcome(),
+            self.mean_covariate(),
+        )
+    }
+
+    fn covariance_of(&self, sum_products: f64, first_mean: f64, second_mean: f64) -> f64 {
+        let n = self.count as f64;
+
+        (sum_products - n * first_mean * second_mean) / (n - 1.0)
+    }
+
+    /// Variance of the outcome adjusted by the covariate with the coefficient `theta`.
+    fn adjusted_variance(&self, theta: f64) -> f64 {
+        // Rounding may push the variance of equal values slightly below zero.
+        (self.variance_outcome() - 2.0 * theta * self.covariance()
+            + theta * theta * self.variance_covariate())
+        .max(0.0)
+    }
+}
+
+/// Effect of a treatment on a metric estimated with and without CUPED.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CupedEstimate {
+    raw_difference: f64,
+    adjusted_difference: f64,
+    variance_reduction: f64,
+    test: Option<SignificanceTest>,
+}
+
+impl CupedEstimate {
+    /// Difference of the mean outcomes per unit.
+    pub fn raw_difference(&self) -> f64 {
+        self.raw_difference
+    }
+
+    /// Difference of the mean outcomes per unit adjusted by the covariate.
+    pub fn adjusted_difference(&self) -> f64 {
+        self.adjusted_difference
+    }
+
+    /// Share of the variance of the difference removed by the adjustment.
+    pub fn variance_reduction(&self) -> f64 {
+        self.variance_reduction
+    }
+
+    /// Welch's t-test of the adjusted outcomes, `None` if they have no variance.
+    pub fn test(&self) -> &Option<SignificanceTest> {
+        &self.test
+    }
+}
+
+/// Estimates the effect of a treatment with CUPED. The adjustment coefficient is estimated
+/// from both samples together, so that it does not depend on the treatment.
+///
+/// # Returns
+/// * `Some(CupedEstimate)` with the raw and adjusted effect.
+/// * `None` if either sample has less than two units.
+pub fn cuped(
+    control: &CovariateSample,
+    treatment: &CovariateSample,
+    alpha: SignificanceLevel,
+) -> Option<CupedEstimate> {
+    if control.count() < 2 || treatment.count() < 2 {
+        return None;
+    }
+
+    let pooled = control.combine(treatment);
+    let variance_covariate = pooled.variance_covariate();
+    // A constant covariate explains nothing, so the outcomes are left as they are.
+    let theta = if variance_covariate > 0.0 {
+        pooled.covariance() / variance_covariate
+    } else {
+        0.0
+    };
+
+    let adjusted_mean = |sample: &CovariateSample| {
+        sample.mean_outcome() - theta * (sample.mean_covariate() - pooled.mean_covariate())
+    };
+    let adjusted_control = MeanSample::new(
+        control.count(),
+        adjusted_mean(control),
+        control.adjusted_variance(theta),
+    );
+    let adjusted_treatment = MeanSample::new(
+        treatment.count(),
+        adjusted_mean(treatment),
+        treatment.adjusted_variance(theta),
+    );
+
+    let raw_variance = control.variance_outcome() / control.count() as f64
+        + treatment.variance_outcome() / treatment.count() as f64;
+    let adjusted_variance = adjusted_control.variance() / control.count() as f64
+        + adjusted_treatment.variance() / treatment.count() as f64;
+
+    Some(CupedEstimate {
+        raw_difference: treatment.mean_outcome() - control.mean_outcome(),
+        adjusted_difference: adjusted_treatment.mean() - adjusted_control.mean(),
+        variance_reduction: if raw_variance > 0.0 {
+            1.0 - adjusted_variance / raw_variance
+        } else {
+            0.0
+        },
+        test: welch_t_test(&adjusted_control, &adjusted_treatment, alpha),
+    })
+}
+
+#[cfg(test)]
+mod cuped_tests {
+    use super::*;
+
+    fn assert_close(result: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (result - expected).abs() < tolerance,
+            "{result} is not close to {expected}"
+        );
+    }
+
+    #[test]
+    fn test_cuped() {
+        // Pooled theta = 0.857923, so the adjusted difference is 1.75 - theta * 0.75.
+        let control = CovariateSample::new(4, &[(1.0, 1.0), (2.0, 2.0), (3.0, 3.0), (4.0, 5.0)]);
+        let treatment = CovariateSample::new(4, &[(2.0, 1.0), (4.0, 3.0), (5.0, 4.0), (6.0, 6.0)]);
+
+        let estimate = cuped(&control, &treatment, SignificanceLevel::DEFAULT).unwrap();
+
+        assert_close(estimate.raw_difference(), 1.75, 1e-12);
+        assert_close(estimate.adjusted_difference(), 1.1065573770491803, 1e-12);
+        assert_close(estimate.variance_reduction(), 0.9571408793660877, 1e-12);
+        assert!(estimate.test().unwrap().is_significant());
+    }
+
+    #[test]
+    fn test_cuped_without_covariate() {
+        // Units missing from the values have zero outcome and covariate.
+        let control = CovariateSample::new(4, &[(1.0, 0.0), (1.0, 0.0)]);
+        let treatment = CovariateSample::new(4, &[(1.0, 0.0), (1.0, 0.0), (1.0, 0.0)]);
+
+        let estimate = cuped(&control, &treatment, SignificanceLevel::DEFAULT).unwrap();
+
+        assert_close(estimate.raw_difference(), 0.25, 1e-12);
+        assert_close(estimate.adjusted_difference(), 0.25, 1e-12);
+        assert_close(estimate.variance_reduction(), 0.0, 1e-12);
+    }
+
+    #[test]
+    fn test_cuped_too_small() {
+        let control = CovariateSample::new(1, &[(1.0, 1.0)]);
+        let treatment = CovariateSample::new(4, &[]);
+
+        assert_eq!(
+            cuped(&control, &treatment, SignificanceLevel::DEFAULT),
+            None
+        );
+    }
+}
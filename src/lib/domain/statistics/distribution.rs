@@ -0,0 +1,356 @@
+//! Probability distributions used by the statistical tests.
+
+use std::f64::consts::{PI, SQRT_2};
+
+/// Relative accuracy of the iterative approximations.
+const EPSILON: f64 = 1e-12;
+const MAX_ITERATIONS: usize = 300;
+
+/// Complementary error function, accurate to about 1e-15.
+///
+/// Uses the continued fraction for large arguments and the Taylor series of `erf` otherwise.
+pub fn erfc(x: f64) -> f64 {
+    if x < 0.0 {
+        return 2.0 - erfc(-x);
+    }
+
+    if x < 2.0 {
+        // erf(x) = 2/sqrt(pi) * sum(-1^n * x^(2n+1) / (n! * (2n+1)))
+        let mut term = x;
+        let mut sum = x;
+        for n in 1..MAX_ITERATIONS {
+            term *= -x * x / n as f64;
+            let delta = term / (2 * n + 1) as f64;
+            sum += delta;
+            if delta.abs() < EPSILON * sum.abs() {
+                break;
+            }
+        }
+
+        return 1.0 - 2.0 / PI.sqrt() * sum;
+    }
+
+    // Lentz's algorithm for erfc(x) = exp(-x^2)/sqrt(pi) * 1/(x + 1/2/(x + 1/(x + 3/2/(x + ...))))
+    let tiny = 1e-300;
+    let mut f = x;
+    let mut c = x;
+    let mut d = 0.0;
+    for n in 1..MAX_ITERATIONS {
+        let a = n as f64 / 2.0;
+        d = x + a * d;
+        d = if d.abs() < tiny { tiny } else { d };
+        c = x + a / c;
+        c = if c.abs() < tiny { tiny } else { c };
+        d = 1.0 / d;
+        let delta = c * d;
+        f *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    (-x * x).exp() / PI.sqrt() / f
+}
+
+/// Cumulative distribution function of the standard normal distribution.
+pub fn normal_cdf(x: f64) -> f64 {
+    0.5 * erfc(-x / SQRT_2)
+}
+
+/// Quantile function of the standard normal distribution.
+///
+/// Acklam's rational approximation refined with a single Halley step.
+///
+/// # Panics
+/// If `p` is outside of the `(0, 1)` range.
+pub fn normal_quantile(p: f64) -> f64 {
+    assert!(p > 0.0 && p < 1.0, "probability must be in (0, 1)");
+
+    const A: [f64; 6] = [
+        -3.969683028665376e+01,
+        2.209460984245205e+02,
+        -2.759285104469687e+02,
+        1.38357751867269e+02,
+        -3.066479806614716e+01,
+        2.506628277459239e+00,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e+01,
+        1.615858368580409e+02,
+        -1.556989798598866e+02,
+        6.680131188771972e+01,
+        -1.328068155288572e+01,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-03,
+        -3.223964580411365e-01,
+        -2.400758277161838e+00,
+        -2.549732539343734e+00,
+        4.374664141464968e+00,
+        2.938163982698783e+00,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-03,
+        3.224671290700398e-01,
+        2.445134137142996e+00,
+        3.754408661907416e+00,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+
+    let x = if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    };
+
+    let e = normal_cdf(x) - p;
+    let u = e * (2.0 * PI).sqrt() * (x * x / 2.0).exp();
+
+    x - u / (1.0 + x * u / 2.0)
+}
+
+/// Natural logarithm of the gamma function for positive arguments (Lanczos approximation).
+pub fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula.
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, coefficient) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += coefficient / (x + i as f64);
+    }
+    let t = x + G + 0.5;
+
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Regularized incomplete beta function `I_x(a, b)`.
+pub fn incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let ln_front = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln();
+
+    // The continued fraction converges quickly only below the mean of the distribution.
+    if x < (a + 1.0) / (a + b + 2.0) {
+        ln_front.exp() * beta_continued_fraction(x, a, b) / a
+    } else {
+        1.0 - ln_front.exp() * beta_continued_fraction(1.0 - x, b, a) / b
+    }
+}
+
+/// Continued fraction of the incomplete beta function evaluated with Lentz's algorithm.
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    let tiny = 1e-300;
+    let clamp = |v: f64| if v.abs() < tiny { tiny } else { v };
+
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+    let mut f = d;
+
+    for m in 1..MAX_ITERATIONS {
+        let m = m as f64;
+
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / clamp(1.0 + even * d);
+        c = clamp(1.0 + even / c);
+        f *= c * d;
+
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / clamp(1.0 + odd * d);
+        c = clamp(1.0 + odd / c);
+        let delta = c * d;
+        f *= delta;
+
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    f
+}
+
+/// Regularized upper incomplete gamma function `Q(a, x)`.
+pub fn incomplete_gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+
+    let ln_front = a * x.ln() - x - ln_gamma(a);
+
+    // The series converges quickly below `a + 1`, the continued fraction above it.
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..MAX_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+
+        1.0 - ln_front.exp() * sum
+    } else {
+        ln_front.exp() * gamma_continued_fraction(a, x)
+    }
+}
+
+/// Continued fraction of the upper incomplete gamma function evaluated with Lentz's algorithm.
+fn gamma_continued_fraction(a: f64, x: f64) -> f64 {
+    let tiny = 1e-300;
+    let clamp = |v: f64| if v.abs() < tiny { tiny } else { v };
+
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / tiny;
+    let mut d = 1.0 / clamp(b);
+    let mut f = d;
+
+    for n in 1..MAX_ITERATIONS {
+        let n = n as f64;
+        let an = -n * (n - a);
+        b += 2.0;
+
+        d = 1.0 / clamp(an * d + b);
+        c = clamp(b + an / c);
+        let delta = c * d;
+        f *= delta;
+
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    f
+}
+
+/// Survival function of the chi-square distribution, the probability of a value above `x`.
+pub fn chi_square_sf(x: f64, degrees_of_freedom: f64) -> f64 {
+    incomplete_gamma_q(degrees_of_freedom / 2.0, x / 2.0)
+}
+
+/// Cumulative distribution function of Student's t distribution.
+pub fn student_t_cdf(t: f64, degrees_of_freedom: f64) -> f64 {
+    let x = degrees_of_freedom / (degrees_of_freedom + t * t);
+    let tail = 0.5 * incomplete_beta(x, degrees_of_freedom / 2.0, 0.5);
+
+    if t > 0.0 { 1.0 - tail } else { tail }
+}
+
+/// Quantile function of Student's t distribution, found by bisection.
+///
+/// # Panics
+/// If `p` is outside of the `(0, 1)` range.
+pub fn student_t_quantile(p: f64, degrees_of_freedom: f64) -> f64 {
+    assert!(p > 0.0 && p < 1.0, "probability must be in (0, 1)");
+
+    // The t distribution has heavier tails than the normal one, so its quantile is further away.
+    let bound = normal_quantile(p).abs().max(1.0);
+    let (mut low, mut high) = (-bound, bound);
+    while student_t_cdf(low, degrees_of_freedom) > p {
+        low *= 2.0;
+    }
+    while student_t_cdf(high, degrees_of_freedom) < p {
+        high *= 2.0;
+    }
+
+    for _ in 0..MAX_ITERATIONS {
+        let middle = (low + high) / 2.0;
+        if student_t_cdf(middle, degrees_of_freedom) < p {
+            low = middle;
+        } else {
+            high = middle;
+        }
+
+        if high - low < EPSILON * middle.abs().max(1.0) {
+            break;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+#[cfg(test)]
+mod distribution_tests {
+    use super::*;
+
+    fn assert_close(result: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (result - expected).abs() < tolerance,
+            "{result} is not close to {expected}"
+        );
+    }
+
+    #[test]
+    fn test_normal_cdf() {
+        assert_close(normal_cdf(0.0), 0.5, 1e-12);
+        assert_close(normal_cdf(1.959963984540054), 0.975, 1e-10);
+        assert_close(normal_cdf(-3.0), 0.0013498980316301, 1e-12);
+        assert_close(normal_cdf(5.0), 0.9999997133484281, 1e-12);
+    }
+
+    #[test]
+    fn test_normal_quantile() {
+        assert_close(normal_quantile(0.975), 1.959963984540054, 1e-9);
+        assert_close(normal_quantile(0.5), 0.0, 1e-12);
+        assert_close(normal_quantile(0.001), -3.090232306167813, 1e-9);
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        assert_close(ln_gamma(1.0), 0.0, 1e-12);
+        assert_close(ln_gamma(5.0), 24f64.ln(), 1e-12);
+        assert_close(ln_gamma(0.5), PI.sqrt().ln(), 1e-12);
+    }
+
+    #[test]
+    fn test_student_t() {
+        // Reference values from the tables of Student's t distribution.
+        assert_close(student_t_cdf(2.228138851986274, 10.0), 0.975, 1e-9);
+        assert_close(student_t_cdf(-1.0, 1.0), 0.25, 1e-12);
+        assert_close(student_t_quantile(0.975, 10.0), 2.228138851986274, 1e-8);
+        assert_close(student_t_quantile(0.025, 30.0), -2.042272456301238, 1e-8);
+    }
+
+    #[test]
+    fn test_chi_square_sf() {
+        // Closed forms: `exp(-x/2)` for two degrees of freedom, `exp(-x/2)(1 + x/2)` for four
+        // and `erfc(sqrt(x/2))` for one.
+        assert_close(chi_square_sf(0.0, 3.0), 1.0, 1e-12);
+        assert_close(chi_square_sf(1.0, 2.0), (-0.5f64).exp(), 1e-12);
+        assert_close(chi_square_sf(20.0, 2.0), (-10f64).exp(), 1e-12);
+        assert_close(chi_square_sf(3.0, 4.0), (-1.5f64).exp() * 2.5, 1e-12);
+        assert_close(chi_square_sf(3.841458820694124, 1.0), 0.05, 1e-10);
+        assert_close(chi_square_sf(12.0, 1.0), erfc(6f64.sqrt()), 1e-12);
+    }
+}
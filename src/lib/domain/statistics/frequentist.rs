@@ -0,0 +1,470 @@
+//! Frequentist hypothesis tests comparing a treatment against a control.
+
+use thiserror::Error;
+
+use crate::domain::statistics::distribution::{
+    chi_square_sf, normal_cdf, normal_quantile, student_t_cdf, student_t_quantile,
+};
+
+/// Represents always valid significance level, the probability of a false positive.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct SignificanceLevel(f64);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("significance level should be more than zero and less than 1")]
+pub struct SignificanceLevelInvalidError;
+impl SignificanceLevel {
+    pub const DEFAULT: Self = Self(0.05);
+
+    pub fn new(value: f64) -> Result<Self, SignificanceLevelInvalidError> {
+        if value > 0.0 && value < 1.0 {
+            Ok(Self(value))
+        } else {
+            Err(SignificanceLevelInvalidError)
+        }
+    }
+
+    pub fn into_inner(self) -> f64 {
+        self.0
+    }
+}
+
+impl Default for SignificanceLevel {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Number of successes out of a number of trials, e.g. converted devices out of assigned ones.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProportionSample {
+    successes: u64,
+    trials: u64,
+}
+
+impl ProportionSample {
+    /// # Panics
+    /// If there are more successes than trials.
+    pub fn new(successes: u64, trials: u64) -> Self {
+        assert!(successes <= trials, "successes cannot exceed trials");
+
+        Self { successes, trials }
+    }
+
+    pub fn successes(&self) -> u64 {
+        self.successes
+    }
+
+    pub fn trials(&self) -> u64 {
+        self.trials
+    }
+
+    /// Share of successes, `None` without trials.
+    pub fn proportion(&self) -> Option<f64> {
+        (self.trials > 0).then(|| self.successes as f64 / self.trials as f64)
+    }
+}
+
+/// Summary of a sample of continuous values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeanSample {
+    count: u64,
+    mean: f64,
+    variance: f64,
+}
+
+impl MeanSample {
+    /// # Arguments
+    /// * `count` - number of values.
+    /// * `mean` - mean of the values.
+    /// * `variance` - unbiased sample variance of the values.
+    pub fn new(count: u64, mean: f64, variance: f64) -> Self {
+        Self {
+            count,
+            mean,
+            variance,
+        }
+    }
+
+    /// Summarizes a sample given the sum and the sum of squares of its values.
+    pub fn from_sums(count: u64, sum: f64, sum_of_squares: f64) -> Self {
+        if count == 0 {
+            return Self::new(0, 0.0, 0.0);
+        }
+
+        let n = count as f64;
+        let mean = sum / n;
+        let variance = if count > 1 {
+            // Rounding may push the variance of equal values slightly below zero.
+            ((sum_of_squares - n * mean * mean) / (n - 1.0)).max(0.0)
+        } else {
+            0.0
+        };
+
+        Self::new(count, mean, variance)
+    }
+
+    /// Summarizes the mean of values pooled over units, e.g. events of devices, given the sum
+    /// and the number of the values of every unit.
+    ///
+    /// Values of the same unit are correlated, so the sample is one of units: its count is the
+    /// number of units and its variance is linearized with the delta method, so that the
+    /// variance of the pooled mean is `variance / count`.
+    pub fn from_unit_sums(units: &[(f64, u64)]) -> Self {
+        let units: Vec<(f64, f64)> = units
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(sum, count)| (*sum, *count as f64))
+            .collect();
+        if units.is_empty() {
+            return Self::new(0, 0.0, 0.0);
+        }
+
+        let n = units.len() as f64;
+        let (sum, count) = units
+            .iter()
+            .fold((0.0, 0.0), |(sum, count), (s, c)| (sum + s, count + c));
+        let mean = sum / count;
+        let variance = if units.len() > 1 {
+            let mean_count = count / n;
+            let residuals = units
+                .iter()
+                .map(|(s, c)| (s - mean * c).powi(2))
+                .sum::<f64>();
+
+            residuals / (n - 1.0) / (mean_count * mean_count)
+        } else {
+            0.0
+        };
+
+        Self::new(units.len() as u64, mean, variance)
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn variance(&self) -> f64 {
+        self.variance
+    }
+}
+
+/// Outcome of comparing a treatment against a control.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SignificanceTest {
+    difference: f64,
+    lift: Option<f64>,
+    confidence_interval: (f64, f64),
+    p_value: f64,
+    significant: bool,
+}
+
+impl SignificanceTest {
+    pub(crate) fn new(
+        difference: f64,
+        control: f64,
+        standard_error: f64,
+        critical_value: f64,
+        p_value: f64,
+        alpha: SignificanceLevel,
+    ) -> Self {
+        let margin = critical_value * standard_error;
+
+        Self {
+            difference,
+            lift: (control != 0.0).then(|| difference / control.abs()),
+            confidence_interval: (difference - margin, difference + margin),
+            p_value,
+            significant: p_value < alpha.into_inner(),
+        }
+    }
+
+    /// Absolute difference between the treatment and the control.
+    pub fn difference(&self) -> f64 {
+        self.difference
+    }
+
+    /// Difference relative to the control, `None` when the control is zero.
+    pub fn lift(&self) -> Option<f64> {
+        self.lift
+    }
+
+    /// Two-sided confidence interval of the absolute difference at `1 - alpha` level.
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        self.confidence_interval
+    }
+
+    /// Two-sided p-value of the hypothesis that there is no difference.
+    pub fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// Whether the p-value is below the significance level.
+    pub fn is_significant(&self) -> bool {
+        self.significant
+    }
+}
+
+/// Compares two proportions with a two-proportion z-test.
+///
+/// The p-value uses the pooled standard error of the null hypothesis, while the confidence
+/// interval uses the unpooled one.
+///
+/// # Returns
+/// * `Some(SignificanceTest)` with the outcome of the test.
+/// * `None` if either sample is empty or the proportions have no variance.
+pub fn two_proportion_z_test(
+    control: &ProportionSample,
+    treatment: &ProportionSample,
+    alpha: SignificanceLevel,
+) -> Option<SignificanceTest> {
+    let p_control = control.proportion()?;
+    let p_treatment = treatment.proportion()?;
+    let (n_control, n_treatment) = (control.trials() as f64, treatment.trials() as f64);
+
+    let pooled = (control.successes() + treatment.successes()) as f64 / (n_control + n_treatment);
+    let pooled_error = (pooled * (1.0 - pooled) * (1.0 / n_control + 1.0 / n_treatment)).sqrt();
+    if pooled_error == 0.0 {
+        return None;
+    }
+
+    let difference = p_treatment - p_control;
+    let z = difference / pooled_error;
+    let p_value = 2.0 * normal_cdf(-z.abs());
+
+    let standard_error = (p_control * (1.0 - p_control) / n_control
+        + p_treatment * (1.0 - p_treatment) / n_treatment)
+        .sqrt();
+    let critical_value = normal_quantile(1.0 - alpha.into_inner() / 2.0);
+
+    Some(SignificanceTest::new(
+        difference,
+        p_control,
+        standard_error,
+        critical_value,
+        p_value,
+        alpha,
+    ))
+}
+
+/// Compares two means with Welch's t-test, which does not assume equal variances.
+///
+/// # Returns
+/// * `Some(SignificanceTest)` with the outcome of the test.
+/// * `None` if either sample has less than two values or both have no variance.
+pub fn welch_t_test(
+    control: &MeanSample,
+    treatment: &MeanSample,
+    alpha: SignificanceLevel,
+) -> Option<SignificanceTest> {
+    if control.count() < 2 || treatment.count() < 2 {
+        return None;
+    }
+
+    let error_control = control.variance() / control.count() as f64;
+    let error_treatment = treatment.variance() / treatment.count() as f64;
+    let standard_error = (error_control + error_treatment).sqrt();
+    if standard_error == 0.0 {
+        return None;
+    }
+
+    // Welch–Satterthwaite approximation of the degrees of freedom.
+    let degrees_of_freedom = (error_control + error_treatment).powi(2)
+        / (error_control.powi(2) / (control.count() - 1) as f64
+            + error_treatment.powi(2) / (treatment.count() - 1) as f64);
+
+    let difference = treatment.mean() - control.mean();
+    let t = difference / standard_error;
+    let p_value = 2.0 * student_t_cdf(-t.abs(), degrees_of_freedom);
+    let critical_value = student_t_quantile(1.0 - alpha.into_inner() / 2.0, degrees_of_freedom);
+
+    Some(SignificanceTest::new(
+        difference,
+        control.mean(),
+        standard_error,
+        critical_value,
+        p_value,
+        alpha,
+    ))
+}
+
+/// Outcome of a chi-square goodness-of-fit test.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GoodnessOfFitTest {
+    statistic: f64,
+    degrees_of_freedom: u64,
+    p_value: f64,
+}
+
+impl GoodnessOfFitTest {
+    /// Pearson's chi-square statistic.
+    pub fn statistic(&self) -> f64 {
+        self.statistic
+    }
+
+    pub fn degrees_of_freedom(&self) -> u64 {
+        self.degrees_of_freedom
+    }
+
+    /// P-value of the hypothesis that the counts follow the expected shares.
+    pub fn p_value(&self) -> f64 {
+        self.p_value
+    }
+}
+
+/// Tests observed counts against expected shares with Pearson's chi-square test.
+///
+/// # Arguments
+/// * `observed` - observed counts of the categories.
+/// * `expected_shares` - expected shares of the categories in the order of `observed`, they
+///   are normalized to sum to one.
+///
+/// # Returns
+/// * `Some(GoodnessOfFitTest)` with the outcome of the test.
+/// * `None` if there are less than two categories, nothing was observed or a share is not
+///   positive.
+pub fn chi_square_goodness_of_fit(
+    observed: &[u64],
+    expected_shares: &[f64],
+) -> Option<GoodnessOfFitTest> {
+    let total = observed.iter().sum::<u64>() as f64;
+    let total_shares = expected_shares.iter().fold(0.0, |sum, share| sum + share);
+    if observed.len() < 2
+        || observed.len() != expected_shares.len()
+        || total == 0.0
+        || expected_shares.iter().any(|&share| share <= 0.0)
+    {
+        return None;
+    }
+
+    let statistic =
+        observed
+            .iter()
+            .zip(expected_shares)
+            .fold(0.0, |statistic, (&observed, share)| {
+                let expected = total * share / total_shares;
+                statistic + (observed as f64 - expected).powi(2) / expected
+            });
+    let degrees_of_freedom = observed.len() as u64 - 1;
+
+    Some(GoodnessOfFitTest {
+        statistic,
+        degrees_of_freedom,
+        p_value: chi_square_sf(statistic, degrees_of_freedom as f64),
+    })
+}
+
+#[cfg(test)]
+mod frequentist_tests {
+    use super::*;
+
+    fn assert_close(result: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (result - expected).abs() < tolerance,
+            "{result} is not close to {expected}"
+        );
+    }
+
+    #[test]
+    fn test_new_significance_level_is_invalid() {
+        assert_eq!(
+            SignificanceLevel::new(1.0),
+            Err(SignificanceLevelInvalidError)
+        );
+    }
+
+    #[test]
+    fn test_two_proportion_z_test() {
+        // 200/1000 against 250/1000: z = 2.6774, p = 0.00742.
+        let control = ProportionSample::new(200, 1000);
+        let treatment = ProportionSample::new(250, 1000);
+
+        let test = two_proportion_z_test(&control, &treatment, SignificanceLevel::DEFAULT).unwrap();
+        let (lower, upper) = test.confidence_interval();
+
+        assert_close(test.difference(), 0.05, 1e-12);
+        assert_close(test.lift().unwrap(), 0.25, 1e-12);
+        assert_close(test.p_value(), 0.0074196, 1e-6);
+        assert_close(lower, 0.0134636, 1e-6);
+        assert_close(upper, 0.0865364, 1e-6);
+        assert!(test.is_significant());
+    }
+
+    #[test]
+    fn test_two_proportion_z_test_without_variance() {
+        let control = ProportionSample::new(0, 100);
+        let treatment = ProportionSample::new(0, 100);
+
+        let result = two_proportion_z_test(&control, &treatment, SignificanceLevel::DEFAULT);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_welch_t_test() {
+        // Means 10 and 11, variances 4 and 9, 30 and 40 values: t = 1.6705, df = 67.19.
+        let control = MeanSample::new(30, 10.0, 4.0);
+        let treatment = MeanSample::new(40, 11.0, 9.0);
+
+        let test = welch_t_test(&control, &treatment, SignificanceLevel::DEFAULT).unwrap();
+
+        assert_close(test.difference(), 1.0, 1e-12);
+        assert_close(test.lift().unwrap(), 0.1, 1e-12);
+        assert_close(test.p_value(), 0.099465, 1e-6);
+        assert!(!test.is_significant());
+    }
+
+    #[test]
+    fn test_mean_sample_from_sums() {
+        // Values 1, 2, 3 and 6.
+        let sample = MeanSample::from_sums(4, 12.0, 50.0);
+
+        assert_eq!(sample, MeanSample::new(4, 3.0, 14.0 / 3.0));
+    }
+
+    #[test]
+    fn test_mean_sample_from_unit_sums() {
+        // Units with a single value each are the values themselves.
+        let sample = MeanSample::from_unit_sums(&[(1.0, 1), (2.0, 1), (3.0, 1), (6.0, 1)]);
+
+        assert_eq!(sample.count(), 4);
+        assert_close(sample.mean(), 3.0, 1e-12);
+        assert_close(sample.variance(), 14.0 / 3.0, 1e-12);
+
+        // Values 1, 1, 1 of one device and 4 of another: the mean is pooled over the values,
+        // the variance over the devices.
+        let sample = MeanSample::from_unit_sums(&[(3.0, 3), (4.0, 1), (0.0, 0)]);
+
+        assert_eq!(sample.count(), 2);
+        assert_close(sample.mean(), 1.75, 1e-12);
+        // Residuals 3 - 1.75 * 3 = -2.25 and 4 - 1.75 = 2.25 over a mean count of 2.
+        assert_close(sample.variance(), 2.0 * 2.25 * 2.25 / 4.0, 1e-12);
+    }
+
+    #[test]
+    fn test_chi_square_goodness_of_fit() {
+        // 520 and 480 out of an even split: chi-square = 1.6 with one degree of freedom.
+        let test = chi_square_goodness_of_fit(&[520, 480], &[50.0, 50.0]).unwrap();
+
+        assert_close(test.statistic(), 1.6, 1e-12);
+        assert_eq!(test.degrees_of_freedom(), 1);
+        assert_close(test.p_value(), 0.20590321073206833, 1e-10);
+
+        // Expected counts 25, 25 and 50: chi-square = 4 with two degrees of freedom.
+        let test = chi_square_goodness_of_fit(&[30, 30, 40], &[1.0, 1.0, 2.0]).unwrap();
+
+        assert_close(test.statistic(), 4.0, 1e-12);
+        assert_close(test.p_value(), (-2f64).exp(), 1e-12);
+    }
+
+    #[test]
+    fn test_chi_square_goodness_of_fit_undefined() {
+        assert_eq!(chi_square_goodness_of_fit(&[10], &[100.0]), None);
+        assert_eq!(chi_square_goodness_of_fit(&[0, 0], &[50.0, 50.0]), None);
+        assert_eq!(chi_square_goodness_of_fit(&[10, 10], &[100.0, 0.0]), None);
+    }
+}
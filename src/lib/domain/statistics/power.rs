@@ -0,0 +1,132 @@
+//! Statistical power and the sample sizes required to reach it.
+
+use thiserror::Error;
+
+use crate::domain::statistics::distribution::normal_quantile;
+use crate::domain::statistics::frequentist::SignificanceLevel;
+
+/// Represents always valid statistical power, the probability of detecting an effect that
+/// exists.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Power(f64);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("power should be more than zero and less than 1")]
+pub struct PowerInvalidError;
+impl Power {
+    pub const DEFAULT: Self = Self(0.8);
+
+    pub fn new(value: f64) -> Result<Self, PowerInvalidError> {
+        if value > 0.0 && value < 1.0 {
+            Ok(Self(value))
+        } else {
+            Err(PowerInvalidError)
+        }
+    }
+
+    pub fn into_inner(self) -> f64 {
+        self.0
+    }
+}
+
+impl Default for Power {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A group of a planned comparison: the variance of a unit and the share of units in the group.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlannedGroup {
+    variance: f64,
+    share: f64,
+}
+
+impl PlannedGroup {
+    pub fn new(variance: f64, share: f64) -> Self {
+        Self { variance, share }
+    }
+}
+
+/// Total number of units, split between the groups by their shares, a two-sided test of the
+/// two groups needs to detect the `difference` with the given power.
+///
+/// # Returns
+/// * `Some(f64)` with the number of units, to be rounded up by the caller.
+/// * `None` if the difference is zero or a share is not positive.
+pub fn required_sample_size(
+    control: PlannedGroup,
+    treatment: PlannedGroup,
+    difference: f64,
+    alpha: SignificanceLevel,
+    power: Power,
+) -> Option<f64> {
+    if difference == 0.0 || control.share <= 0.0 || treatment.share <= 0.0 {
+        return None;
+    }
+
+    let z = normal_quantile(1.0 - alpha.into_inner() / 2.0) + normal_quantile(power.into_inner());
+
+    Some(
+        z * z * (control.variance / control.share + treatment.variance / treatment.share)
+            / (difference * difference),
+    )
+}
+
+#[cfg(test)]
+mod power_tests {
+    use super::*;
+
+    fn assert_close(result: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (result - expected).abs() < tolerance,
+            "{result} is not close to {expected}"
+        );
+    }
+
+    #[test]
+    fn test_required_sample_size() {
+        // Conversion of 10% against 11% split evenly.
+        let result = required_sample_size(
+            PlannedGroup::new(0.1 * 0.9, 0.5),
+            PlannedGroup::new(0.11 * 0.89, 0.5),
+            0.01,
+            SignificanceLevel::DEFAULT,
+            Power::DEFAULT,
+        )
+        .unwrap();
+
+        assert_close(result, 29496.090041683867, 1e-6);
+    }
+
+    #[test]
+    fn test_required_sample_size_unequal_split() {
+        // The smaller group dominates the variance of the difference.
+        let result = required_sample_size(
+            PlannedGroup::new(25.0, 0.8),
+            PlannedGroup::new(25.0, 0.2),
+            1.0,
+            SignificanceLevel::DEFAULT,
+            Power::DEFAULT,
+        )
+        .unwrap();
+
+        assert_close(result, 1226.3874584920447, 1e-6);
+    }
+
+    #[test]
+    fn test_required_sample_size_undefined() {
+        let group = PlannedGroup::new(1.0, 0.5);
+
+        assert_eq!(
+            required_sample_size(
+                group,
+                group,
+                0.0,
+                SignificanceLevel::DEFAULT,
+                Power::DEFAULT
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Mixture sequential probability ratio test (mSPRT), whose p-values and confidence intervals
+//! stay valid however often the results are looked at, so an experiment can be stopped as soon
+//! as it is significant.
+
+use thiserror::Error;
+
+use crate::domain::statistics::frequentist::{
+    MeanSample, ProportionSample, SignificanceLevel, SignificanceTest,
+};
+
+/// Represents always valid standard deviation `τ` of the normal mixture over the effect, in
+/// units of the metric. It only tunes how quickly effects of about its size are detected, the
+/// test is valid for any value as long as it does not depend on the data, so it is fixed when
+/// the metric is defined.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct MixingDeviation(f64);
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("mixing deviation should be a positive number, got {0}")]
+pub struct MixingDeviationInvalidError(f64);
+impl MixingDeviation {
+    /// Deviation of proportions, one percentage point.
+    pub const PROPORTION: Self = Self(0.01);
+
+    pub fn new(raw_deviation: f64) -> Result<Self, MixingDeviationInvalidError> {
+        if raw_deviation.is_finite() && raw_deviation > 0.0 {
+            Ok(Self(raw_deviation))
+        } else {
+            Err(MixingDeviationInvalidError(raw_deviation))
+        }
+    }
+
+    pub fn into_inner(self) -> f64 {
+        self.0
+    }
+}
+
+/// Compares two proportions with the mSPRT on the normal approximation of their difference.
+///
+/// # Returns
+/// * `Some(SignificanceTest)` with the outcome of the test.
+/// * `None` if either sample is empty or the proportions have no variance.
+pub fn sequential_proportion_test(
+    control: &ProportionSample,
+    treatment: &ProportionSample,
+    mixing: MixingDeviation,
+    alpha: SignificanceLevel,
+) -> Option<SignificanceTest> {
+    let p_control = control.proportion()?;
+    let p_treatment = treatment.proportion()?;
+
+    let variance = p_control * (1.0 - p_control) / control.trials() as f64
+        + p_treatment * (1.0 - p_treatment) / treatment.trials() as f64;
+
+    mixture_test(p_control, p_treatment - p_control, variance, mixing, alpha)
+}
+
+/// Compares two means with the mSPRT on the normal approximation of their difference.
+///
+/// # Returns
+/// * `Some(SignificanceTest)` with the outcome of the test.
+/// * `None` if either sample has less than two values or both have no variance.
+pub fn sequential_mean_test(
+    control: &MeanSample,
+    treatment: &MeanSample,
+    mixing: MixingDeviation,
+    alpha: SignificanceLevel,
+) -> Option<SignificanceTest> {
+    if control.count() < 2 || treatment.count() < 2 {
+        return None;
+    }
+
+    let variance = control.variance() / control.count() as f64
+        + treatment.variance() / treatment.count() as f64;
+
+    mixture_test(
+        control.mean(),
+        treatment.mean() - control.mean(),
+        variance,
+        mixing,
+        alpha,
+    )
+}
+
+/// Tests an estimated difference with a normal mixture `N(0, τ²)` over the true effect.
+///
+/// The likelihood ratio against no difference is
+/// `Λ = sqrt(V / (V + τ²)) · exp(d² τ² / (2 V (V + τ²)))`, the p-value is `1 / Λ` and the
+/// confidence interval holds the differences the ratio does not reject at `1 / alpha`.
+fn mixture_test(
+    control: f64,
+    difference: f64,
+    variance: f64,
+    mixing: MixingDeviation,
+    alpha: SignificanceLevel,
+) -> Option<SignificanceTest> {
+    if variance <= 0.0 {
+        return None;
+    }
+
+    let mixing_variance = mixing.into_inner().powi(2);
+
+    let total_variance = variance + mixing_variance;
+    let ln_ratio = 0.5 * (variance / total_variance).ln()
+        + difference.powi(2) * mixing_variance / (2.0 * variance * total_variance);
+    let p_value = (-ln_ratio.max(0.0)).exp();
+
+    let margin = (variance * total_variance / mixing_variance
+        * (2.0 * (1.0 / alpha.into_inner()).ln() + (total_variance / variance).ln()))
+    .sqrt();
+    let standard_error = variance.sqrt();
+
+    Some(SignificanceTest::new(
+        difference,
+        control,
+        standard_error,
+        margin / standard_error,
+        p_value,
+        alpha,
+    ))
+}
+
+#[cfg(test)]
+mod sequential_tests {
+    use super::*;
+    use crate::domain::statistics::frequentist::two_proportion_z_test;
+
+    fn assert_close(result: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (result - expected).abs() < tolerance,
+            "{result} is not close to {expected}"
+        );
+    }
+
+    #[test]
+    fn test_sequential_mean_test() {
+        // Variance of the difference V = 1 and mixing variance τ² = 1 for the control of 10:
+        // Λ = sqrt(1/2) · exp(9/4) and the margin is sqrt(2 (2 ln 20 + ln 2)).
+        let control = MeanSample::new(100, 10.0, 50.0);
+        let treatment = MeanSample::new(100, 13.0, 50.0);
+
+        let mixing = MixingDeviation::new(1.0).unwrap();
+
+        let test =
+            sequential_mean_test(&control, &treatment, mixing, SignificanceLevel::DEFAULT).unwrap();
+        let (lower, upper) = test.confidence_interval();
+
+        assert_close(test.difference(), 3.0, 1e-12);
+        assert_close(test.p_value(), 0.149057012838996, 1e-12);
+        assert_close(lower, 3.0 - 3.6563948713638483, 1e-12);
+        assert_close(upper, 3.0 + 3.6563948713638483, 1e-12);
+        assert!(!test.is_significant());
+    }
+
+    #[test]
+    fn test_sequential_test_is_more_conservative() {
+        let control = ProportionSample::new(100, 1000);
+        let treatment = ProportionSample::new(150, 1000);
+
+        let sequential = sequential_proportion_test(
+            &control,
+            &treatment,
+            MixingDeviation::PROPORTION,
+            SignificanceLevel::DEFAULT,
+        )
+        .unwrap();
+        let fixed =
+            two_proportion_z_test(&control, &treatment, SignificanceLevel::DEFAULT).unwrap();
+
+        assert_close(sequential.p_value(), 0.19770503547160198, 1e-10);
+        assert!(sequential.p_value() > fixed.p_value());
+        assert!(sequential.confidence_interval().0 < fixed.confidence_interval().0);
+    }
+
+    #[test]
+    fn test_sequential_test_undefined() {
+        let empty = ProportionSample::new(0, 100);
+
+        assert_eq!(
+            sequential_proportion_test(
+                &empty,
+                &empty,
+                MixingDeviation::PROPORTION,
+                SignificanceLevel::DEFAULT
+            ),
+            None
+        );
+        assert_eq!(
+            sequential_mean_test(
+                &MeanSample::new(1, 10.0, 0.0),
+                &MeanSample::new(10, 10.0, 1.0),
+                MixingDeviation::new(1.0).unwrap(),
+                SignificanceLevel::DEFAULT
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sequential_test_does_not_depend_on_control_value() {
+        let mixing = MixingDeviation::new(1.0).unwrap();
+        let test = |control: f64| {
+            sequential_mean_test(
+                &MeanSample::new(100, control, 50.0),
+                &MeanSample::new(100, control + 3.0, 50.0),
+                mixing,
+                SignificanceLevel::DEFAULT,
+            )
+            .unwrap()
+        };
+
+        assert_close(test(10.0).p_value(), test(1000.0).p_value(), 1e-12);
+        assert_close(
+            test(10.0).confidence_interval().0,
+            test(1000.0).confidence_interval().0,
+            1e-12,
+        );
+    }
+
+    #[test]
+    fn test_new_mixing_deviation_is_invalid() {
+        assert_eq!(
+            MixingDeviation::new(0.0),
+            Err(MixingDeviationInvalidError(0.0))
+        );
+        assert!(MixingDeviation::new(f64::INFINITY).is_err());
+    }
+}
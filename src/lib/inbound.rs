@@ -1 +1,3 @@
+pub mod guardrail_evaluator;
 pub mod http;
+pub mod scheduler;
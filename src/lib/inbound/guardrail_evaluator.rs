@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use tokio::time::{self, MissedTickBehavior};
+
+use crate::domain::experiment::ports::ExperimentService;
+
+/// Background task stopping experiments that breach their guardrails.
+pub struct GuardrailEvaluator<ES: ExperimentService> {
+    experiment_service: ES,
+    interval: Duration,
+}
+
+impl<ES: ExperimentService> GuardrailEvaluator<ES> {
+    pub fn new(experiment_service: ES, interval: Duration) -> Self {
+        Self {
+            experiment_service,
+            interval,
+        }
+    }
+
+    /// Evaluates the guardrails once every interval, forever. Failures are logged and retried
+    /// on the next tick.
+    pub async fn run(self) {
+        let mut ticks = time::interval(self.interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticks.tick().await;
+
+            match self.experiment_service.evaluate_guardrails().await {
+                Ok(entries) => {
+                    for entry in entries {
+                        tracing::warn!(
+                            "experiment {} is {}: {}",
+                            entry.experiment_id(),
+                            entry.status(),
+                            entry.reason()
+                        );
+                    }
+                }
+                Err(e) => tracing::error!("failed to evaluate experiment guardrails: {:?}", e),
+            }
+        }
+    }
+}
@@ -2,16 +2,27 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use axum::Router;
-use axum::routing::{get, patch, post};
+use axum::routing::{get, patch, post, put};
 use tokio::net;
 
+use crate::domain::device::ports::DeviceService;
+use crate::domain::event::ports::EventService;
 use crate::domain::experiment::ports::ExperimentService;
 use crate::inbound::http::handlers::{
-    create_experiment::create_experiment, get_experiments::get_experiments,
-    get_statistics::get_statistics, patch_experiment::patch_experiment,
+    calculate_sample_size::calculate_sample_size,
+    create_events::create_events,
+    create_experiment::create_experiment,
+    get_audit_trail::get_audit_trail,
+    get_experiments::get_experiments,
+    get_statistics::{get_experiment_statistics, get_statistics},
+    get_timeseries::get_timeseries,
+    link_device::link_device,
+    patch_experiment::patch_experiment,
 };
 
 mod handlers;
+mod requests;
+#[allow(dead_code)]
 mod responses;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -21,8 +32,10 @@ pub struct HttpServerConfig<'a> {
 }
 
 #[derive(Debug, Clone)]
-struct AppState<ES: ExperimentService> {
+struct AppState<ES: ExperimentService, DS: DeviceService, EV: EventService> {
     experiment_service: Arc<ES>,
+    device_service: Arc<DS>,
+    event_service: Arc<EV>,
     auth_token: String,
 }
 
@@ -34,6 +47,8 @@ pub struct HttpServer {
 impl HttpServer {
     pub async fn new(
         experiment_service: impl ExperimentService,
+        device_service: impl DeviceService,
+        event_service: impl EventService,
         config: HttpServerConfig<'_>,
     ) -> anyhow::Result<Self> {
         let trace_layer = tower_http::trace::TraceLayer::new_for_http().make_span_with(
@@ -45,6 +60,8 @@ impl HttpServer {
 
         let state = AppState {
             experiment_service: Arc::new(experiment_service),
+            device_service: Arc::new(device_service),
+            event_service: Arc::new(event_service),
             auth_token: config.auth_token.to_string(),
         };
 
@@ -70,10 +87,23 @@ impl HttpServer {
     }
 }
 
-fn api_routes<ES: ExperimentService>() -> Router<AppState<ES>> {
+fn api_routes<ES: ExperimentService, DS: DeviceService, EV: EventService>()
+-> Router<AppState<ES, DS, EV>> {
     Router::new()
         .route("/experiments", get(get_experiments))
         .route("/experiments", post(create_experiment))
         .route("/experiments/{id}", patch(patch_experiment))
+        .route("/devices/{id}/user", put(link_device))
+        .route("/events", post(create_events))
         .route("/statistics", get(get_statistics))
+        .route(
+            "/experiments/{id}/statistics",
+            get(get_experiment_statistics),
+        )
+        .route(
+            "/experiments/{id}/statistics/timeseries",
+            get(get_timeseries),
+        )
+        .route("/experiments/{id}/audit", get(get_audit_trail))
+        .route("/sample-size", post(calculate_sample_size))
 }
@@ -1,4 +1,9 @@
+pub mod calculate_sample_size;
+pub mod create_events;
 pub mod create_experiment;
+pub mod get_audit_trail;
 pub mod get_experiments;
 pub mod get_statistics;
+pub mod get_timeseries;
+pub mod link_device;
 pub mod patch_experiment;
@@ -0,0 +1,355 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::domain::device::ports::DeviceService;
+use crate::domain::event::ports::EventService;
+use crate::domain::experiment::models::experiment::{
+    DistributionSumError, ExperimentAllocation, ExperimentAllocationInvalidError,
+    ExperimentEligibility, ExperimentEligibilityInvalidError, ExperimentVariants,
+    Variant as ExperimentVariant, VariantData, VariantDataEmptyError, VariantDistribution,
+    VariantDistributionInvalidError,
+};
+use crate::domain::experiment::models::sample_size::{
+    Baseline, BaselineInvalidError, CalculateSampleSizeError, MinimumDetectableEffect,
+    MinimumDetectableEffectInvalidError, SampleSize, SampleSizeRequest,
+    SampleSizeRequestInvalidError,
+};
+use crate::domain::experiment::ports::ExperimentService;
+use crate::domain::statistics::frequentist::{SignificanceLevel, SignificanceLevelInvalidError};
+use crate::domain::statistics::power::{Power, PowerInvalidError};
+use crate::inbound::http::AppState;
+
+#[derive(Debug, Clone)]
+pub struct ApiSuccess<T: Serialize + PartialEq>(StatusCode, Json<ApiResponseBody<T>>);
+
+impl<T> PartialEq for ApiSuccess<T>
+where
+    T: Serialize + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0 && self.1.0 == other.1.0
+    }
+}
+
+impl<T: Serialize + PartialEq> ApiSuccess<T> {
+    fn new(status: StatusCode, data: T) -> Self {
+        ApiSuccess(status, Json(ApiResponseBody::new(data)))
+    }
+}
+
+impl<T: Serialize + PartialEq> IntoResponse for ApiSuccess<T> {
+    fn into_response(self) -> Response {
+        (self.0, self.1).into_response()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    InternalServerError(String),
+    UnprocessableEntity(String),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::InternalServerError(e.to_string())
+    }
+}
+
+impl From<CalculateSampleSizeError> for ApiError {
+    fn from(e: CalculateSampleSizeError) -> Self {
+        tracing::error!("{:?}", e);
+        Self::InternalServerError("Internal server error".to_string())
+    }
+}
+
+impl From<ParseSampleSizeHttpRequestError> for ApiError {
+    fn from(e: ParseSampleSizeHttpRequestError) -> Self {
+        Self::UnprocessableEntity(e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        use ApiError::*;
+
+        match self {
+            InternalServerError(e) => {
+                tracing::error!("{}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponseBody::new_error(
+                        "Internal server error".to_string(),
+                    )),
+                )
+                    .into_response()
+            }
+            UnprocessableEntity(message) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ApiResponseBody::new_error(message)),
+            )
+                .into_response(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiResponseBody<T: Serialize + PartialEq> {
+    data: T,
+}
+
+impl<T: Serialize + PartialEq> ApiResponseBody<T> {
+    pub fn new(data: T) -> Self {
+        Self { data }
+    }
+}
+
+impl ApiResponseBody<ApiErrorData> {
+    pub fn new_error(message: String) -> Self {
+        Self {
+            data: ApiErrorData { message },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiErrorData {
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampleSizeResponseData {
+    variants: Vec<VariantSampleSizeResponseData>,
+    total_devices: u64,
+    daily_devices: f64,
+    duration_days: Option<u64>,
+}
+
+impl From<&SampleSize> for SampleSizeResponseData {
+    fn from(sample_size: &SampleSize) -> Self {
+        Self {
+            variants: sample_size
+                .variants()
+                .iter()
+                .map(|variant| VariantSampleSizeResponseData {
+                    data: variant.data().to_string(),
+                    devices: variant.devices(),
+                })
+                .collect(),
+            total_devices: sample_size.total_devices(),
+            daily_devices: sample_size.daily_devices(),
+            duration_days: sample_size.duration_days(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VariantSampleSizeResponseData {
+    data: String,
+    devices: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Variant {
+    distribution: f64,
+    data: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BaselineHttpRequest {
+    Conversion { rate: f64 },
+    Mean { mean: f64, variance: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampleSizeHttpRequestBody {
+    baseline: BaselineHttpRequest,
+    minimum_detectable_effect: f64,
+    variants: Vec<Variant>,
+    alpha: Option<f64>,
+    power: Option<f64>,
+    allocation: Option<f64>,
+    eligibility: Option<String>,
+}
+
+#[derive(Debug, Clone, Error)]
+enum ParseSampleSizeHttpRequestError {
+    #[error(transparent)]
+    Baseline(#[from] BaselineInvalidError),
+    #[error(transparent)]
+    Effect(#[from] MinimumDetectableEffectInvalidError),
+    #[error(transparent)]
+    VariantData(#[from] VariantDataEmptyError),
+    #[error(transparent)]
+    VariantDistribution(#[from] VariantDistributionInvalidError),
+    #[error(transparent)]
+    DistributionSum(#[from] DistributionSumError),
+    #[error(transparent)]
+    Alpha(#[from] SignificanceLevelInvalidError),
+    #[error(transparent)]
+    Power(#[from] PowerInvalidError),
+    #[error(transparent)]
+    Allocation(#[from] ExperimentAllocationInvalidError),
+    #[error(transparent)]
+    Eligibility(#[from] ExperimentEligibilityInvalidError),
+    #[error(transparent)]
+    Request(#[from] SampleSizeRequestInvalidError),
+}
+
+impl SampleSizeHttpRequestBody {
+    fn try_into_domain(self) -> Result<SampleSizeRequest, ParseSampleSizeHttpRequestError> {
+        let baseline = match self.baseline {
+            BaselineHttpRequest::Conversion { rate } => Baseline::conversion(rate)?,
+            BaselineHttpRequest::Mean { mean, variance } => Baseline::mean(mean, variance)?,
+        };
+        let effect = MinimumDetectableEffect::new(self.minimum_detectable_effect)?;
+        let variants = self
+            .variants
+            .iter()
+            .map(|v| {
+                let data = VariantData::new(&v.data)?;
+                let distribution = VariantDistribution::new(v.distribution)?;
+
+                Ok(ExperimentVariant::new(distribution, data))
+            })
+            .collect::<Result<Vec<ExperimentVariant>, ParseSampleSizeHttpRequestError>>()?;
+        let alpha = self
+            .alpha
+            .map(SignificanceLevel::new)
+            .transpose()?
+            .unwrap_or_default();
+        let power = self.power.map(Power::new).transpose()?.unwrap_or_default();
+        let allocation = self
+            .allocation
+            .map(ExperimentAllocation::new)
+            .transpose()?
+            .unwrap_or(ExperimentAllocation::FULL);
+        let eligibility = self
+            .eligibility
+            .as_deref()
+            .map(ExperimentEligibility::new)
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(
+            SampleSizeRequest::new(baseline, effect, ExperimentVariants::new(variants)?)?
+                .with_alpha(alpha)
+                .with_power(power)
+                .with_allocation(allocation)
+                .with_eligibility(eligibility),
+        )
+    }
+}
+
+pub async fn calculate_sample_size<ES: ExperimentService, DS: DeviceService, EV: EventService>(
+    State(state): State<AppState<ES, DS, EV>>,
+    Json(body): Json<SampleSizeHttpRequestBody>,
+) -> Result<ApiSuccess<SampleSizeResponseData>, ApiError> {
+    let domain_req = body.try_into_domain()?;
+
+    state
+        .experiment_service
+        .calculate_sample_size(&domain_req)
+        .await
+        .map_err(ApiError::from)
+        .map(|ref sample_size| ApiSuccess::new(StatusCode::OK, sample_size.into()))
+}
+
+#[cfg(test)]
+mod calculate_sample_size_tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::domain::device::service::Service as DeviceServiceImpl;
+    use crate::domain::event::service::Service as EventServiceImpl;
+    use crate::domain::experiment::service::Service as ExperimentServiceImpl;
+    use crate::outbound::sqlite::{Sqlite, in_memory_sqlite};
+
+    type TestState = AppState<
+        ExperimentServiceImpl<Sqlite>,
+        DeviceServiceImpl<Sqlite>,
+        EventServiceImpl<Sqlite>,
+    >;
+
+    async fn state() -> TestState {
+        let sqlite = in_memory_sqlite().await;
+
+        AppState {
+            experiment_service: Arc::new(ExperimentServiceImpl::new(sqlite.clone())),
+            device_service: Arc::new(DeviceServiceImpl::new(sqlite.clone())),
+            event_service: Arc::new(EventServiceImpl::new(sqlite)),
+            auth_token: String::new(),
+        }
+    }
+
+    fn body(value: serde_json::Value) -> SampleSizeHttpRequestBody {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_calculate_sample_size() {
+        let body = body(json!({
+            "baseline": { "type": "conversion", "rate": 0.1 },
+            "minimumDetectableEffect": 0.1,
+            "variants": [
+                { "distribution": 50, "data": "blue" },
+                { "distribution": 50, "data": "red" },
+            ],
+            "eligibility": "all",
+        }));
+
+        let response = calculate_sample_size(State(state().await), Json(body))
+            .await
+            .unwrap();
+
+        // No devices were active, so the duration is unknown.
+        let expected = SampleSizeResponseData {
+            variants: vec![
+                VariantSampleSizeResponseData {
+                    data: "blue".to_string(),
+                    devices: 14749,
+                },
+                VariantSampleSizeResponseData {
+                    data: "red".to_string(),
+                    devices: 14749,
+                },
+            ],
+            total_devices: 29498,
+            daily_devices: 0.0,
+            duration_days: None,
+        };
+        assert_eq!(response, ApiSuccess::new(StatusCode::OK, expected));
+    }
+
+    #[tokio::test]
+    async fn test_calculate_sample_size_invalid_eligibility() {
+        let body = body(json!({
+            "baseline": { "type": "mean", "mean": 20, "variance": 25 },
+            "minimumDetectableEffect": 0.05,
+            "variants": [
+                { "distribution": 50, "data": "blue" },
+                { "distribution": 50, "data": "red" },
+            ],
+            "eligibility": "returning",
+        }));
+
+        let error = calculate_sample_size(State(state().await), Json(body))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            ApiError::UnprocessableEntity(
+                "returning is not a valid experiment eligibility".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, HeaderName, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::domain::device::models::device::{
+    DeviceId, DeviceIdError, DeviceIdKind, DeviceIdKindInvalidError,
+};
+use crate::domain::device::ports::DeviceService;
+use crate::domain::event::models::event::{
+    CreateEventRequest, CreateEventsError, CreateEventsRequest, Event, EventAttribution, EventName,
+    EventNameInvalidError, EventValue, EventValueInvalidError, EventsBatchInvalidError,
+};
+use crate::domain::event::ports::EventService;
+use crate::domain::experiment::ports::ExperimentService;
+use crate::inbound::http::AppState;
+
+#[derive(Debug, Clone)]
+pub struct ApiSuccess<T: Serialize + PartialEq>(StatusCode, Json<ApiResponseBody<T>>);
+
+impl<T> PartialEq for ApiSuccess<T>
+where
+    T: Serialize + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0 && self.1.0 == other.1.0
+    }
+}
+
+impl<T: Serialize + PartialEq> ApiSuccess<T> {
+    fn new(status: StatusCode, data: T) -> Self {
+        ApiSuccess(status, Json(ApiResponseBody::new(data)))
+    }
+}
+
+impl<T: Serialize + PartialEq> IntoResponse for ApiSuccess<T> {
+    fn into_response(self) -> Response {
+        (self.0, self.1).into_response()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    InternalServerError(String),
+    UnprocessableEntity(String),
+    NotFound(String),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::InternalServerError(e.to_string())
+    }
+}
+
+impl From<CreateEventsError> for ApiError {
+    fn from(e: CreateEventsError) -> Self {
+        match e {
+            CreateEventsError::DeviceNotFound { id } => {
+                Self::NotFound(format!("device with id {} not found", id))
+            }
+            CreateEventsError::Anonymous => Self::UnprocessableEntity(
+                "events of an anonymous device cannot be recorded".to_string(),
+            ),
+            CreateEventsError::Timestamp(cause) => Self::UnprocessableEntity(cause.to_string()),
+            CreateEventsError::Unknown(cause) => {
+                tracing::error!("{:?}\n{}", cause, cause.backtrace());
+                Self::InternalServerError("Internal server error".to_string())
+            }
+        }
+    }
+}
+
+impl From<ParseCreateEventsHttpRequestError> for ApiError {
+    fn from(e: ParseCreateEventsHttpRequestError) -> Self {
+        Self::UnprocessableEntity(e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        use ApiError::*;
+
+        match self {
+            InternalServerError(e) => {
+                tracing::error!("{}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponseBody::new_error(
+                        "Internal server error".to_string(),
+                    )),
+                )
+                    .into_response()
+            }
+            UnprocessableEntity(message) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ApiResponseBody::new_error(message)),
+            )
+                .into_response(),
+            NotFound(message) => (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponseBody::new_error(message)),
+            )
+                .into_response(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiResponseBody<T: Serialize + PartialEq> {
+    data: T,
+}
+
+impl<T: Serialize + PartialEq> ApiResponseBody<T> {
+    pub fn new(data: T) -> Self {
+        Self { data }
+    }
+}
+
+impl ApiResponseBody<ApiErrorData> {
+    pub fn new_error(message: String) -> Self {
+        Self {
+            data: ApiErrorData { message },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiErrorData {
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CreateEventsResponseData {
+    events: Vec<EventResponseData>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventResponseData {
+    id: String,
+    name: String,
+    value: Option<f64>,
+    timestamp: String,
+    variants: Vec<EventAttributionResponseData>,
+}
+
+impl From<&Event> for EventResponseData {
+    fn from(event: &Event) -> Self {
+        Self {
+            id: event.id().to_string(),
+            name: event.name().to_string(),
+            value: event.value().map(|v| v.into_inner()),
+            timestamp: event.occurred_at().to_rfc3339(),
+            variants: event
+                .attributions()
+                .iter()
+                .map(|attribution| attribution.into())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventAttributionResponseData {
+    experiment_id: String,
+    data: String,
+    version: u32,
+}
+
+impl From<&EventAttribution> for EventAttributionResponseData {
+    fn from(attribution: &EventAttribution) -> Self {
+        Self {
+            experiment_id: attribution.experiment_id().to_string(),
+            data: attribution.data().to_string(),
+            version: attribution.version(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct EventHttpRequestBody {
+    name: String,
+    value: Option<f64>,
+    /// Time the event occurred on the device in RFC 3339 format.
+    timestamp: Option<String>,
+}
+
+/// Events are accepted either one at a time or in batches of a single device.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum CreateEventsHttpRequestBody {
+    Batch { events: Vec<EventHttpRequestBody> },
+    Single(EventHttpRequestBody),
+}
+
+#[derive(Debug, Clone, Error)]
+enum ParseCreateEventsHttpRequestError {
+    #[error(transparent)]
+    Kind(#[from] DeviceIdKindInvalidError),
+    #[error(transparent)]
+    DeviceId(#[from] DeviceIdError),
+    #[error(transparent)]
+    Name(#[from] EventNameInvalidError),
+    #[error(transparent)]
+    Value(#[from] EventValueInvalidError),
+    #[error("event timestamp must be in RFC 3339 format")]
+    Timestamp(#[from] chrono::ParseError),
+    #[error(transparent)]
+    Batch(#[from] EventsBatchInvalidError),
+    #[error("X-Device-Id header is required")]
+    MissingDeviceId,
+}
+
+impl EventHttpRequestBody {
+    fn try_into_domain(self) -> Result<CreateEventRequest, ParseCreateEventsHttpRequestError> {
+        let name = EventName::new(&self.name)?;
+        let value = self.value.map(EventValue::new).transpose()?;
+        let occurred_at = self
+            .timestamp
+            .map(|t| DateTime::parse_from_rfc3339(&t).map(|t| t.with_timezone(&Utc)))
+            .transpose()?;
+
+        Ok(CreateEventRequest::new(name, value, occurred_at))
+    }
+}
+
+impl CreateEventsHttpRequestBody {
+    fn try_into_domain(
+        self,
+        raw_id: Option<&str>,
+        raw_kind: Option<&str>,
+    ) -> Result<CreateEventsRequest, ParseCreateEventsHttpRequestError> {
+        let raw_id = raw_id.ok_or(ParseCreateEventsHttpRequestError::MissingDeviceId)?;
+        let kind = raw_kind
+            .map(DeviceIdKind::new)
+            .transpose()?
+            .unwrap_or(DeviceIdKind::Idfa);
+        let device_id = DeviceId::new(kind, raw_id)?;
+
+        let events = match self {
+            Self::Batch { events } => events,
+            Self::Single(event) => vec![event],
+        };
+        let events = events
+            .into_iter()
+            .map(|event| event.try_into_domain())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(CreateEventsRequest::new(device_id, events)?)
+    }
+}
+
+pub async fn create_events<ES: ExperimentService, DS: DeviceService, EV: EventService>(
+    headers: HeaderMap,
+    State(state): State<AppState<ES, DS, EV>>,
+    Json(body): Json<CreateEventsHttpRequestBody>,
+) -> Result<ApiSuccess<CreateEventsResponseData>, ApiError> {
+    let header = |name: &'static str| {
+        headers
+            .get(HeaderName::from_static(name))
+            .and_then(|v| v.to_str().ok())
+    };
+
+    let domain_req = body.try_into_domain(header("x-device-id"), header("x-device-id-type"))?;
+    state
+        .event_service
+        .create_events(&domain_req)
+        .await
+        .map_err(ApiError::from)
+        .map(|ref events| {
+            ApiSuccess::new(
+                StatusCode::CREATED,
+                CreateEventsResponseData {
+                    events: events.iter().map(|event| event.into()).collect(),
+                },
+            )
+        })
+}
@@ -2,20 +2,43 @@ use axum::Json;
 use axum::extract::State;
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::domain::device::ports::DeviceService;
+use crate::domain::event::models::event::EventNameInvalidError;
+use crate::domain::event::ports::EventService;
 use crate::domain::experiment::models::experiment::{
-    CreateExperimentError, DistributionSumError, ExperimentVariants, VariantData,
-    VariantDistribution, VariantDistributionInvalidError,
+    AnalysisPlan, CreateExperimentError, DistributionSumError, ExperimentAllocation,
+    ExperimentAllocationInvalidError, ExperimentBucketing, ExperimentControlInvalidError,
+    ExperimentEligibility, ExperimentInitialStatusError, ExperimentSalt, ExperimentSaltEmptyError,
+    ExperimentSchedule, ExperimentScheduleInvalidError, ExperimentStatus, ExperimentVariants,
+    VariantData, VariantDistribution, VariantDistributionInvalidError,
 };
 use crate::domain::experiment::models::experiment::{
     CreateExperimentRequest, ExperimentName, ExperimentNameEmptyError,
     Variant as ExperimentVariant, VariantDataEmptyError,
 };
+use crate::domain::experiment::models::guardrail::{
+    DegradationDirection, ExperimentGuardrails, ExperimentGuardrailsInvalidError, Guardrail,
+    GuardrailAction, MaxDegradation, MaxDegradationInvalidError,
+};
+use crate::domain::experiment::models::layer::{
+    ExperimentLayer, LayerBuckets, LayerBucketsInvalidError, LayerName, LayerNameEmptyError,
+};
+use crate::domain::experiment::models::metric::{
+    ExperimentMetrics, ExperimentMetricsInvalidError, Metric, MetricName, MetricNameEmptyError,
+};
+use crate::domain::experiment::models::targeting::{
+    SemverRangeInvalidError, TargetingAttributeEmptyError, TargetingInListEmptyError,
+    TargetingRule, TargetingRules,
+};
 use crate::domain::experiment::ports::ExperimentService;
+use crate::domain::statistics::sequential::MixingDeviationInvalidError;
 use crate::inbound::http::AppState;
+use crate::inbound::http::requests::{MetricHttpRequest, TargetingRuleHttpRequest};
 
 #[derive(Debug, Clone)]
 pub struct ApiSuccess<T: Serialize + PartialEq>(StatusCode, Json<ApiResponseBody<T>>);
@@ -61,6 +84,16 @@ impl From<CreateExperimentError> for ApiError {
             CreateExperimentError::Duplicate { name } => {
                 Self::UnprocessableEntity(format!("experiment with name {} already exists", name))
             }
+            CreateExperimentError::LayerOverlap { layer, name } => Self::UnprocessableEntity(
+                format!("buckets of layer {layer} overlap the buckets of experiment {name}"),
+            ),
+            CreateExperimentError::LayerBucketing {
+                layer,
+                name,
+                bucketing,
+            } => Self::UnprocessableEntity(format!(
+                "experiment {name} of layer {layer} buckets devices by {bucketing}"
+            )),
             CreateExperimentError::Unknown(cause) => {
                 tracing::error!("{:?}\n{}", cause, cause.backtrace());
                 Self::InternalServerError("Internal server error".to_string())
@@ -84,6 +117,25 @@ impl From<ParseCreateExperimentHttpRequestError> for ApiError {
             ParseCreateExperimentHttpRequestError::DistributionSum(cause) => {
                 format!("{cause}")
             }
+            ParseCreateExperimentHttpRequestError::Salt(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::Status(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::Allocation(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::TargetingAttribute(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::TargetingInList(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::SemverRange(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::MetricName(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::MetricEvent(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::Metrics(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::MixingDeviation(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::Control(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::Timestamp(_) => {
+                "schedule must be in RFC 3339 format".to_string()
+            }
+            ParseCreateExperimentHttpRequestError::Schedule(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::MaxDegradation(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::Guardrails(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::LayerName(cause) => format!("{cause}"),
+            ParseCreateExperimentHttpRequestError::LayerBuckets(cause) => format!("{cause}"),
         };
 
         Self::UnprocessableEntity(message)
@@ -148,6 +200,7 @@ pub struct ApiErrorData {
     pub message: String,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct CreateExperimentRequestBody {
     name: String,
@@ -174,6 +227,167 @@ pub struct Variant {
 pub struct CreateExperimentHttpRequestBody {
     name: String,
     variants: Vec<Variant>,
+    salt: Option<String>,
+    allocation: Option<f64>,
+    status: Option<ExperimentStatusHttpRequest>,
+    targeting: Option<Vec<TargetingRuleHttpRequest>>,
+    metrics: Option<Vec<MetricHttpRequest>>,
+    control: Option<String>,
+    bucketing: Option<ExperimentBucketingHttpRequest>,
+    #[serde(rename = "analysisPlan")]
+    analysis_plan: Option<AnalysisPlanHttpRequest>,
+    eligibility: Option<ExperimentEligibilityHttpRequest>,
+    #[serde(rename = "startsAt")]
+    starts_at: Option<String>,
+    #[serde(rename = "endsAt")]
+    ends_at: Option<String>,
+    guardrails: Option<Vec<GuardrailHttpRequest>>,
+    layer: Option<LayerHttpRequest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExperimentBucketingHttpRequest {
+    Device,
+    User,
+}
+
+impl From<ExperimentBucketingHttpRequest> for ExperimentBucketing {
+    fn from(bucketing: ExperimentBucketingHttpRequest) -> Self {
+        match bucketing {
+            ExperimentBucketingHttpRequest::Device => Self::Device,
+            ExperimentBucketingHttpRequest::User => Self::User,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnalysisPlanHttpRequest {
+    Fixed,
+    Sequential,
+}
+
+impl From<AnalysisPlanHttpRequest> for AnalysisPlan {
+    fn from(analysis_plan: AnalysisPlanHttpRequest) -> Self {
+        match analysis_plan {
+            AnalysisPlanHttpRequest::Fixed => Self::Fixed,
+            AnalysisPlanHttpRequest::Sequential => Self::Sequential,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExperimentEligibilityHttpRequest {
+    Existing,
+    New,
+    All,
+}
+
+impl From<ExperimentEligibilityHttpRequest> for ExperimentEligibility {
+    fn from(eligibility: ExperimentEligibilityHttpRequest) -> Self {
+        match eligibility {
+            ExperimentEligibilityHttpRequest::Existing => Self::Existing,
+            ExperimentEligibilityHttpRequest::New => Self::New,
+            ExperimentEligibilityHttpRequest::All => Self::All,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardrailHttpRequest {
+    metric: String,
+    max_degradation: f64,
+    direction: Option<DegradationDirectionHttpRequest>,
+    action: Option<GuardrailActionHttpRequest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DegradationDirectionHttpRequest {
+    Increase,
+    Decrease,
+}
+
+impl From<DegradationDirectionHttpRequest> for DegradationDirection {
+    fn from(direction: DegradationDirectionHttpRequest) -> Self {
+        match direction {
+            DegradationDirectionHttpRequest::Increase => Self::Increase,
+            DegradationDirectionHttpRequest::Decrease => Self::Decrease,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GuardrailActionHttpRequest {
+    Pause,
+    Finish,
+}
+
+impl From<GuardrailActionHttpRequest> for GuardrailAction {
+    fn from(action: GuardrailActionHttpRequest) -> Self {
+        match action {
+            GuardrailActionHttpRequest::Pause => Self::Pause,
+            GuardrailActionHttpRequest::Finish => Self::Finish,
+        }
+    }
+}
+
+impl GuardrailHttpRequest {
+    fn try_into_domain(self) -> Result<Guardrail, ParseCreateExperimentHttpRequestError> {
+        let metric = MetricName::new(&self.metric)?;
+        let max_degradation = MaxDegradation::new(self.max_degradation)?;
+        let direction = self
+            .direction
+            .map(DegradationDirection::from)
+            .unwrap_or_default();
+        let action = self.action.map(GuardrailAction::from).unwrap_or_default();
+
+        Ok(Guardrail::new(metric, max_degradation)
+            .with_direction(direction)
+            .with_action(action))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LayerHttpRequest {
+    name: String,
+    start: u32,
+    end: u32,
+}
+
+impl LayerHttpRequest {
+    fn try_into_domain(self) -> Result<ExperimentLayer, ParseCreateExperimentHttpRequestError> {
+        let name = LayerName::new(&self.name)?;
+        let buckets = LayerBuckets::new(self.start, self.end)?;
+
+        Ok(ExperimentLayer::new(name, buckets))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExperimentStatusHttpRequest {
+    Draft,
+    Running,
+    Paused,
+    Finished,
+    Archived,
+}
+
+impl From<ExperimentStatusHttpRequest> for ExperimentStatus {
+    fn from(status: ExperimentStatusHttpRequest) -> Self {
+        match status {
+            ExperimentStatusHttpRequest::Draft => Self::Draft,
+            ExperimentStatusHttpRequest::Running => Self::Running,
+            ExperimentStatusHttpRequest::Paused => Self::Paused,
+            ExperimentStatusHttpRequest::Finished => Self::Finished,
+            ExperimentStatusHttpRequest::Archived => Self::Archived,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Error)]
@@ -186,6 +400,40 @@ enum ParseCreateExperimentHttpRequestError {
     VariantDistribution(#[from] VariantDistributionInvalidError),
     #[error(transparent)]
     DistributionSum(#[from] DistributionSumError),
+    #[error(transparent)]
+    Salt(#[from] ExperimentSaltEmptyError),
+    #[error(transparent)]
+    Status(#[from] ExperimentInitialStatusError),
+    #[error(transparent)]
+    Allocation(#[from] ExperimentAllocationInvalidError),
+    #[error(transparent)]
+    TargetingAttribute(#[from] TargetingAttributeEmptyError),
+    #[error(transparent)]
+    TargetingInList(#[from] TargetingInListEmptyError),
+    #[error(transparent)]
+    SemverRange(#[from] SemverRangeInvalidError),
+    #[error(transparent)]
+    MetricName(#[from] MetricNameEmptyError),
+    #[error(transparent)]
+    MetricEvent(#[from] EventNameInvalidError),
+    #[error(transparent)]
+    Metrics(#[from] ExperimentMetricsInvalidError),
+    #[error(transparent)]
+    MixingDeviation(#[from] MixingDeviationInvalidError),
+    #[error(transparent)]
+    Control(#[from] ExperimentControlInvalidError),
+    #[error("schedule must be in RFC 3339 format")]
+    Timestamp(#[from] chrono::ParseError),
+    #[error(transparent)]
+    Schedule(#[from] ExperimentScheduleInvalidError),
+    #[error(transparent)]
+    MaxDegradation(#[from] MaxDegradationInvalidError),
+    #[error(transparent)]
+    Guardrails(#[from] ExperimentGuardrailsInvalidError),
+    #[error(transparent)]
+    LayerName(#[from] LayerNameEmptyError),
+    #[error(transparent)]
+    LayerBuckets(#[from] LayerBucketsInvalidError),
 }
 
 impl CreateExperimentHttpRequestBody {
@@ -205,14 +453,92 @@ impl CreateExperimentHttpRequestBody {
             .collect::<Result<Vec<ExperimentVariant>, ParseCreateExperimentHttpRequestError>>()?;
 
         let validated_variants = ExperimentVariants::new(variants.to_owned())?;
+        let salt = self.salt.map(|s| ExperimentSalt::new(&s)).transpose()?;
+        let allocation = self
+            .allocation
+            .map(ExperimentAllocation::new)
+            .transpose()?
+            .unwrap_or(ExperimentAllocation::FULL);
+        let parse_moment = |moment: &str| {
+            DateTime::parse_from_rfc3339(moment).map(|moment| moment.with_timezone(&Utc))
+        };
+        let starts_at = self.starts_at.as_deref().map(parse_moment).transpose()?;
+        let ends_at = self.ends_at.as_deref().map(parse_moment).transpose()?;
+        let schedule = ExperimentSchedule::new(starts_at, ends_at)?;
+
+        // Experiments scheduled to start are drafts until then, unless told otherwise.
+        let status = self
+            .status
+            .map(ExperimentStatus::from)
+            .unwrap_or(match starts_at {
+                Some(_) => ExperimentStatus::Draft,
+                None => ExperimentStatus::Running,
+            })
+            .initial()?;
+        let targeting = self
+            .targeting
+            .unwrap_or_default()
+            .into_iter()
+            .map(TargetingRuleHttpRequest::try_into_domain)
+            .collect::<Result<Vec<TargetingRule>, ParseCreateExperimentHttpRequestError>>()?;
+
+        let metrics = self
+            .metrics
+            .unwrap_or_default()
+            .into_iter()
+            .map(MetricHttpRequest::try_into_domain)
+            .collect::<Result<Vec<Metric>, ParseCreateExperimentHttpRequestError>>()?;
+        let metrics = ExperimentMetrics::new(metrics)?;
+
+        let guardrails = self
+            .guardrails
+            .unwrap_or_default()
+            .into_iter()
+            .map(GuardrailHttpRequest::try_into_domain)
+            .collect::<Result<Vec<Guardrail>, ParseCreateExperimentHttpRequestError>>()?;
+        let guardrails = ExperimentGuardrails::new(guardrails, &metrics)?;
 
-        Ok(CreateExperimentRequest::new(name, validated_variants))
+        let bucketing = self
+            .bucketing
+            .map(ExperimentBucketing::from)
+            .unwrap_or_default();
+        let analysis_plan = self
+            .analysis_plan
+            .map(AnalysisPlan::from)
+            .unwrap_or_default();
+        let eligibility = self
+            .eligibility
+            .map(ExperimentEligibility::from)
+            .unwrap_or_default();
+
+        let layer = self
+            .layer
+            .map(LayerHttpRequest::try_into_domain)
+            .transpose()?;
+
+        let req = CreateExperimentRequest::new(name, validated_variants, salt, allocation, status)
+            .with_targeting(TargetingRules::new(targeting))
+            .with_bucketing(bucketing)
+            .with_metrics(metrics)
+            .with_analysis_plan(analysis_plan)
+            .with_eligibility(eligibility)
+            .with_schedule(schedule)
+            .with_guardrails(guardrails);
+        let req = match layer {
+            Some(layer) => req.with_layer(layer),
+            None => req,
+        };
+
+        match self.control {
+            Some(control) => Ok(req.with_control(VariantData::new(&control)?)?),
+            None => Ok(req),
+        }
     }
 }
 
-pub async fn create_experiment<ES: ExperimentService>(
+pub async fn create_experiment<ES: ExperimentService, DS: DeviceService, EV: EventService>(
     headers: HeaderMap,
-    State(state): State<AppState<ES>>,
+    State(state): State<AppState<ES, DS, EV>>,
     Json(body): Json<CreateExperimentHttpRequestBody>,
 ) -> Result<ApiSuccess<CreateExperimentResponseData>, ApiError> {
     let auth_key = headers.get("Authorization").ok_or(ApiError::Unauthorized)?;
@@ -0,0 +1,154 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::domain::device::ports::DeviceService;
+use crate::domain::event::ports::EventService;
+use crate::domain::experiment::models::guardrail::{AuditEntry, GetAuditTrailError};
+use crate::domain::experiment::ports::ExperimentService;
+use crate::inbound::http::AppState;
+
+#[derive(Debug, Clone)]
+pub struct ApiSuccess<T: Serialize + PartialEq>(StatusCode, Json<ApiResponseBody<T>>);
+
+impl<T> PartialEq for ApiSuccess<T>
+where
+    T: Serialize + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0 && self.1.0 == other.1.0
+    }
+}
+
+impl<T: Serialize + PartialEq> ApiSuccess<T> {
+    fn new(status: StatusCode, data: T) -> Self {
+        ApiSuccess(status, Json(ApiResponseBody::new(data)))
+    }
+}
+
+impl<T: Serialize + PartialEq> IntoResponse for ApiSuccess<T> {
+    fn into_response(self) -> Response {
+        (self.0, self.1).into_response()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    InternalServerError(String),
+    NotFound(String),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::InternalServerError(e.to_string())
+    }
+}
+
+impl From<GetAuditTrailError> for ApiError {
+    fn from(e: GetAuditTrailError) -> Self {
+        match e {
+            GetAuditTrailError::NotFound { id } => {
+                Self::NotFound(format!("experiment with id {} not found", id))
+            }
+            GetAuditTrailError::Unknown(cause) => {
+                tracing::error!("{:?}\n{}", cause, cause.backtrace());
+                Self::InternalServerError("Internal server error".to_string())
+            }
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        use ApiError::*;
+
+        match self {
+            InternalServerError(e) => {
+                tracing::error!("{}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponseBody::new_error(
+                        "Internal server error".to_string(),
+                    )),
+                )
+                    .into_response()
+            }
+            NotFound(message) => (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponseBody::new_error(message)),
+            )
+                .into_response(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiResponseBody<T: Serialize + PartialEq> {
+    data: T,
+}
+
+impl<T: Serialize + PartialEq> ApiResponseBody<T> {
+    pub fn new(data: T) -> Self {
+        Self { data }
+    }
+}
+
+impl ApiResponseBody<ApiErrorData> {
+    pub fn new_error(message: String) -> Self {
+        Self {
+            data: ApiErrorData { message },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiErrorData {
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AuditTrailResponseData {
+    id: String,
+    entries: Vec<AuditEntryResponseData>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntryResponseData {
+    status: String,
+    reason: String,
+    created_at: String,
+}
+
+impl From<&AuditEntry> for AuditEntryResponseData {
+    fn from(entry: &AuditEntry) -> Self {
+        Self {
+            status: entry.status().to_string(),
+            reason: entry.reason().to_string(),
+            created_at: entry.created_at().to_rfc3339(),
+        }
+    }
+}
+
+pub async fn get_audit_trail<ES: ExperimentService, DS: DeviceService, EV: EventService>(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState<ES, DS, EV>>,
+) -> Result<ApiSuccess<AuditTrailResponseData>, ApiError> {
+    state
+        .experiment_service
+        .get_audit_trail(&id)
+        .await
+        .map_err(ApiError::from)
+        .map(|entries| {
+            ApiSuccess::new(
+                StatusCode::OK,
+                AuditTrailResponseData {
+                    id: id.to_string(),
+                    entries: entries.iter().map(AuditEntryResponseData::from).collect(),
+                },
+            )
+        })
+}
@@ -1,14 +1,27 @@
+use std::collections::HashMap;
+
 use axum::Json;
 use axum::extract::State;
 use axum::http::{HeaderMap, HeaderName, StatusCode};
 use axum::response::{IntoResponse, Response};
 use serde::{Deserialize, Serialize};
 
-use crate::domain::device::models::device::{DeviceId, DeviceIdError};
+use crate::domain::device::models::device::{
+    DeviceAttributes, DeviceId, DeviceIdError, DeviceIdKind, DeviceIdKindInvalidError,
+    DevicePlatform, DevicePlatformInvalidError, RegisterDeviceError, RegisterDeviceRequest,
+};
+use crate::domain::device::ports::DeviceService;
+use crate::domain::event::ports::EventService;
 use crate::domain::experiment::models::experiment::{
     DeviceExperiment, GetAllDeviceExperimentsError, GetAllExperimentsError,
 };
 use crate::domain::experiment::models::experiment::{Experiment, Variant as ExperimentVariant};
+use crate::domain::experiment::models::guardrail::Guardrail;
+use crate::domain::experiment::models::layer::ExperimentLayer;
+use crate::domain::experiment::models::metric::{Metric, MetricKind};
+use crate::domain::experiment::models::targeting::{
+    NumericComparison, TargetingOperator, TargetingRule,
+};
 use crate::domain::experiment::ports::ExperimentService;
 use crate::inbound::http::AppState;
 
@@ -62,12 +75,36 @@ impl From<GetAllDeviceExperimentsError> for ApiError {
     }
 }
 
+impl From<RegisterDeviceError> for ApiError {
+    fn from(e: RegisterDeviceError) -> Self {
+        match e {
+            RegisterDeviceError::Anonymous => Self::UnprocessableEntity(e.to_string()),
+            RegisterDeviceError::Unknown(cause) => {
+                tracing::error!("{:?}\n{}", cause, cause.backtrace());
+                Self::InternalServerError("Internal server error".to_string())
+            }
+        }
+    }
+}
+
 impl From<DeviceIdError> for ApiError {
     fn from(e: DeviceIdError) -> Self {
         Self::UnprocessableEntity(e.to_string())
     }
 }
 
+impl From<DeviceIdKindInvalidError> for ApiError {
+    fn from(e: DeviceIdKindInvalidError) -> Self {
+        Self::UnprocessableEntity(e.to_string())
+    }
+}
+
+impl From<DevicePlatformInvalidError> for ApiError {
+    fn from(e: DevicePlatformInvalidError) -> Self {
+        Self::UnprocessableEntity(e.to_string())
+    }
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         use ApiError::*;
@@ -120,7 +157,22 @@ pub struct ApiErrorData {
 pub struct ExperimentResponseData {
     id: String,
     name: String,
+    status: String,
+    allocation: f64,
+    bucketing: String,
+    control: String,
+    #[serde(rename = "analysisPlan")]
+    analysis_plan: String,
+    eligibility: String,
+    #[serde(rename = "startsAt")]
+    starts_at: Option<String>,
+    #[serde(rename = "endsAt")]
+    ends_at: Option<String>,
     variants: Vec<Variant>,
+    targeting: Vec<TargetingRuleResponseData>,
+    metrics: Vec<MetricResponseData>,
+    guardrails: Vec<GuardrailResponseData>,
+    layer: Option<LayerResponseData>,
 }
 
 impl From<&Experiment> for ExperimentResponseData {
@@ -128,12 +180,188 @@ impl From<&Experiment> for ExperimentResponseData {
         Self {
             id: experiment.id().to_string(),
             name: experiment.name().to_string(),
+            status: experiment.status().to_string(),
+            allocation: experiment.allocation().into_inner(),
+            bucketing: experiment.bucketing().to_string(),
+            control: experiment.control().to_string(),
+            analysis_plan: experiment.analysis_plan().to_string(),
+            eligibility: experiment.eligibility().to_string(),
+            starts_at: experiment
+                .schedule()
+                .starts_at()
+                .map(|starts_at| starts_at.to_rfc3339()),
+            ends_at: experiment
+                .schedule()
+                .ends_at()
+                .map(|ends_at| ends_at.to_rfc3339()),
             variants: experiment
                 .variants()
                 .variants()
                 .iter()
                 .map(|variant| variant.into())
                 .collect(),
+            targeting: experiment
+                .targeting()
+                .rules()
+                .iter()
+                .map(|rule| rule.into())
+                .collect(),
+            metrics: experiment
+                .metrics()
+                .metrics()
+                .iter()
+                .map(|metric| metric.into())
+                .collect(),
+            guardrails: experiment
+                .guardrails()
+                .guardrails()
+                .iter()
+                .map(|guardrail| guardrail.into())
+                .collect(),
+            layer: experiment.layer().map(|layer| layer.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LayerResponseData {
+    name: String,
+    start: u32,
+    end: u32,
+}
+
+impl From<&ExperimentLayer> for LayerResponseData {
+    fn from(layer: &ExperimentLayer) -> Self {
+        Self {
+            name: layer.name().to_string(),
+            start: layer.buckets().start(),
+            end: layer.buckets().end(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardrailResponseData {
+    metric: String,
+    max_degradation: f64,
+    direction: String,
+    action: String,
+}
+
+impl From<&Guardrail> for GuardrailResponseData {
+    fn from(guardrail: &Guardrail) -> Self {
+        Self {
+            metric: guardrail.metric().to_string(),
+            max_degradation: guardrail.max_degradation().into_inner(),
+            direction: guardrail.direction().to_string(),
+            action: guardrail.action().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricResponseData {
+    name: String,
+    #[serde(flatten)]
+    kind: MetricKindResponseData,
+    role: String,
+    #[serde(rename = "mixingDeviation")]
+    mixing_deviation: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MetricKindResponseData {
+    Conversion {
+        event: String,
+    },
+    Sum {
+        event: String,
+    },
+    Mean {
+        event: String,
+    },
+    Count {
+        event: String,
+    },
+    Ratio {
+        numerator: String,
+        denominator: String,
+    },
+}
+
+impl From<&Metric> for MetricResponseData {
+    fn from(metric: &Metric) -> Self {
+        let kind = match metric.kind() {
+            MetricKind::Conversion(event) => MetricKindResponseData::Conversion {
+                event: event.to_string(),
+            },
+            MetricKind::Sum(event) => MetricKindResponseData::Sum {
+                event: event.to_string(),
+            },
+            MetricKind::Mean(event) => MetricKindResponseData::Mean {
+                event: event.to_string(),
+            },
+            MetricKind::Count(event) => MetricKindResponseData::Count {
+                event: event.to_string(),
+            },
+            MetricKind::Ratio {
+                numerator,
+                denominator,
+            } => MetricKindResponseData::Ratio {
+                numerator: numerator.to_string(),
+                denominator: denominator.to_string(),
+            },
+        };
+
+        Self {
+            name: metric.name().to_string(),
+            kind,
+            role: metric.role().to_string(),
+            mixing_deviation: metric.mixing_deviation().map(|m| m.into_inner()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TargetingRuleResponseData {
+    attribute: String,
+    #[serde(flatten)]
+    operator: TargetingOperatorResponseData,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "operator", content = "value", rename_all = "lowercase")]
+pub enum TargetingOperatorResponseData {
+    Eq(String),
+    In(Vec<String>),
+    Semver(String),
+    Lt(f64),
+    Lte(f64),
+    Gt(f64),
+    Gte(f64),
+}
+
+impl From<&TargetingRule> for TargetingRuleResponseData {
+    fn from(rule: &TargetingRule) -> Self {
+        let operator = match rule.operator() {
+            TargetingOperator::Equals(value) => TargetingOperatorResponseData::Eq(value.to_owned()),
+            TargetingOperator::In(values) => TargetingOperatorResponseData::In(values.to_owned()),
+            TargetingOperator::SemverRange(range) => {
+                TargetingOperatorResponseData::Semver(range.to_string())
+            }
+            TargetingOperator::Numeric(comparison, v) => match comparison {
+                NumericComparison::Lt => TargetingOperatorResponseData::Lt(*v),
+                NumericComparison::Lte => TargetingOperatorResponseData::Lte(*v),
+                NumericComparison::Gt => TargetingOperatorResponseData::Gt(*v),
+                NumericComparison::Gte => TargetingOperatorResponseData::Gte(*v),
+            },
+        };
+
+        Self {
+            attribute: rule.attribute().to_string(),
+            operator,
         }
     }
 }
@@ -209,9 +437,74 @@ impl From<&ExperimentVariant> for Variant {
     }
 }
 
-pub async fn get_experiments<ES: ExperimentService>(
+/// Prefix of headers carrying free-form device properties, e.g. `X-Attribute-Tier: gold` sets
+/// the `tier` property.
+const PROPERTY_HEADER_PREFIX: &str = "x-attribute-";
+
+/// Device attributes optionally sent in the body of the request, they take precedence over the
+/// ones sent in headers.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceAttributesHttpRequestBody {
+    platform: Option<String>,
+    os_version: Option<String>,
+    app_version: Option<String>,
+    country: Option<String>,
+    locale: Option<String>,
+    properties: Option<HashMap<String, String>>,
+}
+
+impl DeviceAttributesHttpRequestBody {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &'static str| {
+            headers
+                .get(HeaderName::from_static(name))
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+
+        let properties = headers
+            .iter()
+            .filter_map(|(name, value)| {
+                let key = name.as_str().strip_prefix(PROPERTY_HEADER_PREFIX)?;
+                let value = value.to_str().ok()?;
+
+                Some((key.replace('-', "_"), value.to_string()))
+            })
+            .collect();
+
+        Self {
+            platform: header("x-platform"),
+            os_version: header("x-os-version"),
+            app_version: header("x-app-version"),
+            country: header("x-country"),
+            locale: header("x-locale"),
+            properties: Some(properties),
+        }
+    }
+
+    fn try_into_domain(self) -> Result<DeviceAttributes, DevicePlatformInvalidError> {
+        let platform = self
+            .platform
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| DevicePlatform::new(&p))
+            .transpose()?;
+
+        Ok(DeviceAttributes::new(
+            platform,
+            self.os_version,
+            self.app_version,
+            self.country,
+            self.locale,
+            self.properties.unwrap_or_default(),
+        ))
+    }
+}
+
+pub async fn get_experiments<ES: ExperimentService, DS: DeviceService, EV: EventService>(
     headers: HeaderMap,
-    State(state): State<AppState<ES>>,
+    State(state): State<AppState<ES, DS, EV>>,
+    body: Option<Json<DeviceAttributesHttpRequestBody>>,
 ) -> Result<ApiSuccess<GetAllExperimentsResponseData>, ApiError> {
     let device_id = headers
         .get(HeaderName::from_static("x-device-id"))
@@ -219,11 +512,35 @@ pub async fn get_experiments<ES: ExperimentService>(
 
     match device_id {
         Some(device_id) => {
-            let device_id = DeviceId::new(device_id)?;
+            let kind = headers
+                .get(HeaderName::from_static("x-device-id-type"))
+                .and_then(|v| v.to_str().ok())
+                .map(DeviceIdKind::new)
+                .transpose()?
+                .unwrap_or(DeviceIdKind::Idfa);
+            let device_id = DeviceId::new(kind, device_id)?;
+            let header_attributes =
+                DeviceAttributesHttpRequestBody::from_headers(&headers).try_into_domain()?;
+            let body_attributes = body
+                .map(|Json(body)| body.try_into_domain())
+                .transpose()?
+                .unwrap_or_default();
+            let attributes = header_attributes.merge(&body_attributes);
+
+            let req = RegisterDeviceRequest::new(device_id, attributes);
+            let device = match state.device_service.register_device(&req).await {
+                Ok(device) => device,
+                // Anonymous devices cannot be told apart, so they are neither stored nor assigned.
+                Err(RegisterDeviceError::Anonymous) => {
+                    let experiments: Vec<DeviceExperiment> = vec![];
+                    return Ok(ApiSuccess::new(StatusCode::OK, (&experiments).into()));
+                }
+                Err(e) => return Err(e.into()),
+            };
 
             state
                 .experiment_service
-                .get_all_device_participating_experiments(&device_id)
+                .get_all_device_participating_experiments(&device)
                 .await
                 .map_err(ApiError::from)
                 .map(|ref experiments| ApiSuccess::new(StatusCode::OK, experiments.into()))
@@ -236,3 +553,68 @@ pub async fn get_experiments<ES: ExperimentService>(
             .map(|ref experiments| ApiSuccess::new(StatusCode::OK, experiments.into())),
     }
 }
+
+#[cfg(test)]
+mod get_experiments_tests {
+    use std::sync::Arc;
+
+    use axum::http::HeaderValue;
+    use serde_json::json;
+
+    use super::*;
+    use crate::domain::device::ports::DeviceRepository;
+    use crate::domain::device::service::Service as DeviceServiceImpl;
+    use crate::domain::event::service::Service as EventServiceImpl;
+    use crate::domain::experiment::service::Service as ExperimentServiceImpl;
+    use crate::outbound::sqlite::{Sqlite, in_memory_sqlite};
+
+    type TestState = AppState<
+        ExperimentServiceImpl<Sqlite>,
+        DeviceServiceImpl<Sqlite>,
+        EventServiceImpl<Sqlite>,
+    >;
+
+    fn state(sqlite: &Sqlite) -> TestState {
+        AppState {
+            experiment_service: Arc::new(ExperimentServiceImpl::new(sqlite.clone())),
+            device_service: Arc::new(DeviceServiceImpl::new(sqlite.clone())),
+            event_service: Arc::new(EventServiceImpl::new(sqlite.clone())),
+            auth_token: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_experiments_merges_body_attributes_over_headers() {
+        let sqlite = in_memory_sqlite().await;
+        let raw_id = "550e8400-e29b-41d4-a716-446655440000";
+        let mut headers = HeaderMap::new();
+        headers.insert("x-device-id", HeaderValue::from_static(raw_id));
+        headers.insert("x-platform", HeaderValue::from_static("android"));
+        headers.insert("x-country", HeaderValue::from_static("US"));
+        headers.insert("x-attribute-tier", HeaderValue::from_static("silver"));
+        let body: DeviceAttributesHttpRequestBody = serde_json::from_value(json!({
+            "platform": "ios",
+            "locale": "en-US",
+            "properties": { "tier": "gold" },
+        }))
+        .unwrap();
+
+        get_experiments(headers, State(state(&sqlite)), Some(Json(body)))
+            .await
+            .unwrap();
+
+        let device = sqlite
+            .get_device_by_id(&DeviceId::new(DeviceIdKind::Idfa, raw_id).unwrap())
+            .await
+            .unwrap();
+        let expected = DeviceAttributes::new(
+            Some(DevicePlatform::new("ios").unwrap()),
+            None,
+            None,
+            Some("US".to_string()),
+            Some("en-US".to_string()),
+            HashMap::from([("tier".to_string(), "gold".to_string())]),
+        );
+        assert_eq!(device.attributes(), &expected);
+    }
+}
@@ -1,15 +1,34 @@
 use axum::Json;
-use axum::extract::State;
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
 
 use crate::domain::device::models::device::{DeviceIdError, GetAllDevicesError};
+use crate::domain::device::ports::DeviceService;
+use crate::domain::event::ports::EventService;
 use crate::domain::experiment::models::experiment::{
-    DeviceExperiment, GetAllDeviceExperimentsError, GetAllExperimentsError, StaticticsExperiment,
-    StatisticsVariant,
+    AnalysisMethod, AnalysisMethodInvalidError, CovariateWindow, CovariateWindowInvalidError,
+    DeviceExperiment, GetAllDeviceExperimentsError, GetAllExperimentsError, GetStatisticsRequest,
+    SampleRatioCheck, StaticticsExperiment, StatisticsVariant, StatisticsVersion,
+};
+use crate::domain::experiment::models::metric::StatisticsMetric;
+use crate::domain::experiment::models::segment::{
+    GetExperimentStatisticsError, GetExperimentStatisticsRequest, StatisticsPeriod,
+    StatisticsPeriodInvalidError, StatisticsSegment,
+};
+use crate::domain::experiment::models::targeting::{
+    TargetingAttribute, TargetingAttributeEmptyError,
 };
 use crate::domain::experiment::ports::ExperimentService;
+use crate::domain::statistics::bayesian::BayesianComparison;
+use crate::domain::statistics::cuped::CupedEstimate;
+use crate::domain::statistics::frequentist::{
+    SignificanceLevel, SignificanceLevelInvalidError, SignificanceTest,
+};
 use crate::inbound::http::AppState;
 
 #[derive(Debug, Clone)]
@@ -40,6 +59,7 @@ impl<T: Serialize + PartialEq> IntoResponse for ApiSuccess<T> {
 pub enum ApiError {
     InternalServerError(String),
     UnprocessableEntity(String),
+    NotFound(String),
 }
 
 impl From<anyhow::Error> for ApiError {
@@ -55,6 +75,20 @@ impl From<GetAllExperimentsError> for ApiError {
     }
 }
 
+impl From<GetExperimentStatisticsError> for ApiError {
+    fn from(e: GetExperimentStatisticsError) -> Self {
+        match e {
+            GetExperimentStatisticsError::NotFound { id } => {
+                Self::NotFound(format!("experiment with id {} not found", id))
+            }
+            GetExperimentStatisticsError::Unknown(cause) => {
+                tracing::error!("{:?}\n{}", cause, cause.backtrace());
+                Self::InternalServerError("Internal server error".to_string())
+            }
+        }
+    }
+}
+
 impl From<GetAllDeviceExperimentsError> for ApiError {
     fn from(e: GetAllDeviceExperimentsError) -> Self {
         tracing::error!("{:?}", e);
@@ -68,6 +102,12 @@ impl From<DeviceIdError> for ApiError {
     }
 }
 
+impl From<ParseGetStatisticsHttpRequestError> for ApiError {
+    fn from(e: ParseGetStatisticsHttpRequestError) -> Self {
+        Self::UnprocessableEntity(e.to_string())
+    }
+}
+
 impl From<GetAllDevicesError> for ApiError {
     fn from(e: GetAllDevicesError) -> Self {
         tracing::error!("{:?}", e);
@@ -95,6 +135,11 @@ impl IntoResponse for ApiError {
                 Json(ApiResponseBody::new_error(message)),
             )
                 .into_response(),
+            NotFound(message) => (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponseBody::new_error(message)),
+            )
+                .into_response(),
         }
     }
 }
@@ -128,8 +173,17 @@ pub struct ApiErrorData {
 pub struct StatisticsExperimentResponseData {
     id: String,
     name: String,
+    version: u32,
     total_devices: usize,
+    control: String,
+    analysis_plan: String,
+    eligibility: String,
     variants: Vec<Variant>,
+    versions: Vec<StatisticsVersionResponseData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sample_ratio: Option<SampleRatioCheckResponseData>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    segments: Vec<StatisticsSegmentResponseData>,
 }
 
 impl From<&StaticticsExperiment> for StatisticsExperimentResponseData {
@@ -137,22 +191,110 @@ impl From<&StaticticsExperiment> for StatisticsExperimentResponseData {
         Self {
             id: experiment.id().to_string(),
             name: experiment.name().to_string(),
+            version: experiment.version(),
             total_devices: experiment.total_devices(),
+            control: experiment.control().to_string(),
+            analysis_plan: experiment.analysis_plan().to_string(),
+            eligibility: experiment.eligibility().to_string(),
             variants: experiment
                 .variants()
                 .variants()
                 .iter()
                 .map(|variant| variant.into())
                 .collect(),
+            versions: experiment
+                .versions()
+                .iter()
+                .map(|version| version.into())
+                .collect(),
+            sample_ratio: experiment
+                .sample_ratio()
+                .as_ref()
+                .map(|sample_ratio| sample_ratio.into()),
+            segments: experiment
+                .segments()
+                .iter()
+                .map(|segment| segment.into())
+                .collect(),
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatisticsSegmentResponseData {
+    value: Option<String>,
+    total_devices: usize,
+    variants: Vec<Variant>,
+}
+
+impl From<&StatisticsSegment> for StatisticsSegmentResponseData {
+    fn from(segment: &StatisticsSegment) -> Self {
+        Self {
+            value: segment.value().map(str::to_string),
+            total_devices: segment.total_devices(),
+            variants: segment
+                .variants()
+                .variants()
+                .iter()
+                .map(|variant| variant.into())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampleRatioCheckResponseData {
+    chi_square: f64,
+    p_value: f64,
+    mismatch: bool,
+}
+
+impl From<&SampleRatioCheck> for SampleRatioCheckResponseData {
+    fn from(sample_ratio: &SampleRatioCheck) -> Self {
+        Self {
+            chi_square: sample_ratio.statistic(),
+            p_value: sample_ratio.p_value(),
+            mismatch: sample_ratio.is_mismatch(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatisticsVersionResponseData {
+    version: u32,
+    total_devices: usize,
+    variants: Vec<Variant>,
+}
+
+impl From<&StatisticsVersion> for StatisticsVersionResponseData {
+    fn from(version: &StatisticsVersion) -> Self {
+        Self {
+            version: version.version(),
+            total_devices: version.total_devices(),
+            variants: version
+                .variants()
+                .variants()
+                .iter()
+                .map(|variant| variant.into())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct GetAllStatisticsExperimentsResponseData {
+    alpha: f64,
+    method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cuped_window: Option<u32>,
     experiments: Vec<StatisticsExperimentResponseData>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct DeviceExperimentResponseData {
     id: String,
@@ -176,6 +318,8 @@ pub struct Variant {
     data: String,
     total_devices: usize,
     percentage_devices: f64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    metrics: Vec<StatisticsMetricResponseData>,
 }
 
 impl From<&StatisticsVariant> for Variant {
@@ -184,13 +328,114 @@ impl From<&StatisticsVariant> for Variant {
             data: variant.data().to_string(),
             total_devices: variant.total_devices(),
             percentage_devices: variant.percentage_devices(),
+            metrics: variant
+                .metrics()
+                .iter()
+                .map(|metric| metric.into())
+                .collect(),
         }
     }
 }
 
-impl From<&Vec<StaticticsExperiment>> for GetAllStatisticsExperimentsResponseData {
-    fn from(experiments: &Vec<StaticticsExperiment>) -> Self {
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatisticsMetricResponseData {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    role: String,
+    value: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    test: Option<SignificanceTestResponseData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bayesian: Option<BayesianComparisonResponseData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cuped: Option<CupedEstimateResponseData>,
+}
+
+impl From<&StatisticsMetric> for StatisticsMetricResponseData {
+    fn from(metric: &StatisticsMetric) -> Self {
         Self {
+            name: metric.name().to_string(),
+            kind: metric.kind().to_string(),
+            role: metric.role().to_string(),
+            value: metric.value(),
+            test: metric.test().as_ref().map(|test| test.into()),
+            bayesian: metric
+                .comparison()
+                .as_ref()
+                .map(|comparison| comparison.into()),
+            cuped: metric.cuped().as_ref().map(|cuped| cuped.into()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignificanceTestResponseData {
+    difference: f64,
+    lift: Option<f64>,
+    confidence_interval: [f64; 2],
+    p_value: f64,
+    significant: bool,
+}
+
+impl From<&SignificanceTest> for SignificanceTestResponseData {
+    fn from(test: &SignificanceTest) -> Self {
+        let (lower, upper) = test.confidence_interval();
+
+        Self {
+            difference: test.difference(),
+            lift: test.lift(),
+            confidence_interval: [lower, upper],
+            p_value: test.p_value(),
+            significant: test.is_significant(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BayesianComparisonResponseData {
+    probability_to_be_best: f64,
+    expected_loss: f64,
+}
+
+impl From<&BayesianComparison> for BayesianComparisonResponseData {
+    fn from(comparison: &BayesianComparison) -> Self {
+        Self {
+            probability_to_be_best: comparison.probability_to_be_best(),
+            expected_loss: comparison.expected_loss(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CupedEstimateResponseData {
+    raw_difference: f64,
+    adjusted_difference: f64,
+    variance_reduction: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    test: Option<SignificanceTestResponseData>,
+}
+
+impl From<&CupedEstimate> for CupedEstimateResponseData {
+    fn from(cuped: &CupedEstimate) -> Self {
+        Self {
+            raw_difference: cuped.raw_difference(),
+            adjusted_difference: cuped.adjusted_difference(),
+            variance_reduction: cuped.variance_reduction(),
+            test: cuped.test().as_ref().map(|test| test.into()),
+        }
+    }
+}
+
+impl GetAllStatisticsExperimentsResponseData {
+    fn new(req: &GetStatisticsRequest, experiments: &[StaticticsExperiment]) -> Self {
+        Self {
+            alpha: req.alpha().into_inner(),
+            method: req.method().to_string(),
+            cuped_window: req.cuped().map(|window| window.days()),
             experiments: experiments
                 .iter()
                 .map(|experiment| experiment.into())
@@ -199,19 +444,161 @@ impl From<&Vec<StaticticsExperiment>> for GetAllStatisticsExperimentsResponseDat
     }
 }
 
-pub async fn get_statistics<ES: ExperimentService>(
-    State(state): State<AppState<ES>>,
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetExperimentStatisticsResponseData {
+    alpha: f64,
+    method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    segment: Option<String>,
+    #[serde(flatten)]
+    experiment: StatisticsExperimentResponseData,
+}
+
+impl GetExperimentStatisticsResponseData {
+    fn new(req: &GetExperimentStatisticsRequest, experiment: &StaticticsExperiment) -> Self {
+        Self {
+            alpha: req.statistics().alpha().into_inner(),
+            method: req.statistics().method().to_string(),
+            from: req.period().from().map(|from| from.to_rfc3339()),
+            to: req.period().to().map(|to| to.to_rfc3339()),
+            segment: req.segment().map(|segment| segment.to_string()),
+            experiment: experiment.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct GetStatisticsHttpRequestQuery {
+    alpha: Option<f64>,
+    method: Option<String>,
+    /// Days before the experiment CUPED takes the values of the devices from.
+    #[serde(rename = "cupedWindow")]
+    cuped_window: Option<u32>,
+}
+
+#[derive(Debug, Clone, Error)]
+enum ParseGetStatisticsHttpRequestError {
+    #[error(transparent)]
+    Alpha(#[from] SignificanceLevelInvalidError),
+    #[error(transparent)]
+    Method(#[from] AnalysisMethodInvalidError),
+    #[error(transparent)]
+    CupedWindow(#[from] CovariateWindowInvalidError),
+    #[error("period bounds must be in RFC 3339 format")]
+    Timestamp(#[from] chrono::ParseError),
+    #[error(transparent)]
+    Period(#[from] StatisticsPeriodInvalidError),
+    #[error(transparent)]
+    Segment(#[from] TargetingAttributeEmptyError),
+}
+
+impl GetStatisticsHttpRequestQuery {
+    fn try_into_domain(self) -> Result<GetStatisticsRequest, ParseGetStatisticsHttpRequestError> {
+        let alpha = self
+            .alpha
+            .map(SignificanceLevel::new)
+            .transpose()?
+            .unwrap_or_default();
+        let method = self
+            .method
+            .as_deref()
+            .map(AnalysisMethod::new)
+            .transpose()?
+            .unwrap_or_default();
+
+        let req = GetStatisticsRequest::new(alpha).with_method(method);
+
+        match self.cuped_window {
+            Some(days) => Ok(req.with_cuped(CovariateWindow::new(days)?)),
+            None => Ok(req),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct GetExperimentStatisticsHttpRequestQuery {
+    alpha: Option<f64>,
+    method: Option<String>,
+    #[serde(rename = "cupedWindow")]
+    cuped_window: Option<u32>,
+    from: Option<String>,
+    to: Option<String>,
+    /// Device attribute the statistics are broken down by, e.g. `platform`.
+    segment: Option<String>,
+}
+
+impl GetExperimentStatisticsHttpRequestQuery {
+    fn try_into_domain(
+        self,
+        id: Uuid,
+    ) -> Result<GetExperimentStatisticsRequest, ParseGetStatisticsHttpRequestError> {
+        let statistics = GetStatisticsHttpRequestQuery {
+            alpha: self.alpha,
+            method: self.method,
+            cuped_window: self.cuped_window,
+        }
+        .try_into_domain()?;
+
+        let parse_bound = |bound: &str| {
+            DateTime::parse_from_rfc3339(bound).map(|bound| bound.with_timezone(&Utc))
+        };
+        let from = self.from.as_deref().map(parse_bound).transpose()?;
+        let to = self.to.as_deref().map(parse_bound).transpose()?;
+
+        let req = GetExperimentStatisticsRequest::new(id, statistics)
+            .with_period(StatisticsPeriod::new(from, to)?);
+
+        match self.segment {
+            Some(segment) => Ok(req.with_segment(TargetingAttribute::new(&segment)?)),
+            None => Ok(req),
+        }
+    }
+}
+
+pub async fn get_statistics<ES: ExperimentService, DS: DeviceService, EV: EventService>(
+    State(state): State<AppState<ES, DS, EV>>,
+    Query(query): Query<GetStatisticsHttpRequestQuery>,
 ) -> Result<ApiSuccess<GetAllStatisticsExperimentsResponseData>, ApiError> {
-    let devices = state
+    let domain_req = query.try_into_domain()?;
+
+    state
         .experiment_service
-        .get_all_devices()
+        .get_statistics(&domain_req)
         .await
-        .map_err(ApiError::from)?;
+        .map_err(ApiError::from)
+        .map(|ref experiments| {
+            ApiSuccess::new(
+                StatusCode::OK,
+                GetAllStatisticsExperimentsResponseData::new(&domain_req, experiments),
+            )
+        })
+}
+
+pub async fn get_experiment_statistics<
+    ES: ExperimentService,
+    DS: DeviceService,
+    EV: EventService,
+>(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState<ES, DS, EV>>,
+    Query(query): Query<GetExperimentStatisticsHttpRequestQuery>,
+) -> Result<ApiSuccess<GetExperimentStatisticsResponseData>, ApiError> {
+    let domain_req = query.try_into_domain(id)?;
 
     state
         .experiment_service
-        .get_statistics(devices)
+        .get_experiment_statistics(&domain_req)
         .await
         .map_err(ApiError::from)
-        .map(|ref experiments| ApiSuccess::new(StatusCode::OK, experiments.into()))
+        .map(|ref experiment| {
+            ApiSuccess::new(
+                StatusCode::OK,
+                GetExperimentStatisticsResponseData::new(&domain_req, experiment),
+            )
+        })
 }
@@ -0,0 +1,271 @@
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::domain::device::ports::DeviceService;
+use crate::domain::event::ports::EventService;
+use crate::domain::experiment::models::metric::StatisticsMetric;
+use crate::domain::experiment::models::timeseries::{
+    GetTimeseriesError, GetTimeseriesRequest, Timeseries, TimeseriesBucket, TimeseriesGranularity,
+    TimeseriesGranularityInvalidError, TimeseriesTimezone, TimeseriesTimezoneInvalidError,
+    TimeseriesVariant,
+};
+use crate::domain::experiment::ports::ExperimentService;
+use crate::inbound::http::AppState;
+
+#[derive(Debug, Clone)]
+pub struct ApiSuccess<T: Serialize + PartialEq>(StatusCode, Json<ApiResponseBody<T>>);
+
+impl<T> PartialEq for ApiSuccess<T>
+where
+    T: Serialize + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0 && self.1.0 == other.1.0
+    }
+}
+
+impl<T: Serialize + PartialEq> ApiSuccess<T> {
+    fn new(status: StatusCode, data: T) -> Self {
+        ApiSuccess(status, Json(ApiResponseBody::new(data)))
+    }
+}
+
+impl<T: Serialize + PartialEq> IntoResponse for ApiSuccess<T> {
+    fn into_response(self) -> Response {
+        (self.0, self.1).into_response()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    InternalServerError(String),
+    UnprocessableEntity(String),
+    NotFound(String),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::InternalServerError(e.to_string())
+    }
+}
+
+impl From<GetTimeseriesError> for ApiError {
+    fn from(e: GetTimeseriesError) -> Self {
+        match e {
+            GetTimeseriesError::NotFound { id } => {
+                Self::NotFound(format!("experiment with id {} not found", id))
+            }
+            e @ GetTimeseriesError::TooManyBuckets { .. } => {
+                Self::UnprocessableEntity(e.to_string())
+            }
+            GetTimeseriesError::Unknown(cause) => {
+                tracing::error!("{:?}\n{}", cause, cause.backtrace());
+                Self::InternalServerError("Internal server error".to_string())
+            }
+        }
+    }
+}
+
+impl From<ParseGetTimeseriesHttpRequestError> for ApiError {
+    fn from(e: ParseGetTimeseriesHttpRequestError) -> Self {
+        Self::UnprocessableEntity(e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        use ApiError::*;
+
+        match self {
+            InternalServerError(e) => {
+                tracing::error!("{}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponseBody::new_error(
+                        "Internal server error".to_string(),
+                    )),
+                )
+                    .into_response()
+            }
+            UnprocessableEntity(message) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ApiResponseBody::new_error(message)),
+            )
+                .into_response(),
+            NotFound(message) => (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponseBody::new_error(message)),
+            )
+                .into_response(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiResponseBody<T: Serialize + PartialEq> {
+    data: T,
+}
+
+impl<T: Serialize + PartialEq> ApiResponseBody<T> {
+    pub fn new(data: T) -> Self {
+        Self { data }
+    }
+}
+
+impl ApiResponseBody<ApiErrorData> {
+    pub fn new_error(message: String) -> Self {
+        Self {
+            data: ApiErrorData { message },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiErrorData {
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeseriesResponseData {
+    id: String,
+    granularity: String,
+    timezone: String,
+    buckets: Vec<TimeseriesBucketResponseData>,
+}
+
+impl From<&Timeseries> for TimeseriesResponseData {
+    fn from(timeseries: &Timeseries) -> Self {
+        Self {
+            id: timeseries.experiment_id().to_string(),
+            granularity: timeseries.granularity().to_string(),
+            timezone: timeseries.timezone().to_string(),
+            buckets: timeseries
+                .buckets()
+                .iter()
+                .map(|bucket| bucket.into())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TimeseriesBucketResponseData {
+    start: String,
+    variants: Vec<TimeseriesVariantResponseData>,
+}
+
+impl From<&TimeseriesBucket> for TimeseriesBucketResponseData {
+    fn from(bucket: &TimeseriesBucket) -> Self {
+        Self {
+            start: bucket.start().to_rfc3339(),
+            variants: bucket
+                .variants()
+                .iter()
+                .map(|variant| variant.into())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeseriesVariantResponseData {
+    data: String,
+    new_devices: usize,
+    total_devices: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    metrics: Vec<TimeseriesMetricResponseData>,
+}
+
+impl From<&TimeseriesVariant> for TimeseriesVariantResponseData {
+    fn from(variant: &TimeseriesVariant) -> Self {
+        Self {
+            data: variant.data().to_string(),
+            new_devices: variant.new_devices(),
+            total_devices: variant.total_devices(),
+            metrics: variant
+                .metrics()
+                .iter()
+                .map(|metric| metric.into())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TimeseriesMetricResponseData {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    value: Option<f64>,
+}
+
+impl From<&StatisticsMetric> for TimeseriesMetricResponseData {
+    fn from(metric: &StatisticsMetric) -> Self {
+        Self {
+            name: metric.name().to_string(),
+            kind: metric.kind().to_string(),
+            value: metric.value(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct GetTimeseriesHttpRequestQuery {
+    granularity: Option<String>,
+    /// Offset from UTC buckets are aligned to, e.g. `+03:00`.
+    timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Error)]
+enum ParseGetTimeseriesHttpRequestError {
+    #[error(transparent)]
+    Granularity(#[from] TimeseriesGranularityInvalidError),
+    #[error(transparent)]
+    Timezone(#[from] TimeseriesTimezoneInvalidError),
+}
+
+impl GetTimeseriesHttpRequestQuery {
+    fn try_into_domain(
+        self,
+        id: Uuid,
+    ) -> Result<GetTimeseriesRequest, ParseGetTimeseriesHttpRequestError> {
+        let granularity = self
+            .granularity
+            .as_deref()
+            .map(TimeseriesGranularity::new)
+            .transpose()?
+            .unwrap_or_default();
+        let timezone = self
+            .timezone
+            .as_deref()
+            .map(TimeseriesTimezone::new)
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(GetTimeseriesRequest::new(id)
+            .with_granularity(granularity)
+            .with_timezone(timezone))
+    }
+}
+
+pub async fn get_timeseries<ES: ExperimentService, DS: DeviceService, EV: EventService>(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState<ES, DS, EV>>,
+    Query(query): Query<GetTimeseriesHttpRequestQuery>,
+) -> Result<ApiSuccess<TimeseriesResponseData>, ApiError> {
+    let domain_req = query.try_into_domain(id)?;
+
+    state
+        .experiment_service
+        .get_timeseries(&domain_req)
+        .await
+        .map_err(ApiError::from)
+        .map(|ref timeseries| ApiSuccess::new(StatusCode::OK, timeseries.into()))
+}
@@ -0,0 +1,196 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, HeaderName, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::domain::device::models::device::{
+    Device, DeviceId, DeviceIdError, DeviceIdKind, DeviceIdKindInvalidError, LinkDeviceError,
+    LinkDeviceRequest, UserId, UserIdInvalidError,
+};
+use crate::domain::device::ports::DeviceService;
+use crate::domain::event::ports::EventService;
+use crate::domain::experiment::ports::ExperimentService;
+use crate::inbound::http::AppState;
+
+#[derive(Debug, Clone)]
+pub struct ApiSuccess<T: Serialize + PartialEq>(StatusCode, Json<ApiResponseBody<T>>);
+
+impl<T> PartialEq for ApiSuccess<T>
+where
+    T: Serialize + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0 && self.1.0 == other.1.0
+    }
+}
+
+impl<T: Serialize + PartialEq> ApiSuccess<T> {
+    fn new(status: StatusCode, data: T) -> Self {
+        ApiSuccess(status, Json(ApiResponseBody::new(data)))
+    }
+}
+
+impl<T: Serialize + PartialEq> IntoResponse for ApiSuccess<T> {
+    fn into_response(self) -> Response {
+        (self.0, self.1).into_response()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    InternalServerError(String),
+    UnprocessableEntity(String),
+    NotFound(String),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::InternalServerError(e.to_string())
+    }
+}
+
+impl From<LinkDeviceError> for ApiError {
+    fn from(e: LinkDeviceError) -> Self {
+        match e {
+            LinkDeviceError::NotFound { id } => {
+                Self::NotFound(format!("device with id {} not found", id))
+            }
+            LinkDeviceError::Anonymous => {
+                Self::UnprocessableEntity("anonymous device cannot be linked to a user".to_string())
+            }
+            LinkDeviceError::Unknown(cause) => {
+                tracing::error!("{:?}\n{}", cause, cause.backtrace());
+                Self::InternalServerError("Internal server error".to_string())
+            }
+        }
+    }
+}
+
+impl From<ParseLinkDeviceHttpRequestError> for ApiError {
+    fn from(e: ParseLinkDeviceHttpRequestError) -> Self {
+        Self::UnprocessableEntity(e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        use ApiError::*;
+
+        match self {
+            InternalServerError(e) => {
+                tracing::error!("{}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponseBody::new_error(
+                        "Internal server error".to_string(),
+                    )),
+                )
+                    .into_response()
+            }
+            UnprocessableEntity(message) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ApiResponseBody::new_error(message)),
+            )
+                .into_response(),
+            NotFound(message) => (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponseBody::new_error(message)),
+            )
+                .into_response(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiResponseBody<T: Serialize + PartialEq> {
+    data: T,
+}
+
+impl<T: Serialize + PartialEq> ApiResponseBody<T> {
+    pub fn new(data: T) -> Self {
+        Self { data }
+    }
+}
+
+impl ApiResponseBody<ApiErrorData> {
+    pub fn new_error(message: String) -> Self {
+        Self {
+            data: ApiErrorData { message },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiErrorData {
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkDeviceResponseData {
+    id: String,
+    user_id: Option<String>,
+}
+
+impl From<&Device> for LinkDeviceResponseData {
+    fn from(device: &Device) -> Self {
+        Self {
+            id: device.id().to_string(),
+            user_id: device.user_id().as_ref().map(|u| u.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkDeviceHttpRequestBody {
+    user_id: String,
+}
+
+#[derive(Debug, Clone, Error)]
+enum ParseLinkDeviceHttpRequestError {
+    #[error(transparent)]
+    Kind(#[from] DeviceIdKindInvalidError),
+    #[error(transparent)]
+    DeviceId(#[from] DeviceIdError),
+    #[error(transparent)]
+    UserId(#[from] UserIdInvalidError),
+}
+
+impl LinkDeviceHttpRequestBody {
+    fn try_into_domain(
+        self,
+        raw_id: &str,
+        raw_kind: Option<&str>,
+    ) -> Result<LinkDeviceRequest, ParseLinkDeviceHttpRequestError> {
+        let kind = raw_kind
+            .map(DeviceIdKind::new)
+            .transpose()?
+            .unwrap_or(DeviceIdKind::Idfa);
+        let id = DeviceId::new(kind, raw_id)?;
+        let user_id = UserId::new(&self.user_id)?;
+
+        Ok(LinkDeviceRequest::new(id, user_id))
+    }
+}
+
+pub async fn link_device<ES: ExperimentService, DS: DeviceService, EV: EventService>(
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    State(state): State<AppState<ES, DS, EV>>,
+    Json(body): Json<LinkDeviceHttpRequestBody>,
+) -> Result<ApiSuccess<LinkDeviceResponseData>, ApiError> {
+    let kind = headers
+        .get(HeaderName::from_static("x-device-id-type"))
+        .and_then(|v| v.to_str().ok());
+
+    let domain_req = body.try_into_domain(&id, kind)?;
+    state
+        .device_service
+        .link_device_to_user(&domain_req)
+        .await
+        .map_err(ApiError::from)
+        .map(|ref device| ApiSuccess::new(StatusCode::OK, device.into()))
+}
@@ -6,14 +6,27 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::domain::device::ports::DeviceService;
+use crate::domain::event::models::event::EventNameInvalidError;
+use crate::domain::event::ports::EventService;
 use crate::domain::experiment::models::experiment::{
-    DistributionSumError, FinishExperimentError, VariantDistributionInvalidError,
+    ChangeExperimentStatusError, DistributionSumError, ExperimentAllocation,
+    ExperimentAllocationInvalidError, ExperimentName, ExperimentNameEmptyError, ExperimentStatus,
+    ExperimentVariants, FinishExperimentError, UpdateExperimentError, UpdateExperimentRequest,
+    Variant as ExperimentVariant, VariantData, VariantDataEmptyError, VariantDistribution,
+    VariantDistributionInvalidError,
 };
-use crate::domain::experiment::models::experiment::{
-    ExperimentNameEmptyError, VariantDataEmptyError,
+use crate::domain::experiment::models::metric::{
+    ExperimentMetrics, ExperimentMetricsInvalidError, Metric, MetricNameEmptyError,
+};
+use crate::domain::experiment::models::targeting::{
+    SemverRangeInvalidError, TargetingAttributeEmptyError, TargetingInListEmptyError,
+    TargetingRule, TargetingRules,
 };
 use crate::domain::experiment::ports::ExperimentService;
+use crate::domain::statistics::sequential::MixingDeviationInvalidError;
 use crate::inbound::http::AppState;
+use crate::inbound::http::requests::{MetricHttpRequest, TargetingRuleHttpRequest};
 
 #[derive(Debug, Clone)]
 pub struct ApiSuccess<T: Serialize + PartialEq>(StatusCode, Json<ApiResponseBody<T>>);
@@ -42,6 +55,7 @@ impl<T: Serialize + PartialEq> IntoResponse for ApiSuccess<T> {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ApiError {
     InternalServerError(String),
+    UnprocessableEntity(String),
     NotFound(String),
     Conflict(String),
     Unauthorized,
@@ -63,6 +77,10 @@ impl From<FinishExperimentError> for ApiError {
             FinishExperimentError::Finished { id } => {
                 Self::Conflict(format!("experiment with id {} is already finished", id))
             }
+            FinishExperimentError::InvalidTransition { id, from } => Self::Conflict(format!(
+                "experiment with id {} cannot be finished from {}",
+                id, from
+            )),
             FinishExperimentError::Unknown(cause) => {
                 tracing::error!("{:?}\n{}", cause, cause.backtrace());
                 Self::InternalServerError("Internal server error".to_string())
@@ -71,6 +89,92 @@ impl From<FinishExperimentError> for ApiError {
     }
 }
 
+impl From<UpdateExperimentError> for ApiError {
+    fn from(e: UpdateExperimentError) -> Self {
+        match e {
+            UpdateExperimentError::NotFound { id } => {
+                Self::NotFound(format!("experiment with id {} not found", id))
+            }
+            UpdateExperimentError::NotEditable { id, status } => Self::Conflict(format!(
+                "experiment with id {} cannot be edited in status {}",
+                id, status
+            )),
+            UpdateExperimentError::Duplicate { name } => {
+                Self::UnprocessableEntity(format!("experiment with name {} already exists", name))
+            }
+            UpdateExperimentError::InvalidControl { id, control } => {
+                Self::UnprocessableEntity(format!(
+                    "control {} is not a variant of experiment with id {}",
+                    control, id
+                ))
+            }
+            UpdateExperimentError::InvalidTransition { id, from, to } => Self::Conflict(format!(
+                "experiment with id {} cannot change status from {} to {}",
+                id, from, to
+            )),
+            UpdateExperimentError::GuardrailMetric { id, metric } => {
+                Self::UnprocessableEntity(format!(
+                    "metric {} of experiment with id {} is watched by a guardrail and needs a mixing deviation",
+                    metric, id
+                ))
+            }
+            UpdateExperimentError::Unknown(cause) => {
+                tracing::error!("{:?}\n{}", cause, cause.backtrace());
+                Self::InternalServerError("Internal server error".to_string())
+            }
+        }
+    }
+}
+
+impl From<ParsePatchExperimentHttpRequestError> for ApiError {
+    fn from(e: ParsePatchExperimentHttpRequestError) -> Self {
+        let message = match e {
+            ParsePatchExperimentHttpRequestError::Name(_) => {
+                "experiment name cannot be empty".to_string()
+            }
+            ParsePatchExperimentHttpRequestError::VariantData(_) => {
+                "variant data cannot be empty".to_string()
+            }
+            ParsePatchExperimentHttpRequestError::VariantDistribution(cause) => {
+                format!("{cause}")
+            }
+            ParsePatchExperimentHttpRequestError::DistributionSum(cause) => {
+                format!("{cause}")
+            }
+            ParsePatchExperimentHttpRequestError::Allocation(cause) => format!("{cause}"),
+            ParsePatchExperimentHttpRequestError::TargetingAttribute(cause) => format!("{cause}"),
+            ParsePatchExperimentHttpRequestError::TargetingInList(cause) => format!("{cause}"),
+            ParsePatchExperimentHttpRequestError::SemverRange(cause) => format!("{cause}"),
+            ParsePatchExperimentHttpRequestError::MetricName(cause) => format!("{cause}"),
+            ParsePatchExperimentHttpRequestError::MetricEvent(cause) => format!("{cause}"),
+            ParsePatchExperimentHttpRequestError::Metrics(cause) => format!("{cause}"),
+            ParsePatchExperimentHttpRequestError::MixingDeviation(cause) => format!("{cause}"),
+        };
+
+        Self::UnprocessableEntity(message)
+    }
+}
+
+impl From<ChangeExperimentStatusError> for ApiError {
+    fn from(e: ChangeExperimentStatusError) -> Self {
+        match e {
+            ChangeExperimentStatusError::NotFound { id } => {
+                Self::NotFound(format!("experiment with id {} not found", id))
+            }
+            ChangeExperimentStatusError::InvalidTransition { id, from, to } => {
+                Self::Conflict(format!(
+                    "experiment with id {} cannot change status from {} to {}",
+                    id, from, to
+                ))
+            }
+            ChangeExperimentStatusError::Unknown(cause) => {
+                tracing::error!("{:?}\n{}", cause, cause.backtrace());
+                Self::InternalServerError("Internal server error".to_string())
+            }
+        }
+    }
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         use ApiError::*;
@@ -86,6 +190,11 @@ impl IntoResponse for ApiError {
                 )
                     .into_response()
             }
+            UnprocessableEntity(message) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ApiResponseBody::new_error(message)),
+            )
+                .into_response(),
             NotFound(message) => (
                 StatusCode::NOT_FOUND,
                 Json(ApiResponseBody::new_error(message)),
@@ -134,6 +243,7 @@ pub struct ApiErrorData {
     pub message: String,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct CreateExperimentRequestBody {
     name: String,
@@ -158,15 +268,38 @@ pub struct Variant {
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct PatchExperimentHttpRequestBody {
-    status: ExperimentStatusHttpRequest,
+    status: Option<ExperimentStatusHttpRequest>,
+    name: Option<String>,
+    variants: Option<Vec<Variant>>,
+    allocation: Option<f64>,
+    targeting: Option<Vec<TargetingRuleHttpRequest>>,
+    metrics: Option<Vec<MetricHttpRequest>>,
+    control: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ExperimentStatusHttpRequest {
+    Draft,
+    Running,
+    Paused,
     Finished,
+    Archived,
 }
 
+impl From<ExperimentStatusHttpRequest> for ExperimentStatus {
+    fn from(status: ExperimentStatusHttpRequest) -> Self {
+        match status {
+            ExperimentStatusHttpRequest::Draft => Self::Draft,
+            ExperimentStatusHttpRequest::Running => Self::Running,
+            ExperimentStatusHttpRequest::Paused => Self::Paused,
+            ExperimentStatusHttpRequest::Finished => Self::Finished,
+            ExperimentStatusHttpRequest::Archived => Self::Archived,
+        }
+    }
+}
+
+#[allow(dead_code)]
 #[derive(Debug, Clone, Error)]
 enum ParseCreateExperimentHttpRequestError {
     #[error(transparent)]
@@ -179,11 +312,103 @@ enum ParseCreateExperimentHttpRequestError {
     DistributionSum(#[from] DistributionSumError),
 }
 
-pub async fn patch_experiment<ES: ExperimentService>(
+#[derive(Debug, Clone, Error)]
+enum ParsePatchExperimentHttpRequestError {
+    #[error(transparent)]
+    Name(#[from] ExperimentNameEmptyError),
+    #[error(transparent)]
+    VariantData(#[from] VariantDataEmptyError),
+    #[error(transparent)]
+    VariantDistribution(#[from] VariantDistributionInvalidError),
+    #[error(transparent)]
+    DistributionSum(#[from] DistributionSumError),
+    #[error(transparent)]
+    Allocation(#[from] ExperimentAllocationInvalidError),
+    #[error(transparent)]
+    TargetingAttribute(#[from] TargetingAttributeEmptyError),
+    #[error(transparent)]
+    TargetingInList(#[from] TargetingInListEmptyError),
+    #[error(transparent)]
+    SemverRange(#[from] SemverRangeInvalidError),
+    #[error(transparent)]
+    MetricName(#[from] MetricNameEmptyError),
+    #[error(transparent)]
+    MetricEvent(#[from] EventNameInvalidError),
+    #[error(transparent)]
+    Metrics(#[from] ExperimentMetricsInvalidError),
+    #[error(transparent)]
+    MixingDeviation(#[from] MixingDeviationInvalidError),
+}
+
+impl PatchExperimentHttpRequestBody {
+    fn has_edits(&self) -> bool {
+        self.name.is_some()
+            || self.variants.is_some()
+            || self.allocation.is_some()
+            || self.targeting.is_some()
+            || self.metrics.is_some()
+            || self.control.is_some()
+    }
+
+    fn try_into_domain(
+        self,
+        id: Uuid,
+    ) -> Result<UpdateExperimentRequest, ParsePatchExperimentHttpRequestError> {
+        let name = self.name.map(|n| ExperimentName::new(&n)).transpose()?;
+        let variants = self
+            .variants
+            .map(|variants| {
+                let variants = variants
+                    .iter()
+                    .map(|v| {
+                        let data = VariantData::new(&v.data)?;
+                        let distribution = VariantDistribution::new(v.distribution)?;
+
+                        Ok(ExperimentVariant::new(distribution, data))
+                    })
+                    .collect::<Result<Vec<ExperimentVariant>, ParsePatchExperimentHttpRequestError>>()?;
+
+                Ok::<_, ParsePatchExperimentHttpRequestError>(ExperimentVariants::new(variants)?)
+            })
+            .transpose()?;
+
+        let allocation = self.allocation.map(ExperimentAllocation::new).transpose()?;
+        let targeting = self
+            .targeting
+            .map(|rules| {
+                rules
+                    .into_iter()
+                    .map(TargetingRuleHttpRequest::try_into_domain)
+                    .collect::<Result<Vec<TargetingRule>, ParsePatchExperimentHttpRequestError>>()
+                    .map(TargetingRules::new)
+            })
+            .transpose()?;
+
+        let metrics = self
+            .metrics
+            .map(|metrics| {
+                let metrics = metrics
+                    .into_iter()
+                    .map(MetricHttpRequest::try_into_domain)
+                    .collect::<Result<Vec<Metric>, ParsePatchExperimentHttpRequestError>>()?;
+
+                Ok::<_, ParsePatchExperimentHttpRequestError>(ExperimentMetrics::new(metrics)?)
+            })
+            .transpose()?;
+
+        let control = self.control.map(|c| VariantData::new(&c)).transpose()?;
+
+        Ok(UpdateExperimentRequest::new(
+            id, name, variants, allocation, targeting, metrics, control,
+        ))
+    }
+}
+
+pub async fn patch_experiment<ES: ExperimentService, DS: DeviceService, EV: EventService>(
     headers: HeaderMap,
     Path(id): Path<Uuid>,
-    State(state): State<AppState<ES>>,
-    Json(_): Json<PatchExperimentHttpRequestBody>,
+    State(state): State<AppState<ES, DS, EV>>,
+    Json(body): Json<PatchExperimentHttpRequestBody>,
 ) -> Result<ApiSuccess<PatchExperimentResponseData>, ApiError> {
     let auth_key = headers.get("Authorization").ok_or(ApiError::Unauthorized)?;
 
@@ -196,10 +421,40 @@ pub async fn patch_experiment<ES: ExperimentService>(
         Err(_) => return Err(ApiError::Unauthorized),
     }
 
-    state
-        .experiment_service
-        .finish_experiment(&id)
-        .await
-        .map_err(ApiError::from)
-        .map(|ref experiment| ApiSuccess::new(StatusCode::OK, experiment.into()))
+    let status = body.status.clone().map(ExperimentStatus::from);
+
+    if !body.has_edits() && status.is_none() {
+        return Err(ApiError::UnprocessableEntity(
+            "nothing to update in experiment".to_string(),
+        ));
+    }
+
+    // Edits are applied together with the status change, so that an experiment can be edited
+    // and started in a single request.
+    if body.has_edits() {
+        let domain_req = body.try_into_domain(id)?.with_status(status);
+
+        return state
+            .experiment_service
+            .update_experiment(&domain_req)
+            .await
+            .map_err(ApiError::from)
+            .map(|ref experiment| ApiSuccess::new(StatusCode::OK, experiment.into()));
+    }
+
+    match status {
+        Some(ExperimentStatus::Finished) => state
+            .experiment_service
+            .finish_experiment(&id)
+            .await
+            .map_err(ApiError::from)
+            .map(|ref experiment| ApiSuccess::new(StatusCode::OK, experiment.into())),
+        Some(status) => state
+            .experiment_service
+            .change_experiment_status(&id, status)
+            .await
+            .map_err(ApiError::from)
+            .map(|ref experiment| ApiSuccess::new(StatusCode::OK, experiment.into())),
+        None => Ok(ApiSuccess::new(StatusCode::OK, (&id).into())),
+    }
 }
@@ -0,0 +1,147 @@
+use serde::Deserialize;
+
+use crate::domain::event::models::event::{EventName, EventNameInvalidError};
+use crate::domain::experiment::models::metric::{
+    Metric, MetricKind, MetricName, MetricNameEmptyError, MetricRole,
+};
+use crate::domain::experiment::models::targeting::{
+    NumericComparison, SemverRange, SemverRangeInvalidError, TargetingAttribute,
+    TargetingAttributeEmptyError, TargetingInListEmptyError, TargetingOperator, TargetingRule,
+};
+use crate::domain::statistics::sequential::{MixingDeviation, MixingDeviationInvalidError};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TargetingRuleHttpRequest {
+    attribute: String,
+    #[serde(flatten)]
+    operator: TargetingOperatorHttpRequest,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "operator", content = "value", rename_all = "lowercase")]
+pub enum TargetingOperatorHttpRequest {
+    Eq(String),
+    In(Vec<String>),
+    Semver(String),
+    Lt(f64),
+    Lte(f64),
+    Gt(f64),
+    Gte(f64),
+}
+
+impl TargetingRuleHttpRequest {
+    /// Parses the rule into the error type of the request body it is part of.
+    pub fn try_into_domain<E>(self) -> Result<TargetingRule, E>
+    where
+        E: From<TargetingAttributeEmptyError>
+            + From<TargetingInListEmptyError>
+            + From<SemverRangeInvalidError>,
+    {
+        let attribute = TargetingAttribute::new(&self.attribute)?;
+        let operator = match self.operator {
+            TargetingOperatorHttpRequest::Eq(value) => TargetingOperator::Equals(value),
+            TargetingOperatorHttpRequest::In(values) => TargetingOperator::new_in(values)?,
+            TargetingOperatorHttpRequest::Semver(range) => {
+                TargetingOperator::SemverRange(SemverRange::new(&range)?)
+            }
+            TargetingOperatorHttpRequest::Lt(v) => {
+                TargetingOperator::Numeric(NumericComparison::Lt, v)
+            }
+            TargetingOperatorHttpRequest::Lte(v) => {
+                TargetingOperator::Numeric(NumericComparison::Lte, v)
+            }
+            TargetingOperatorHttpRequest::Gt(v) => {
+                TargetingOperator::Numeric(NumericComparison::Gt, v)
+            }
+            TargetingOperatorHttpRequest::Gte(v) => {
+                TargetingOperator::Numeric(NumericComparison::Gte, v)
+            }
+        };
+
+        Ok(TargetingRule::new(attribute, operator))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MetricHttpRequest {
+    name: String,
+    #[serde(flatten)]
+    kind: MetricKindHttpRequest,
+    role: Option<MetricRoleHttpRequest>,
+    /// Deviation of the effect the sequential test mixes over, in units of the metric.
+    #[serde(rename = "mixingDeviation")]
+    mixing_deviation: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MetricKindHttpRequest {
+    Conversion {
+        event: String,
+    },
+    Sum {
+        event: String,
+    },
+    Mean {
+        event: String,
+    },
+    Count {
+        event: String,
+    },
+    Ratio {
+        numerator: String,
+        denominator: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricRoleHttpRequest {
+    Primary,
+    Secondary,
+}
+
+impl From<MetricRoleHttpRequest> for MetricRole {
+    fn from(role: MetricRoleHttpRequest) -> Self {
+        match role {
+            MetricRoleHttpRequest::Primary => Self::Primary,
+            MetricRoleHttpRequest::Secondary => Self::Secondary,
+        }
+    }
+}
+
+impl MetricHttpRequest {
+    /// Parses the metric into the error type of the request body it is part of.
+    pub fn try_into_domain<E>(self) -> Result<Metric, E>
+    where
+        E: From<MetricNameEmptyError>
+            + From<EventNameInvalidError>
+            + From<MixingDeviationInvalidError>,
+    {
+        let name = MetricName::new(&self.name)?;
+        let kind = match self.kind {
+            MetricKindHttpRequest::Conversion { event } => {
+                MetricKind::Conversion(EventName::new(&event)?)
+            }
+            MetricKindHttpRequest::Sum { event } => MetricKind::Sum(EventName::new(&event)?),
+            MetricKindHttpRequest::Mean { event } => MetricKind::Mean(EventName::new(&event)?),
+            MetricKindHttpRequest::Count { event } => MetricKind::Count(EventName::new(&event)?),
+            MetricKindHttpRequest::Ratio {
+                numerator,
+                denominator,
+            } => MetricKind::Ratio {
+                numerator: EventName::new(&numerator)?,
+                denominator: EventName::new(&denominator)?,
+            },
+        };
+        let role = self.role.map(MetricRole::from).unwrap_or_default();
+        let metric = Metric::new(name, kind, role);
+
+        Ok(match self.mixing_deviation {
+            Some(mixing_deviation) => {
+                metric.with_mixing_deviation(MixingDeviation::new(mixing_deviation)?)
+            }
+            None => metric,
+        })
+    }
+}
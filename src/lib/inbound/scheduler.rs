@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::time::{self, MissedTickBehavior};
+
+use crate::domain::experiment::ports::ExperimentService;
+
+/// Background task moving experiments between lifecycle states at their scheduled times.
+pub struct Scheduler<ES: ExperimentService> {
+    experiment_service: ES,
+    interval: Duration,
+}
+
+impl<ES: ExperimentService> Scheduler<ES> {
+    pub fn new(experiment_service: ES, interval: Duration) -> Self {
+        Self {
+            experiment_service,
+            interval,
+        }
+    }
+
+    /// Applies the schedules once every interval, forever. Failures are logged and retried on
+    /// the next tick.
+    pub async fn run(self) {
+        let mut ticks = time::interval(self.interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticks.tick().await;
+
+            match self.experiment_service.apply_schedules(&Utc::now()).await {
+                Ok(moved) if !moved.is_empty() => {
+                    tracing::info!("applied schedules of experiments {:?}", moved)
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("failed to apply experiment schedules: {:?}", e),
+            }
+        }
+    }
+}
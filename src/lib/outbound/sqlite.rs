@@ -1,22 +1,53 @@
+use std::collections::HashMap;
 use std::str::FromStr;
 
 use anyhow::{Context, anyhow};
-use chrono::Utc;
-use sqlx::{Executor, SqlitePool, Transaction};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::{Executor, SqliteConnection, SqlitePool, Transaction};
 use sqlx::{QueryBuilder, sqlite::SqliteConnectOptions};
 use uuid::Uuid;
 
 use crate::domain::device::models::device::{
-    CreateDeviceError, CreateDeviceRequest, Device, DeviceId, GetAllDevicesError,
-    GetDeviceByIdError,
+    CreateDeviceError, CreateDeviceRequest, Device, DeviceAttributes, DeviceId, DeviceIdKind,
+    DevicePlatform, GetAllDevicesError, GetDeviceByIdError, LinkDeviceError, LinkDeviceRequest,
+    RegisterDeviceError, RegisteredDevice, UpdateDeviceAttributesError,
+    UpdateDeviceAttributesRequest, UserId,
 };
 use crate::domain::device::ports::DeviceRepository;
+use crate::domain::event::models::event::{CreateEventsError, Event, EventName, SaveEventRequest};
+use crate::domain::event::ports::EventRepository;
+use crate::domain::experiment::models::assignment::{
+    Assignment, BackfillAssignmentsError, CreateAssignmentRequest, CreateAssignmentsError,
+    GetAllAssignmentsError,
+};
 use crate::domain::experiment::models::experiment::{
-    CreateExperimentError, CreateExperimentRequest, DeviceExperiment, Experiment, ExperimentName,
-    ExperimentVariants, FinishExperimentError, GetAllDeviceExperimentsError,
-    GetAllExperimentsError, Variant as ExperimentVariant, VariantData, VariantDistribution,
+    AnalysisPlan, ChangeExperimentStatusError, CovariateWindow, CreateExperimentError,
+    CreateExperimentRequest, Experiment, ExperimentAllocation, ExperimentBucketing,
+    ExperimentEligibility, ExperimentLifecycle, ExperimentName, ExperimentSalt, ExperimentSchedule,
+    ExperimentStatus, ExperimentVariants, FinishExperimentError, GetAllExperimentsError,
+    GetExperimentByIdError, UpdateExperimentError, UpdateExperimentRequest,
+    Variant as ExperimentVariant, VariantData, VariantDistribution,
+};
+use crate::domain::experiment::models::guardrail::{
+    AuditEntry, CreateAuditEntryRequest, DegradationDirection, ExperimentGuardrails,
+    GetAuditTrailError, Guardrail, GuardrailAction, MaxDegradation, StopExperimentError,
+};
+use crate::domain::experiment::models::layer::{ExperimentLayer, LayerBuckets, LayerName};
+use crate::domain::experiment::models::metric::{
+    ExperimentMetrics, GetAllMetricObservationsError, Metric, MetricKind, MetricName,
+    MetricObservation, MetricRole,
+};
+use crate::domain::experiment::models::sample_size::CalculateSampleSizeError;
+use crate::domain::experiment::models::segment::{
+    GetExperimentStatisticsError, GetExperimentStatisticsRequest, ParticipantCount,
 };
+use crate::domain::experiment::models::targeting::{
+    NumericComparison, SemverRange, TargetingAttribute, TargetingOperator, TargetingRule,
+    TargetingRules,
+};
+use crate::domain::experiment::models::timeseries::TimedMetricObservation;
 use crate::domain::experiment::ports::ExperimentRepository;
+use crate::domain::statistics::sequential::MixingDeviation;
 
 #[derive(Debug, Clone)]
 pub struct Sqlite {
@@ -36,20 +67,286 @@ impl Sqlite {
         Ok(Sqlite { pool })
     }
 
+    /// Fetches experiments along with their variants, rules, metrics and guardrails, either all
+    /// of them or only the one with the given id.
+    async fn fetch_experiments(
+        &self,
+        id: Option<&str>,
+    ) -> Result<Vec<Experiment>, GetAllExperimentsError> {
+        let experiment_rows = sqlx::query!(
+            "SELECT id, name, version, salt, allocation, status, bucketing, control, analysis_plan,
+                eligibility, starts_at, ends_at, layer, layer_start, layer_end, created_at,
+                finished_at
+            FROM experiments
+            WHERE $1 IS NULL OR id = $1",
+            id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            GetAllExperimentsError::Unknown(anyhow!(e).context("failed to fetch experiments"))
+        })?;
+
+        // Rows of the experiments are fetched at once and grouped by experiment, rather than
+        // queried experiment by experiment.
+        let mut variant_rows: HashMap<String, Vec<_>> = HashMap::new();
+        for v in sqlx::query!(
+            "SELECT v.experiment_id, v.data, v.distribution FROM experiment_variants v
+            JOIN experiments x ON x.id = v.experiment_id AND x.version = v.version
+            WHERE $1 IS NULL OR v.experiment_id = $1
+            ORDER BY v.rowid",
+            id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch experiment variants")?
+        {
+            variant_rows
+                .entry(v.experiment_id.clone())
+                .or_default()
+                .push(v);
+        }
+
+        let mut rule_rows: HashMap<String, Vec<_>> = HashMap::new();
+        for r in sqlx::query!(
+            "SELECT experiment_id, attribute, operator, value FROM experiment_targeting_rules
+            WHERE $1 IS NULL OR experiment_id = $1
+            ORDER BY rowid",
+            id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch experiment targeting rules")?
+        {
+            rule_rows
+                .entry(r.experiment_id.clone())
+                .or_default()
+                .push(r);
+        }
+
+        let mut metric_rows: HashMap<String, Vec<_>> = HashMap::new();
+        for m in sqlx::query!(
+            "SELECT experiment_id, name, kind, event, denominator_event, role, mixing_deviation
+            FROM experiment_metrics
+            WHERE $1 IS NULL OR experiment_id = $1
+            ORDER BY rowid",
+            id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch experiment metrics")?
+        {
+            metric_rows
+                .entry(m.experiment_id.clone())
+                .or_default()
+                .push(m);
+        }
+
+        let mut guardrail_rows: HashMap<String, Vec<_>> = HashMap::new();
+        for g in sqlx::query!(
+            "SELECT experiment_id, metric, max_degradation, direction, action
+            FROM experiment_guardrails
+            WHERE $1 IS NULL OR experiment_id = $1
+            ORDER BY rowid",
+            id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch experiment guardrails")?
+        {
+            guardrail_rows
+                .entry(g.experiment_id.clone())
+                .or_default()
+                .push(g);
+        }
+
+        let mut experiments = Vec::new();
+        for row in experiment_rows {
+            let id = Uuid::parse_str(&row.id).context("invalid UUID format")?;
+            let name = ExperimentName::new(&row.name)?;
+            let salt = row.salt.map(|s| ExperimentSalt::new(&s)).transpose()?;
+            let allocation = ExperimentAllocation::new(row.allocation)?;
+            let status = ExperimentStatus::new(&row.status)?;
+            let bucketing = ExperimentBucketing::new(&row.bucketing)?;
+            let control = row.control.map(|c| VariantData::new(&c)).transpose()?;
+            let analysis_plan = AnalysisPlan::new(&row.analysis_plan)?;
+            let eligibility = ExperimentEligibility::new(&row.eligibility)?;
+            let created_at = row
+                .created_at
+                .parse()
+                .context("failed to parse created_at as DateTime<Utc>")?;
+            let finished_at = row
+                .finished_at
+                .map(|f| {
+                    f.parse()
+                        .context("failed to parse finished_at as DateTime<Utc>")
+                })
+                .transpose()?;
+            let starts_at = row
+                .starts_at
+                .map(|s| {
+                    s.parse()
+                        .context("failed to parse starts_at as DateTime<Utc>")
+                })
+                .transpose()?;
+            let ends_at = row
+                .ends_at
+                .map(|e| {
+                    e.parse()
+                        .context("failed to parse ends_at as DateTime<Utc>")
+                })
+                .transpose()?;
+            let schedule = ExperimentSchedule::new(starts_at, ends_at)?;
+            let layer = match (row.layer, row.layer_start, row.layer_end) {
+                (Some(layer), Some(start), Some(end)) => Some(ExperimentLayer::new(
+                    LayerName::new(&layer)?,
+                    LayerBuckets::new(start as u32, end as u32)?,
+                )),
+                _ => None,
+            };
+
+            let variants = variant_rows
+                .remove(&row.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|v| {
+                    let data = VariantData::new(&v.data)?;
+                    let distribution = VariantDistribution::new(v.distribution)?;
+
+                    Ok(ExperimentVariant::new(distribution, data))
+                })
+                .collect::<Result<Vec<_>, GetAllExperimentsError>>()?;
+
+            let validated_variants = ExperimentVariants::new(variants).map_err(|e| {
+                GetAllExperimentsError::Unknown(anyhow!(e).context("invalid experiment variants"))
+            })?;
+
+            let rules = rule_rows
+                .remove(&row.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|r| {
+                    let attribute = TargetingAttribute::new(&r.attribute)?;
+                    let operator = targeting_operator_from_row(&r.operator, &r.value)?;
+
+                    Ok(TargetingRule::new(attribute, operator))
+                })
+                .collect::<Result<Vec<_>, GetAllExperimentsError>>()?;
+
+            let metrics = metric_rows
+                .remove(&row.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|m| {
+                    let name = MetricName::new(&m.name)?;
+                    let kind = metric_kind_from_row(&m.kind, &m.event, m.denominator_event)?;
+                    let role = MetricRole::new(&m.role)?;
+                    let metric = Metric::new(name, kind, role);
+
+                    Ok(match m.mixing_deviation {
+                        Some(mixing_deviation) => {
+                            metric.with_mixing_deviation(MixingDeviation::new(mixing_deviation)?)
+                        }
+                        None => metric,
+                    })
+                })
+                .collect::<Result<Vec<_>, GetAllExperimentsError>>()?;
+            let metrics = ExperimentMetrics::new(metrics)?;
+
+            let guardrails = guardrail_rows
+                .remove(&row.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|g| {
+                    let metric = MetricName::new(&g.metric)?;
+                    let max_degradation = MaxDegradation::new(g.max_degradation)?;
+                    let direction = DegradationDirection::new(&g.direction)?;
+                    let action = GuardrailAction::new(&g.action)?;
+
+                    Ok(Guardrail::new(metric, max_degradation)
+                        .with_direction(direction)
+                        .with_action(action))
+                })
+                .collect::<Result<Vec<_>, GetAllExperimentsError>>()?;
+            let guardrails = ExperimentGuardrails::new(guardrails, &metrics)?;
+
+            let lifecycle =
+                ExperimentLifecycle::new(status, created_at, finished_at).with_schedule(schedule);
+            let experiment = Experiment::new(
+                id,
+                name,
+                validated_variants,
+                row.version as u32,
+                salt,
+                allocation,
+                lifecycle,
+            )
+            .with_targeting(TargetingRules::new(rules))
+            .with_bucketing(bucketing)
+            .with_metrics(metrics)
+            .with_analysis_plan(analysis_plan)
+            .with_eligibility(eligibility)
+            .with_guardrails(guardrails);
+            let experiment = match control {
+                Some(control) => experiment.with_control(control),
+                None => experiment,
+            };
+            let experiment = match layer {
+                Some(layer) => experiment.with_layer(layer),
+                None => experiment,
+            };
+
+            experiments.push(experiment);
+        }
+
+        Ok(experiments)
+    }
+
     async fn save_experiment(
         &self,
         tx: &mut Transaction<'_, sqlx::Sqlite>,
-        name: &ExperimentName,
+        req: &CreateExperimentRequest,
     ) -> Result<Uuid, sqlx::Error> {
         let id = Uuid::new_v4();
         let id_as_string = id.to_string();
-        let name = &name.to_string();
+        let name = req.name().to_string();
+        let salt = req
+            .salt()
+            .as_ref()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| id_as_string.clone());
+        let allocation = req.allocation().into_inner();
+        let status = req.status().to_string();
+        let bucketing = req.bucketing().to_string();
+        let control = req.control().as_ref().map(|c| c.to_string());
+        let analysis_plan = req.analysis_plan().to_string();
+        let eligibility = req.eligibility().to_string();
+        let starts_at = req.schedule().starts_at();
+        let ends_at = req.schedule().ends_at();
+        let layer = req.layer().map(|l| l.name().to_string());
+        let layer_start = req.layer().map(|l| l.buckets().start());
+        let layer_end = req.layer().map(|l| l.buckets().end());
         let now = Utc::now();
 
         let query = sqlx::query!(
-            "INSERT INTO experiments (id, name, created_at) VALUES ($1, $2, $3)",
+            "INSERT INTO experiments
+                (id, name, salt, allocation, status, bucketing, control, analysis_plan,
+                eligibility, starts_at, ends_at, layer, layer_start, layer_end, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)",
             id_as_string,
             name,
+            salt,
+            allocation,
+            status,
+            bucketing,
+            control,
+            analysis_plan,
+            eligibility,
+            starts_at,
+            ends_at,
+            layer,
+            layer_start,
+            layer_end,
             now,
         );
 
@@ -58,17 +355,65 @@ impl Sqlite {
         Ok(id)
     }
 
+    /// Checks that the experiment may join the layer: active experiments of the layer must be
+    /// bucketed the same way, so that a device is hashed into the same bucket by each of them,
+    /// and must not claim any of the requested buckets.
+    async fn check_layer(
+        &self,
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        layer: &ExperimentLayer,
+        bucketing: ExperimentBucketing,
+    ) -> Result<(), CreateExperimentError> {
+        let layer_name = layer.name().to_string();
+
+        let rows = sqlx::query!(
+            r#"SELECT name, bucketing, layer_start AS "layer_start!: u32",
+                layer_end AS "layer_end!: u32"
+            FROM experiments
+            WHERE layer = $1 AND status NOT IN ('finished', 'archived')"#,
+            layer_name,
+        )
+        .fetch_all(&mut **tx)
+        .await
+        .context("failed to fetch experiments of the layer")?;
+
+        for row in rows {
+            let name = ExperimentName::new(&row.name).context("invalid experiment name")?;
+            let existing =
+                ExperimentBucketing::new(&row.bucketing).context("invalid experiment bucketing")?;
+            if existing != bucketing {
+                return Err(CreateExperimentError::LayerBucketing {
+                    layer: layer.name().clone(),
+                    name,
+                    bucketing: existing,
+                });
+            }
+
+            let buckets = LayerBuckets::new(row.layer_start, row.layer_end)
+                .context("invalid layer buckets")?;
+            if buckets.overlaps(layer.buckets()) {
+                return Err(CreateExperimentError::LayerOverlap {
+                    layer: layer.name().clone(),
+                    name,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     async fn save_experiment_variants(
         &self,
         tx: &mut Transaction<'_, sqlx::Sqlite>,
         experiment_id: &Uuid,
         variants: &ExperimentVariants,
+        version: u32,
     ) -> Result<(), sqlx::Error> {
         let experiment_id = experiment_id.to_string();
         let variants = variants.variants();
 
         let mut query_builder = QueryBuilder::new(
-            "INSERT INTO experiment_variants (id, experiment_id,  data, distribution) ",
+            "INSERT INTO experiment_variants (id, experiment_id, data, distribution, version) ",
         );
 
         let query = query_builder
@@ -80,7 +425,8 @@ impl Sqlite {
                 b.push_bind(id)
                     .push_bind(&experiment_id)
                     .push_bind(data)
-                    .push_bind(distribution);
+                    .push_bind(distribution)
+                    .push_bind(version);
             })
             .build();
 
@@ -89,23 +435,239 @@ impl Sqlite {
         Ok(())
     }
 
+    async fn save_experiment_targeting(
+        &self,
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        experiment_id: &Uuid,
+        targeting: &TargetingRules,
+    ) -> Result<(), anyhow::Error> {
+        let experiment_id = experiment_id.to_string();
+
+        sqlx::query!(
+            "DELETE FROM experiment_targeting_rules WHERE experiment_id = $1",
+            experiment_id,
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        for rule in targeting.rules() {
+            let id = Uuid::new_v4().to_string();
+            let attribute = rule.attribute().to_string();
+            let (operator, value) = targeting_operator_to_row(rule.operator())?;
+
+            sqlx::query!(
+                "INSERT INTO experiment_targeting_rules (id, experiment_id, attribute, operator, value)
+                VALUES ($1, $2, $3, $4, $5)",
+                id,
+                experiment_id,
+                attribute,
+                operator,
+                value,
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn save_experiment_metrics(
+        &self,
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        experiment_id: &Uuid,
+        metrics: &ExperimentMetrics,
+    ) -> Result<(), sqlx::Error> {
+        let experiment_id = experiment_id.to_string();
+
+        sqlx::query!(
+            "DELETE FROM experiment_metrics WHERE experiment_id = $1",
+            experiment_id,
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        for metric in metrics.metrics() {
+            let id = Uuid::new_v4().to_string();
+            let name = metric.name().to_string();
+            let kind = metric.kind().to_string();
+            let (event, denominator_event) = metric_kind_to_row(metric.kind());
+            let role = metric.role().to_string();
+            let mixing_deviation = metric.mixing_deviation().map(|m| m.into_inner());
+
+            sqlx::query!(
+                "INSERT INTO experiment_metrics
+                (id, experiment_id, name, kind, event, denominator_event, role, mixing_deviation)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                id,
+                experiment_id,
+                name,
+                kind,
+                event,
+                denominator_event,
+                role,
+                mixing_deviation,
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn save_experiment_guardrails(
+        &self,
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        experiment_id: &Uuid,
+        guardrails: &ExperimentGuardrails,
+    ) -> Result<(), sqlx::Error> {
+        let experiment_id = experiment_id.to_string();
+
+        for guardrail in guardrails.guardrails() {
+            let id = Uuid::new_v4().to_string();
+            let metric = guardrail.metric().to_string();
+            let max_degradation = guardrail.max_degradation().into_inner();
+            let direction = guardrail.direction().to_string();
+            let action = guardrail.action().to_string();
+
+            sqlx::query!(
+                "INSERT INTO experiment_guardrails
+                    (id, experiment_id, metric, max_degradation, direction, action)
+                VALUES ($1, $2, $3, $4, $5, $6)",
+                id,
+                experiment_id,
+                metric,
+                max_degradation,
+                direction,
+                action,
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetches assignments of all devices linked to a user in user-bucketed experiments.
+    async fn get_user_assignments(
+        &self,
+        conn: &mut SqliteConnection,
+        user_id: &UserId,
+    ) -> Result<Vec<Assignment>, anyhow::Error> {
+        let user_id = user_id.to_string();
+
+        let rows = sqlx::query!(
+            "SELECT a.device_id, d.kind, a.experiment_id, a.data, a.version, a.assigned_at
+            FROM assignments a
+            JOIN devices d ON d.kind = a.device_kind AND d.id = a.device_id
+            JOIN experiments e ON e.id = a.experiment_id
+            WHERE d.user_id = $1 AND e.bucketing = 'user'",
+            user_id,
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("failed to fetch user assignments")?;
+
+        let mut assignments = Vec::new();
+        for row in rows {
+            let kind = DeviceIdKind::new(&row.kind).context("invalid device ID kind")?;
+            let device_id = DeviceId::new(kind, &row.device_id)?;
+            let experiment_id =
+                Uuid::parse_str(&row.experiment_id).context("invalid UUID format")?;
+            let data = VariantData::new(&row.data)?;
+            let assigned_at = row
+                .assigned_at
+                .parse()
+                .context("failed to parse assigned_at as DateTime<Utc>")?;
+
+            assignments.push(Assignment::new(
+                device_id,
+                experiment_id,
+                data,
+                row.version as u32,
+                assigned_at,
+            ));
+        }
+
+        Ok(assignments)
+    }
+
+    /// Inserts the device unless it already exists.
+    ///
+    /// # Returns
+    /// * `Some(Device)` with the inserted device.
+    /// * `None` if the device already exists.
     async fn save_device(
         &self,
         tx: &mut Transaction<'_, sqlx::Sqlite>,
         id: &DeviceId,
-    ) -> Result<Device, sqlx::Error> {
+    ) -> Result<Option<Device>, sqlx::Error> {
         let id_as_string = id.to_string();
+        let kind = id.kind().to_string();
         let now = Utc::now();
 
         let query = sqlx::query!(
-            "INSERT INTO devices (id, created_at) VALUES ($1, $2)",
+            "INSERT INTO devices (id, kind, created_at) VALUES ($1, $2, $3)
+            ON CONFLICT (kind, id) DO NOTHING",
             id_as_string,
+            kind,
             now,
         );
 
+        let result = tx.execute(query).await?;
+
+        Ok((result.rows_affected() == 1).then(|| Device::new(id.clone(), now)))
+    }
+
+    async fn save_assignment(
+        &self,
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        req: &CreateAssignmentRequest,
+    ) -> Result<Assignment, anyhow::Error> {
+        let device_kind = req.device_id().kind().to_string();
+        let device_id = req.device_id().to_string();
+        let experiment_id = req.experiment_id().to_string();
+        let data = req.data().to_string();
+        let version = req.version();
+        let assigned_at = req.assigned_at().copied().unwrap_or_else(Utc::now);
+
+        let query = sqlx::query!(
+            "INSERT INTO assignments
+                (device_kind, device_id, experiment_id, data, version, assigned_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (device_kind, device_id, experiment_id) DO NOTHING",
+            device_kind,
+            device_id,
+            experiment_id,
+            data,
+            version,
+            assigned_at,
+        );
+
         tx.execute(query).await?;
 
-        Ok(Device::new(id.clone(), now))
+        let row = sqlx::query!(
+            "SELECT data, version, assigned_at FROM assignments
+            WHERE device_kind = $1 AND device_id = $2 AND experiment_id = $3",
+            device_kind,
+            device_id,
+            experiment_id,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        let data = VariantData::new(&row.data)?;
+        let assigned_at = row
+            .assigned_at
+            .parse()
+            .context("failed to parse assigned_at as DateTime<Utc>")?;
+
+        Ok(Assignment::new(
+            req.device_id().to_owned(),
+            req.experiment_id().to_owned(),
+            data,
+            row.version as u32,
+            assigned_at,
+        ))
     }
 }
 
@@ -117,17 +679,13 @@ impl DeviceRepository for Sqlite {
             .await
             .context("failed to start SQLite transaction")?;
 
-        let device = self.save_device(&mut tx, req.id()).await.map_err(|e| {
-            if is_primary_key_constraint_violation(&e) {
-                CreateDeviceError::Duplicate {
-                    id: req.id().clone(),
-                }
-            } else {
-                anyhow!(e)
-                    .context(format!("failed to save device with id {}", req.id()))
-                    .into()
-            }
-        })?;
+        let device = self
+            .save_device(&mut tx, req.id())
+            .await
+            .context(format!("failed to save device with id {}", req.id()))?
+            .ok_or_else(|| CreateDeviceError::Duplicate {
+                id: req.id().clone(),
+            })?;
 
         tx.commit()
             .await
@@ -136,11 +694,47 @@ impl DeviceRepository for Sqlite {
         Ok(device)
     }
 
+    async fn upsert_device(&self, id: &DeviceId) -> Result<RegisteredDevice, RegisterDeviceError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("failed to start SQLite transaction")?;
+
+        let saved = self
+            .save_device(&mut tx, id)
+            .await
+            .context(format!("failed to save device with id {}", id))?;
+
+        tx.commit()
+            .await
+            .context("failed to commit SQLite transaction")?;
+
+        match saved {
+            Some(device) => Ok(RegisteredDevice::new(device, true)),
+            None => {
+                let device = self.get_device_by_id(id).await.map_err(|e| {
+                    RegisterDeviceError::Unknown(
+                        anyhow!(e).context(format!("failed to get device with id {}", id)),
+                    )
+                })?;
+
+                Ok(RegisteredDevice::new(device, false))
+            }
+        }
+    }
+
     async fn get_device_by_id(&self, id: &DeviceId) -> Result<Device, GetDeviceByIdError> {
-        let id_as_string = id.to_owned().into_inner().to_string();
+        let kind = id.kind().to_string();
+        let id_as_string = id.to_string();
 
         let device = sqlx::query!(
-            "SELECT id, created_at FROM devices WHERE id = $1",
+            r#"SELECT d.created_at, d.user_id, a.platform, a.os_version, a.app_version, a.country,
+            a.locale, a.properties AS "properties?"
+            FROM devices d
+            LEFT JOIN device_attributes a ON a.device_kind = d.kind AND a.device_id = d.id
+            WHERE d.kind = $1 AND d.id = $2"#,
+            kind,
             id_as_string
         )
         .fetch_one(&self.pool)
@@ -156,11 +750,113 @@ impl DeviceRepository for Sqlite {
             .created_at
             .parse()
             .context("failed to parse created_at as DateTime<Utc>")?;
-
-        let device = Device::new(id.to_owned(), created_at);
+        let attributes = device_attributes_from_row(
+            device.platform,
+            device.os_version,
+            device.app_version,
+            device.country,
+            device.locale,
+            device.properties,
+        )?;
+        let user_id = device
+            .user_id
+            .map(|u| UserId::new(&u))
+            .transpose()
+            .context("invalid user id")?;
+
+        let device = Device::new(id.to_owned(), created_at)
+            .with_attributes(attributes)
+            .with_user_id(user_id);
 
         Ok(device)
     }
+
+    async fn update_device_attributes(
+        &self,
+        req: &UpdateDeviceAttributesRequest,
+    ) -> Result<Device, UpdateDeviceAttributesError> {
+        let not_found = |e| match e {
+            GetDeviceByIdError::NotFound { id } => UpdateDeviceAttributesError::NotFound { id },
+            GetDeviceByIdError::Unknown(e) => e.into(),
+        };
+        self.get_device_by_id(req.id()).await.map_err(not_found)?;
+
+        let attributes = req.attributes();
+        let kind = req.id().kind().to_string();
+        let id_as_string = req.id().to_string();
+        let platform = attributes.platform().map(|p| p.to_string());
+        let os_version = attributes.os_version().clone();
+        let app_version = attributes.app_version().clone();
+        let country = attributes.country().clone();
+        let locale = attributes.locale().clone();
+        let properties = serde_json::to_string(attributes.properties())
+            .context("failed to serialize device properties")?;
+        let now = Utc::now();
+
+        // The attributes are merged by the upsert itself, so that concurrent reports of the same
+        // device do not overwrite each other.
+        sqlx::query!(
+            "INSERT INTO device_attributes
+            (device_kind, device_id, platform, os_version, app_version, country, locale,
+            properties, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (device_kind, device_id) DO UPDATE SET
+            platform = COALESCE(excluded.platform, device_attributes.platform),
+            os_version = COALESCE(excluded.os_version, device_attributes.os_version),
+            app_version = COALESCE(excluded.app_version, device_attributes.app_version),
+            country = COALESCE(excluded.country, device_attributes.country),
+            locale = COALESCE(excluded.locale, device_attributes.locale),
+            properties = json_patch(device_attributes.properties, excluded.properties),
+            updated_at = excluded.updated_at",
+            kind,
+            id_as_string,
+            platform,
+            os_version,
+            app_version,
+            country,
+            locale,
+            properties,
+            now,
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("failed to save attributes of device with id {}", req.id()))?;
+
+        self.get_device_by_id(req.id()).await.map_err(not_found)
+    }
+
+    async fn link_device_to_user(
+        &self,
+        req: &LinkDeviceRequest,
+    ) -> Result<Device, LinkDeviceError> {
+        let kind = req.id().kind().to_string();
+        let id_as_string = req.id().to_string();
+        let user_id = req.user_id().to_string();
+
+        // Assignments the devices already got are kept, so that their events stay attributed to
+        // the variant they were exposed to. Only devices not assigned yet inherit the variant of
+        // the user.
+        let result = sqlx::query!(
+            "UPDATE devices SET user_id = $1 WHERE kind = $2 AND id = $3",
+            user_id,
+            kind,
+            id_as_string,
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("failed to link device with id {}", req.id()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(LinkDeviceError::NotFound {
+                id: req.id().to_owned(),
+            });
+        }
+
+        self.get_device_by_id(req.id()).await.map_err(|e| match e {
+            GetDeviceByIdError::NotFound { id } => LinkDeviceError::NotFound { id },
+            GetDeviceByIdError::Unknown(e) => e.into(),
+        })
+    }
 }
 
 impl ExperimentRepository for Sqlite {
@@ -174,28 +870,41 @@ impl ExperimentRepository for Sqlite {
             .await
             .context("failed to start SQLite transaction")?;
 
-        let id = self
-            .save_experiment(&mut tx, req.name())
-            .await
-            .map_err(|e| {
-                if is_unique_constraint_violation(&e) {
-                    CreateExperimentError::Duplicate {
-                        name: req.name().clone(),
-                    }
-                } else {
-                    anyhow!(e)
-                        .context(format!(
-                            "failed to save experiment with name {:?}",
-                            req.name()
-                        ))
-                        .into()
+        if let Some(layer) = req.layer() {
+            self.check_layer(&mut tx, layer, req.bucketing()).await?;
+        }
+
+        let id = self.save_experiment(&mut tx, req).await.map_err(|e| {
+            if is_unique_constraint_violation(&e) {
+                CreateExperimentError::Duplicate {
+                    name: req.name().clone(),
                 }
-            })?;
+            } else {
+                anyhow!(e)
+                    .context(format!(
+                        "failed to save experiment with name {:?}",
+                        req.name()
+                    ))
+                    .into()
+            }
+        })?;
 
-        self.save_experiment_variants(&mut tx, &id, req.variants())
+        self.save_experiment_variants(&mut tx, &id, req.variants(), 1)
             .await
             .map_err(|e| anyhow!(e).context("failed to save experiment variants"))?;
 
+        self.save_experiment_targeting(&mut tx, &id, req.targeting())
+            .await
+            .context("failed to save experiment targeting rules")?;
+
+        self.save_experiment_metrics(&mut tx, &id, req.metrics())
+            .await
+            .context("failed to save experiment metrics")?;
+
+        self.save_experiment_guardrails(&mut tx, &id, req.guardrails())
+            .await
+            .context("failed to save experiment guardrails")?;
+
         tx.commit()
             .await
             .context("failed to commit SQLite transaction")?;
@@ -204,173 +913,2764 @@ impl ExperimentRepository for Sqlite {
     }
 
     async fn get_all_experiments(&self) -> Result<Vec<Experiment>, GetAllExperimentsError> {
-        let experiment_rows =
-            sqlx::query!("SELECT id, name, created_at, finished_at FROM experiments")
-                .fetch_all(&self.pool)
-                .await
-                .map_err(|e| {
-                    GetAllExperimentsError::Unknown(
-                        anyhow!(e).context("failed to fetch experiments"),
-                    )
-                })?;
+        self.fetch_experiments(None).await
+    }
 
-        let mut experiments = Vec::new();
-        for row in experiment_rows {
-            let id = Uuid::parse_str(&row.id).context("invalid UUID format")?;
-            let name = ExperimentName::new(&row.name)?;
+    async fn get_experiment_by_id(&self, id: &Uuid) -> Result<Experiment, GetExperimentByIdError> {
+        let id_as_string = id.to_string();
+
+        self.fetch_experiments(Some(&id_as_string))
+            .await
+            .map_err(|e| anyhow!(e).context(format!("failed to get experiment with id {}", id)))?
+            .pop()
+            .ok_or(GetExperimentByIdError::NotFound { id: *id })
+    }
+
+    async fn get_all_devices(&self) -> Result<Vec<Device>, GetAllDevicesError> {
+        let rows = sqlx::query!(
+            r#"SELECT d.id, d.kind, d.created_at, d.user_id, a.platform, a.os_version, a.app_version, a.country,
+            a.locale, a.properties AS "properties?"
+            FROM devices d
+            LEFT JOIN device_attributes a ON a.device_kind = d.kind AND a.device_id = d.id"#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch devices")?;
+
+        let mut devices = Vec::new();
+        for row in rows {
+            let kind = DeviceIdKind::new(&row.kind).context("invalid device ID kind")?;
             let created_at = row
                 .created_at
                 .parse()
                 .context("failed to parse created_at as DateTime<Utc>")?;
-            let finished_at = row
-                .finished_at
-                .map(|f| {
-                    f.parse()
-                        .context("failed to parse finished_at as DateTime<Utc>")
-                })
-                .transpose()?;
-
-            let id_str = id.to_string();
-            let variant_rows = sqlx::query!(
-                "SELECT data, distribution FROM experiment_variants WHERE experiment_id = $1",
-                id_str
-            )
-            .fetch_all(&self.pool)
+            let attributes = device_attributes_from_row(
+                row.platform,
+                row.os_version,
+                row.app_version,
+                row.country,
+                row.locale,
+                row.properties,
+            )?;
+
+            let device_id = DeviceId::new(kind, &row.id).context("failed to create device ID")?;
+
+            let user_id = row
+                .user_id
+                .map(|u| UserId::new(&u))
+                .transpose()
+                .context("invalid user id")?;
+
+            let device = Device::new(device_id, created_at)
+                .with_attributes(attributes)
+                .with_user_id(user_id);
+            devices.push(device);
+        }
+
+        Ok(devices)
+    }
+
+    async fn update_experiment(
+        &self,
+        req: &UpdateExperimentRequest,
+    ) -> Result<Uuid, UpdateExperimentError> {
+        let id = req.id();
+        let id_as_string = id.to_string();
+
+        let mut tx = self
+            .pool
+            .begin()
             .await
-            .context("failed to fetch experiment variants")?;
+            .context("failed to start SQLite transaction")?;
 
-            let variants = variant_rows
-                .into_iter()
-                .map(|v| {
-                    let data = VariantData::new(&v.data)?;
-                    let distribution = VariantDistribution::new(v.distribution)?;
+        let row = sqlx::query!(
+            "SELECT status, version FROM experiments WHERE id = $1",
+            id_as_string
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("failed to fetch experiment")?
+        .ok_or(UpdateExperimentError::NotFound { id: id.to_owned() })?;
+
+        let status = ExperimentStatus::new(&row.status).context("invalid experiment status")?;
+        if !status.is_editable() {
+            return Err(UpdateExperimentError::NotEditable {
+                id: id.to_owned(),
+                status,
+            });
+        }
 
-                    Ok(ExperimentVariant::new(distribution, data))
-                })
-                .collect::<Result<Vec<_>, GetAllExperimentsError>>()?;
+        let version = row.version + 1;
 
-            let validated_variants = ExperimentVariants::new(variants).map_err(|e| {
-                GetAllExperimentsError::Unknown(anyhow!(e).context("invalid experiment variants"))
+        if let Some(name) = req.name() {
+            let name_as_string = name.to_string();
+
+            sqlx::query!(
+                "UPDATE experiments SET name = $1 WHERE id = $2",
+                name_as_string,
+                id_as_string,
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                if is_unique_constraint_violation(&e) {
+                    UpdateExperimentError::Duplicate {
+                        name: name.to_owned(),
+                    }
+                } else {
+                    anyhow!(e)
+                        .context(format!("failed to rename experiment with id {}", id))
+                        .into()
+                }
             })?;
+        }
 
-            let experiment = Experiment::new(id, name, validated_variants, created_at, finished_at);
+        if let Some(allocation) = req.allocation() {
+            let allocation = allocation.into_inner();
 
-            experiments.push(experiment);
+            sqlx::query!(
+                "UPDATE experiments SET allocation = $1 WHERE id = $2",
+                allocation,
+                id_as_string,
+            )
+            .execute(&mut *tx)
+            .await
+            .context("failed to update experiment allocation")?;
         }
 
-        Ok(experiments)
-    }
+        if let Some(targeting) = req.targeting() {
+            self.save_experiment_targeting(&mut tx, id, targeting)
+                .await
+                .context("failed to save experiment targeting rules")?;
+        }
 
-    async fn get_all_device_participating_experiments(
-        &self,
-        device_id: &DeviceId,
-    ) -> Result<Vec<DeviceExperiment>, GetAllDeviceExperimentsError> {
-        let create_device_req = CreateDeviceRequest::new(device_id.to_owned());
-        let device = self.create_device(&create_device_req).await;
+        if let Some(metrics) = req.metrics() {
+            self.save_experiment_metrics(&mut tx, id, metrics)
+                .await
+                .context("failed to save experiment metrics")?;
+
+            // Guardrails only watch metrics the experiment still has and can test sequentially.
+            let orphaned = sqlx::query!(
+                "SELECT metric FROM experiment_guardrails WHERE experiment_id = $1
+                AND metric NOT IN (
+                    SELECT name FROM experiment_metrics
+                    WHERE experiment_id = $1 AND mixing_deviation IS NOT NULL
+                )
+                LIMIT 1",
+                id_as_string,
+            )
+            .fetch_optional(&mut *tx)
+            .await
+            .context("failed to fetch experiment guardrails")?;
+            if let Some(orphaned) = orphaned {
+                return Err(UpdateExperimentError::GuardrailMetric {
+                    id: id.to_owned(),
+                    metric: MetricName::new(&orphaned.metric)
+                        .context("invalid guardrail metric")?,
+                });
+            }
+        }
 
-        if device.is_ok() {
-            return Ok(vec![]);
+        match req.variants() {
+            Some(variants) => self
+                .save_experiment_variants(&mut tx, id, variants, version as u32)
+                .await
+                .map_err(|e| anyhow!(e).context("failed to save experiment variants"))?,
+            None => {
+                // The new version keeps the split of the previous one.
+                let variant_rows = sqlx::query!(
+                    "SELECT data, distribution FROM experiment_variants
+                    WHERE experiment_id = $1 AND version = $2",
+                    id_as_string,
+                    row.version,
+                )
+                .fetch_all(&mut *tx)
+                .await
+                .context("failed to fetch experiment variants")?;
+
+                let variants = variant_rows
+                    .into_iter()
+                    .map(|v| {
+                        let data = VariantData::new(&v.data)?;
+                        let distribution = VariantDistribution::new(v.distribution)?;
+
+                        Ok(ExperimentVariant::new(distribution, data))
+                    })
+                    .collect::<Result<Vec<_>, anyhow::Error>>()?;
+                let variants =
+                    ExperimentVariants::new(variants).context("invalid experiment variants")?;
+
+                self.save_experiment_variants(&mut tx, id, &variants, version as u32)
+                    .await
+                    .map_err(|e| anyhow!(e).context("failed to copy experiment variants"))?;
+            }
         }
 
-        if let Err(CreateDeviceError::Unknown(e)) = device {
-            return Err(GetAllDeviceExperimentsError::Unknown(
-                anyhow!(e).context("failed to create device"),
-            ));
+        if let Some(control) = req.control() {
+            let control_as_string = control.to_string();
+
+            let variant = sqlx::query!(
+                "SELECT id FROM experiment_variants
+                WHERE experiment_id = $1 AND version = $2 AND data = $3",
+                id_as_string,
+                version,
+                control_as_string,
+            )
+            .fetch_optional(&mut *tx)
+            .await
+            .context("failed to fetch control variant")?;
+            if variant.is_none() {
+                return Err(UpdateExperimentError::InvalidControl {
+                    id: id.to_owned(),
+                    control: control.to_owned(),
+                });
+            }
+
+            sqlx::query!(
+                "UPDATE experiments SET control = $1 WHERE id = $2",
+                control_as_string,
+                id_as_string,
+            )
+            .execute(&mut *tx)
+            .await
+            .context("failed to update experiment control")?;
         }
 
-        let device = self.get_device_by_id(device_id).await.map_err(|e| {
-            GetAllDeviceExperimentsError::Unknown(anyhow!(e).context("failed to get device"))
-        })?;
+        sqlx::query!(
+            "UPDATE experiments SET version = $1 WHERE id = $2",
+            version,
+            id_as_string,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("failed to update experiment version")?;
+
+        // The status changes along with the edits, so that a failed transition keeps them out.
+        if let Some(next) = req.status() {
+            status
+                .transition_to(next)
+                .map_err(|e| UpdateExperimentError::InvalidTransition {
+                    id: id.to_owned(),
+                    from: e.from,
+                    to: e.to,
+                })?;
+            let next_as_string = next.to_string();
+            let finished_at = (next == ExperimentStatus::Finished).then(Utc::now);
+
+            sqlx::query!(
+                "UPDATE experiments SET status = $1, finished_at = COALESCE(finished_at, $2)
+                WHERE id = $3",
+                next_as_string,
+                finished_at,
+                id_as_string,
+            )
+            .execute(&mut *tx)
+            .await
+            .context("failed to change experiment status")?;
+        }
+
+        tx.commit()
+            .await
+            .context("failed to commit SQLite transaction")?;
+
+        Ok(id.to_owned())
+    }
+
+    async fn finish_experiment(&self, id: &Uuid) -> Result<Uuid, FinishExperimentError> {
+        let id_as_string = id.to_string();
+        let now = Utc::now();
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("failed to start SQLite transaction")?;
 
-        let experiments = self.get_all_experiments().await.map_err(|e| {
-            GetAllDeviceExperimentsError::Unknown(
-                anyhow!(e).context("failed to get all experiments"),
+        let row = sqlx::query!(
+            "SELECT status, finished_at FROM experiments WHERE id = $1",
+            id_as_string
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("failed to fetch experiment status")?
+        .ok_or(FinishExperimentError::NotFound { id: id.to_owned() })?;
+
+        let current = ExperimentStatus::new(&row.status).context("invalid experiment status")?;
+        if row.finished_at.is_some()
+            || matches!(
+                current,
+                ExperimentStatus::Finished | ExperimentStatus::Archived
             )
+        {
+            return Err(FinishExperimentError::Finished { id: id.to_owned() });
+        }
+
+        current
+            .transition_to(ExperimentStatus::Finished)
+            .map_err(|e| FinishExperimentError::InvalidTransition {
+                id: id.to_owned(),
+                from: e.from,
+            })?;
+
+        // `finished_at` is only ever set once.
+        sqlx::query!(
+            "UPDATE experiments SET status = 'finished', finished_at = $1
+            WHERE id = $2 AND finished_at IS NULL",
+            now,
+            id_as_string,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("failed to finish experiment")?;
+
+        tx.commit()
+            .await
+            .context("failed to commit SQLite transaction")?;
+
+        Ok(id.to_owned())
+    }
+
+    async fn change_experiment_status(
+        &self,
+        id: &Uuid,
+        status: ExperimentStatus,
+    ) -> Result<Uuid, ChangeExperimentStatusError> {
+        let id_as_string = id.to_string();
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("failed to start SQLite transaction")?;
+
+        let row = sqlx::query!("SELECT status FROM experiments WHERE id = $1", id_as_string)
+            .fetch_optional(&mut *tx)
+            .await
+            .context("failed to fetch experiment status")?
+            .ok_or(ChangeExperimentStatusError::NotFound { id: id.to_owned() })?;
+
+        let current = ExperimentStatus::new(&row.status).context("invalid experiment status")?;
+        let next = current.transition_to(status).map_err(|e| {
+            ChangeExperimentStatusError::InvalidTransition {
+                id: id.to_owned(),
+                from: e.from,
+                to: e.to,
+            }
         })?;
+        let next = next.to_string();
 
-        let device_experiments = experiments
-            .into_iter()
-            .filter(|exp| {
-                exp.created_at().cmp(device.created_at()).is_ge() && exp.finished_at().is_none()
-            })
-            .map(|exp| {
-                let data = exp
-                    .variants()
-                    .assign_variant(format!("{}", device.id().to_owned().into_inner()).as_str());
+        sqlx::query!(
+            "UPDATE experiments SET status = $1 WHERE id = $2",
+            next,
+            id_as_string,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("failed to change experiment status")?;
 
-                DeviceExperiment::new(*exp.id(), exp.name().to_owned(), data.to_owned())
-            })
-            .collect();
+        tx.commit()
+            .await
+            .context("failed to commit SQLite transaction")?;
 
-        Ok(device_experiments)
+        Ok(id.to_owned())
     }
 
-    async fn get_all_devices(&self) -> Result<Vec<Device>, GetAllDevicesError> {
-        let rows = sqlx::query!("SELECT * FROM devices")
-            .fetch_all(&self.pool)
+    async fn create_assignments(
+        &self,
+        reqs: &[CreateAssignmentRequest],
+    ) -> Result<Vec<Assignment>, CreateAssignmentsError> {
+        let mut tx = self
+            .pool
+            .begin()
             .await
-            .context("failed to fetch devices")?;
+            .context("failed to start SQLite transaction")?;
 
-        let mut devices = Vec::new();
+        let mut assignments = Vec::new();
+        for req in reqs {
+            let assignment = self.save_assignment(&mut tx, req).await.with_context(|| {
+                format!(
+                    "failed to save assignment of device {} to experiment {}",
+                    req.device_id(),
+                    req.experiment_id()
+                )
+            })?;
+
+            assignments.push(assignment);
+        }
+
+        tx.commit()
+            .await
+            .context("failed to commit SQLite transaction")?;
+
+        Ok(assignments)
+    }
+
+    async fn replace_assignments(
+        &self,
+        reqs: &[CreateAssignmentRequest],
+    ) -> Result<Vec<Assignment>, CreateAssignmentsError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("failed to start SQLite transaction")?;
+
+        let mut assignments = Vec::new();
+        for req in reqs {
+            let device_kind = req.device_id().kind().to_string();
+            let device_id = req.device_id().to_string();
+            let experiment_id = req.experiment_id().to_string();
+            let data = req.data().to_string();
+            let version = req.version();
+            let assigned_at = req.assigned_at().copied().unwrap_or_else(Utc::now);
+
+            sqlx::query!(
+                "INSERT INTO assignments
+                    (device_kind, device_id, experiment_id, data, version, assigned_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (device_kind, device_id, experiment_id) DO UPDATE SET
+                data = excluded.data,
+                version = excluded.version,
+                assigned_at = excluded.assigned_at",
+                device_kind,
+                device_id,
+                experiment_id,
+                data,
+                version,
+                assigned_at,
+            )
+            .execute(&mut *tx)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to replace assignment of device {} to experiment {}",
+                    req.device_id(),
+                    req.experiment_id()
+                )
+            })?;
+
+            assignments.push(Assignment::new(
+                req.device_id().to_owned(),
+                req.experiment_id().to_owned(),
+                req.data().to_owned(),
+                version,
+                assigned_at,
+            ));
+        }
+
+        tx.commit()
+            .await
+            .context("failed to commit SQLite transaction")?;
+
+        Ok(assignments)
+    }
+
+    async fn get_all_assignments(&self) -> Result<Vec<Assignment>, GetAllAssignmentsError> {
+        let rows = sqlx::query!(
+            "SELECT device_id, device_kind AS kind, experiment_id, data, version, assigned_at
+            FROM assignments"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch assignments")?;
+
+        let mut assignments = Vec::new();
         for row in rows {
-            let id = Uuid::parse_str(&row.id)
-                .context("invalid UUID format")?
-                .to_string();
-            let created_at = row
-                .created_at
+            let kind = DeviceIdKind::new(&row.kind).context("invalid device ID kind")?;
+            let device_id = DeviceId::new(kind, &row.device_id)?;
+            let experiment_id =
+                Uuid::parse_str(&row.experiment_id).context("invalid UUID format")?;
+            let data = VariantData::new(&row.data)?;
+            let assigned_at = row
+                .assigned_at
                 .parse()
-                .context("failed to parse created_at as DateTime<Utc>")?;
+                .context("failed to parse assigned_at as DateTime<Utc>")?;
+
+            let assignment = Assignment::new(
+                device_id,
+                experiment_id,
+                data,
+                row.version as u32,
+                assigned_at,
+            );
+            assignments.push(assignment);
+        }
 
-            let device_id = DeviceId::new(&id).context("failed to create device ID")?;
+        Ok(assignments)
+    }
 
-            let device = Device::new(device_id, created_at);
-            devices.push(device);
+    async fn get_experiment_assignments(
+        &self,
+        experiment_id: &Uuid,
+    ) -> Result<Vec<Assignment>, GetAllAssignmentsError> {
+        let experiment_id_as_string = experiment_id.to_string();
+
+        let rows = sqlx::query!(
+            "SELECT device_id, device_kind AS kind, data, version, assigned_at
+            FROM assignments
+            WHERE experiment_id = $1",
+            experiment_id_as_string,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch experiment assignments")?;
+
+        let mut assignments = Vec::new();
+        for row in rows {
+            let kind = DeviceIdKind::new(&row.kind).context("invalid device ID kind")?;
+            let device_id = DeviceId::new(kind, &row.device_id)?;
+            let data = VariantData::new(&row.data)?;
+            let assigned_at = row
+                .assigned_at
+                .parse()
+                .context("failed to parse assigned_at as DateTime<Utc>")?;
+
+            let assignment = Assignment::new(
+                device_id,
+                *experiment_id,
+                data,
+                row.version as u32,
+                assigned_at,
+            );
+            assignments.push(assignment);
         }
 
-        Ok(devices)
+        Ok(assignments)
     }
 
-    async fn finish_experiment(&self, id: &Uuid) -> Result<Uuid, FinishExperimentError> {
-        let id_as_string = id.to_string();
+    async fn get_assignments_persisted_since(
+        &self,
+    ) -> Result<DateTime<Utc>, BackfillAssignmentsError> {
+        // The bookkeeping table of migrations is not checked at compile time.
+        let installed_on: NaiveDateTime =
+            sqlx::query_scalar("SELECT installed_on FROM _sqlx_migrations WHERE version = $1")
+                .bind(ASSIGNMENTS_MIGRATION_VERSION)
+                .fetch_one(&self.pool)
+                .await
+                .context("failed to fetch the assignments migration")?;
+
+        Ok(installed_on.and_utc())
+    }
+
+    async fn is_assignments_backfilled(&self) -> Result<bool, BackfillAssignmentsError> {
+        let row = sqlx::query!("SELECT completed_at FROM assignment_backfills LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await
+            .context("failed to fetch the assignments backfill")?;
+
+        Ok(row.is_some())
+    }
+
+    async fn save_backfilled_assignments(
+        &self,
+        reqs: &[CreateAssignmentRequest],
+    ) -> Result<(), BackfillAssignmentsError> {
         let now = Utc::now();
 
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("failed to start SQLite transaction")?;
+
+        for req in reqs {
+            self.save_assignment(&mut tx, req).await.with_context(|| {
+                format!(
+                    "failed to save assignment of device {} to experiment {}",
+                    req.device_id(),
+                    req.experiment_id()
+                )
+            })?;
+        }
+
         sqlx::query!(
-            "UPDATE experiments SET finished_at = $1 WHERE id = $2",
+            "INSERT INTO assignment_backfills (completed_at) VALUES ($1)",
             now,
-            id_as_string,
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await
-        .context("failed to finish experiment")?;
+        .context("failed to record the assignments backfill")?;
 
-        Ok(id.to_owned())
+        tx.commit()
+            .await
+            .context("failed to commit SQLite transaction")?;
+
+        Ok(())
     }
-}
 
-const UNIQUE_CONSTRAINT_VIOLATION_CODE: &str = "2067";
+    async fn get_all_user_assignments(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<Assignment>, GetAllAssignmentsError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .context("failed to acquire SQLite connection")?;
 
-fn is_unique_constraint_violation(err: &sqlx::Error) -> bool {
-    if let sqlx::Error::Database(db_err) = err {
-        if let Some(code) = db_err.code() {
-            if code == UNIQUE_CONSTRAINT_VIOLATION_CODE {
-                return true;
-            }
+        Ok(self.get_user_assignments(&mut conn, user_id).await?)
+    }
+
+    async fn get_all_metric_observations(
+        &self,
+    ) -> Result<Vec<MetricObservation>, GetAllMetricObservationsError> {
+        let rows = sqlx::query!(
+            r#"SELECT ea.experiment_id, e.device_id, e.device_kind AS kind, ea.data, e.name,
+                COUNT(*) AS "count!: i64", COUNT(e.value) AS "value_count!: i64",
+                TOTAL(e.value) AS "sum!: f64",
+                TOTAL(e.value * e.value) AS "sum_of_squares!: f64"
+            FROM event_attributions ea
+            JOIN events e ON e.id = ea.event_id
+            JOIN assignments a ON a.device_kind = e.device_kind AND a.device_id = e.device_id
+                AND a.experiment_id = ea.experiment_id AND a.data = ea.data
+            GROUP BY ea.experiment_id, e.device_kind, e.device_id, ea.data, e.name"#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch metric observations")?;
+
+        let mut observations = Vec::new();
+        for row in rows {
+            let experiment_id =
+                Uuid::parse_str(&row.experiment_id).context("invalid UUID format")?;
+            let kind = DeviceIdKind::new(&row.kind).context("invalid device ID kind")?;
+            let device_id = DeviceId::new(kind, &row.device_id)?;
+            let data = VariantData::new(&row.data)?;
+            let event = EventName::new(&row.name)?;
+
+            observations.push(
+                MetricObservation::new(
+                    experiment_id,
+                    device_id,
+                    data,
+                    event,
+                    row.count as u64,
+                    row.sum,
+                    row.sum_of_squares,
+                )
+                .with_value_count(row.value_count as u64),
+            );
         }
+
+        Ok(observations)
     }
 
-    false
-}
+    async fn count_experiment_participants(
+        &self,
+        req: &GetExperimentStatisticsRequest,
+    ) -> Result<Vec<ParticipantCount>, GetExperimentStatisticsError> {
+        let id_as_string = req.id().to_string();
+        let segment = req.segment().map(|attribute| attribute.to_string());
+        let (from, to) = (req.period().from(), req.period().to());
+
+        // Parameters are numbered, as named ones are indexed in the order they first appear.
+        let rows = sqlx::query!(
+            r#"SELECT a.data, a.version,
+                CASE ?2
+                    WHEN 'platform' THEN da.platform
+                    WHEN 'os_version' THEN da.os_version
+                    WHEN 'app_version' THEN da.app_version
+                    WHEN 'country' THEN da.country
+                    WHEN 'locale' THEN da.locale
+                    ELSE json_extract(da.properties, '$.' || json_quote(?2))
+                END AS "segment?: String",
+                COUNT(*) AS "devices!: i64"
+            FROM assignments a
+            LEFT JOIN device_attributes da
+                ON da.device_kind = a.device_kind AND da.device_id = a.device_id
+            WHERE a.experiment_id = ?1
+                AND (?3 IS NULL OR julianday(a.assigned_at) >= julianday(?3))
+                AND (?4 IS NULL OR julianday(a.assigned_at) < julianday(?4))
+            GROUP BY a.data, a.version, 3"#,
+            id_as_string,
+            segment,
+            from,
+            to,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to count experiment participants")?;
 
-const PRIMARYKEY_CONSTRAINT_VIOLATION_CODE: &str = "1555";
+        let mut counts = Vec::new();
+        for row in rows {
+            let data = VariantData::new(&row.data).context("invalid variant data")?;
 
-fn is_primary_key_constraint_violation(err: &sqlx::Error) -> bool {
-    if let sqlx::Error::Database(db_err) = err {
-        if let Some(code) = db_err.code() {
-            if code == PRIMARYKEY_CONSTRAINT_VIOLATION_CODE {
-                return true;
-            }
+            counts.push(ParticipantCount::new(
+                data,
+                row.version as u32,
+                row.segment,
+                row.devices as usize,
+            ));
         }
+
+        Ok(counts)
     }
 
-    false
+    async fn get_experiment_metric_observations(
+        &self,
+        req: &GetExperimentStatisticsRequest,
+    ) -> Result<Vec<MetricObservation>, GetAllMetricObservationsError> {
+        let id_as_string = req.id().to_string();
+        let segment = req.segment().map(|attribute| attribute.to_string());
+        let (from, to) = (req.period().from(), req.period().to());
+
+        // Only devices assigned within the period are observed, the same as they are counted, and
+        // only events of the variant they are counted in.
+        let rows = sqlx::query!(
+            r#"SELECT e.device_id, e.device_kind AS kind, ea.data, e.name,
+                CASE ?2
+                    WHEN 'platform' THEN da.platform
+                    WHEN 'os_version' THEN da.os_version
+                    WHEN 'app_version' THEN da.app_version
+                    WHEN 'country' THEN da.country
+                    WHEN 'locale' THEN da.locale
+                    ELSE json_extract(da.properties, '$.' || json_quote(?2))
+                END AS "segment?: String",
+                COUNT(*) AS "count!: i64", COUNT(e.value) AS "value_count!: i64",
+                TOTAL(e.value) AS "sum!: f64",
+                TOTAL(e.value * e.value) AS "sum_of_squares!: f64"
+            FROM event_attributions ea
+            JOIN events e ON e.id = ea.event_id
+            JOIN assignments a ON a.device_kind = e.device_kind AND a.device_id = e.device_id
+                AND a.experiment_id = ea.experiment_id AND a.data = ea.data
+            LEFT JOIN device_attributes da
+                ON da.device_kind = e.device_kind AND da.device_id = e.device_id
+            WHERE ea.experiment_id = ?1
+                AND (?3 IS NULL OR julianday(a.assigned_at) >= julianday(?3))
+                AND (?4 IS NULL OR julianday(a.assigned_at) < julianday(?4))
+                AND (?3 IS NULL OR julianday(e.occurred_at) >= julianday(?3))
+                AND (?4 IS NULL OR julianday(e.occurred_at) < julianday(?4))
+            GROUP BY e.device_kind, e.device_id, ea.data, e.name"#,
+            id_as_string,
+            segment,
+            from,
+            to,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch experiment metric observations")?;
+
+        let mut observations = Vec::new();
+        for row in rows {
+            let kind = DeviceIdKind::new(&row.kind).context("invalid device ID kind")?;
+            let device_id = DeviceId::new(kind, &row.device_id)?;
+            let data = VariantData::new(&row.data)?;
+            let event = EventName::new(&row.name)?;
+
+            observations.push(
+                MetricObservation::new(
+                    *req.id(),
+                    device_id,
+                    data,
+                    event,
+                    row.count as u64,
+                    row.sum,
+                    row.sum_of_squares,
+                )
+                .with_value_count(row.value_count as u64)
+                .with_segment(row.segment),
+            );
+        }
+
+        Ok(observations)
+    }
+
+    async fn get_timed_metric_observations(
+        &self,
+        experiment_id: &Uuid,
+    ) -> Result<Vec<TimedMetricObservation>, GetAllMetricObservationsError> {
+        let experiment_id_as_string = experiment_id.to_string();
+
+        let rows = sqlx::query!(
+            r#"SELECT e.device_id, e.device_kind AS kind, ea.data, e.name, e.value, e.occurred_at
+            FROM event_attributions ea
+            JOIN events e ON e.id = ea.event_id
+            JOIN assignments a ON a.device_kind = e.device_kind AND a.device_id = e.device_id
+                AND a.experiment_id = ea.experiment_id AND a.data = ea.data
+            WHERE ea.experiment_id = $1"#,
+            experiment_id_as_string,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch timed metric observations")?;
+
+        let mut observations = Vec::new();
+        for row in rows {
+            let kind = DeviceIdKind::new(&row.kind).context("invalid device ID kind")?;
+            let device_id = DeviceId::new(kind, &row.device_id)?;
+            let data = VariantData::new(&row.data)?;
+            let event = EventName::new(&row.name)?;
+            let occurred_at = row
+                .occurred_at
+                .parse()
+                .context("failed to parse occurred_at as DateTime<Utc>")?;
+            // Events without a value are not summed, as in the aggregated observations.
+            let value = row.value.unwrap_or_default();
+
+            observations.push(TimedMetricObservation::new(
+                MetricObservation::new(
+                    *experiment_id,
+                    device_id,
+                    data,
+                    event,
+                    1,
+                    value,
+                    value * value,
+                )
+                .with_value_count(row.value.is_some() as u64),
+                occurred_at,
+            ));
+        }
+
+        Ok(observations)
+    }
+
+    async fn get_all_pre_experiment_observations(
+        &self,
+        window: CovariateWindow,
+    ) -> Result<Vec<MetricObservation>, GetAllMetricObservationsError> {
+        let days = window.days();
+
+        // Times are compared as julian days, as their text may differ in precision.
+        let rows = sqlx::query!(
+            r#"SELECT a.experiment_id, e.device_id, e.device_kind AS kind, a.data, e.name,
+                COUNT(*) AS "count!: i64", COUNT(e.value) AS "value_count!: i64",
+                TOTAL(e.value) AS "sum!: f64",
+                TOTAL(e.value * e.value) AS "sum_of_squares!: f64"
+            FROM assignments a
+            JOIN experiments x ON x.id = a.experiment_id
+            JOIN events e ON e.device_kind = a.device_kind AND e.device_id = a.device_id
+            WHERE julianday(e.occurred_at) >= julianday(x.created_at) - $1
+                AND julianday(e.occurred_at) < julianday(x.created_at)
+            GROUP BY a.experiment_id, e.device_kind, e.device_id, a.data, e.name"#,
+            days,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch pre-experiment observations")?;
+
+        let mut observations = Vec::new();
+        for row in rows {
+            let experiment_id =
+                Uuid::parse_str(&row.experiment_id).context("invalid UUID format")?;
+            let kind = DeviceIdKind::new(&row.kind).context("invalid device ID kind")?;
+            let device_id = DeviceId::new(kind, &row.device_id)?;
+            let data = VariantData::new(&row.data)?;
+            let event = EventName::new(&row.name)?;
+
+            observations.push(
+                MetricObservation::new(
+                    experiment_id,
+                    device_id,
+                    data,
+                    event,
+                    row.count as u64,
+                    row.sum,
+                    row.sum_of_squares,
+                )
+                .with_value_count(row.value_count as u64),
+            );
+        }
+
+        Ok(observations)
+    }
+
+    async fn get_pre_experiment_observations(
+        &self,
+        req: &GetExperimentStatisticsRequest,
+        window: CovariateWindow,
+    ) -> Result<Vec<MetricObservation>, GetAllMetricObservationsError> {
+        let id_as_string = req.id().to_string();
+        let days = window.days();
+        let segment = req.segment().map(|attribute| attribute.to_string());
+        let (from, to) = (req.period().from(), req.period().to());
+
+        // Only devices assigned within the period are observed, the same as they are counted.
+        let rows = sqlx::query!(
+            r#"SELECT e.device_id, e.device_kind AS kind, a.data, e.name,
+                CASE ?3
+                    WHEN 'platform' THEN da.platform
+                    WHEN 'os_version' THEN da.os_version
+                    WHEN 'app_version' THEN da.app_version
+                    WHEN 'country' THEN da.country
+                    WHEN 'locale' THEN da.locale
+                    ELSE json_extract(da.properties, '$.' || json_quote(?3))
+                END AS "segment?: String",
+                COUNT(*) AS "count!: i64", COUNT(e.value) AS "value_count!: i64",
+                TOTAL(e.value) AS "sum!: f64",
+                TOTAL(e.value * e.value) AS "sum_of_squares!: f64"
+            FROM assignments a
+            JOIN experiments x ON x.id = a.experiment_id
+            JOIN events e ON e.device_kind = a.device_kind AND e.device_id = a.device_id
+            LEFT JOIN device_attributes da
+                ON da.device_kind = e.device_kind AND da.device_id = e.device_id
+            WHERE a.experiment_id = ?1
+                AND julianday(e.occurred_at) >= julianday(x.created_at) - ?2
+                AND julianday(e.occurred_at) < julianday(x.created_at)
+                AND (?4 IS NULL OR julianday(a.assigned_at) >= julianday(?4))
+                AND (?5 IS NULL OR julianday(a.assigned_at) < julianday(?5))
+            GROUP BY e.device_kind, e.device_id, a.data, e.name"#,
+            id_as_string,
+            days,
+            segment,
+            from,
+            to,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch pre-experiment observations")?;
+
+        let mut observations = Vec::new();
+        for row in rows {
+            let kind = DeviceIdKind::new(&row.kind).context("invalid device ID kind")?;
+            let device_id = DeviceId::new(kind, &row.device_id)?;
+            let data = VariantData::new(&row.data)?;
+            let event = EventName::new(&row.name)?;
+
+            observations.push(
+                MetricObservation::new(
+                    *req.id(),
+                    device_id,
+                    data,
+                    event,
+                    row.count as u64,
+                    row.sum,
+                    row.sum_of_squares,
+                )
+                .with_value_count(row.value_count as u64)
+                .with_segment(row.segment),
+            );
+        }
+
+        Ok(observations)
+    }
+
+    async fn count_active_devices_since(
+        &self,
+        since: &DateTime<Utc>,
+        eligibility: ExperimentEligibility,
+    ) -> Result<u64, CalculateSampleSizeError> {
+        let eligibility = eligibility.to_string();
+
+        // A device is active if it was created, got an assignment or sent an event since then.
+        let row = sqlx::query!(
+            r#"SELECT COUNT(*) AS "count!: i64" FROM devices d
+            WHERE CASE $2
+                    WHEN 'existing' THEN julianday(d.created_at) < julianday($1)
+                    WHEN 'new' THEN julianday(d.created_at) >= julianday($1)
+                    ELSE 1
+                END
+                AND (julianday(d.created_at) >= julianday($1)
+                    OR EXISTS (SELECT 1 FROM assignments a
+                        WHERE a.device_kind = d.kind AND a.device_id = d.id
+                            AND julianday(a.assigned_at) >= julianday($1))
+                    OR EXISTS (SELECT 1 FROM events e
+                        WHERE e.device_kind = d.kind AND e.device_id = d.id
+                            AND julianday(e.received_at) >= julianday($1)))"#,
+            since,
+            eligibility,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("failed to count devices")?;
+
+        Ok(row.count as u64)
+    }
+
+    async fn stop_experiment(
+        &self,
+        req: &CreateAuditEntryRequest,
+    ) -> Result<AuditEntry, StopExperimentError> {
+        let id = req.experiment_id();
+        let id_as_string = id.to_string();
+        let now = Utc::now();
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("failed to start SQLite transaction")?;
+
+        let row = sqlx::query!("SELECT status FROM experiments WHERE id = $1", id_as_string)
+            .fetch_optional(&mut *tx)
+            .await
+            .context("failed to fetch experiment status")?
+            .ok_or(StopExperimentError::NotFound { id: id.to_owned() })?;
+
+        let current = ExperimentStatus::new(&row.status).context("invalid experiment status")?;
+        let next = current.transition_to(req.status()).map_err(|e| {
+            StopExperimentError::InvalidTransition {
+                id: id.to_owned(),
+                from: e.from,
+                to: e.to,
+            }
+        })?;
+        let finished_at = (next == ExperimentStatus::Finished).then_some(now);
+        let next = next.to_string();
+
+        // `finished_at` is only ever set once.
+        sqlx::query!(
+            "UPDATE experiments SET status = $1, finished_at = COALESCE(finished_at, $2)
+            WHERE id = $3",
+            next,
+            finished_at,
+            id_as_string,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("failed to stop experiment")?;
+
+        let entry_id = Uuid::new_v4().to_string();
+        let reason = req.reason();
+
+        sqlx::query!(
+            "INSERT INTO experiment_audit_log (id, experiment_id, status, reason, created_at)
+            VALUES ($1, $2, $3, $4, $5)",
+            entry_id,
+            id_as_string,
+            next,
+            reason,
+            now,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("failed to save audit entry")?;
+
+        tx.commit()
+            .await
+            .context("failed to commit SQLite transaction")?;
+
+        Ok(AuditEntry::new(
+            id.to_owned(),
+            req.status(),
+            reason.to_string(),
+            now,
+        ))
+    }
+
+    async fn get_audit_trail(
+        &self,
+        experiment_id: &Uuid,
+    ) -> Result<Vec<AuditEntry>, GetAuditTrailError> {
+        let id_as_string = experiment_id.to_string();
+
+        sqlx::query!("SELECT id FROM experiments WHERE id = $1", id_as_string)
+            .fetch_optional(&self.pool)
+            .await
+            .context("failed to fetch experiment")?
+            .ok_or(GetAuditTrailError::NotFound {
+                id: experiment_id.to_owned(),
+            })?;
+
+        let rows = sqlx::query!(
+            "SELECT status, reason, created_at FROM experiment_audit_log
+            WHERE experiment_id = $1 ORDER BY julianday(created_at), rowid",
+            id_as_string,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch audit entries")?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let status = ExperimentStatus::new(&row.status).context("invalid experiment status")?;
+            let created_at = row
+                .created_at
+                .parse()
+                .context("failed to parse created_at as DateTime<Utc>")?;
+
+            entries.push(AuditEntry::new(
+                experiment_id.to_owned(),
+                status,
+                row.reason,
+                created_at,
+            ));
+        }
+
+        Ok(entries)
+    }
+}
+
+impl EventRepository for Sqlite {
+    async fn get_device_assignments(
+        &self,
+        id: &DeviceId,
+    ) -> Result<Vec<Assignment>, GetAllAssignmentsError> {
+        let kind = id.kind().to_string();
+        let id_as_string = id.to_string();
+
+        let rows = sqlx::query!(
+            "SELECT experiment_id, data, version, assigned_at FROM assignments
+            WHERE device_kind = $1 AND device_id = $2",
+            kind,
+            id_as_string,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch device assignments")?;
+
+        let mut assignments = Vec::new();
+        for row in rows {
+            let experiment_id =
+                Uuid::parse_str(&row.experiment_id).context("invalid UUID format")?;
+            let data = VariantData::new(&row.data)?;
+            let assigned_at = row
+                .assigned_at
+                .parse()
+                .context("failed to parse assigned_at as DateTime<Utc>")?;
+
+            assignments.push(Assignment::new(
+                id.to_owned(),
+                experiment_id,
+                data,
+                row.version as u32,
+                assigned_at,
+            ));
+        }
+
+        Ok(assignments)
+    }
+
+    async fn save_events(
+        &self,
+        id: &DeviceId,
+        reqs: &[SaveEventRequest],
+    ) -> Result<Vec<Event>, CreateEventsError> {
+        let kind = id.kind().to_string();
+        let id_as_string = id.to_string();
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("failed to start SQLite transaction")?;
+
+        let device = sqlx::query!(
+            "SELECT id FROM devices WHERE kind = $1 AND id = $2",
+            kind,
+            id_as_string
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("failed to fetch device")?;
+        if device.is_none() {
+            return Err(CreateEventsError::DeviceNotFound { id: id.to_owned() });
+        }
+
+        let now = Utc::now();
+        let mut events = Vec::new();
+        for req in reqs {
+            let event_id = Uuid::new_v4();
+            let event_id_as_string = event_id.to_string();
+            let name = req.name().to_string();
+            let value = req.value().map(|v| v.into_inner());
+            let occurred_at = req.occurred_at();
+
+            let query = sqlx::query!(
+                "INSERT INTO events (id, device_kind, device_id, name, value, occurred_at, received_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                event_id_as_string,
+                kind,
+                id_as_string,
+                name,
+                value,
+                occurred_at,
+                now,
+            );
+            tx.execute(query)
+                .await
+                .with_context(|| format!("failed to save event {}", name))?;
+
+            for attribution in req.attributions() {
+                let experiment_id = attribution.experiment_id().to_string();
+                let data = attribution.data().to_string();
+                let version = attribution.version();
+
+                let query = sqlx::query!(
+                    "INSERT INTO event_attributions (event_id, experiment_id, data, version)
+                    VALUES ($1, $2, $3, $4)",
+                    event_id_as_string,
+                    experiment_id,
+                    data,
+                    version,
+                );
+                tx.execute(query)
+                    .await
+                    .with_context(|| format!("failed to save attribution of event {}", event_id))?;
+            }
+
+            events.push(Event::new(
+                event_id,
+                id.to_owned(),
+                req.name().to_owned(),
+                req.value(),
+                req.occurred_at().to_owned(),
+                req.attributions().to_owned(),
+            ));
+        }
+
+        tx.commit()
+            .await
+            .context("failed to commit SQLite transaction")?;
+
+        Ok(events)
+    }
+}
+
+const UNIQUE_CONSTRAINT_VIOLATION_CODE: &str = "2067";
+
+/// Version of the migration that started persisting assignments.
+const ASSIGNMENTS_MIGRATION_VERSION: i64 = 20250702090000;
+
+/// Builds device attributes from the nullable columns of `device_attributes`, which are all
+/// `NULL` for devices that have not reported attributes yet.
+fn device_attributes_from_row(
+    platform: Option<String>,
+    os_version: Option<String>,
+    app_version: Option<String>,
+    country: Option<String>,
+    locale: Option<String>,
+    properties: Option<String>,
+) -> Result<DeviceAttributes, anyhow::Error> {
+    let platform = platform
+        .map(|p| DevicePlatform::new(&p))
+        .transpose()
+        .context("invalid device platform")?;
+    let properties: HashMap<String, String> = properties
+        .map(|p| serde_json::from_str(&p))
+        .transpose()
+        .context("invalid device properties")?
+        .unwrap_or_default();
+
+    Ok(DeviceAttributes::new(
+        platform,
+        os_version,
+        app_version,
+        country,
+        locale,
+        properties,
+    ))
+}
+
+/// Maps a targeting operator onto the `operator` and `value` columns, in-lists are stored as
+/// JSON arrays.
+fn targeting_operator_to_row(
+    operator: &TargetingOperator,
+) -> Result<(&'static str, String), anyhow::Error> {
+    let row = match operator {
+        TargetingOperator::Equals(value) => ("eq", value.to_owned()),
+        TargetingOperator::In(values) => ("in", serde_json::to_string(values)?),
+        TargetingOperator::SemverRange(range) => ("semver", range.to_string()),
+        TargetingOperator::Numeric(comparison, threshold) => {
+            let operator = match comparison {
+                NumericComparison::Lt => "lt",
+                NumericComparison::Lte => "lte",
+                NumericComparison::Gt => "gt",
+                NumericComparison::Gte => "gte",
+            };
+
+            (operator, threshold.to_string())
+        }
+    };
+
+    Ok(row)
+}
+
+fn targeting_operator_from_row(
+    operator: &str,
+    value: &str,
+) -> Result<TargetingOperator, GetAllExperimentsError> {
+    let numeric = |comparison| -> Result<TargetingOperator, GetAllExperimentsError> {
+        let threshold = value
+            .parse::<f64>()
+            .with_context(|| format!("invalid numeric targeting value {}", value))?;
+
+        Ok(TargetingOperator::Numeric(comparison, threshold))
+    };
+
+    match operator {
+        "eq" => Ok(TargetingOperator::Equals(value.to_owned())),
+        "in" => {
+            let values: Vec<String> = serde_json::from_str(value)
+                .with_context(|| format!("invalid targeting in-list {}", value))?;
+
+            Ok(TargetingOperator::new_in(values)?)
+        }
+        "semver" => Ok(TargetingOperator::SemverRange(SemverRange::new(value)?)),
+        "lt" => numeric(NumericComparison::Lt),
+        "lte" => numeric(NumericComparison::Lte),
+        "gt" => numeric(NumericComparison::Gt),
+        "gte" => numeric(NumericComparison::Gte),
+        _ => Err(anyhow!("unknown targeting operator {}", operator).into()),
+    }
+}
+
+/// Maps a metric kind onto the `event` and `denominator_event` columns, only ratios have a
+/// denominator.
+fn metric_kind_to_row(kind: &MetricKind) -> (String, Option<String>) {
+    match kind {
+        MetricKind::Conversion(event)
+        | MetricKind::Sum(event)
+        | MetricKind::Mean(event)
+        | MetricKind::Count(event) => (event.to_string(), None),
+        MetricKind::Ratio {
+            numerator,
+            denominator,
+        } => (numerator.to_string(), Some(denominator.to_string())),
+    }
+}
+
+fn metric_kind_from_row(
+    kind: &str,
+    event: &str,
+    denominator_event: Option<String>,
+) -> Result<MetricKind, GetAllExperimentsError> {
+    let event = EventName::new(event)?;
+
+    match kind {
+        "conversion" => Ok(MetricKind::Conversion(event)),
+        "sum" => Ok(MetricKind::Sum(event)),
+        "mean" => Ok(MetricKind::Mean(event)),
+        "count" => Ok(MetricKind::Count(event)),
+        "ratio" => {
+            let denominator = denominator_event
+                .ok_or_else(|| anyhow!("ratio metric without a denominator event"))?;
+
+            Ok(MetricKind::Ratio {
+                numerator: event,
+                denominator: EventName::new(&denominator)?,
+            })
+        }
+        _ => Err(anyhow!("unknown metric kind {}", kind).into()),
+    }
+}
+
+#[allow(clippy::collapsible_if)]
+fn is_unique_constraint_violation(err: &sqlx::Error) -> bool {
+    if let sqlx::Error::Database(db_err) = err {
+        if let Some(code) = db_err.code() {
+            if code == UNIQUE_CONSTRAINT_VIOLATION_CODE {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+#[allow(dead_code)]
+const PRIMARYKEY_CONSTRAINT_VIOLATION_CODE: &str = "1555";
+
+#[allow(dead_code, clippy::collapsible_if)]
+fn is_primary_key_constraint_violation(err: &sqlx::Error) -> bool {
+    if let sqlx::Error::Database(db_err) = err {
+        if let Some(code) = db_err.code() {
+            if code == PRIMARYKEY_CONSTRAINT_VIOLATION_CODE {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Creates an adapter over a migrated in-memory database. A single connection is used, since
+/// every connection to `sqlite::memory:` opens a separate database.
+#[cfg(test)]
+pub(crate) async fn in_memory_sqlite() -> Sqlite {
+    let options = SqliteConnectOptions::from_str("sqlite::memory:")
+        .unwrap()
+        .pragma("foreign_keys", "ON");
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await
+        .unwrap();
+
+    sqlx::migrate!().run(&pool).await.unwrap();
+
+    Sqlite { pool }
+}
+
+#[cfg(test)]
+mod sqlite_tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::domain::event::models::event::{EventAttribution, EventValue, attribute_event};
+    use crate::domain::experiment::models::experiment::GetStatisticsRequest;
+    use crate::domain::experiment::models::segment::StatisticsPeriod;
+
+    async fn create_experiment(sqlite: &Sqlite, status: ExperimentStatus) -> Uuid {
+        let variant = ExperimentVariant::new(
+            VariantDistribution::new(100.0).unwrap(),
+            VariantData::new("blue").unwrap(),
+        );
+        let req = CreateExperimentRequest::new(
+            ExperimentName::new("color").unwrap(),
+            ExperimentVariants::new(vec![variant]).unwrap(),
+            None,
+            ExperimentAllocation::FULL,
+            status,
+        );
+
+        sqlite.create_experiment(&req).await.unwrap()
+    }
+
+    async fn get_experiment(sqlite: &Sqlite, id: &Uuid) -> Experiment {
+        sqlite.get_experiment_by_id(id).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_experiment_by_id() {
+        let sqlite = in_memory_sqlite().await;
+        let first_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+        let variant = ExperimentVariant::new(
+            VariantDistribution::new(100.0).unwrap(),
+            VariantData::new("small").unwrap(),
+        );
+        let req = CreateExperimentRequest::new(
+            ExperimentName::new("size").unwrap(),
+            ExperimentVariants::new(vec![variant]).unwrap(),
+            None,
+            ExperimentAllocation::FULL,
+            ExperimentStatus::Draft,
+        );
+        let second_id = sqlite.create_experiment(&req).await.unwrap();
+        let missing_id = Uuid::new_v4();
+
+        let experiments = sqlite.get_all_experiments().await.unwrap();
+        let second = sqlite.get_experiment_by_id(&second_id).await.unwrap();
+        let missing = sqlite.get_experiment_by_id(&missing_id).await;
+
+        assert_eq!(experiments.len(), 2);
+        assert!(experiments.iter().any(|exp| exp.id() == &first_id));
+        assert!(experiments.contains(&second));
+        assert!(
+            matches!(missing, Err(GetExperimentByIdError::NotFound { id }) if id == missing_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_finish_experiment_success() {
+        let sqlite = in_memory_sqlite().await;
+        let id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+
+        let result = sqlite.finish_experiment(&id).await.unwrap();
+        let experiment = get_experiment(&sqlite, &id).await;
+
+        assert_eq!(result, id);
+        assert_eq!(experiment.status(), ExperimentStatus::Finished);
+        assert!(experiment.finished_at().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_finish_experiment_not_found() {
+        let sqlite = in_memory_sqlite().await;
+        let id = Uuid::new_v4();
+
+        let result = sqlite.finish_experiment(&id).await;
+
+        assert!(matches!(result, Err(FinishExperimentError::NotFound { id: e }) if e == id));
+    }
+
+    #[tokio::test]
+    async fn test_finish_experiment_already_finished() {
+        let sqlite = in_memory_sqlite().await;
+        let id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+
+        sqlite.finish_experiment(&id).await.unwrap();
+        let finished_at = *get_experiment(&sqlite, &id).await.finished_at();
+
+        let result = sqlite.finish_experiment(&id).await;
+        let experiment = get_experiment(&sqlite, &id).await;
+
+        assert!(matches!(result, Err(FinishExperimentError::Finished { id: e }) if e == id));
+        assert_eq!(*experiment.finished_at(), finished_at);
+    }
+
+    #[tokio::test]
+    async fn test_finish_experiment_draft() {
+        let sqlite = in_memory_sqlite().await;
+        let id = create_experiment(&sqlite, ExperimentStatus::Draft).await;
+
+        let result = sqlite.finish_experiment(&id).await;
+        let experiment = get_experiment(&sqlite, &id).await;
+
+        assert!(matches!(
+            result,
+            Err(FinishExperimentError::InvalidTransition {
+                from: ExperimentStatus::Draft,
+                ..
+            })
+        ));
+        assert!(experiment.finished_at().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_experiment_success() {
+        let sqlite = in_memory_sqlite().await;
+        let id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+
+        let variants = ExperimentVariants::new(vec![
+            ExperimentVariant::new(
+                VariantDistribution::new(50.0).unwrap(),
+                VariantData::new("blue").unwrap(),
+            ),
+            ExperimentVariant::new(
+                VariantDistribution::new(50.0).unwrap(),
+                VariantData::new("red").unwrap(),
+            ),
+        ])
+        .unwrap();
+        let req = UpdateExperimentRequest::new(
+            id,
+            Some(ExperimentName::new("colour").unwrap()),
+            Some(variants.clone()),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        sqlite.update_experiment(&req).await.unwrap();
+        let experiment = get_experiment(&sqlite, &id).await;
+
+        assert_eq!(experiment.version(), 2);
+        assert_eq!(experiment.name(), &ExperimentName::new("colour").unwrap());
+        assert_eq!(experiment.variants(), &variants);
+    }
+
+    #[tokio::test]
+    async fn test_update_experiment_keeps_variants() {
+        let sqlite = in_memory_sqlite().await;
+        let id = create_experiment(&sqlite, ExperimentStatus::Draft).await;
+        let variants = get_experiment(&sqlite, &id).await.variants().to_owned();
+
+        let req = UpdateExperimentRequest::new(
+            id,
+            Some(ExperimentName::new("colour").unwrap()),
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+
+        sqlite.update_experiment(&req).await.unwrap();
+        let experiment = get_experiment(&sqlite, &id).await;
+
+        assert_eq!(experiment.version(), 2);
+        assert_eq!(experiment.variants(), &variants);
+
+        let id_as_string = id.to_string();
+        let variant_ids = sqlx::query!(
+            "SELECT id FROM experiment_variants WHERE experiment_id = $1",
+            id_as_string
+        )
+        .fetch_all(&sqlite.pool)
+        .await
+        .unwrap();
+        assert_eq!(variant_ids.len(), 2);
+        assert!(variant_ids.iter().all(|v| Uuid::parse_str(&v.id).is_ok()));
+    }
+
+    #[tokio::test]
+    async fn test_update_experiment_with_status() {
+        let sqlite = in_memory_sqlite().await;
+        let id = create_experiment(&sqlite, ExperimentStatus::Draft).await;
+        let rename = |name: &str, status: ExperimentStatus| {
+            UpdateExperimentRequest::new(
+                id,
+                Some(ExperimentName::new(name).unwrap()),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .with_status(Some(status))
+        };
+
+        sqlite
+            .update_experiment(&rename("colour", ExperimentStatus::Running))
+            .await
+            .unwrap();
+        let started = get_experiment(&sqlite, &id).await;
+        let result = sqlite
+            .update_experiment(&rename("shade", ExperimentStatus::Draft))
+            .await;
+        let rejected = get_experiment(&sqlite, &id).await;
+
+        assert_eq!(started.name().to_string(), "colour");
+        assert_eq!(started.status(), ExperimentStatus::Running);
+        assert!(matches!(
+            result,
+            Err(UpdateExperimentError::InvalidTransition {
+                from: ExperimentStatus::Running,
+                to: ExperimentStatus::Draft,
+                ..
+            })
+        ));
+        assert_eq!(rejected.name().to_string(), "colour");
+        assert_eq!(rejected.version(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_experiment_not_editable() {
+        let sqlite = in_memory_sqlite().await;
+        let id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+        sqlite.finish_experiment(&id).await.unwrap();
+
+        let req = UpdateExperimentRequest::new(
+            id,
+            Some(ExperimentName::new("colour").unwrap()),
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        let result = sqlite.update_experiment(&req).await;
+
+        assert!(matches!(
+            result,
+            Err(UpdateExperimentError::NotEditable {
+                status: ExperimentStatus::Finished,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_update_experiment_invalid_control() {
+        let sqlite = in_memory_sqlite().await;
+        let id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+
+        let req = UpdateExperimentRequest::new(
+            id,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(VariantData::new("red").unwrap()),
+        );
+        let result = sqlite.update_experiment(&req).await;
+
+        assert!(matches!(
+            result,
+            Err(UpdateExperimentError::InvalidControl { .. })
+        ));
+        assert_eq!(get_experiment(&sqlite, &id).await.version(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_targeting_rules_round_trip() {
+        let sqlite = in_memory_sqlite().await;
+
+        let targeting = TargetingRules::new(vec![
+            TargetingRule::new(
+                TargetingAttribute::new("country").unwrap(),
+                TargetingOperator::new_in(vec!["US".to_string(), "CA".to_string()]).unwrap(),
+            ),
+            TargetingRule::new(
+                TargetingAttribute::new("app_version").unwrap(),
+                TargetingOperator::SemverRange(SemverRange::new(">=2.1.0 <3").unwrap()),
+            ),
+            TargetingRule::new(
+                TargetingAttribute::new("sessions").unwrap(),
+                TargetingOperator::Numeric(NumericComparison::Gte, 5.5),
+            ),
+        ]);
+        let variant = ExperimentVariant::new(
+            VariantDistribution::new(100.0).unwrap(),
+            VariantData::new("blue").unwrap(),
+        );
+        let req = CreateExperimentRequest::new(
+            ExperimentName::new("color").unwrap(),
+            ExperimentVariants::new(vec![variant]).unwrap(),
+            None,
+            ExperimentAllocation::FULL,
+            ExperimentStatus::Running,
+        )
+        .with_targeting(targeting.clone());
+
+        let id = sqlite.create_experiment(&req).await.unwrap();
+        let experiment = get_experiment(&sqlite, &id).await;
+
+        assert_eq!(experiment.targeting(), &targeting);
+    }
+
+    #[tokio::test]
+    async fn test_analysis_plan_round_trip() {
+        let sqlite = in_memory_sqlite().await;
+        let fixed_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+
+        let variant = ExperimentVariant::new(
+            VariantDistribution::new(100.0).unwrap(),
+            VariantData::new("blue").unwrap(),
+        );
+        let req = CreateExperimentRequest::new(
+            ExperimentName::new("price").unwrap(),
+            ExperimentVariants::new(vec![variant]).unwrap(),
+            None,
+            ExperimentAllocation::FULL,
+            ExperimentStatus::Running,
+        )
+        .with_analysis_plan(AnalysisPlan::Sequential);
+        let sequential_id = sqlite.create_experiment(&req).await.unwrap();
+
+        assert_eq!(
+            get_experiment(&sqlite, &fixed_id).await.analysis_plan(),
+            AnalysisPlan::Fixed
+        );
+        assert_eq!(
+            get_experiment(&sqlite, &sequential_id)
+                .await
+                .analysis_plan(),
+            AnalysisPlan::Sequential
+        );
+    }
+
+    #[tokio::test]
+    async fn test_eligibility_round_trip() {
+        let sqlite = in_memory_sqlite().await;
+        let existing_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+
+        let variant = ExperimentVariant::new(
+            VariantDistribution::new(100.0).unwrap(),
+            VariantData::new("blue").unwrap(),
+        );
+        let req = CreateExperimentRequest::new(
+            ExperimentName::new("onboarding").unwrap(),
+            ExperimentVariants::new(vec![variant]).unwrap(),
+            None,
+            ExperimentAllocation::FULL,
+            ExperimentStatus::Running,
+        )
+        .with_eligibility(ExperimentEligibility::New);
+        let new_id = sqlite.create_experiment(&req).await.unwrap();
+
+        assert_eq!(
+            get_experiment(&sqlite, &existing_id).await.eligibility(),
+            ExperimentEligibility::Existing
+        );
+        assert_eq!(
+            get_experiment(&sqlite, &new_id).await.eligibility(),
+            ExperimentEligibility::New
+        );
+    }
+
+    #[tokio::test]
+    async fn test_schedule_round_trip() {
+        let sqlite = in_memory_sqlite().await;
+        let unscheduled_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+
+        let variant = ExperimentVariant::new(
+            VariantDistribution::new(100.0).unwrap(),
+            VariantData::new("blue").unwrap(),
+        );
+        let starts_at: DateTime<Utc> = "2025-07-20T09:00:00Z".parse().unwrap();
+        let ends_at: DateTime<Utc> = "2025-08-03T09:00:00Z".parse().unwrap();
+        let schedule = ExperimentSchedule::new(Some(starts_at), Some(ends_at)).unwrap();
+        let req = CreateExperimentRequest::new(
+            ExperimentName::new("summer").unwrap(),
+            ExperimentVariants::new(vec![variant]).unwrap(),
+            None,
+            ExperimentAllocation::FULL,
+            ExperimentStatus::Draft,
+        )
+        .with_schedule(schedule);
+        let scheduled_id = sqlite.create_experiment(&req).await.unwrap();
+
+        assert_eq!(
+            get_experiment(&sqlite, &unscheduled_id).await.schedule(),
+            &ExperimentSchedule::default()
+        );
+        assert_eq!(
+            get_experiment(&sqlite, &scheduled_id).await.schedule(),
+            &schedule
+        );
+    }
+
+    #[tokio::test]
+    async fn test_layers_round_trip_and_overlap() {
+        let sqlite = in_memory_sqlite().await;
+        let layered = |name: &str, start: u32, end: u32| {
+            let variant = ExperimentVariant::new(
+                VariantDistribution::new(100.0).unwrap(),
+                VariantData::new("blue").unwrap(),
+            );
+            CreateExperimentRequest::new(
+                ExperimentName::new(name).unwrap(),
+                ExperimentVariants::new(vec![variant]).unwrap(),
+                None,
+                ExperimentAllocation::FULL,
+                ExperimentStatus::Running,
+            )
+            .with_layer(ExperimentLayer::new(
+                LayerName::new("checkout").unwrap(),
+                LayerBuckets::new(start, end).unwrap(),
+            ))
+        };
+
+        let left = layered("button", 0, 500);
+        let left_id = sqlite.create_experiment(&left).await.unwrap();
+        assert_eq!(
+            get_experiment(&sqlite, &left_id).await.layer(),
+            left.layer()
+        );
+
+        let result = sqlite
+            .create_experiment(&layered("banner", 400, 1000))
+            .await;
+        assert!(matches!(
+            result,
+            Err(CreateExperimentError::LayerOverlap { name, .. }) if name.to_string() == "button"
+        ));
+
+        let right = sqlite
+            .create_experiment(&layered("banner", 500, 1000))
+            .await;
+        assert!(right.is_ok());
+
+        // Devices of a layer are bucketed by the same key in all of its experiments.
+        let result = sqlite
+            .create_experiment(&layered("copy", 0, 500).with_bucketing(ExperimentBucketing::User))
+            .await;
+        assert!(matches!(
+            result,
+            Err(CreateExperimentError::LayerBucketing { bucketing, .. })
+                if bucketing == ExperimentBucketing::Device
+        ));
+
+        // Finished experiments release their buckets.
+        sqlite.finish_experiment(&left_id).await.unwrap();
+        let result = sqlite.create_experiment(&layered("copy", 0, 500)).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_guardrails_round_trip() {
+        let sqlite = in_memory_sqlite().await;
+        let crash_rate = Metric::new(
+            MetricName::new("crash_rate").unwrap(),
+            MetricKind::Conversion(EventName::new("crash").unwrap()),
+            MetricRole::Secondary,
+        );
+        let metrics = ExperimentMetrics::new(vec![crash_rate]).unwrap();
+        let guardrails = ExperimentGuardrails::new(
+            vec![
+                Guardrail::new(
+                    MetricName::new("crash_rate").unwrap(),
+                    MaxDegradation::new(0.1).unwrap(),
+                )
+                .with_action(GuardrailAction::Finish),
+            ],
+            &metrics,
+        )
+        .unwrap();
+        let variant = ExperimentVariant::new(
+            VariantDistribution::new(100.0).unwrap(),
+            VariantData::new("blue").unwrap(),
+        );
+        let req = CreateExperimentRequest::new(
+            ExperimentName::new("color").unwrap(),
+            ExperimentVariants::new(vec![variant]).unwrap(),
+            None,
+            ExperimentAllocation::FULL,
+            ExperimentStatus::Running,
+        )
+        .with_metrics(metrics)
+        .with_guardrails(guardrails.clone());
+        let id = sqlite.create_experiment(&req).await.unwrap();
+
+        assert_eq!(get_experiment(&sqlite, &id).await.guardrails(), &guardrails);
+
+        // The metric cannot be removed while a guardrail watches it.
+        let req = UpdateExperimentRequest::new(
+            id,
+            None,
+            None,
+            None,
+            None,
+            Some(ExperimentMetrics::default()),
+            None,
+        );
+        let result = sqlite.update_experiment(&req).await;
+
+        assert!(matches!(
+            result,
+            Err(UpdateExperimentError::GuardrailMetric { metric, .. })
+                if metric == MetricName::new("crash_rate").unwrap()
+        ));
+        assert_eq!(get_experiment(&sqlite, &id).await.guardrails(), &guardrails);
+    }
+
+    #[tokio::test]
+    async fn test_audit_trail() {
+        let sqlite = in_memory_sqlite().await;
+        let id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+
+        assert!(sqlite.get_audit_trail(&id).await.unwrap().is_empty());
+
+        let paused = sqlite
+            .stop_experiment(&CreateAuditEntryRequest::new(
+                id,
+                ExperimentStatus::Paused,
+                "crash rate".to_string(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            get_experiment(&sqlite, &id).await.status(),
+            ExperimentStatus::Paused
+        );
+
+        let finished = sqlite
+            .stop_experiment(&CreateAuditEntryRequest::new(
+                id,
+                ExperimentStatus::Finished,
+                "retention".to_string(),
+            ))
+            .await
+            .unwrap();
+        let experiment = get_experiment(&sqlite, &id).await;
+        assert_eq!(experiment.status(), ExperimentStatus::Finished);
+        assert!(experiment.finished_at().is_some());
+
+        // A status the experiment cannot move to is not audited.
+        let result = sqlite
+            .stop_experiment(&CreateAuditEntryRequest::new(
+                id,
+                ExperimentStatus::Paused,
+                "crash rate".to_string(),
+            ))
+            .await;
+        assert!(matches!(
+            result,
+            Err(StopExperimentError::InvalidTransition { from, .. })
+                if from == ExperimentStatus::Finished
+        ));
+
+        assert_eq!(
+            sqlite.get_audit_trail(&id).await.unwrap(),
+            vec![paused, finished]
+        );
+
+        let unknown = Uuid::new_v4();
+        assert!(matches!(
+            sqlite.get_audit_trail(&unknown).await,
+            Err(GetAuditTrailError::NotFound { id }) if id == unknown
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_device() {
+        let sqlite = in_memory_sqlite().await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+
+        let first = sqlite.upsert_device(&id).await.unwrap();
+        let second = sqlite.upsert_device(&id).await.unwrap();
+
+        assert!(first.created());
+        assert!(!second.created());
+        assert_eq!(second.device().id(), &id);
+        assert_eq!(second.device().created_at(), first.device().created_at());
+        assert!(matches!(
+            sqlite.create_device(&CreateDeviceRequest::new(id.clone())).await,
+            Err(CreateDeviceError::Duplicate { id: e }) if e == id
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_devices_of_different_kinds_do_not_collide() {
+        let sqlite = in_memory_sqlite().await;
+        let idfa =
+            DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let aaid =
+            DeviceId::new(DeviceIdKind::Aaid, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let experiment_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+
+        for (id, data) in [(&idfa, "blue"), (&aaid, "red")] {
+            sqlite
+                .create_device(&CreateDeviceRequest::new(id.clone()))
+                .await
+                .unwrap();
+            sqlite
+                .create_assignments(&[CreateAssignmentRequest::new(
+                    id.clone(),
+                    experiment_id,
+                    VariantData::new(data).unwrap(),
+                    1,
+                )])
+                .await
+                .unwrap();
+        }
+        sqlite
+            .update_device_attributes(&UpdateDeviceAttributesRequest::new(
+                aaid.clone(),
+                DeviceAttributes::new(
+                    Some(DevicePlatform::Android),
+                    None,
+                    None,
+                    None,
+                    None,
+                    HashMap::new(),
+                ),
+            ))
+            .await
+            .unwrap();
+
+        let assignments = sqlite.get_all_assignments().await.unwrap();
+        let data_of = |id: &DeviceId| {
+            assignments
+                .iter()
+                .filter(|a| a.device_id() == id)
+                .map(|a| a.data().to_string())
+                .collect::<Vec<_>>()
+        };
+        let idfa_device = sqlite.get_device_by_id(&idfa).await.unwrap();
+
+        assert_eq!(sqlite.get_all_devices().await.unwrap().len(), 2);
+        assert_eq!(data_of(&idfa), vec!["blue"]);
+        assert_eq!(data_of(&aaid), vec!["red"]);
+        assert_eq!(idfa_device.attributes(), &DeviceAttributes::default());
+    }
+
+    #[tokio::test]
+    async fn test_update_device_attributes_merges_visits() {
+        let sqlite = in_memory_sqlite().await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        sqlite
+            .create_device(&CreateDeviceRequest::new(id.clone()))
+            .await
+            .unwrap();
+
+        let first_visit = DeviceAttributes::new(
+            Some(DevicePlatform::Android),
+            Some("14".to_string()),
+            Some("1.0.0".to_string()),
+            Some("DE".to_string()),
+            None,
+            HashMap::from([("tier".to_string(), "gold".to_string())]),
+        );
+        let second_visit = DeviceAttributes::new(
+            None,
+            None,
+            Some("1.1.0".to_string()),
+            None,
+            Some("de-DE".to_string()),
+            HashMap::from([("cohort".to_string(), "b".to_string())]),
+        );
+        for attributes in [first_visit.clone(), second_visit.clone()] {
+            sqlite
+                .update_device_attributes(&UpdateDeviceAttributesRequest::new(
+                    id.clone(),
+                    attributes,
+                ))
+                .await
+                .unwrap();
+        }
+
+        let device = sqlite.get_device_by_id(&id).await.unwrap();
+
+        assert_eq!(device.attributes(), &first_visit.merge(&second_visit));
+    }
+
+    #[tokio::test]
+    async fn test_update_device_attributes_not_found() {
+        let sqlite = in_memory_sqlite().await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+
+        let req = UpdateDeviceAttributesRequest::new(id.clone(), DeviceAttributes::default());
+        let result = sqlite.update_device_attributes(&req).await;
+
+        assert!(matches!(result, Err(UpdateDeviceAttributesError::NotFound { id: e }) if e == id));
+    }
+
+    #[tokio::test]
+    async fn test_link_device_to_user_keeps_assignments() {
+        let sqlite = in_memory_sqlite().await;
+        let variants = ExperimentVariants::new(vec![
+            ExperimentVariant::new(
+                VariantDistribution::new(50.0).unwrap(),
+                VariantData::new("blue").unwrap(),
+            ),
+            ExperimentVariant::new(
+                VariantDistribution::new(50.0).unwrap(),
+                VariantData::new("red").unwrap(),
+            ),
+        ])
+        .unwrap();
+        let req = CreateExperimentRequest::new(
+            ExperimentName::new("color").unwrap(),
+            variants,
+            None,
+            ExperimentAllocation::FULL,
+            ExperimentStatus::Running,
+        )
+        .with_bucketing(ExperimentBucketing::User);
+        let experiment_id = sqlite.create_experiment(&req).await.unwrap();
+
+        let phone =
+            DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let tablet =
+            DeviceId::new(DeviceIdKind::Idfv, "6ba7b810-9dad-11d1-80b4-00c04fd430c8").unwrap();
+        for (device_id, data) in [(&phone, "blue"), (&tablet, "red")] {
+            sqlite
+                .create_device(&CreateDeviceRequest::new(device_id.clone()))
+                .await
+                .unwrap();
+            sqlite
+                .create_assignments(&[CreateAssignmentRequest::new(
+                    device_id.clone(),
+                    experiment_id,
+                    VariantData::new(data).unwrap(),
+                    1,
+                )])
+                .await
+                .unwrap();
+        }
+
+        let user_id = UserId::new("user-42").unwrap();
+        for device_id in [&tablet, &phone] {
+            sqlite
+                .link_device_to_user(&LinkDeviceRequest::new(device_id.clone(), user_id.clone()))
+                .await
+                .unwrap();
+        }
+
+        let assignments = sqlite.get_all_assignments().await.unwrap();
+        let data_of = |device_id: &DeviceId| {
+            assignments
+                .iter()
+                .find(|a| a.device_id() == device_id)
+                .map(|a| a.data().to_string())
+        };
+
+        assert_eq!(data_of(&phone).as_deref(), Some("blue"));
+        assert_eq!(data_of(&tablet).as_deref(), Some("red"));
+    }
+
+    #[tokio::test]
+    async fn test_link_device_to_user_not_found() {
+        let sqlite = in_memory_sqlite().await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+
+        let req = LinkDeviceRequest::new(id.clone(), UserId::new("user-42").unwrap());
+        let result = sqlite.link_device_to_user(&req).await;
+
+        assert!(matches!(result, Err(LinkDeviceError::NotFound { id: e }) if e == id));
+    }
+
+    #[tokio::test]
+    async fn test_save_events_with_attributions() {
+        let sqlite = in_memory_sqlite().await;
+        let experiment_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        sqlite
+            .create_device(&CreateDeviceRequest::new(id.clone()))
+            .await
+            .unwrap();
+        sqlite
+            .create_assignments(&[CreateAssignmentRequest::new(
+                id.clone(),
+                experiment_id,
+                VariantData::new("blue").unwrap(),
+                1,
+            )])
+            .await
+            .unwrap();
+
+        let assignments = sqlite.get_device_assignments(&id).await.unwrap();
+        let occurred_at = Utc::now();
+        let req = SaveEventRequest::new(
+            id.clone(),
+            EventName::new("purchase").unwrap(),
+            Some(EventValue::new(9.99).unwrap()),
+            occurred_at,
+            attribute_event(&occurred_at, &assignments),
+        );
+        let events = sqlite.save_events(&id, &[req]).await.unwrap();
+        let event_id = events[0].id().to_string();
+
+        let attributions = sqlx::query!(
+            "SELECT experiment_id, data FROM event_attributions WHERE event_id = $1",
+            event_id
+        )
+        .fetch_all(&sqlite.pool)
+        .await
+        .unwrap();
+
+        assert_eq!(attributions.len(), 1);
+        assert_eq!(attributions[0].experiment_id, experiment_id.to_string());
+        assert_eq!(attributions[0].data, "blue");
+    }
+
+    #[tokio::test]
+    async fn test_save_events_device_not_found() {
+        let sqlite = in_memory_sqlite().await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+
+        let req = SaveEventRequest::new(
+            id.clone(),
+            EventName::new("purchase").unwrap(),
+            None,
+            Utc::now(),
+            vec![],
+        );
+        let result = sqlite.save_events(&id, &[req]).await;
+
+        assert!(matches!(result, Err(CreateEventsError::DeviceNotFound { id: e }) if e == id));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_round_trip_and_observations() {
+        let sqlite = in_memory_sqlite().await;
+        let metrics = ExperimentMetrics::new(vec![
+            Metric::new(
+                MetricName::new("purchases").unwrap(),
+                MetricKind::Conversion(EventName::new("purchase").unwrap()),
+                MetricRole::Primary,
+            ),
+            Metric::new(
+                MetricName::new("ctr").unwrap(),
+                MetricKind::Ratio {
+                    numerator: EventName::new("click").unwrap(),
+                    denominator: EventName::new("view").unwrap(),
+                },
+                MetricRole::Secondary,
+            )
+            .with_mixing_deviation(MixingDeviation::new(0.05).unwrap()),
+        ])
+        .unwrap();
+        let variant = ExperimentVariant::new(
+            VariantDistribution::new(100.0).unwrap(),
+            VariantData::new("blue").unwrap(),
+        );
+        let req = CreateExperimentRequest::new(
+            ExperimentName::new("color").unwrap(),
+            ExperimentVariants::new(vec![variant]).unwrap(),
+            None,
+            ExperimentAllocation::FULL,
+            ExperimentStatus::Running,
+        )
+        .with_metrics(metrics.clone());
+        let experiment_id = sqlite.create_experiment(&req).await.unwrap();
+
+        let experiment = get_experiment(&sqlite, &experiment_id).await;
+
+        assert_eq!(experiment.metrics(), &metrics);
+
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        sqlite
+            .create_device(&CreateDeviceRequest::new(id.clone()))
+            .await
+            .unwrap();
+        sqlite
+            .create_assignments(&[CreateAssignmentRequest::new(
+                id.clone(),
+                experiment_id,
+                VariantData::new("blue").unwrap(),
+                1,
+            )])
+            .await
+            .unwrap();
+        // Events attributed to a variant the device is no longer assigned to are not observed.
+        let stale = EventAttribution::new(experiment_id, VariantData::new("red").unwrap(), 1);
+        sqlite
+            .save_events(
+                &id,
+                &[SaveEventRequest::new(
+                    id.clone(),
+                    EventName::new("purchase").unwrap(),
+                    None,
+                    Utc::now(),
+                    vec![stale],
+                )],
+            )
+            .await
+            .unwrap();
+        let attribution =
+            EventAttribution::new(experiment_id, VariantData::new("blue").unwrap(), 1);
+        let reqs: Vec<SaveEventRequest> = [Some(9.5), Some(0.5), None]
+            .into_iter()
+            .map(|value| {
+                SaveEventRequest::new(
+                    id.clone(),
+                    EventName::new("purchase").unwrap(),
+                    value.map(|v| EventValue::new(v).unwrap()),
+                    Utc::now(),
+                    vec![attribution.clone()],
+                )
+            })
+            .collect();
+        sqlite.save_events(&id, &reqs).await.unwrap();
+
+        let observations = sqlite.get_all_metric_observations().await.unwrap();
+
+        assert_eq!(observations.len(), 1);
+        assert_eq!(observations[0].count(), 3);
+        assert_eq!(observations[0].value_count(), 2);
+        assert_eq!(observations[0].sum(), 10.0);
+    }
+
+    #[tokio::test]
+    async fn test_create_assignments_keeps_first_exposure() {
+        let sqlite = in_memory_sqlite().await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        sqlite
+            .create_device(&CreateDeviceRequest::new(id.clone()))
+            .await
+            .unwrap();
+        let experiment_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+        let exposed_at = Utc::now() - TimeDelta::days(1);
+
+        let first = sqlite
+            .create_assignments(&[CreateAssignmentRequest::new(
+                id.clone(),
+                experiment_id,
+                VariantData::new("blue").unwrap(),
+                1,
+            )
+            .with_assigned_at(exposed_at)])
+            .await
+            .unwrap();
+        let second = sqlite
+            .create_assignments(&[CreateAssignmentRequest::new(
+                id.clone(),
+                experiment_id,
+                VariantData::new("red").unwrap(),
+                2,
+            )])
+            .await
+            .unwrap();
+        let all = sqlite.get_all_assignments().await.unwrap();
+        let of_experiment = sqlite
+            .get_experiment_assignments(&experiment_id)
+            .await
+            .unwrap();
+        let of_missing = sqlite
+            .get_experiment_assignments(&Uuid::new_v4())
+            .await
+            .unwrap();
+
+        let expected = Assignment::new(
+            id.clone(),
+            experiment_id,
+            VariantData::new("blue").unwrap(),
+            1,
+            exposed_at,
+        );
+        assert_eq!(first, vec![expected.clone()]);
+        assert_eq!(second, vec![expected.clone()]);
+        assert_eq!(of_experiment, vec![expected.clone()]);
+        assert!(of_missing.is_empty());
+        assert_eq!(all, vec![expected]);
+    }
+
+    #[tokio::test]
+    async fn test_replace_assignments() {
+        let sqlite = in_memory_sqlite().await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        sqlite
+            .create_device(&CreateDeviceRequest::new(id.clone()))
+            .await
+            .unwrap();
+        let experiment_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+        let assign = |data: &str, version: u32| {
+            CreateAssignmentRequest::new(
+                id.clone(),
+                experiment_id,
+                VariantData::new(data).unwrap(),
+                version,
+            )
+        };
+
+        sqlite
+            .create_assignments(&[assign("blue", 1)])
+            .await
+            .unwrap();
+        let replaced = sqlite
+            .replace_assignments(&[assign("red", 2)])
+            .await
+            .unwrap();
+        let all = sqlite.get_all_assignments().await.unwrap();
+
+        assert_eq!(all, replaced);
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].data().to_string(), "red");
+        assert_eq!(all[0].version(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_create_assignments_device_not_found() {
+        let sqlite = in_memory_sqlite().await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let experiment_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+
+        let result = sqlite
+            .create_assignments(&[CreateAssignmentRequest::new(
+                id,
+                experiment_id,
+                VariantData::new("blue").unwrap(),
+                1,
+            )])
+            .await;
+
+        assert!(result.is_err());
+        assert!(sqlite.get_all_assignments().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_assignments_persisted_since() {
+        let before = Utc::now() - TimeDelta::seconds(1);
+        let sqlite = in_memory_sqlite().await;
+
+        let since = sqlite.get_assignments_persisted_since().await.unwrap();
+
+        assert!(since >= before && since <= Utc::now());
+    }
+
+    #[tokio::test]
+    async fn test_save_backfilled_assignments() {
+        let sqlite = in_memory_sqlite().await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        sqlite
+            .create_device(&CreateDeviceRequest::new(id.clone()))
+            .await
+            .unwrap();
+        let experiment_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+        let req =
+            CreateAssignmentRequest::new(id, experiment_id, VariantData::new("blue").unwrap(), 1);
+
+        let before = sqlite.is_assignments_backfilled().await.unwrap();
+        sqlite.save_backfilled_assignments(&[req]).await.unwrap();
+        let after = sqlite.is_assignments_backfilled().await.unwrap();
+
+        assert!(!before);
+        assert!(after);
+        assert_eq!(sqlite.get_all_assignments().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_count_active_devices_since() {
+        let sqlite = in_memory_sqlite().await;
+        let mut ids = Vec::new();
+        for raw_idfa in [
+            "550e8400-e29b-41d4-a716-446655440000",
+            "6ba7b810-9dad-11d1-80b4-00c04fd430c8",
+            "6ba7b811-9dad-11d1-80b4-00c04fd430c8",
+        ] {
+            let id = DeviceId::new(DeviceIdKind::Idfa, raw_idfa).unwrap();
+            sqlite
+                .create_device(&CreateDeviceRequest::new(id.clone()))
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        let now = Utc::now();
+        // The first two devices were created long ago, only the first of them sends events.
+        for id in &ids[..2] {
+            sqlx::query("UPDATE devices SET created_at = $1 WHERE kind = $2 AND id = $3")
+                .bind(now - TimeDelta::days(60))
+                .bind(id.kind().to_string())
+                .bind(id.to_string())
+                .execute(&sqlite.pool)
+                .await
+                .unwrap();
+        }
+        sqlite
+            .save_events(
+                &ids[0],
+                &[SaveEventRequest::new(
+                    ids[0].clone(),
+                    EventName::new("purchase").unwrap(),
+                    None,
+                    now,
+                    Vec::new(),
+                )],
+            )
+            .await
+            .unwrap();
+        let since = now - TimeDelta::hours(1);
+
+        let existing = sqlite
+            .count_active_devices_since(&since, ExperimentEligibility::Existing)
+            .await
+            .unwrap();
+        let new = sqlite
+            .count_active_devices_since(&since, ExperimentEligibility::New)
+            .await
+            .unwrap();
+        let all = sqlite
+            .count_active_devices_since(&since, ExperimentEligibility::All)
+            .await
+            .unwrap();
+
+        assert_eq!(existing, 1);
+        assert_eq!(new, 1);
+        assert_eq!(all, 2);
+    }
+
+    #[tokio::test]
+    async fn test_pre_experiment_observations() {
+        let sqlite = in_memory_sqlite().await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        sqlite
+            .create_device(&CreateDeviceRequest::new(id.clone()))
+            .await
+            .unwrap();
+        let experiment_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+        let created_at = *get_experiment(&sqlite, &experiment_id).await.created_at();
+        sqlite
+            .create_assignments(&[CreateAssignmentRequest::new(
+                id.clone(),
+                experiment_id,
+                VariantData::new("blue").unwrap(),
+                1,
+            )])
+            .await
+            .unwrap();
+
+        // Only the first event is within the window before the experiment.
+        let reqs: Vec<SaveEventRequest> = [
+            (2.0, created_at - TimeDelta::days(1)),
+            (5.0, created_at - TimeDelta::days(10)),
+            (7.0, created_at + TimeDelta::minutes(1)),
+        ]
+        .into_iter()
+        .map(|(value, occurred_at)| {
+            SaveEventRequest::new(
+                id.clone(),
+                EventName::new("purchase").unwrap(),
+                Some(EventValue::new(value).unwrap()),
+                occurred_at,
+                Vec::new(),
+            )
+        })
+        .collect();
+        sqlite.save_events(&id, &reqs).await.unwrap();
+
+        let observations = sqlite
+            .get_all_pre_experiment_observations(CovariateWindow::new(7).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(observations.len(), 1);
+        assert_eq!(observations[0].experiment_id(), &experiment_id);
+        assert_eq!(observations[0].data(), &VariantData::new("blue").unwrap());
+        assert_eq!(observations[0].count(), 1);
+        assert_eq!(observations[0].sum(), 2.0);
+
+        let req =
+            GetExperimentStatisticsRequest::new(experiment_id, GetStatisticsRequest::default());
+        let experiment_observations = sqlite
+            .get_pre_experiment_observations(&req, CovariateWindow::new(7).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(experiment_observations, observations);
+
+        // Devices assigned outside the period are not observed.
+        let future = StatisticsPeriod::new(Some(Utc::now() + TimeDelta::days(1)), None).unwrap();
+        let future_observations = sqlite
+            .get_pre_experiment_observations(
+                &req.with_period(future),
+                CovariateWindow::new(7).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert!(future_observations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_timed_metric_observations() {
+        let sqlite = in_memory_sqlite().await;
+        let id = DeviceId::new(DeviceIdKind::Idfa, "550e8400-e29b-41d4-a716-446655440000").unwrap();
+        sqlite
+            .create_device(&CreateDeviceRequest::new(id.clone()))
+            .await
+            .unwrap();
+        let experiment_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+        let data = VariantData::new("blue").unwrap();
+        let occurred_at: DateTime<Utc> = "2025-07-10T12:00:00Z".parse().unwrap();
+        sqlite
+            .create_assignments(&[CreateAssignmentRequest::new(
+                id.clone(),
+                experiment_id,
+                data.clone(),
+                1,
+            )])
+            .await
+            .unwrap();
+
+        let reqs: Vec<SaveEventRequest> = [Some(3.0), None]
+            .into_iter()
+            .map(|value| {
+                SaveEventRequest::new(
+                    id.clone(),
+                    EventName::new("purchase").unwrap(),
+                    value.map(|value| EventValue::new(value).unwrap()),
+                    occurred_at,
+                    vec![EventAttribution::new(experiment_id, data.clone(), 1)],
+                )
+            })
+            .collect();
+        sqlite.save_events(&id, &reqs).await.unwrap();
+
+        let mut observations = sqlite
+            .get_timed_metric_observations(&experiment_id)
+            .await
+            .unwrap();
+        observations.sort_by(|a, b| b.observation().sum().total_cmp(&a.observation().sum()));
+
+        // Every event is kept on its own, events without a value are not summed.
+        assert_eq!(observations.len(), 2);
+        assert_eq!(observations[0].occurred_at(), &occurred_at);
+        assert_eq!(observations[0].observation().data(), &data);
+        assert_eq!(observations[0].observation().count(), 1);
+        assert_eq!(observations[0].observation().sum(), 3.0);
+        assert_eq!(observations[0].observation().sum_of_squares(), 9.0);
+        assert_eq!(observations[1].observation().sum(), 0.0);
+        assert!(
+            sqlite
+                .get_timed_metric_observations(&Uuid::new_v4())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    async fn segment_counts(
+        sqlite: &Sqlite,
+        req: &GetExperimentStatisticsRequest,
+    ) -> Vec<(Option<String>, usize)> {
+        let mut counts: Vec<(Option<String>, usize)> = sqlite
+            .count_experiment_participants(req)
+            .await
+            .unwrap()
+            .iter()
+            .map(|c| (c.segment().map(str::to_string), c.devices()))
+            .collect();
+        counts.sort();
+        counts
+    }
+
+    #[tokio::test]
+    async fn test_experiment_participants_by_segment() {
+        let sqlite = in_memory_sqlite().await;
+        let experiment_id = create_experiment(&sqlite, ExperimentStatus::Running).await;
+        let data = VariantData::new("blue").unwrap();
+        let attributes = [
+            (
+                "550e8400-e29b-41d4-a716-446655440000",
+                Some(DevicePlatform::Android),
+                HashMap::from([("tier".to_string(), "gold".to_string())]),
+            ),
+            (
+                "550e8400-e29b-41d4-a716-446655440001",
+                Some(DevicePlatform::Ios),
+                HashMap::new(),
+            ),
+            ("550e8400-e29b-41d4-a716-446655440002", None, HashMap::new()),
+        ];
+        for (raw_id, platform, properties) in attributes {
+            let id = DeviceId::new(DeviceIdKind::Idfa, raw_id).unwrap();
+            sqlite
+                .create_device(&CreateDeviceRequest::new(id.clone()))
+                .await
+                .unwrap();
+            sqlite
+                .update_device_attributes(&UpdateDeviceAttributesRequest::new(
+                    id.clone(),
+                    DeviceAttributes::new(platform, None, None, None, None, properties),
+                ))
+                .await
+                .unwrap();
+            sqlite
+                .create_assignments(&[CreateAssignmentRequest::new(
+                    id.clone(),
+                    experiment_id,
+                    data.clone(),
+                    1,
+                )])
+                .await
+                .unwrap();
+            sqlite
+                .save_events(
+                    &id,
+                    &[SaveEventRequest::new(
+                        id.clone(),
+                        EventName::new("purchase").unwrap(),
+                        Some(EventValue::new(2.0).unwrap()),
+                        Utc::now(),
+                        vec![EventAttribution::new(experiment_id, data.clone(), 1)],
+                    )],
+                )
+                .await
+                .unwrap();
+        }
+
+        let req =
+            GetExperimentStatisticsRequest::new(experiment_id, GetStatisticsRequest::default());
+
+        assert_eq!(segment_counts(&sqlite, &req).await, vec![(None, 3)]);
+        assert_eq!(
+            segment_counts(
+                &sqlite,
+                &req.clone()
+                    .with_segment(TargetingAttribute::new("platform").unwrap())
+            )
+            .await,
+            vec![
+                (None, 1),
+                (Some("android".to_string()), 1),
+                (Some("ios".to_string()), 1)
+            ]
+        );
+        assert_eq!(
+            segment_counts(
+                &sqlite,
+                &req.clone()
+                    .with_segment(TargetingAttribute::new("tier").unwrap())
+            )
+            .await,
+            vec![(None, 2), (Some("gold".to_string()), 1)]
+        );
+
+        // Devices assigned and events sent before the period are left out.
+        let future = StatisticsPeriod::new(Some(Utc::now() + TimeDelta::days(1)), None).unwrap();
+        assert!(
+            segment_counts(&sqlite, &req.clone().with_period(future))
+                .await
+                .is_empty()
+        );
+        assert!(
+            sqlite
+                .get_experiment_metric_observations(&req.clone().with_period(future))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let observations = sqlite
+            .get_experiment_metric_observations(
+                &req.with_segment(TargetingAttribute::new("tier").unwrap()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(observations.len(), 3);
+        assert_eq!(
+            observations
+                .iter()
+                .filter(|o| o.segment() == Some("gold"))
+                .count(),
+            1
+        );
+        assert!(observations.iter().all(|o| o.sum() == 2.0));
+    }
 }